@@ -0,0 +1,329 @@
+//! Pluggable key-value backends
+//!
+//! [`Database::with_backend`](crate::Database::with_backend) runs a database
+//! on top of any [`KvBackend`], such as a fault-injecting wrapper used in
+//! tests or an alternative engine. Indexes, queries and transactions are
+//! layered on top by the database as for the built-in backends.
+
+use std::sync::Arc;
+
+use rustlite_core::transaction::{
+    BaseStore, KeyWrite, MVCCStorage, TransactionId, TransactionManager,
+};
+use rustlite_core::Result;
+use rustlite_storage::StorageEngine;
+
+/// Key-value store underneath a [`Database`](crate::Database)
+///
+/// Implementations must be safe to call from several threads at once.
+/// Writes are acknowledged once they are visible to later reads; whether
+/// they are durable is up to the backend and [`sync`](KvBackend::sync).
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::BTreeMap;
+/// use std::sync::Mutex;
+///
+/// use rustlite::{Database, Error, KvBackend, Result};
+///
+/// #[derive(Default)]
+/// struct MapBackend(Mutex<BTreeMap<Vec<u8>, Vec<u8>>>);
+///
+/// impl KvBackend for MapBackend {
+///     fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+///         let map = self.0.lock().map_err(|_| Error::LockPoisoned)?;
+///         Ok(map.get(key).cloned())
+///     }
+///
+///     fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+///         let mut map = self.0.lock().map_err(|_| Error::LockPoisoned)?;
+///         map.insert(key.to_vec(), value.to_vec());
+///         Ok(())
+///     }
+///
+///     fn delete(&self, key: &[u8]) -> Result<()> {
+///         let mut map = self.0.lock().map_err(|_| Error::LockPoisoned)?;
+///         map.remove(key);
+///         Ok(())
+///     }
+///
+///     fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+///         let map = self.0.lock().map_err(|_| Error::LockPoisoned)?;
+///         Ok(map
+///             .range(start.to_vec()..end.to_vec())
+///             .map(|(k, v)| (k.clone(), v.clone()))
+///             .collect())
+///     }
+///
+///     fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+///         let map = self.0.lock().map_err(|_| Error::LockPoisoned)?;
+///         Ok(map.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+///     }
+/// }
+///
+/// let db = Database::with_backend(Box::new(MapBackend::default()));
+/// db.put(b"key", b"value")?;
+/// assert_eq!(db.get(b"key")?, Some(b"value".to_vec()));
+/// # Ok::<(), rustlite::Error>(())
+/// ```
+pub trait KvBackend: Send + Sync {
+    /// Get the value of a key
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Insert or replace the value of a key
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()>;
+
+    /// Delete a key. Deleting a missing key is not an error.
+    fn delete(&self, key: &[u8]) -> Result<()>;
+
+    /// Key-value pairs with keys in `[start, end)`, in ascending key order
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// Every key-value pair, in ascending key order
+    fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// Delete every key in `[start, end)`. Deletes the keys listed by
+    /// [`scan_range`](KvBackend::scan_range) one at a time by default.
+    fn delete_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        for (key, _) in self.scan_range(start, end)? {
+            self.delete(&key)?;
+        }
+        Ok(())
+    }
+
+    /// Delete every key. Deletes the keys listed by
+    /// [`entries`](KvBackend::entries) one at a time by default.
+    fn clear(&self) -> Result<()> {
+        for (key, _) in self.entries()? {
+            self.delete(&key)?;
+        }
+        Ok(())
+    }
+
+    /// Keys starting with `prefix`, in ascending key order. Filters
+    /// [`entries`](KvBackend::entries) by default.
+    fn scan_keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .map(|(key, _)| key)
+            .filter(|key| key.starts_with(prefix))
+            .collect())
+    }
+
+    /// Number of keys. Counts [`entries`](KvBackend::entries) by default.
+    fn len(&self) -> Result<u64> {
+        Ok(self.entries()?.len() as u64)
+    }
+
+    /// Estimate of the number of keys that is cheap to compute. Same as
+    /// [`len`](KvBackend::len) by default.
+    fn approximate_len(&self) -> Result<u64> {
+        self.len()
+    }
+
+    /// Returns true if there are no keys
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Make acknowledged writes durable. Does nothing by default.
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Make the latest write to `key` durable. Calls
+    /// [`sync`](KvBackend::sync) by default.
+    fn sync_key(&self, _key: &[u8]) -> Result<()> {
+        self.sync()
+    }
+
+    /// Whether the latest write to `key` is not yet durable. Always false
+    /// by default.
+    fn needs_sync(&self, _key: &[u8]) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Lets callers keep a handle on a backend after passing it to a database
+impl<B: KvBackend + ?Sized> KvBackend for Arc<B> {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        (**self).get(key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        (**self).put(key, value)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        (**self).delete(key)
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        (**self).scan_range(start, end)
+    }
+
+    fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        (**self).entries()
+    }
+
+    fn delete_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        (**self).delete_range(start, end)
+    }
+
+    fn clear(&self) -> Result<()> {
+        (**self).clear()
+    }
+
+    fn scan_keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        (**self).scan_keys(prefix)
+    }
+
+    fn len(&self) -> Result<u64> {
+        (**self).len()
+    }
+
+    fn approximate_len(&self) -> Result<u64> {
+        (**self).approximate_len()
+    }
+
+    fn sync(&self) -> Result<()> {
+        (**self).sync()
+    }
+
+    fn sync_key(&self, key: &[u8]) -> Result<()> {
+        (**self).sync_key(key)
+    }
+
+    fn needs_sync(&self, key: &[u8]) -> Result<bool> {
+        (**self).needs_sync(key)
+    }
+}
+
+impl KvBackend for StorageEngine {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        StorageEngine::get(self, key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        StorageEngine::put(self, key, value)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        StorageEngine::delete(self, key)
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        StorageEngine::scan_range(self, start, end)
+    }
+
+    fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        StorageEngine::entries(self)
+    }
+
+    fn delete_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        StorageEngine::delete_range(self, start, end)
+    }
+
+    fn clear(&self) -> Result<()> {
+        StorageEngine::clear(self)
+    }
+
+    fn scan_keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        StorageEngine::scan_keys(self, prefix)
+    }
+
+    fn len(&self) -> Result<u64> {
+        StorageEngine::len(self)
+    }
+
+    fn approximate_len(&self) -> Result<u64> {
+        StorageEngine::approximate_len(self)
+    }
+
+    fn sync(&self) -> Result<()> {
+        StorageEngine::sync(self)
+    }
+
+    fn sync_key(&self, key: &[u8]) -> Result<()> {
+        StorageEngine::sync_key(self, key)
+    }
+
+    fn needs_sync(&self, key: &[u8]) -> Result<bool> {
+        StorageEngine::needs_sync(self, key)
+    }
+}
+
+/// Backend of [`Database::in_memory`](crate::Database::in_memory)
+///
+/// Keys live in the MVCC version chains themselves. Writes are committed
+/// through the transaction manager, so each call becomes visible to
+/// transactions at once. `delete_range` and `clear` find and delete their
+/// keys as one atomic batch.
+pub(crate) struct MemoryBackend {
+    store: Arc<MVCCStorage>,
+    manager: Arc<TransactionManager>,
+}
+
+impl MemoryBackend {
+    /// Backend over `store`, which must be the storage of `manager`
+    pub(crate) fn new(store: Arc<MVCCStorage>, manager: Arc<TransactionManager>) -> Self {
+        Self { store, manager }
+    }
+}
+
+impl KvBackend for MemoryBackend {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.store.read_latest(key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.manager
+            .write_committed(vec![(key.to_vec(), Some(value.to_vec()))])
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.manager.write_committed(vec![(key.to_vec(), None)])
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.store.latest_range(start, Some(end))
+    }
+
+    fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.store.latest_entries()
+    }
+
+    fn delete_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        self.manager.delete_range_committed(start, Some(end))
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.manager.delete_range_committed(&[], None)
+    }
+
+    fn len(&self) -> Result<u64> {
+        Ok(self.store.latest_len()? as u64)
+    }
+}
+
+/// Adapts a [`KvBackend`] as the base layer of MVCC reads, so committed
+/// transactions are written through to it
+pub(crate) struct BackendBase(pub(crate) Arc<dyn KvBackend>);
+
+impl BaseStore for BackendBase {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.0.get(key)
+    }
+
+    fn commit_writes(&self, _txn_id: TransactionId, writes: &[KeyWrite]) -> Result<()> {
+        for (key, value) in writes {
+            match value {
+                Some(value) => self.0.put(key, value)?,
+                None => self.0.delete(key)?,
+            }
+        }
+        Ok(())
+    }
+}
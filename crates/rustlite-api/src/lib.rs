@@ -70,40 +70,55 @@
 //!
 //! See [ROADMAP.md](https://github.com/VIRTUMEM-AI-LABS/rustlite/blob/main/docs/ROADMAP.md) for details.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::Path;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::Instant;
 
 use tracing::{debug, info, instrument, warn};
 
+use backend::{BackendBase, MemoryBackend};
+use metrics::MetricsCounters;
+
+mod backend;
 pub mod logging;
+mod metrics;
 mod security;
 
+pub use backend::KvBackend;
+pub use metrics::DbMetrics;
+pub use security::ResourceLimits;
+
 // Re-export core types
-pub use rustlite_core::index::{BTreeIndex, HashIndex, Index, IndexInfo, IndexManager, IndexType};
+pub use rustlite_core::index::{
+    BTreeIndex, HashIndex, Index, IndexContents, IndexInfo, IndexManager, IndexSnapshot, IndexType,
+};
 pub use rustlite_core::{Error, Result};
 
 // Transaction support (v0.5.0+)
 pub use rustlite_core::transaction::{
     IsolationLevel, MVCCStorage, Timestamp, Transaction, TransactionId, TransactionManager,
-    VersionChain, VersionedValue,
+    VersionChain, VersionStats, VersionedValue,
 };
 
 // Query engine (v0.4.0+)
 pub use rustlite_core::query::{
-    Column, ExecutionContext, Executor, Lexer, Parser, PhysicalPlan, Planner, Query, Row, Value,
+    Column, ExecutionContext, Executor, IndexMetadata, Lexer, Parser, PhysicalPlan, Planner, Query,
+    Row, Statement, Value,
 };
 
 // WAL components
 pub use rustlite_wal::{
-    RecoveryManager, RecoveryStats, SyncMode, WalConfig, WalManager, WalReader, WalRecord,
+    AppendOutcome, RecoveryManager, RecoveryPolicy, RecoveryStats, SyncMode, WalConfig, WalManager,
+    WalPosition, WalReader, WalRecord,
 };
 
 // Storage components
 pub use rustlite_storage::{
-    CompactionConfig, CompactionStats, CompactionWorker, Manifest, Memtable, MemtableEntry,
-    SSTableEntry, SSTableMeta, SSTableReader, SSTableWriter, StorageConfig, StorageEngine,
-    StorageStats,
+    CheckpointInfo, ChecksumAlgorithm, CompactionConfig, CompactionStats, CompactionWorker,
+    DbObserver, IntegrityConfig, Manifest, Memtable, MemtableEntry, MergeOperator, SSTableEntry,
+    SSTableMeta, SSTableReader, SSTableWriter, StorageConfig, StorageEngine, StorageStats,
 };
 
 // Snapshot components
@@ -114,22 +129,192 @@ pub use rustlite_snapshot::{
 // Version information
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-/// Storage backend for the database
-enum StorageBackend {
-    /// In-memory storage using HashMap
-    Memory(RwLock<HashMap<Vec<u8>, Vec<u8>>>),
-    /// Persistent storage using LSM-tree
-    Persistent(StorageEngine),
+/// Extracts a secondary-index key from a record's key and value.
+///
+/// Returning `None` leaves the record out of the index.
+pub type IndexExtractor = Arc<dyn Fn(&[u8], &[u8]) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Derives an `(index_key, record_id)` entry from a record's key and value
+/// when rebuilding an index.
+///
+/// Returning `None` leaves the record out of the index.
+pub type IndexEntryExtractor = Arc<dyn Fn(&[u8], &[u8]) -> Option<(Vec<u8>, u64)> + Send + Sync>;
+
+/// Indexes kept in sync with the data by `put` and `delete`
+#[derive(Default)]
+struct AutoIndexes {
+    /// Extractor for each automatically maintained index
+    extractors: HashMap<String, IndexExtractor>,
+    /// Row IDs assigned to indexed primary keys
+    row_ids: HashMap<Vec<u8>, u64>,
+    /// Primary keys by row ID
+    row_keys: HashMap<u64, Vec<u8>>,
+    /// Next row ID to assign
+    next_row_id: u64,
+}
+
+/// Number of lock stripes in `KeyLocks`
+const KEY_LOCK_STRIPES: usize = 64;
+
+/// Key-value pairs fetched at a time by [`Database::iter_prefix`] and
+/// [`Database::scan_range_rev`]
+const PREFIX_PAGE_SIZE: usize = 256;
+
+/// Smallest key greater than every key starting with `prefix`, or `None` if
+/// there is none (the prefix is empty or all `0xFF`)
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&b| b != 0xFF)?;
+    let mut successor = prefix[..=last].to_vec();
+    successor[last] += 1;
+    Some(successor)
+}
+
+/// Locks serializing read-then-write operations on the same key, striped
+/// by key hash so memory stays fixed however many keys are used
+struct KeyLocks {
+    stripes: Vec<Mutex<()>>,
+}
+
+impl Default for KeyLocks {
+    fn default() -> Self {
+        Self {
+            stripes: (0..KEY_LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+        }
+    }
+}
+
+impl KeyLocks {
+    /// Lock the stripe guarding `key`, shared with the keys of the same
+    /// stripe
+    ///
+    /// The stripes guard no data, so one poisoned by a panic is safe to
+    /// take over.
+    fn lock(&self, key: &[u8]) -> MutexGuard<'_, ()> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.stripes[hasher.finish() as usize % self.stripes.len()]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock every stripe, in order, for an operation spanning many keys
+    fn lock_all(&self) -> Vec<MutexGuard<'_, ()>> {
+        self.stripes
+            .iter()
+            .map(|stripe| stripe.lock().unwrap_or_else(PoisonError::into_inner))
+            .collect()
+    }
+}
+
+/// Locks serializing operations on the same key, one per key currently held
+///
+/// Unlike `KeyLocks`, unrelated keys never share a lock, so a holder may
+/// take the lock of another key. Entries are removed on release.
+#[derive(Default)]
+struct PerKeyLocks {
+    held: Mutex<HashSet<Vec<u8>>>,
+    released: Condvar,
+}
+
+impl PerKeyLocks {
+    /// Lock `key`, waiting while another caller holds it
+    ///
+    /// The set guards no data beyond its own entries, so one poisoned by a
+    /// panic is safe to take over.
+    fn lock(&self, key: &[u8]) -> PerKeyGuard<'_> {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+        while held.contains(key) {
+            held = self
+                .released
+                .wait(held)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        held.insert(key.to_vec());
+        PerKeyGuard {
+            locks: self,
+            key: key.to_vec(),
+        }
+    }
+}
+
+/// Holds a key locked in `PerKeyLocks` until dropped
+struct PerKeyGuard<'a> {
+    locks: &'a PerKeyLocks,
+    key: Vec<u8>,
+}
+
+impl Drop for PerKeyGuard<'_> {
+    fn drop(&mut self) {
+        self.locks
+            .held
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.key);
+        self.locks.released.notify_all();
+    }
+}
+
+/// How `Database::write_value` treats an existing value
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WriteMode {
+    /// Replace it without reading it
+    Overwrite,
+    /// Replace it and return it
+    ReturnPrevious,
+    /// Keep it and return it, writing only if the key is absent
+    InsertIfAbsent,
 }
 
 /// Inner database state
 struct DatabaseInner {
-    /// Storage backend
-    storage: StorageBackend,
+    /// Key-value store holding the records
+    backend: Arc<dyn KvBackend>,
+    /// The LSM engine behind `backend`, for persistent databases
+    engine: Option<Arc<StorageEngine>>,
     /// Index manager for secondary indexes
     indexes: RwLock<IndexManager>,
     /// MVCC transaction manager (v0.5.0+)
     transaction_manager: Option<Arc<TransactionManager>>,
+    /// Optional observer for operation callbacks
+    observer: RwLock<Option<Arc<dyn DbObserver>>>,
+    /// Operator used by `merge` to fold operands into values
+    merge_operator: RwLock<Option<Arc<dyn MergeOperator>>>,
+    /// Indexes maintained automatically from written records
+    auto_indexes: Mutex<AutoIndexes>,
+    /// Per-key critical sections for `get_or_insert_with`
+    key_locks: PerKeyLocks,
+    /// Per-key critical sections making a non-transactional write, the
+    /// previous value it reads and its automatic index update atomic
+    write_locks: KeyLocks,
+    /// Input limits checked by `validate_key`
+    limits: RwLock<ResourceLimits>,
+    /// Counters reported by `metrics`
+    metrics: MetricsCounters,
+}
+
+impl DatabaseInner {
+    /// State of a newly opened database: no observer, merge operator or
+    /// automatic indexes, and default limits
+    fn new(
+        backend: Arc<dyn KvBackend>,
+        engine: Option<Arc<StorageEngine>>,
+        indexes: IndexManager,
+        transaction_manager: Arc<TransactionManager>,
+    ) -> Self {
+        Self {
+            backend,
+            engine,
+            indexes: RwLock::new(indexes),
+            transaction_manager: Some(transaction_manager),
+            observer: RwLock::new(None),
+            merge_operator: RwLock::new(None),
+            auto_indexes: Mutex::new(AutoIndexes::default()),
+            key_locks: PerKeyLocks::default(),
+            write_locks: KeyLocks::default(),
+            limits: RwLock::new(ResourceLimits::default()),
+            metrics: MetricsCounters::default(),
+        }
+    }
 }
 
 /// The main database handle.
@@ -180,16 +365,19 @@ impl Database {
         let path_ref = path.as_ref();
         info!(path = ?path_ref, "Opening RustLite database");
 
-        let engine = StorageEngine::open(path)?;
-        let mvcc_storage = Arc::new(MVCCStorage::new());
+        let engine = Arc::new(StorageEngine::open(path)?);
+        let mvcc_storage = Arc::new(MVCCStorage::with_base(engine.clone()));
         let tx_manager = TransactionManager::new(mvcc_storage);
 
+        let indexes = Self::recovered_indexes(&engine)?;
+
         Ok(Database {
-            inner: Arc::new(DatabaseInner {
-                storage: StorageBackend::Persistent(engine),
-                indexes: RwLock::new(IndexManager::new()),
-                transaction_manager: Some(tx_manager),
-            }),
+            inner: Arc::new(DatabaseInner::new(
+                engine.clone(),
+                Some(engine),
+                indexes,
+                tx_manager,
+            )),
         })
     }
 
@@ -200,19 +388,32 @@ impl Database {
     /// * `path` - Directory path where database files will be stored
     /// * `config` - Storage configuration options
     pub fn open_with_config<P: AsRef<Path>>(path: P, config: StorageConfig) -> Result<Self> {
-        let engine = StorageEngine::open_with_config(path, config)?;
-        let mvcc_storage = Arc::new(MVCCStorage::new());
+        let engine = Arc::new(StorageEngine::open_with_config(path, config)?);
+        let mvcc_storage = Arc::new(MVCCStorage::with_base(engine.clone()));
         let tx_manager = TransactionManager::new(mvcc_storage);
 
+        let indexes = Self::recovered_indexes(&engine)?;
+
         Ok(Database {
-            inner: Arc::new(DatabaseInner {
-                storage: StorageBackend::Persistent(engine),
-                indexes: RwLock::new(IndexManager::new()),
-                transaction_manager: Some(tx_manager),
-            }),
+            inner: Arc::new(DatabaseInner::new(
+                engine.clone(),
+                Some(engine),
+                indexes,
+                tx_manager,
+            )),
         })
     }
 
+    /// Recreates the indexes whose definitions the engine recovered from
+    /// its WAL. They start out empty.
+    fn recovered_indexes(engine: &StorageEngine) -> Result<IndexManager> {
+        let mut indexes = IndexManager::new();
+        for (name, index_type) in engine.indexes()? {
+            indexes.create_index(&name, index_type)?;
+        }
+        Ok(indexes)
+    }
+
     /// Creates an in-memory database.
     ///
     /// Data is stored only in memory and will be lost when the database
@@ -232,17 +433,45 @@ impl Database {
         info!("Creating in-memory RustLite database");
 
         let mvcc_storage = Arc::new(MVCCStorage::new());
-        let tx_manager = TransactionManager::new(mvcc_storage);
+        let tx_manager = TransactionManager::new(Arc::clone(&mvcc_storage));
+        let backend = MemoryBackend::new(mvcc_storage, Arc::clone(&tx_manager));
 
         Ok(Database {
-            inner: Arc::new(DatabaseInner {
-                storage: StorageBackend::Memory(RwLock::new(HashMap::new())),
-                indexes: RwLock::new(IndexManager::new()),
-                transaction_manager: Some(tx_manager),
-            }),
+            inner: Arc::new(DatabaseInner::new(
+                Arc::new(backend),
+                None,
+                IndexManager::new(),
+                tx_manager,
+            )),
         })
     }
 
+    /// Creates a database on top of a custom key-value backend.
+    ///
+    /// Indexes, queries and transactions work as with the built-in
+    /// backends; committed transactions are written through to `backend`.
+    /// Features of the persistent engine, such as checkpoints, snapshots and
+    /// SSTable statistics, behave as for an in-memory database. See
+    /// [`KvBackend`] for an example.
+    pub fn with_backend(backend: Box<dyn KvBackend>) -> Self {
+        info!("Creating RustLite database on a custom backend");
+
+        let backend: Arc<dyn KvBackend> = Arc::from(backend);
+        let mvcc_storage = Arc::new(MVCCStorage::with_base(Arc::new(BackendBase(Arc::clone(
+            &backend,
+        )))));
+        let tx_manager = TransactionManager::new(mvcc_storage);
+
+        Database {
+            inner: Arc::new(DatabaseInner::new(
+                backend,
+                None,
+                IndexManager::new(),
+                tx_manager,
+            )),
+        }
+    }
+
     /// Creates a new in-memory database (alias for `in_memory()`).
     ///
     /// For backward compatibility with v0.1.0.
@@ -263,6 +492,11 @@ impl Database {
     /// * `key` - The key to insert
     /// * `value` - The value to associate with the key
     ///
+    /// Keys and values can be anything that views as bytes, such as
+    /// `&[u8]`, `Vec<u8>`, `&str` or `String`. An empty value is stored
+    /// like any other: it reads back as `Some(vec![])`, never as a deleted
+    /// key.
+    ///
     /// # Examples
     ///
     /// ```rust,no_run
@@ -270,25 +504,129 @@ impl Database {
     ///
     /// let db = Database::open("./data")?;
     /// db.put(b"name", b"Alice")?;
-    /// db.put(b"name", b"Bob")?; // Updates the value
+    /// db.put("name", "Bob")?; // Updates the value
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(
+        skip(self, key, value),
+        fields(key_len = key.as_ref().len(), value_len = value.as_ref().len())
+    )]
+    pub fn put(&self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Result<()> {
+        self.write_value(key.as_ref(), value.as_ref(), WriteMode::Overwrite)?;
+        Ok(())
+    }
+
+    /// Stores a key-value pair and returns the value it replaced.
+    ///
+    /// Returns `None` if the key had no value. No other `put`, `delete`,
+    /// `delete_range` or `merge` can run between reading the previous value
+    /// and writing the new one, so concurrent callers never both see the
+    /// same previous value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// assert_eq!(db.put_returning_previous(b"counter", b"1")?, None);
+    /// assert_eq!(
+    ///     db.put_returning_previous(b"counter", b"2")?,
+    ///     Some(b"1".to_vec())
+    /// );
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    #[instrument(skip(self, key, value), fields(key_len = key.len(), value_len = value.len()))]
-    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+    #[instrument(
+        skip(self, key, value),
+        fields(key_len = key.as_ref().len(), value_len = value.as_ref().len())
+    )]
+    pub fn put_returning_previous(
+        &self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<Option<Vec<u8>>> {
+        self.write_value(key.as_ref(), value.as_ref(), WriteMode::ReturnPrevious)
+    }
+
+    /// Stores a key-value pair as `mode` directs, returning the previous
+    /// value unless `mode` is `Overwrite`.
+    ///
+    /// The previous value is read under the key's write lock, which every
+    /// non-transactional write holds, so it cannot change before the new
+    /// value is written.
+    fn write_value(&self, key: &[u8], value: &[u8], mode: WriteMode) -> Result<Option<Vec<u8>>> {
         // Security: Validate inputs
-        security::validate_key(key)?;
+        self.validate_key(key)?;
         security::validate_value(value)?;
 
         debug!("Writing key-value pair");
 
-        match &self.inner.storage {
-            StorageBackend::Memory(store) => {
-                let mut store = store.write().map_err(|_| Error::LockPoisoned)?;
-                store.insert(key.to_vec(), value.to_vec());
-                Ok(())
-            }
-            StorageBackend::Persistent(engine) => engine.put(key, value),
+        let start = Instant::now();
+
+        let write = self.inner.write_locks.lock(key);
+        let previous = if mode != WriteMode::Overwrite || self.has_auto_indexes()? {
+            self.load(key)?
+        } else {
+            None
+        };
+        if mode == WriteMode::InsertIfAbsent && previous.is_some() {
+            return Ok(previous);
+        }
+
+        self.inner.backend.put(key, value)?;
+        self.write_through(key)?;
+
+        self.update_auto_indexes(key, previous.as_deref(), Some(value))?;
+        drop(write);
+
+        self.inner.metrics.record_put(key.len() + value.len());
+
+        if let Some(observer) = self.observer() {
+            observer.on_put(key.len(), value.len(), start.elapsed());
+        }
+
+        Ok(previous)
+    }
+
+    /// Returns the value of `key`, computing and storing it with `f` if the
+    /// key is absent.
+    ///
+    /// Concurrent calls for the same key are serialized, so `f` runs at most
+    /// once and every caller sees the same value. `f` may itself call
+    /// `get_or_insert_with` for other keys. Plain [`put`](Database::put) and
+    /// [`delete`](Database::delete) calls do not wait for `f`; if one stores
+    /// a value for the key while `f` runs, that value is kept and returned
+    /// instead of `f`'s. If `f` panics, nothing is stored and later calls run
+    /// their own closure.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// let value = db.get_or_insert_with(b"config", || b"defaults".to_vec())?;
+    /// assert_eq!(value, b"defaults".to_vec());
+    ///
+    /// // Already present, so the closure is not called
+    /// let value = db.get_or_insert_with(b"config", || unreachable!())?;
+    /// assert_eq!(value, b"defaults".to_vec());
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn get_or_insert_with<F>(&self, key: &[u8], f: F) -> Result<Vec<u8>>
+    where
+        F: FnOnce() -> Vec<u8>,
+    {
+        self.validate_key(key)?;
+
+        let _guard = self.inner.key_locks.lock(key);
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
         }
+
+        let value = f();
+        let existing = self.write_value(key, &value, WriteMode::InsertIfAbsent)?;
+        Ok(existing.unwrap_or(value))
     }
 
     /// Retrieves a value by key.
@@ -313,22 +651,81 @@ impl Database {
     /// }
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    #[instrument(skip(self, key), fields(key_len = key.len()))]
-    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+    #[instrument(skip(self, key), fields(key_len = key.as_ref().len()))]
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Result<Option<Vec<u8>>> {
+        let key = key.as_ref();
+
         // Security: Validate inputs
-        security::validate_key(key)?;
+        self.validate_key(key)?;
 
         debug!("Reading key");
 
-        match &self.inner.storage {
-            StorageBackend::Memory(store) => {
-                let store = store.read().map_err(|_| Error::LockPoisoned)?;
-                Ok(store.get(key).cloned())
-            }
-            StorageBackend::Persistent(engine) => engine.get(key),
+        let start = Instant::now();
+
+        let value = self.load(key)?;
+
+        self.inner.metrics.record_get(value.is_some());
+        if let Some(observer) = self.observer() {
+            observer.on_get(key.len(), value.is_some(), start.elapsed());
+        }
+
+        Ok(value)
+    }
+
+    /// Retrieves the value a key had at a past timestamp.
+    ///
+    /// `ts` is usually a commit timestamp returned by
+    /// [`Transaction::commit`]. Only versions still retained by the MVCC
+    /// layer can be read; once garbage collection (or a later direct write)
+    /// has dropped the version visible at `ts`, this returns
+    /// `Error::Transaction("version garbage-collected")`. On a persistent
+    /// database, keys with no retained history, such as ones only ever
+    /// written with [`put`](Database::put), fail with
+    /// `Error::Transaction("no version history for key")`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    ///
+    /// let mut txn = db.begin()?;
+    /// txn.put(b"price".to_vec(), b"10".to_vec())?;
+    /// let before = txn.commit()?;
+    ///
+    /// let mut txn = db.begin()?;
+    /// txn.put(b"price".to_vec(), b"12".to_vec())?;
+    /// txn.commit()?;
+    ///
+    /// assert_eq!(db.get_as_of(b"price", before)?, Some(b"10".to_vec()));
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn get_as_of(&self, key: &[u8], ts: Timestamp) -> Result<Option<Vec<u8>>> {
+        self.validate_key(key)?;
+
+        match self.inner.transaction_manager {
+            Some(ref manager) => manager.read_as_of(key, ts),
+            None => Err(Error::Transaction(
+                "Transaction support not initialized".into(),
+            )),
         }
     }
 
+    /// Makes a direct write to `key` visible to transactions that begin
+    /// afterwards, even if an earlier transaction wrote the key.
+    fn write_through(&self, key: &[u8]) -> Result<()> {
+        match self.inner.transaction_manager {
+            Some(ref manager) => manager.write_through(vec![key.to_vec()]),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads a value from the storage backend without validation or callbacks.
+    fn load(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.inner.backend.get(key)
+    }
+
     /// Deletes a key-value pair.
     ///
     /// Returns `true` if the key existed and was deleted, `false` otherwise.
@@ -348,27 +745,95 @@ impl Database {
     /// assert_eq!(db.get(b"temp")?, None);
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    #[instrument(skip(self, key), fields(key_len = key.len()))]
-    pub fn delete(&self, key: &[u8]) -> Result<bool> {
+    #[instrument(skip(self, key), fields(key_len = key.as_ref().len()))]
+    pub fn delete(&self, key: impl AsRef<[u8]>) -> Result<bool> {
+        let key = key.as_ref();
+
         // Security: Validate inputs
-        security::validate_key(key)?;
+        self.validate_key(key)?;
 
         debug!("Deleting key");
 
-        match &self.inner.storage {
-            StorageBackend::Memory(store) => {
-                let mut store = store.write().map_err(|_| Error::LockPoisoned)?;
-                Ok(store.remove(key).is_some())
-            }
-            StorageBackend::Persistent(engine) => {
-                // Check if key exists before deleting
-                let existed = engine.get(key)?.is_some();
-                if existed {
-                    engine.delete(key)?;
-                }
-                Ok(existed)
+        let _write = self.inner.write_locks.lock(key);
+
+        // Check if key exists before deleting
+        let previous = self.inner.backend.get(key)?;
+        if previous.is_some() {
+            self.inner.backend.delete(key)?;
+            self.write_through(key)?;
+        }
+
+        if let Some(previous) = &previous {
+            self.update_auto_indexes(key, Some(previous), None)?;
+        }
+
+        Ok(previous.is_some())
+    }
+
+    /// Deletes all keys in the half-open range `[start, end)`.
+    ///
+    /// Persistent databases record a single range tombstone instead of one
+    /// tombstone per key, so deleting a large range costs one WAL record.
+    /// Keys written after the call are not affected. An empty range (where
+    /// `start >= end`) is a no-op.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.put(b"log:001", b"a")?;
+    /// db.put(b"log:002", b"b")?;
+    /// db.put(b"user:1", b"alice")?;
+    ///
+    /// db.delete_range(b"log:", b"log;")?;
+    /// assert_eq!(db.get(b"log:001")?, None);
+    /// assert_eq!(db.get(b"user:1")?, Some(b"alice".to_vec()));
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(skip(self, start, end), fields(start_len = start.len(), end_len = end.len()))]
+    pub fn delete_range(&self, start: &[u8], end: &[u8]) -> Result<()> {
+        // Security: Validate inputs
+        self.validate_key(start)?;
+        self.validate_key(end)?;
+
+        debug!("Deleting key range");
+
+        if start >= end {
+            return Ok(());
+        }
+
+        let _writes = self.inner.write_locks.lock_all();
+
+        // Only records with a row ID can have automatic index entries
+        let mut indexed = Vec::new();
+        let in_range: Vec<Vec<u8>> = self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
+            .row_ids
+            .keys()
+            .filter(|k| k.as_slice() >= start && k.as_slice() < end)
+            .cloned()
+            .collect();
+        for key in in_range {
+            if let Some(value) = self.load(&key)? {
+                indexed.push((key, value));
             }
         }
+
+        self.inner.backend.delete_range(start, end)?;
+        if let Some(ref manager) = self.inner.transaction_manager {
+            manager.write_through_range(start, Some(end))?;
+        }
+
+        for (key, value) in indexed {
+            self.update_auto_indexes(&key, Some(&value), None)?;
+        }
+
+        Ok(())
     }
 
     /// Forces all pending writes to disk.
@@ -387,243 +852,475 @@ impl Database {
     /// # Ok::<(), rustlite::Error>(())
     /// ```
     pub fn sync(&self) -> Result<()> {
-        match &self.inner.storage {
-            StorageBackend::Memory(_) => Ok(()),
-            StorageBackend::Persistent(engine) => engine.sync(),
-        }
+        self.inner.backend.sync()
     }
 
-    /// Returns whether this is a persistent database.
-    pub fn is_persistent(&self) -> bool {
-        matches!(&self.inner.storage, StorageBackend::Persistent(_))
-    }
-
-    // =========================================================================
-    // Index Operations (v0.3.0+)
-    // =========================================================================
-
-    /// Creates a new index with the specified name and type.
-    ///
-    /// # Arguments
+    /// Pauses compaction until [`resume_compaction`](Database::resume_compaction)
+    /// is called.
     ///
-    /// * `name` - Unique name for the index
-    /// * `index_type` - Type of index (BTree for range queries, Hash for fast lookups)
+    /// Level 0 SSTables accumulate while paused, which avoids repeatedly
+    /// compacting during bulk loads. A compaction already running completes
+    /// before this returns. No-op for in-memory databases.
     ///
     /// # Examples
     ///
-    /// ```rust
-    /// use rustlite::{Database, IndexType};
+    /// ```rust,no_run
+    /// use rustlite::Database;
     ///
-    /// let db = Database::in_memory()?;
-    /// db.create_index("users_by_name", IndexType::BTree)?;
-    /// db.create_index("sessions", IndexType::Hash)?;
+    /// let db = Database::open("./data")?;
+    /// db.pause_compaction()?;
+    /// for i in 0..10_000u32 {
+    ///     db.put(&i.to_be_bytes(), b"value")?;
+    /// }
+    /// db.resume_compaction();
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    #[instrument(skip(self), fields(name = %name, index_type = ?index_type))]
-    pub fn create_index(&self, name: &str, index_type: IndexType) -> Result<()> {
-        // Security: Validate index name
-        security::validate_index_name(name)?;
-
-        info!("Creating index");
-
-        let mut indexes = self
-            .inner
-            .indexes
-            .write()
-            .map_err(|_| Error::LockPoisoned)?;
-        indexes.create_index(name, index_type)
+    pub fn pause_compaction(&self) -> Result<()> {
+        match &self.inner.engine {
+            Some(engine) => engine.pause_compaction(),
+            None => Ok(()),
+        }
     }
 
-    /// Drops an index by name.
+    /// Takes a snapshot of a live database into `dest`.
     ///
-    /// Returns `true` if the index existed and was dropped.
+    /// The memtable is flushed and the WAL synced first, and writes,
+    /// flushes and compaction wait while the files are copied, so the
+    /// snapshot is consistent even while other threads keep writing. The
+    /// returned metadata records the sequence number of the latest write
+    /// it captures. Restore it with [`SnapshotManager::restore_snapshot`].
+    ///
+    /// Fails with [`Error::InvalidOperation`] for in-memory databases.
     ///
     /// # Examples
     ///
-    /// ```rust
-    /// use rustlite::{Database, IndexType};
+    /// ```rust,no_run
+    /// use rustlite::{Database, SnapshotManager};
     ///
-    /// let db = Database::in_memory()?;
-    /// db.create_index("temp_index", IndexType::Hash)?;
-    /// assert!(db.drop_index("temp_index")?);
-    /// assert!(!db.drop_index("temp_index")?); // Already dropped
+    /// let db = Database::open("./data")?;
+    /// db.put(b"key", b"value")?;
+    /// let snapshot = db.create_snapshot("./backup")?;
+    ///
+    /// SnapshotManager::new("./backup")?.restore_snapshot(&snapshot, "./restored")?;
+    /// let restored = Database::open("./restored")?;
+    /// assert_eq!(restored.get(b"key")?, Some(b"value".to_vec()));
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    pub fn drop_index(&self, name: &str) -> Result<bool> {
-        let mut indexes = self
-            .inner
-            .indexes
-            .write()
-            .map_err(|_| Error::LockPoisoned)?;
-        indexes.drop_index(name)
+    pub fn create_snapshot(&self, dest: impl AsRef<Path>) -> Result<SnapshotMeta> {
+        match &self.inner.engine {
+            Some(engine) => engine.with_files_frozen(|sequence| {
+                SnapshotManager::new(engine.path())?.create_snapshot_at(dest, sequence)
+            }),
+            None => Err(Error::InvalidOperation(
+                "snapshots require a persistent database".to_string(),
+            )),
+        }
     }
 
-    /// Inserts a key-value pair into a named index.
+    /// Resumes compaction paused by [`pause_compaction`](Database::pause_compaction).
     ///
-    /// The value is typically a record ID or offset pointing to the actual data.
+    /// The background compaction thread catches up on the level 0 SSTables
+    /// that built up while paused.
+    pub fn resume_compaction(&self) {
+        if let Some(engine) = &self.inner.engine {
+            engine.resume_compaction();
+        }
+    }
+
+    /// Compacts only the SSTables overlapping the inclusive range
+    /// `[start, end]`.
     ///
-    /// # Examples
+    /// Reclaims the space of a deleted range right away, without waiting
+    /// for level 0 to fill up: the tombstones of keys in the range are
+    /// dropped along with the data they hid. SSTables holding only keys
+    /// outside the range are left untouched. Returns the statistics of this
+    /// compaction; all zero if nothing overlapped the range. Fails with
+    /// [`Error::InvalidOperation`] while compaction is paused. No-op for
+    /// in-memory databases.
     ///
-    /// ```rust
-    /// use rustlite::{Database, IndexType};
+    /// # Examples
     ///
-    /// let db = Database::in_memory()?;
-    /// db.create_index("names", IndexType::BTree)?;
+    /// ```rust,no_run
+    /// use rustlite::Database;
     ///
-    /// // Index "alice" pointing to record ID 100
-    /// db.index_insert("names", b"alice", 100)?;
-    /// db.index_insert("names", b"bob", 101)?;
+    /// let db = Database::open("./data")?;
+    /// db.delete_range(b"log:", b"log;")?;
+    /// let stats = db.compact_range(b"log:", b"log;")?;
+    /// println!("dropped {} entries", stats.entries_removed);
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    pub fn index_insert(&self, name: &str, key: &[u8], value: u64) -> Result<()> {
-        let mut indexes = self
-            .inner
-            .indexes
-            .write()
-            .map_err(|_| Error::LockPoisoned)?;
-        indexes.insert(name, key, value)
+    pub fn compact_range(&self, start: &[u8], end: &[u8]) -> Result<CompactionStats> {
+        match &self.inner.engine {
+            Some(engine) => engine.compact_range(start, end),
+            None => Ok(CompactionStats::default()),
+        }
     }
 
-    /// Finds all values matching a key in a named index.
+    /// Closes the database, reporting any error from the final flush.
+    ///
+    /// For persistent databases this flushes the memtable, syncs the WAL
+    /// and rewrites the manifest. Dropping the last handle instead only
+    /// stops the background workers: unflushed writes are replayed from the
+    /// WAL on the next open, and under `SyncMode::Async` or `SyncMode::None`
+    /// writes not yet synced may be lost.
+    ///
+    /// Fails with [`Error::InvalidOperation`] if other clones of this handle
+    /// or transactions begun on it are still alive; the database stays open
+    /// for them.
     ///
     /// # Examples
     ///
-    /// ```rust
-    /// use rustlite::{Database, IndexType};
+    /// ```rust,no_run
+    /// use rustlite::Database;
     ///
-    /// let db = Database::in_memory()?;
-    /// db.create_index("names", IndexType::Hash)?;
-    /// db.index_insert("names", b"alice", 100)?;
+    /// let db = Database::open("./data")?;
+    /// db.put(b"key", b"value")?;
+    /// db.close()?;
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn close(self) -> Result<()> {
+        let in_use = || Error::InvalidOperation("database still in use".to_string());
+        let inner = Arc::try_unwrap(self.inner).map_err(|_| in_use())?;
+        let DatabaseInner {
+            backend,
+            engine,
+            transaction_manager,
+            ..
+        } = inner;
+
+        // Live transactions hold the engine through the transaction manager
+        drop(transaction_manager);
+        match engine {
+            Some(engine) => {
+                drop(backend);
+                Arc::try_unwrap(engine).map_err(|_| in_use())?.close()
+            }
+            None => backend.sync(),
+        }
+    }
+
+    /// Makes the latest write to `key` durable.
     ///
-    /// let results = db.index_find("names", b"alice")?;
-    /// assert_eq!(results, vec![100]);
+    /// Under `SyncMode::Async` or `SyncMode::None` writes may sit in the
+    /// WAL buffer. This waits until the write to `key` is on disk, syncing
+    /// the WAL only if it is not already, so a caller can make individual
+    /// operations durable without paying for an fsync on every write.
+    /// No-op for in-memory databases.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use rustlite::{Database, StorageConfig, SyncMode};
+    ///
+    /// let config = StorageConfig {
+    ///     sync_mode: SyncMode::Async,
+    ///     ..Default::default()
+    /// };
+    /// let db = Database::open_with_config("./data", config)?;
+    /// db.put(b"order:1", b"paid")?;
+    /// db.sync_key(b"order:1")?; // Durable before acknowledging the order
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    pub fn index_find(&self, name: &str, key: &[u8]) -> Result<Vec<u64>> {
-        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
-        indexes.find(name, key)
+    pub fn sync_key(&self, key: impl AsRef<[u8]>) -> Result<()> {
+        self.inner.backend.sync_key(key.as_ref())
     }
 
-    /// Removes a key from a named index.
+    /// Returns true if the latest write to `key` is not yet durable.
     ///
-    /// Returns `true` if the key existed and was removed.
-    pub fn index_remove(&self, name: &str, key: &[u8]) -> Result<bool> {
+    /// Under `SyncMode::Sync` every write is synced before it returns, so
+    /// this is always false. Under `SyncMode::Async` or `SyncMode::None` it
+    /// tells whether a [`sync_key`](Database::sync_key) call is still needed
+    /// for the write to survive a crash. Always false for in-memory
+    /// databases, which have nothing to sync.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use rustlite::{Database, StorageConfig, SyncMode};
+    ///
+    /// let config = StorageConfig {
+    ///     sync_mode: SyncMode::None,
+    ///     ..Default::default()
+    /// };
+    /// let db = Database::open_with_config("./data", config)?;
+    /// db.put(b"order:1", b"paid")?;
+    /// assert!(db.needs_sync(b"order:1")?);
+    /// db.sync_key(b"order:1")?;
+    /// assert!(!db.needs_sync(b"order:1")?);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn needs_sync(&self, key: impl AsRef<[u8]>) -> Result<bool> {
+        self.inner.backend.needs_sync(key.as_ref())
+    }
+
+    /// Removes all keys from the database.
+    ///
+    /// For persistent databases the WAL is truncated, all SSTables are
+    /// deleted and the manifest is reset, so the cleared data does not come
+    /// back after a restart. Concurrent readers see either the pre-clear or
+    /// the post-clear value for a key, never a partially cleared state. The
+    /// handle remains usable afterwards. Indexes created with
+    /// [`define_index`](Database::define_index) are emptied; other secondary
+    /// indexes are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.put(b"key", b"value")?;
+    /// db.clear()?;
+    /// assert_eq!(db.get(b"key")?, None);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(skip(self))]
+    pub fn clear(&self) -> Result<()> {
+        info!("Clearing all data");
+
+        let _writes = self.inner.write_locks.lock_all();
+
+        self.inner.backend.clear()?;
+        if let Some(ref manager) = self.inner.transaction_manager {
+            manager.write_through_range(&[], None)?;
+        }
+
+        let mut auto = self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?;
+
         let mut indexes = self
             .inner
             .indexes
             .write()
             .map_err(|_| Error::LockPoisoned)?;
-        indexes.remove(name, key)
+        for name in auto.extractors.keys() {
+            if let Some(index) = indexes.get_index_mut(name) {
+                index.clear();
+            }
+        }
+        auto.row_ids.clear();
+        auto.row_keys.clear();
+
+        Ok(())
     }
 
-    /// Lists all index names in the database.
+    /// Returns the live key-value pairs with keys in `[start, end)`, in
+    /// ascending key order.
+    ///
+    /// For persistent databases this performs a full merge scan over the
+    /// memtable and every SSTable.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use rustlite::{Database, IndexType};
+    /// use rustlite::Database;
     ///
     /// let db = Database::in_memory()?;
-    /// db.create_index("idx1", IndexType::BTree)?;
-    /// db.create_index("idx2", IndexType::Hash)?;
+    /// db.put(b"log:001", b"a")?;
+    /// db.put(b"log:002", b"b")?;
+    /// db.put(b"user:1", b"alice")?;
     ///
-    /// let names = db.list_indexes()?;
-    /// assert_eq!(names.len(), 2);
+    /// let logs = db.scan_range(b"log:", b"log;")?;
+    /// assert_eq!(logs.len(), 2);
+    /// assert_eq!(logs[0].0, b"log:001");
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    pub fn list_indexes(&self) -> Result<Vec<String>> {
-        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
-        Ok(indexes
-            .list_indexes()
-            .iter()
-            .map(|s| s.to_string())
-            .collect())
+    pub fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.inner.backend.scan_range(start, end)
     }
 
-    /// Gets information about all indexes.
+    /// Returns the live keys starting with `prefix`, in ascending key order.
+    ///
+    /// Cheaper than scanning key-value pairs when only the keys are needed,
+    /// such as listing every `order:` key: deleted keys are left out, but
+    /// values are never copied. For persistent databases this still merges
+    /// the memtable and every SSTable.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use rustlite::{Database, IndexType};
+    /// use rustlite::Database;
     ///
     /// let db = Database::in_memory()?;
-    /// db.create_index("users", IndexType::BTree)?;
-    /// db.index_insert("users", b"alice", 1)?;
+    /// db.put(b"order:1", b"{...}")?;
+    /// db.put(b"order:2", b"{...}")?;
+    /// db.put(b"user:1", b"alice")?;
+    /// db.delete(b"order:1")?;
     ///
-    /// for info in db.index_info()? {
-    ///     println!("Index: {}, Type: {}, Entries: {}",
-    ///              info.name, info.index_type, info.entry_count);
-    /// }
+    /// assert_eq!(db.scan_keys(b"order:")?, vec![b"order:2".to_vec()]);
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    pub fn index_info(&self) -> Result<Vec<IndexInfo>> {
-        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
-        Ok(indexes.index_info())
+    pub fn scan_keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        self.inner.backend.scan_keys(prefix)
     }
 
-    /// Executes a SQL-like query and returns results (v0.4.0+).
-    ///
-    /// Parses, plans, and executes a SELECT query against in-memory data.
-    /// Currently supports: SELECT, FROM, WHERE, ORDER BY, LIMIT, JOIN.
+    /// Iterates over the live key-value pairs whose keys start with
+    /// `prefix`, in ascending key order.
     ///
-    /// # Arguments
+    /// Unlike [`scan_range`], pairs are fetched a page at a time as the
+    /// iterator advances, and for persistent databases each page only reads
+    /// the memtable entries and SSTable blocks within the prefix. Other
+    /// backends are asked for the key range covering the prefix, so keys
+    /// outside it are never scanned. Writes made while iterating may or may
+    /// not be seen. If reading a page fails, the error is yielded and
+    /// iteration ends.
     ///
-    /// * `sql` - SQL-like query string
-    /// * `context` - Execution context with data and indexes
+    /// [`scan_range`]: Database::scan_range
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use rustlite::{Database, ExecutionContext, Row, Column, Value};
-    /// use std::collections::HashMap;
+    /// use rustlite::Database;
     ///
     /// let db = Database::in_memory()?;
+    /// db.put(b"session:1", b"a")?;
+    /// db.put(b"session:2", b"b")?;
+    /// db.put(b"user:1", b"alice")?;
     ///
-    /// // Prepare test data
-    /// let mut context = ExecutionContext::new();
-    /// context.data.insert("users".to_string(), vec![
-    ///     Row {
-    ///         columns: vec![
-    ///             Column { name: "name".to_string(), alias: None },
-    ///             Column { name: "age".to_string(), alias: None },
-    ///         ],
-    ///         values: vec![Value::String("Alice".to_string()), Value::Integer(30)],
-    ///     },
-    /// ]);
-    ///
-    /// let results = db.query("SELECT name FROM users WHERE age > 18", context)?;
-    /// assert_eq!(results.len(), 1);
+    /// let sessions: Vec<_> = db.iter_prefix(b"session:")?.collect::<Result<_, _>>()?;
+    /// assert_eq!(sessions.len(), 2);
+    /// assert_eq!(sessions[0].0, b"session:1");
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    #[instrument(skip(self, sql, context), fields(sql_len = sql.len()))]
-    pub fn query(&self, sql: &str, context: ExecutionContext) -> Result<Vec<Row>> {
-        // Security: Validate query length
-        security::validate_query(sql)?;
-
-        debug!(sql = %sql, "Executing query");
-
-        // Parse the SQL
-        let mut parser =
-            Parser::new(sql).map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
-        let query = parser
-            .parse()
-            .map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
+    pub fn iter_prefix(
+        &self,
+        prefix: &[u8],
+    ) -> Result<impl Iterator<Item = Result<(Vec<u8>, Vec<u8>)>>> {
+        let inner = Arc::clone(&self.inner);
+        let prefix = prefix.to_vec();
+        let mut page = VecDeque::new();
+        let mut after: Option<Vec<u8>> = None;
+        let mut exhausted = false;
+
+        Ok(std::iter::from_fn(move || loop {
+            if let Some(entry) = page.pop_front() {
+                return Some(Ok(entry));
+            }
+            if exhausted {
+                return None;
+            }
+            // Whatever happens, this is the last page unless it says otherwise
+            exhausted = true;
+            let entries = match &inner.engine {
+                Some(engine) => engine
+                    .scan_prefix_page(&prefix, after.as_deref(), PREFIX_PAGE_SIZE)
+                    .map(|fetched| {
+                        exhausted = fetched.next.is_none();
+                        after = fetched.next;
+                        fetched.entries
+                    }),
+                None => match prefix_successor(&prefix) {
+                    Some(end) => inner.backend.scan_range(&prefix, &end),
+                    // No key bounds the prefix from above, so it reaches the
+                    // end of the keyspace
+                    None => inner.backend.entries().map(|entries| {
+                        entries
+                            .into_iter()
+                            .filter(|(key, _)| key.starts_with(&prefix))
+                            .collect()
+                    }),
+                },
+            };
+            match entries {
+                Ok(entries) => page.extend(entries),
+                Err(e) => return Some(Err(e)),
+            }
+        }))
+    }
 
-        // Plan the query
-        let planner = Planner::new();
-        let plan = planner
-            .plan(&query)
-            .map_err(|e| Error::InvalidInput(format!("Planning error: {}", e)))?;
+    /// Iterates over the live key-value pairs with keys in `[start, end)`,
+    /// in descending key order.
+    ///
+    /// Useful for "most recent first" reads over time-ordered keys, such as
+    /// fetching the last N entries before a key. Pairs are fetched a page at
+    /// a time from the end of the range, and for persistent databases each
+    /// page only reads the memtable entries and SSTable blocks it needs, so
+    /// taking the first few pairs of a large range stays cheap. Writes made
+    /// while iterating may or may not be seen. If reading a page fails, the
+    /// error is yielded and iteration ends.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.put(b"log:001", b"a")?;
+    /// db.put(b"log:002", b"b")?;
+    /// db.put(b"log:003", b"c")?;
+    ///
+    /// // The two most recent entries before log:003
+    /// let recent: Vec<_> = db
+    ///     .scan_range_rev(b"log:", b"log:003")?
+    ///     .take(2)
+    ///     .collect::<Result<_, _>>()?;
+    /// assert_eq!(recent[0].0, b"log:002");
+    /// assert_eq!(recent[1].0, b"log:001");
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn scan_range_rev(
+        &self,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<impl Iterator<Item = Result<(Vec<u8>, Vec<u8>)>>> {
+        let inner = Arc::clone(&self.inner);
+        let (start, end) = (start.to_vec(), end.to_vec());
+        let mut page = VecDeque::new();
+        let mut before: Option<Vec<u8>> = None;
+        let mut exhausted = false;
+
+        Ok(std::iter::from_fn(move || loop {
+            if let Some(entry) = page.pop_front() {
+                return Some(Ok(entry));
+            }
+            if exhausted {
+                return None;
+            }
+            // Whatever happens, this is the last page unless it says otherwise
+            exhausted = true;
+            let entries = match &inner.engine {
+                Some(engine) => engine
+                    .scan_range_rev_page(&start, &end, before.as_deref(), PREFIX_PAGE_SIZE)
+                    .map(|fetched| {
+                        exhausted = fetched.next.is_none();
+                        before = fetched.next;
+                        fetched.entries
+                    }),
+                None => inner.backend.scan_range(&start, &end).map(|mut entries| {
+                    entries.reverse();
+                    entries
+                }),
+            };
+            match entries {
+                Ok(entries) => page.extend(entries),
+                Err(e) => return Some(Err(e)),
+            }
+        }))
+    }
 
-        // Execute the query
-        let mut executor = Executor::new(context);
-        executor.execute(&plan)
+    /// Returns an approximate count of stored entries.
+    ///
+    /// For persistent databases this sums the memtable entry count and the
+    /// SSTable entry counts recorded in the manifest. It performs no disk
+    /// I/O, but keys that were overwritten or deleted after a flush are
+    /// counted more than once and tombstones are included. Use [`len`] for
+    /// an exact count.
+    ///
+    /// [`len`]: Database::len
+    pub fn approximate_len(&self) -> Result<u64> {
+        self.inner.backend.approximate_len()
     }
 
-    /// Prepares a SQL-like query for repeated execution (v0.4.0+).
+    /// Returns the exact number of live keys.
     ///
-    /// Parses and plans the query once, returning a reusable plan.
+    /// For persistent databases this performs a full merge scan over the
+    /// memtable and every SSTable, so its cost grows with the size of the
+    /// database. Prefer [`approximate_len`] when an estimate is enough.
+    ///
+    /// [`approximate_len`]: Database::approximate_len
     ///
     /// # Examples
     ///
@@ -631,69 +1328,104 @@ impl Database {
     /// use rustlite::Database;
     ///
     /// let db = Database::in_memory()?;
-    /// let plan = db.prepare("SELECT * FROM users WHERE age > 18")?;
-    /// // Plan can be executed multiple times with different contexts
+    /// db.put(b"a", b"1")?;
+    /// db.put(b"b", b"2")?;
+    /// db.delete(b"a")?;
+    /// assert_eq!(db.len()?, 1);
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    pub fn prepare(&self, sql: &str) -> Result<PhysicalPlan> {
-        let mut parser =
-            Parser::new(sql).map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
-        let query = parser
-            .parse()
-            .map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
-
-        let planner = Planner::new();
-        planner
-            .plan(&query)
-            .map_err(|e| Error::InvalidInput(format!("Planning error: {}", e)))
+    pub fn len(&self) -> Result<u64> {
+        self.inner.backend.len()
     }
 
-    /// Executes a prepared query plan with given context (v0.4.0+).
-    pub fn execute_plan(&self, plan: &PhysicalPlan, context: ExecutionContext) -> Result<Vec<Row>> {
-        let mut executor = Executor::new(context);
-        executor.execute(plan)
+    /// Returns true if the database contains no live keys.
+    ///
+    /// Has the same cost as [`len`](Database::len).
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
     }
 
-    // ===== Transaction Methods (v0.5.0+) =====
+    /// Returns the bytes on disk used by SSTables and the WAL.
+    ///
+    /// Cheaper than [`metrics`](Database::metrics) when only disk usage is
+    /// needed, e.g. for quota enforcement. Always 0 for in-memory databases.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.put(b"key", b"value")?;
+    /// assert_eq!(db.disk_usage()?, 0);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn disk_usage(&self) -> Result<u64> {
+        match &self.inner.engine {
+            Some(engine) => engine.disk_usage(),
+            None => Ok(0),
+        }
+    }
 
-    /// Begins a new MVCC transaction with the specified isolation level (v0.5.0+).
+    /// Returns how full the active memtable is, from 0.0 to 1.0.
     ///
-    /// Returns a Transaction handle that provides snapshot isolation and
-    /// ACID guarantees. Changes are buffered until commit.
+    /// The memtable is flushed to an SSTable when this reaches 1.0. Bulk
+    /// loaders can use it to pace writes or to call [`sync`](Database::sync)
+    /// at a convenient boundary instead. In-memory databases always return
+    /// 0.0.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use rustlite::{Database, IsolationLevel};
+    /// use rustlite::Database;
     ///
     /// let db = Database::in_memory()?;
+    /// assert_eq!(db.memtable_pressure()?, 0.0);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn memtable_pressure(&self) -> Result<f64> {
+        match &self.inner.engine {
+            Some(engine) => engine.memtable_pressure(),
+            None => Ok(0.0),
+        }
+    }
+
+    /// Flushes all buffered writes to SSTables and truncates the WAL.
     ///
-    /// // Start a transaction
-    /// let mut txn = db.begin_transaction(IsolationLevel::RepeatableRead)?;
+    /// Everything written before the call is persisted in SSTables, a
+    /// checkpoint record is written and the WAL segments it covers are
+    /// removed (or archived, if configured). Reopening afterwards replays
+    /// only writes made since the checkpoint, which keeps recovery time
+    /// predictable. In-memory databases return a zeroed result.
     ///
-    /// // Read and write within transaction
-    /// txn.put(b"key1".to_vec(), b"value1".to_vec())?;
-    /// txn.put(b"key2".to_vec(), b"value2".to_vec())?;
+    /// # Examples
     ///
-    /// // Commit changes
-    /// txn.commit()?;
+    /// ```rust,no_run
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::open("./data")?;
+    /// db.put(b"key", b"value")?;
+    /// let info = db.checkpoint()?;
+    /// println!("checkpointed sequence {}, removed {} WAL segments",
+    ///     info.sequence, info.segments_removed);
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    #[instrument(skip(self), fields(isolation = ?isolation))]
-    pub fn begin_transaction(&self, isolation: IsolationLevel) -> Result<Transaction> {
-        info!("Beginning transaction");
-        if let Some(ref manager) = self.inner.transaction_manager {
-            manager.begin(isolation)
-        } else {
-            Err(Error::Transaction(
-                "Transaction support not initialized".into(),
-            ))
+    pub fn checkpoint(&self) -> Result<CheckpointInfo> {
+        match &self.inner.engine {
+            Some(engine) => engine.checkpoint(),
+            None => Ok(CheckpointInfo {
+                sequence: 0,
+                segments_removed: 0,
+            }),
         }
     }
 
-    /// Begins a new transaction with default isolation level (RepeatableRead).
+    /// Returns record and transaction counts for the write-ahead log.
     ///
-    /// Convenience method equivalent to `begin_transaction(IsolationLevel::RepeatableRead)`.
+    /// After a crash, `transactions_incomplete` reports how many
+    /// transactions were started but never committed; recovery discards
+    /// their writes. In-memory databases have no WAL and return zeroed
+    /// stats.
     ///
     /// # Examples
     ///
@@ -701,19 +1433,24 @@ impl Database {
     /// use rustlite::Database;
     ///
     /// let db = Database::in_memory()?;
-    /// let mut txn = db.begin()?;
-    /// txn.put(b"key".to_vec(), b"value".to_vec())?;
-    /// txn.commit()?;
+    /// assert_eq!(db.wal_stats()?.total_records, 0);
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    pub fn begin(&self) -> Result<Transaction> {
-        self.begin_transaction(IsolationLevel::default())
+    pub fn wal_stats(&self) -> Result<RecoveryStats> {
+        match &self.inner.engine {
+            Some(engine) => engine.wal_stats(),
+            None => Ok(RecoveryStats::default()),
+        }
     }
 
-    /// Performs garbage collection on MVCC version chains (v0.5.0+).
+    /// Returns the metadata of each SSTable on disk.
     ///
-    /// Removes old versions that are no longer visible to any active transaction.
-    /// This helps reduce memory usage in long-running databases.
+    /// Each entry has the file's path, LSM level, key range, entry count
+    /// and size, ordered by level and then age. Useful for spotting an
+    /// unusually large file, or one whose entry count is high for its key
+    /// range because it is mostly tombstones. The data comes from the
+    /// manifest, so no SSTable is opened. In-memory databases return an
+    /// empty list.
     ///
     /// # Examples
     ///
@@ -721,76 +1458,2300 @@ impl Database {
     /// use rustlite::Database;
     ///
     /// let db = Database::in_memory()?;
-    /// // ... perform many transactions ...
-    /// db.gc()?; // Clean up old versions
+    /// assert!(db.sstable_details()?.is_empty());
     /// # Ok::<(), rustlite::Error>(())
     /// ```
-    pub fn gc(&self) -> Result<()> {
-        if let Some(ref manager) = self.inner.transaction_manager {
-            manager.gc()
-        } else {
-            Ok(()) // No-op if transactions not initialized
+    pub fn sstable_details(&self) -> Result<Vec<SSTableMeta>> {
+        match &self.inner.engine {
+            Some(engine) => engine.sstable_details(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns a snapshot of cumulative database counters.
+    ///
+    /// Counters are maintained with atomics on the hot paths, so this is
+    /// cheap enough to call from a metrics exporter on every scrape. Storage
+    /// counters (flushes, compactions, WAL syncs and SSTables per level) are
+    /// zero for in-memory databases.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.put(b"key", b"value")?;
+    /// db.get(b"missing")?;
+    ///
+    /// let metrics = db.metrics();
+    /// assert_eq!(metrics.puts, 1);
+    /// assert_eq!(metrics.get_misses, 1);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn metrics(&self) -> DbMetrics {
+        let mut metrics = self.inner.metrics.snapshot();
+
+        if let Some(engine) = &self.inner.engine {
+            let stats = engine.stats();
+            metrics.flushes = stats.flush_count;
+            metrics.compactions = stats.compaction_stats.compaction_count;
+            metrics.wal_syncs = stats.wal_sync_count;
+            metrics.sstables_per_level = stats.level_counts;
         }
+
+        metrics
+    }
+
+    /// Returns whether this is a persistent database.
+    pub fn is_persistent(&self) -> bool {
+        self.inner.engine.is_some()
+    }
+
+    /// Replaces the input limits applied to keys.
+    ///
+    /// See [`ResourceLimits`] for the available settings. Limits are not
+    /// persisted, so set them again after reopening a database.
+    pub fn set_resource_limits(&self, limits: ResourceLimits) -> Result<()> {
+        let mut slot = self.inner.limits.write().map_err(|_| Error::LockPoisoned)?;
+        *slot = limits;
+        Ok(())
+    }
+
+    /// Checks `key` against the current resource limits.
+    fn validate_key(&self, key: &[u8]) -> Result<()> {
+        let limits = self.inner.limits.read().map_err(|_| Error::LockPoisoned)?;
+        security::validate_key(key, &limits)
+    }
+
+    /// Installs an observer that receives operation callbacks.
+    ///
+    /// The observer is notified of puts and gets on any database, and of
+    /// memtable flushes and compactions on persistent databases. Replaces
+    /// any previously installed observer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, DbObserver};
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// #[derive(Default)]
+    /// struct PutCounter(AtomicU64);
+    ///
+    /// impl DbObserver for PutCounter {
+    ///     fn on_put(&self, _key_len: usize, _value_len: usize, _latency: Duration) {
+    ///         self.0.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    /// }
+    ///
+    /// let db = Database::in_memory()?;
+    /// let counter = Arc::new(PutCounter::default());
+    /// db.set_observer(counter.clone())?;
+    ///
+    /// db.put(b"key", b"value")?;
+    /// assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn set_observer(&self, observer: Arc<dyn DbObserver>) -> Result<()> {
+        if let Some(engine) = &self.inner.engine {
+            engine.set_observer(Arc::clone(&observer))?;
+        }
+
+        let mut slot = self
+            .inner
+            .observer
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+        *slot = Some(observer);
+        Ok(())
+    }
+
+    /// Returns the installed observer, if any.
+    fn observer(&self) -> Option<Arc<dyn DbObserver>> {
+        self.inner.observer.read().ok().and_then(|o| o.clone())
+    }
+
+    /// Registers the operator used by [`Database::merge`].
+    ///
+    /// The operator folds merge operands into the existing value for a key.
+    /// Persistent databases apply it lazily on reads and when memtables are
+    /// flushed, so the same operator must be registered after every reopen
+    /// before pending operands are read. Replaces any previously registered
+    /// operator.
+    pub fn register_merge_operator(&self, operator: Arc<dyn MergeOperator>) -> Result<()> {
+        if let Some(engine) = &self.inner.engine {
+            engine.set_merge_operator(Arc::clone(&operator))?;
+        }
+
+        let mut slot = self
+            .inner
+            .merge_operator
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+        *slot = Some(operator);
+        Ok(())
+    }
+
+    /// Applies a merge operand to a key without reading it first.
+    ///
+    /// The operand is appended to the WAL and memtable and combined with the
+    /// current value by the registered [`MergeOperator`]. This makes
+    /// read-modify-write updates such as counters a single write. Returns
+    /// `Error::InvalidOperation` if no merge operator has been registered.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, MergeOperator};
+    /// use std::sync::Arc;
+    ///
+    /// struct AddU64;
+    ///
+    /// impl MergeOperator for AddU64 {
+    ///     fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[Vec<u8>]) -> Vec<u8> {
+    ///         let decode = |b: &[u8]| u64::from_le_bytes(b.try_into().unwrap_or([0; 8]));
+    ///         let total = operands.iter().map(|o| decode(o)).sum::<u64>() + existing.map_or(0, decode);
+    ///         total.to_le_bytes().to_vec()
+    ///     }
+    /// }
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.register_merge_operator(Arc::new(AddU64))?;
+    /// db.merge(b"hits", &1u64.to_le_bytes())?;
+    /// db.merge(b"hits", &2u64.to_le_bytes())?;
+    /// assert_eq!(db.get(b"hits")?, Some(3u64.to_le_bytes().to_vec()));
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(skip(self, key, operand), fields(key_len = key.len(), operand_len = operand.len()))]
+    pub fn merge(&self, key: &[u8], operand: &[u8]) -> Result<()> {
+        // Security: Validate inputs
+        self.validate_key(key)?;
+        security::validate_value(operand)?;
+
+        debug!("Merging operand");
+
+        let _write = self.inner.write_locks.lock(key);
+        let indexed = self.has_auto_indexes()?;
+        let previous = if indexed { self.load(key)? } else { None };
+
+        match &self.inner.engine {
+            Some(engine) => {
+                engine.merge(key, operand)?;
+                self.write_through(key)?;
+            }
+            None => {
+                let operator = self
+                    .inner
+                    .merge_operator
+                    .read()
+                    .map_err(|_| Error::LockPoisoned)?
+                    .clone()
+                    .ok_or_else(|| {
+                        Error::InvalidOperation("No merge operator registered".to_string())
+                    })?;
+                let existing = self.load(key)?;
+                let merged = operator.merge(key, existing.as_deref(), &[operand.to_vec()]);
+                self.inner.backend.put(key, &merged)?;
+                self.write_through(key)?;
+            }
+        }
+
+        if indexed {
+            let current = self.load(key)?;
+            self.update_auto_indexes(key, previous.as_deref(), current.as_deref())?;
+        }
+
+        self.inner.metrics.record_merge(key.len() + operand.len());
+        Ok(())
+    }
+
+    // =========================================================================
+    // Index Operations (v0.3.0+)
+    // =========================================================================
+
+    /// Creates a new index with the specified name and type.
+    ///
+    /// For persistent databases the definition is written to the WAL, so
+    /// the index exists again after reopening. Its entries are not
+    /// persisted and must be inserted again.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Unique name for the index
+    /// * `index_type` - Type of index (BTree for range queries, Hash for fast lookups)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("users_by_name", IndexType::BTree)?;
+    /// db.create_index("sessions", IndexType::Hash)?;
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(skip(self), fields(name = %name, index_type = ?index_type))]
+    pub fn create_index(&self, name: &str, index_type: IndexType) -> Result<()> {
+        // Security: Validate index name
+        security::validate_index_name(name)?;
+
+        info!("Creating index");
+
+        let mut indexes = self
+            .inner
+            .indexes
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+        indexes.create_index(name, index_type)?;
+
+        if let Some(engine) = &self.inner.engine {
+            if let Err(e) = engine.create_index(name, index_type) {
+                indexes.drop_index(name)?;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops an index by name.
+    ///
+    /// Returns `true` if the index existed and was dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("temp_index", IndexType::Hash)?;
+    /// assert!(db.drop_index("temp_index")?);
+    /// assert!(!db.drop_index("temp_index")?); // Already dropped
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn drop_index(&self, name: &str) -> Result<bool> {
+        self.inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
+            .extractors
+            .remove(name);
+
+        let mut indexes = self
+            .inner
+            .indexes
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+        if let Some(engine) = &self.inner.engine {
+            engine.drop_index(name)?;
+        }
+        indexes.drop_index(name)
+    }
+
+    /// Creates an index that is maintained automatically on every write.
+    ///
+    /// After each `put`, `merge` and `delete`, `extractor` is called with the
+    /// record's key and value to compute its index key, and the index is
+    /// updated to match. Records for which the extractor returns `None` are
+    /// left out of the index. When a record is overwritten, the extractor
+    /// also runs on the previous value so the record's stale entry is removed
+    /// before the new one is added; other records sharing the old index key
+    /// keep theirs. Each indexed record is assigned a row ID, which
+    /// is what [`index_find`](Database::index_find) returns; use
+    /// [`index_find_keys`](Database::index_find_keys) to get the primary keys
+    /// directly.
+    ///
+    /// Only writes made after the index is defined are indexed. As the
+    /// extractor cannot be stored, the index is not persisted: define it
+    /// again each time the database is opened.
+    ///
+    /// Transaction commits bypass index maintenance, so a database with
+    /// defined indexes does not support transactions:
+    /// [`begin_transaction`](Database::begin_transaction) fails once an index
+    /// is defined, and defining one fails while a transaction is active.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    /// use std::sync::Arc;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.define_index(
+    ///     "users_by_name",
+    ///     IndexType::Hash,
+    ///     Arc::new(|_key: &[u8], value: &[u8]| Some(value.to_vec())),
+    /// )?;
+    ///
+    /// db.put(b"user:1", b"alice")?;
+    /// assert_eq!(db.index_find_keys("users_by_name", b"alice")?, vec![b"user:1".to_vec()]);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(skip(self, extractor), fields(name = %name, index_type = ?index_type))]
+    pub fn define_index(
+        &self,
+        name: &str,
+        index_type: IndexType,
+        extractor: IndexExtractor,
+    ) -> Result<()> {
+        let mut auto = self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?;
+
+        security::validate_index_name(name)?;
+        if let Some(manager) = &self.inner.transaction_manager {
+            if manager.active_count()? > 0 {
+                return Err(Error::InvalidOperation(
+                    "cannot define an index while transactions are active".into(),
+                ));
+            }
+        }
+        self.inner
+            .indexes
+            .write()
+            .map_err(|_| Error::LockPoisoned)?
+            .create_index(name, index_type)?;
+        auto.extractors.insert(name.to_string(), extractor);
+        Ok(())
+    }
+
+    /// Finds the primary keys of records matching a key in an index created
+    /// with [`define_index`](Database::define_index).
+    pub fn index_find_keys(&self, name: &str, key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let auto = self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?;
+        let row_ids = self.index_find(name, key)?;
+        Ok(row_ids
+            .iter()
+            .filter_map(|id| auto.row_keys.get(id).cloned())
+            .collect())
+    }
+
+    /// Whether any index created with [`define_index`](Database::define_index)
+    /// is maintained on writes
+    fn has_auto_indexes(&self) -> Result<bool> {
+        Ok(!self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
+            .extractors
+            .is_empty())
+    }
+
+    /// Updates automatically maintained indexes after a record changes
+    /// from `old` to `new`.
+    ///
+    /// Callers hold the key's write lock. A record has a row ID only while
+    /// some index holds an entry for it.
+    fn update_auto_indexes(
+        &self,
+        key: &[u8],
+        old: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<()> {
+        let mut auto = self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?;
+
+        let mut indexed = false;
+        let changes: Vec<_> = auto
+            .extractors
+            .iter()
+            .filter_map(|(name, extractor)| {
+                let old_key = old.and_then(|v| extractor(key, v));
+                let new_key = new.and_then(|v| extractor(key, v));
+                indexed |= new_key.is_some();
+                (old_key != new_key).then(|| (name.clone(), old_key, new_key))
+            })
+            .collect();
+
+        let row_id = match auto.row_ids.get(key) {
+            Some(&id) => id,
+            // Without a row ID the record has no entries to remove
+            None if !indexed => return Ok(()),
+            None => {
+                let id = auto.next_row_id;
+                auto.next_row_id += 1;
+                auto.row_ids.insert(key.to_vec(), id);
+                auto.row_keys.insert(id, key.to_vec());
+                id
+            }
+        };
+
+        if !changes.is_empty() {
+            let mut indexes = self
+                .inner
+                .indexes
+                .write()
+                .map_err(|_| Error::LockPoisoned)?;
+            for (name, old_key, new_key) in changes {
+                if let Some(old_key) = old_key {
+                    // Other rows sharing the old index key keep their entries
+                    indexes.remove_value(&name, &old_key, row_id)?;
+                }
+                if let Some(new_key) = new_key {
+                    indexes.insert(&name, &new_key, row_id)?;
+                }
+            }
+        }
+
+        if !indexed {
+            auto.row_ids.remove(key);
+            auto.row_keys.remove(&row_id);
+        }
+
+        Ok(())
+    }
+
+    /// Inserts a key-value pair into a named index.
+    ///
+    /// The value is typically a record ID or offset pointing to the actual data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("names", IndexType::BTree)?;
+    ///
+    /// // Index "alice" pointing to record ID 100
+    /// db.index_insert("names", b"alice", 100)?;
+    /// db.index_insert("names", b"bob", 101)?;
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn index_insert(&self, name: &str, key: &[u8], value: u64) -> Result<()> {
+        let mut indexes = self
+            .inner
+            .indexes
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+        indexes.insert(name, key, value)
+    }
+
+    /// Inserts many key-value pairs into a named index.
+    ///
+    /// Same result as calling [`index_insert`](Database::index_insert) for
+    /// each entry, but the index sizes its storage once for the whole batch,
+    /// which makes bulk imports faster.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("skus", IndexType::Hash)?;
+    ///
+    /// let entries: Vec<(Vec<u8>, u64)> = (0..1000u64)
+    ///     .map(|id| (format!("sku-{}", id).into_bytes(), id))
+    ///     .collect();
+    /// db.index_insert_many("skus", &entries)?;
+    /// assert_eq!(db.index_find("skus", b"sku-42")?, vec![42]);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn index_insert_many(&self, name: &str, entries: &[(Vec<u8>, u64)]) -> Result<()> {
+        let mut indexes = self
+            .inner
+            .indexes
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+        indexes.insert_many(name, entries)
+    }
+
+    /// Finds all values matching a key in a named index.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("names", IndexType::Hash)?;
+    /// db.index_insert("names", b"alice", 100)?;
+    ///
+    /// let results = db.index_find("names", b"alice")?;
+    /// assert_eq!(results, vec![100]);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn index_find(&self, name: &str, key: &[u8]) -> Result<Vec<u64>> {
+        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
+        indexes.find(name, key)
+    }
+
+    /// Finds the values matching every `(index, key)` lookup.
+    ///
+    /// Returns the intersection of the lookups' results, sorted and without
+    /// duplicates, for compound lookups such as active products in one
+    /// category. All lookups see the indexes in the same state. No lookups
+    /// match nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("by_category", IndexType::Hash)?;
+    /// db.create_index("by_status", IndexType::Hash)?;
+    /// db.index_insert_many("by_category", &[(b"books".to_vec(), 1), (b"books".to_vec(), 2)])?;
+    /// db.index_insert("by_status", b"active", 2)?;
+    ///
+    /// let ids = db.index_find_all(&[("by_category", b"books"), ("by_status", b"active")])?;
+    /// assert_eq!(ids, vec![2]);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn index_find_all(&self, queries: &[(&str, &[u8])]) -> Result<Vec<u64>> {
+        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
+        let mut matches: Option<BTreeSet<u64>> = None;
+        for (name, key) in queries {
+            let found: BTreeSet<u64> = indexes.find(name, key)?.into_iter().collect();
+            matches = Some(match matches {
+                Some(matches) => matches.intersection(&found).copied().collect(),
+                None => found,
+            });
+        }
+        Ok(matches.unwrap_or_default().into_iter().collect())
+    }
+
+    /// Removes a key from a named index.
+    ///
+    /// Returns `true` if the key existed and was removed.
+    pub fn index_remove(&self, name: &str, key: &[u8]) -> Result<bool> {
+        let mut indexes = self
+            .inner
+            .indexes
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+        indexes.remove(name, key)
+    }
+
+    /// Clears an index and repopulates it from the stored data.
+    ///
+    /// Every stored key-value pair is passed to `extractor`, which returns
+    /// the `(index_key, record_id)` entry for it or `None` to leave it out.
+    /// Use this to recover an index that has drifted from the data, for
+    /// example after a crash or a bulk import that bypassed the index.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    /// use std::sync::Arc;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("names", IndexType::Hash)?;
+    /// db.put(b"user:1", b"alice")?;
+    ///
+    /// db.rebuild_index(
+    ///     "names",
+    ///     Arc::new(|key: &[u8], value: &[u8]| {
+    ///         let id = std::str::from_utf8(key).ok()?.strip_prefix("user:")?;
+    ///         Some((value.to_vec(), id.parse().ok()?))
+    ///     }),
+    /// )?;
+    /// assert_eq!(db.index_find("names", b"alice")?, vec![1]);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn rebuild_index(&self, name: &str, extractor: IndexEntryExtractor) -> Result<()> {
+        let entries = self.inner.backend.entries()?;
+
+        let mut indexes = self
+            .inner
+            .indexes
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+        indexes.rebuild_index(name, entries, extractor.as_ref())
+    }
+
+    /// Lists all index names in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("idx1", IndexType::BTree)?;
+    /// db.create_index("idx2", IndexType::Hash)?;
+    ///
+    /// let names = db.list_indexes()?;
+    /// assert_eq!(names.len(), 2);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn list_indexes(&self) -> Result<Vec<String>> {
+        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
+        Ok(indexes
+            .list_indexes()
+            .iter()
+            .map(|s| s.to_string())
+            .collect())
+    }
+
+    /// Gets information about all indexes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("users", IndexType::BTree)?;
+    /// db.index_insert("users", b"alice", 1)?;
+    ///
+    /// for info in db.index_info()? {
+    ///     println!("Index: {}, Type: {}, Entries: {}",
+    ///              info.name, info.index_type, info.entry_count);
+    /// }
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn index_info(&self) -> Result<Vec<IndexInfo>> {
+        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
+        Ok(indexes.index_info())
+    }
+
+    /// Captures the type and contents of every index at a single point.
+    ///
+    /// Writes to indexes created with [`define_index`] are applied in full
+    /// or not at all, so the snapshot never holds half of an update. The
+    /// snapshot is serializable, for backing indexes up alongside the data.
+    ///
+    /// [`define_index`]: Database::define_index
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.create_index("users", IndexType::BTree)?;
+    /// db.index_insert("users", b"alice", 1)?;
+    ///
+    /// let snapshot = db.snapshot_indexes()?;
+    /// db.index_insert("users", b"bob", 2)?;
+    ///
+    /// db.restore_indexes(snapshot)?;
+    /// assert!(db.index_find("users", b"bob")?.is_empty());
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn snapshot_indexes(&self) -> Result<IndexSnapshot> {
+        let _auto = self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?;
+        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
+        Ok(indexes.snapshot())
+    }
+
+    /// Replaces every index with the ones captured by
+    /// [`snapshot_indexes`](Database::snapshot_indexes).
+    ///
+    /// Indexes missing from the snapshot are dropped and indexes only in
+    /// the snapshot are created, so for persistent databases the stored
+    /// index definitions match the snapshot too. Indexes created with
+    /// [`define_index`](Database::define_index) keep being maintained if
+    /// they are in the snapshot; their row IDs only map back to primary
+    /// keys for records that still exist.
+    pub fn restore_indexes(&self, snapshot: IndexSnapshot) -> Result<()> {
+        for name in snapshot.indexes.keys() {
+            security::validate_index_name(name)?;
+        }
+
+        let mut auto = self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?;
+        let mut indexes = self
+            .inner
+            .indexes
+            .write()
+            .map_err(|_| Error::LockPoisoned)?;
+
+        let previous: HashMap<String, IndexType> = indexes
+            .index_info()
+            .into_iter()
+            .map(|info| (info.name, info.index_type))
+            .collect();
+        let created: Vec<(String, IndexType)> = snapshot
+            .indexes
+            .iter()
+            .filter(|(name, contents)| previous.get(*name) != Some(&contents.index_type))
+            .map(|(name, contents)| (name.clone(), contents.index_type))
+            .collect();
+        let dropped: Vec<String> = previous
+            .iter()
+            .filter(|(name, index_type)| {
+                snapshot
+                    .indexes
+                    .get(*name)
+                    .map_or(true, |contents| contents.index_type != **index_type)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        indexes.restore(snapshot)?;
+        auto.extractors
+            .retain(|name, _| indexes.get_index(name).is_some());
+
+        if let Some(engine) = &self.inner.engine {
+            for name in &dropped {
+                engine.drop_index(name)?;
+            }
+            for (name, index_type) in &created {
+                engine.create_index(name, *index_type)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes a SQL-like query and returns results (v0.4.0+).
+    ///
+    /// Parses, plans, and executes a SELECT query against in-memory data.
+    /// Currently supports: SELECT, FROM, WHERE, ORDER BY, LIMIT, JOIN.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - SQL-like query string
+    /// * `context` - Execution context with data and indexes
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, ExecutionContext, Row, Column, Value};
+    /// use std::collections::HashMap;
+    ///
+    /// let db = Database::in_memory()?;
+    ///
+    /// // Prepare test data
+    /// let mut context = ExecutionContext::new();
+    /// context.data.insert("users".to_string(), vec![
+    ///     Row {
+    ///         columns: vec![
+    ///             Column { name: "name".to_string(), alias: None },
+    ///             Column { name: "age".to_string(), alias: None },
+    ///         ],
+    ///         values: vec![Value::String("Alice".to_string()), Value::Integer(30)],
+    ///     },
+    /// ]);
+    ///
+    /// let results = db.query("SELECT name FROM users WHERE age > 18", context)?;
+    /// assert_eq!(results.len(), 1);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(skip(self, sql, context), fields(sql_len = sql.len()))]
+    pub fn query(&self, sql: &str, context: ExecutionContext) -> Result<Vec<Row>> {
+        // Security: Validate query length
+        security::validate_query(sql)?;
+
+        debug!(sql = %sql, "Executing query");
+
+        // Parse the SQL
+        let mut parser =
+            Parser::new(sql).map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
+        let query = parser
+            .parse()
+            .map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
+
+        // Plan the query
+        let planner = Planner::with_indexes(context.index_metadata.clone());
+        let plan = planner
+            .plan(&query)
+            .map_err(|e| Error::InvalidInput(format!("Planning error: {}", e)))?;
+
+        // Execute the query
+        let mut executor = Executor::new(context);
+        executor.execute(&plan)
+    }
+
+    /// Builds an execution context over the records of the given tables.
+    ///
+    /// A table is the set of records whose keys start with `"{table}:"`.
+    /// Each record becomes a row with a `key` column (the key without the
+    /// table prefix) and a `value` column, both decoded as UTF-8 strings
+    /// (invalid bytes are replaced).
+    ///
+    /// An index created with [`define_index`](Database::define_index) is
+    /// copied into the context, with its row IDs mapped to row positions,
+    /// when it covers records of exactly one of these tables and its
+    /// extractor returns the `key` or `value` column (as the UTF-8 bytes the
+    /// query compares) for every record of that table. Queries filtering
+    /// that column of that table can then use index scans; any other index
+    /// is left out, and queries fall back to scanning the table.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IndexType};
+    /// use std::sync::Arc;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.define_index(
+    ///     "users_by_value",
+    ///     IndexType::Hash,
+    ///     Arc::new(|_key: &[u8], value: &[u8]| Some(value.to_vec())),
+    /// )?;
+    /// db.put(b"users:1", b"alice")?;
+    /// db.put(b"users:2", b"bob")?;
+    ///
+    /// let context = db.execution_context(&["users"])?;
+    /// let rows = db.query("SELECT key FROM users WHERE value = 'bob'", context)?;
+    /// assert_eq!(rows.len(), 1);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn execution_context(&self, tables: &[&str]) -> Result<ExecutionContext> {
+        let mut context = ExecutionContext::new();
+
+        // Row position of each record, by primary key
+        let mut positions: HashMap<Vec<u8>, (&str, u64)> = HashMap::new();
+        // Records of each table and the length of its key prefix
+        let mut scanned = Vec::new();
+        for &table in tables {
+            // ';' is the byte after ':', so this covers exactly the prefix
+            let start = format!("{}:", table).into_bytes();
+            let end = format!("{};", table).into_bytes();
+
+            let records = self.scan_range(&start, &end)?;
+            let mut rows = Vec::new();
+            for (key, value) in &records {
+                let row = Row {
+                    columns: ["key", "value"]
+                        .iter()
+                        .map(|name| Column {
+                            name: name.to_string(),
+                            alias: None,
+                        })
+                        .collect(),
+                    values: vec![
+                        Value::String(String::from_utf8_lossy(&key[start.len()..]).into_owned()),
+                        Value::String(String::from_utf8_lossy(value).into_owned()),
+                    ],
+                };
+                positions.insert(key.clone(), (table, rows.len() as u64));
+                rows.push(row);
+            }
+            // Records come back in key order, which the lossy decoding of
+            // invalid UTF-8 could break
+            let sorted =
+                rows.windows(2)
+                    .all(|pair| match (&pair[0].values[0], &pair[1].values[0]) {
+                        (Value::String(a), Value::String(b)) => a <= b,
+                        _ => false,
+                    });
+            if sorted {
+                context
+                    .sorted_by
+                    .insert(table.to_string(), "key".to_string());
+            }
+            context.data.insert(table.to_string(), rows);
+            scanned.push((table, start.len(), records));
+        }
+
+        let auto = self
+            .inner
+            .auto_indexes
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?;
+        let indexes = self.inner.indexes.read().map_err(|_| Error::LockPoisoned)?;
+        for (name, extractor) in &auto.extractors {
+            let Some(index) = indexes.get_index(name) else {
+                continue;
+            };
+
+            // Row positions are per table, so the index must cover exactly one
+            let mut covered = scanned.iter().filter(|(_, _, records)| {
+                records
+                    .iter()
+                    .any(|(key, value)| extractor(key, value).is_some())
+            });
+            let (Some(&(table, prefix_len, ref records)), None) = (covered.next(), covered.next())
+            else {
+                continue;
+            };
+            let Some(column) = indexed_column(extractor, prefix_len, records) else {
+                continue;
+            };
+
+            let mut entries = HashMap::new();
+            let mut row_count = 0;
+            for (key, row_ids) in index.entries() {
+                let rows: Vec<u64> = row_ids
+                    .iter()
+                    .filter_map(|id| auto.row_keys.get(id))
+                    .filter_map(|pk| positions.get(pk))
+                    .filter(|(t, _)| *t == table)
+                    .map(|&(_, pos)| pos)
+                    .collect();
+                row_count += rows.len();
+                if !rows.is_empty() {
+                    entries.insert(key, rows);
+                }
+            }
+            // An index that cannot list every row would hide matches
+            if row_count != records.len() {
+                continue;
+            }
+
+            context.indexes.insert(name.clone(), entries);
+            context.index_metadata.push(IndexMetadata {
+                name: name.clone(),
+                table: table.to_string(),
+                column: column.to_string(),
+                index_type: index.index_type().to_string(),
+            });
+        }
+
+        Ok(context)
+    }
+
+    /// Executes a SQL statement against a mutable context.
+    ///
+    /// Accepts everything `query` does plus `DROP TABLE name` and
+    /// `TRUNCATE [TABLE] name`, which modify `context` in place and return no
+    /// rows. Dropping or truncating a table that does not exist is an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, ExecutionContext};
+    ///
+    /// let db = Database::in_memory()?;
+    /// let mut context = ExecutionContext::new();
+    /// context.data.insert("users".to_string(), vec![]);
+    ///
+    /// db.execute("DROP TABLE users", &mut context)?;
+    /// assert!(!context.data.contains_key("users"));
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(skip(self, sql, context), fields(sql_len = sql.len()))]
+    pub fn execute(&self, sql: &str, context: &mut ExecutionContext) -> Result<Vec<Row>> {
+        security::validate_query(sql)?;
+
+        debug!(sql = %sql, "Executing statement");
+
+        let mut parser =
+            Parser::new(sql).map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
+        let statement = parser
+            .parse_statement()
+            .map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
+
+        match statement {
+            Statement::Select(query) => {
+                let plan = Planner::with_indexes(context.index_metadata.clone())
+                    .plan(&query)
+                    .map_err(|e| Error::InvalidInput(format!("Planning error: {}", e)))?;
+
+                let mut executor = Executor::new(std::mem::take(context));
+                let result = executor.execute(&plan);
+                *context = executor.into_context();
+                result
+            }
+            Statement::DropTable { table } => {
+                if !context.drop_table(&table) {
+                    return Err(Error::InvalidInput(format!(
+                        "table '{}' does not exist",
+                        table
+                    )));
+                }
+                Ok(Vec::new())
+            }
+            Statement::TruncateTable { table } => {
+                if !context.data.contains_key(&table) {
+                    return Err(Error::InvalidInput(format!(
+                        "table '{}' does not exist",
+                        table
+                    )));
+                }
+                context.truncate_table(&table);
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Prepares a SQL-like query for repeated execution (v0.4.0+).
+    ///
+    /// Parses and plans the query once, returning a reusable plan.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// let plan = db.prepare("SELECT * FROM users WHERE age > 18")?;
+    /// // Plan can be executed multiple times with different contexts
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn prepare(&self, sql: &str) -> Result<PhysicalPlan> {
+        let mut parser =
+            Parser::new(sql).map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
+        let query = parser
+            .parse()
+            .map_err(|e| Error::InvalidInput(format!("Parse error: {}", e)))?;
+
+        let planner = Planner::new();
+        planner
+            .plan(&query)
+            .map_err(|e| Error::InvalidInput(format!("Planning error: {}", e)))
+    }
+
+    /// Executes a prepared query plan with given context (v0.4.0+).
+    pub fn execute_plan(&self, plan: &PhysicalPlan, context: ExecutionContext) -> Result<Vec<Row>> {
+        let mut executor = Executor::new(context);
+        executor.execute(plan)
+    }
+
+    // ===== Transaction Methods (v0.5.0+) =====
+
+    /// Begins a new MVCC transaction with the specified isolation level (v0.5.0+).
+    ///
+    /// Returns a Transaction handle that provides snapshot isolation and
+    /// ACID guarantees. Changes are buffered until commit. For persistent
+    /// databases, commit appends the whole write set to the WAL as a single
+    /// batch with one sync, so after a crash either all of a transaction's
+    /// writes are recovered or none are.
+    ///
+    /// Fails with `Error::InvalidOperation` on a database with indexes
+    /// created by [`define_index`](Database::define_index), since commits
+    /// would not update them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::{Database, IsolationLevel};
+    ///
+    /// let db = Database::in_memory()?;
+    ///
+    /// // Start a transaction
+    /// let mut txn = db.begin_transaction(IsolationLevel::RepeatableRead)?;
+    ///
+    /// // Read and write within transaction
+    /// txn.put(b"key1".to_vec(), b"value1".to_vec())?;
+    /// txn.put(b"key2".to_vec(), b"value2".to_vec())?;
+    ///
+    /// // Commit changes
+    /// txn.commit()?;
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    #[instrument(skip(self), fields(isolation = ?isolation))]
+    pub fn begin_transaction(&self, isolation: IsolationLevel) -> Result<Transaction> {
+        info!("Beginning transaction");
+        if let Some(ref manager) = self.inner.transaction_manager {
+            // Held across begin so a concurrent define_index sees this
+            // transaction as active
+            let auto = self
+                .inner
+                .auto_indexes
+                .lock()
+                .map_err(|_| Error::LockPoisoned)?;
+            if !auto.extractors.is_empty() {
+                return Err(Error::InvalidOperation(
+                    "transactions are not supported on databases with defined indexes".into(),
+                ));
+            }
+            manager.begin(isolation)
+        } else {
+            Err(Error::Transaction(
+                "Transaction support not initialized".into(),
+            ))
+        }
+    }
+
+    /// Limits the number of distinct keys a transaction may write.
+    ///
+    /// Transaction writes are buffered in memory until commit. Past the
+    /// limit, [`Transaction::put`] and [`Transaction::delete`] fail with
+    /// `Error::Transaction("write set limit exceeded")`; commit and continue
+    /// in a new transaction. `None` removes the limit. Only transactions
+    /// begun afterwards are affected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.set_max_transaction_writes(Some(1));
+    ///
+    /// let mut txn = db.begin()?;
+    /// txn.put(b"a".to_vec(), b"1".to_vec())?;
+    /// assert!(txn.put(b"b".to_vec(), b"2".to_vec()).is_err());
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn set_max_transaction_writes(&self, limit: Option<usize>) {
+        if let Some(ref manager) = self.inner.transaction_manager {
+            manager.set_max_transaction_writes(limit);
+        }
+    }
+
+    /// Begins a new transaction with default isolation level (RepeatableRead).
+    ///
+    /// Convenience method equivalent to `begin_transaction(IsolationLevel::RepeatableRead)`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// let mut txn = db.begin()?;
+    /// txn.put(b"key".to_vec(), b"value".to_vec())?;
+    /// txn.commit()?;
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn begin(&self) -> Result<Transaction> {
+        self.begin_transaction(IsolationLevel::default())
+    }
+
+    /// Performs garbage collection on MVCC version chains (v0.5.0+).
+    ///
+    /// Removes old versions that are no longer visible to any active transaction.
+    /// This helps reduce memory usage in long-running databases.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// // ... perform many transactions ...
+    /// db.gc()?; // Clean up old versions
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn gc(&self) -> Result<()> {
+        if let Some(ref manager) = self.inner.transaction_manager {
+            manager.gc()
+        } else {
+            Ok(()) // No-op if transactions not initialized
+        }
+    }
+
+    /// Reports the size of the MVCC version chains.
+    ///
+    /// Shows how many keys and versions are held in memory, the deepest
+    /// chain, and the GC watermark: the oldest snapshot still in use. A
+    /// watermark that stays old points at a long-running transaction that
+    /// keeps [`gc`](Database::gc) from reclaiming versions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite::Database;
+    ///
+    /// let db = Database::in_memory()?;
+    /// db.put(b"key", b"value")?;
+    /// db.gc()?;
+    /// assert_eq!(db.version_stats()?.max_chain_depth, 1);
+    /// # Ok::<(), rustlite::Error>(())
+    /// ```
+    pub fn version_stats(&self) -> Result<VersionStats> {
+        match self.inner.transaction_manager {
+            Some(ref manager) => manager.version_stats(),
+            None => Ok(VersionStats::default()),
+        }
+    }
+}
+
+/// Column of an [`execution_context`](Database::execution_context) table
+/// whose encoding `extractor` returns for every record, if any
+fn indexed_column(
+    extractor: &IndexExtractor,
+    prefix_len: usize,
+    records: &[(Vec<u8>, Vec<u8>)],
+) -> Option<&'static str> {
+    if records.is_empty() {
+        return None;
+    }
+    ["key", "value"].into_iter().find(|&column| {
+        records.iter().all(|(key, value)| {
+            let encoded = match column {
+                "key" => String::from_utf8_lossy(&key[prefix_len..]),
+                _ => String::from_utf8_lossy(value),
+            };
+            extractor(key, value).as_deref() == Some(encoded.as_bytes())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_version() {
+        assert_eq!(VERSION, "0.8.0");
+    }
+
+    #[test]
+    fn test_in_memory_database() {
+        let db = Database::in_memory().unwrap();
+        db.put(b"key", b"value").unwrap();
+        assert_eq!(db.get(b"key").unwrap(), Some(b"value".to_vec()));
+        assert!(!db.is_persistent());
+    }
+
+    #[test]
+    fn test_persistent_database() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+
+        db.put(b"persist", b"data").unwrap();
+        assert_eq!(db.get(b"persist").unwrap(), Some(b"data".to_vec()));
+        assert!(db.is_persistent());
+    }
+
+    #[test]
+    fn test_empty_value_is_not_a_tombstone() {
+        let check = |db: &Database| {
+            assert_eq!(db.get(b"empty").unwrap(), Some(Vec::new()));
+            assert_eq!(db.get(b"deleted").unwrap(), None);
+            assert_eq!(db.scan_keys(b"").unwrap(), vec![b"empty".to_vec()]);
+        };
+        let write = |db: &Database| {
+            db.put(b"empty", b"").unwrap();
+            db.put(b"deleted", b"value").unwrap();
+            assert!(db.delete(b"deleted").unwrap());
+        };
+
+        let db = Database::in_memory().unwrap();
+        write(&db);
+        check(&db);
+        let mut txn = db.begin().unwrap();
+        assert_eq!(txn.get(b"empty").unwrap(), Some(Vec::new()));
+        txn.put(b"txn".to_vec(), Vec::new()).unwrap();
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"txn").unwrap(), Some(Vec::new()));
+
+        let dir = tempdir().unwrap();
+        {
+            let db = Database::open(dir.path()).unwrap();
+            write(&db);
+            check(&db);
+            // Recovered from the WAL
+        }
+        {
+            let db = Database::open(dir.path()).unwrap();
+            check(&db);
+            db.sync().unwrap();
+            // Read back from an SSTable
+            check(&db);
+        }
+        let db = Database::open(dir.path()).unwrap();
+        check(&db);
+    }
+
+    #[test]
+    fn test_persistence_across_reopens() {
+        let dir = tempdir().unwrap();
+
+        // Write data
+        {
+            let db = Database::open(dir.path()).unwrap();
+            db.put(b"key1", b"value1").unwrap();
+            db.put(b"key2", b"value2").unwrap();
+            db.sync().unwrap();
+        }
+
+        // Reopen and verify
+        {
+            let db = Database::open(dir.path()).unwrap();
+            assert_eq!(db.get(b"key1").unwrap(), Some(b"value1".to_vec()));
+            assert_eq!(db.get(b"key2").unwrap(), Some(b"value2".to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_pause_and_resume_compaction() {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig {
+            compaction_interval: std::time::Duration::from_millis(10),
+            ..Default::default()
+        };
+        let db = Database::open_with_config(dir.path(), config).unwrap();
+        let level0 = |db: &Database| db.metrics().sstables_per_level.first().copied();
+
+        db.pause_compaction().unwrap();
+        for i in 0..6 {
+            db.put(format!("key{}", i).as_bytes(), b"value").unwrap();
+            db.sync().unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(level0(&db), Some(6));
+        assert_eq!(db.metrics().compactions, 0);
+
+        db.resume_compaction();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while level0(&db).unwrap_or(0) > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(level0(&db).unwrap_or(0), 0);
+        assert!(db.metrics().compactions > 0);
+        for i in 0..6 {
+            assert_eq!(
+                db.get(format!("key{}", i).as_bytes()).unwrap(),
+                Some(b"value".to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn test_sync_key() {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig {
+            sync_mode: SyncMode::Async,
+            ..Default::default()
+        };
+
+        {
+            let db = Database::open_with_config(dir.path(), config.clone()).unwrap();
+            db.put(b"a", b"1").unwrap();
+            db.put(b"b", b"2").unwrap();
+            let syncs = db.metrics().wal_syncs;
+
+            db.sync_key(b"a").unwrap();
+            assert_eq!(db.metrics().wal_syncs, syncs + 1);
+
+            // Covered by the previous sync, and never written
+            db.sync_key(b"b").unwrap();
+            db.sync_key(b"missing").unwrap();
+            assert_eq!(db.metrics().wal_syncs, syncs + 1);
+        }
+
+        let db = Database::open_with_config(dir.path(), config).unwrap();
+        assert_eq!(db.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get(b"b").unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[test]
+    fn test_iter_prefix_skips_other_keys() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        for i in 0..2000 {
+            db.put(format!("other:{:04}", i).as_bytes(), b"x").unwrap();
+        }
+        for i in 0..(PREFIX_PAGE_SIZE + 10) {
+            db.put(format!("item:{:04}", i).as_bytes(), b"v").unwrap();
+        }
+        db.sync().unwrap();
+        db.delete(b"item:0003").unwrap();
+        db.put(b"items", b"no match").unwrap();
+
+        let items: Vec<(Vec<u8>, Vec<u8>)> = db
+            .iter_prefix(b"item:")
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let expected: Vec<Vec<u8>> = (0..(PREFIX_PAGE_SIZE + 10))
+            .filter(|&i| i != 3)
+            .map(|i| format!("item:{:04}", i).into_bytes())
+            .collect();
+        assert_eq!(
+            items.into_iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            expected
+        );
+
+        let first = db.iter_prefix(b"item:").unwrap().next().unwrap().unwrap();
+        assert_eq!(first, (b"item:0000".to_vec(), b"v".to_vec()));
+        assert_eq!(db.iter_prefix(b"none:").unwrap().count(), 0);
+
+        let mem = Database::in_memory().unwrap();
+        mem.put(b"item:1", b"a").unwrap();
+        mem.put(b"other", b"b").unwrap();
+        let items: Vec<_> = mem.iter_prefix(b"item:").unwrap().collect();
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_and_restore_indexes() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        db.create_index("tags", IndexType::Hash).unwrap();
+        db.define_index(
+            "by_name",
+            IndexType::BTree,
+            Arc::new(|_key: &[u8], value: &[u8]| Some(value.to_vec())),
+        )
+        .unwrap();
+        db.index_insert("tags", b"red", 1).unwrap();
+        db.put(b"user:1", b"alice").unwrap();
+
+        let snapshot = db.snapshot_indexes().unwrap();
+
+        db.index_insert("tags", b"blue", 2).unwrap();
+        db.put(b"user:2", b"bob").unwrap();
+        db.drop_index("tags").unwrap();
+        db.create_index("scratch", IndexType::Hash).unwrap();
+        assert_ne!(db.snapshot_indexes().unwrap(), snapshot);
+
+        db.restore_indexes(snapshot.clone()).unwrap();
+        assert_eq!(db.snapshot_indexes().unwrap(), snapshot);
+        assert_eq!(db.index_find("tags", b"red").unwrap(), vec![1]);
+        assert!(db.index_find("tags", b"blue").unwrap().is_empty());
+        assert!(db.index_find("by_name", b"bob").unwrap().is_empty());
+        assert_eq!(
+            db.index_find_keys("by_name", b"alice").unwrap(),
+            vec![b"user:1".to_vec()]
+        );
+        let mut names = db.list_indexes().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["by_name".to_string(), "tags".to_string()]);
+
+        // The defined index is still maintained after the restore
+        db.put(b"user:3", b"carol").unwrap();
+        assert_eq!(
+            db.index_find_keys("by_name", b"carol").unwrap(),
+            vec![b"user:3".to_vec()]
+        );
+
+        // Stored definitions follow the snapshot; defined indexes are never
+        // stored
+        drop(db);
+        let db = Database::open(dir.path()).unwrap();
+        assert_eq!(db.list_indexes().unwrap(), vec!["tags".to_string()]);
+    }
+
+    #[test]
+    fn test_put_returning_previous() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        assert_eq!(db.put_returning_previous(b"key", b"v1").unwrap(), None);
+        assert_eq!(
+            db.put_returning_previous(b"key", b"v2").unwrap(),
+            Some(b"v1".to_vec())
+        );
+        assert_eq!(db.get(b"key").unwrap(), Some(b"v2".to_vec()));
+
+        db.delete(b"key").unwrap();
+        assert_eq!(db.put_returning_previous(b"key", b"v3").unwrap(), None);
+
+        // Every value written is replaced exactly once, so concurrent
+        // writers never both see the same previous value
+        let db = Arc::new(db);
+        db.put(b"shared", b"initial").unwrap();
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let db = Arc::clone(&db);
+                std::thread::spawn(move || {
+                    (0..50)
+                        .map(|i| {
+                            db.put_returning_previous(b"shared", format!("{}:{}", t, i))
+                                .unwrap()
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut replaced: Vec<Vec<u8>> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        replaced.push(db.get(b"shared").unwrap().unwrap());
+        replaced.sort();
+
+        let mut written: Vec<Vec<u8>> = (0..4)
+            .flat_map(|t| (0..50).map(move |i| format!("{}:{}", t, i).into_bytes()))
+            .collect();
+        written.push(b"initial".to_vec());
+        written.sort();
+        assert_eq!(replaced, written);
+    }
+
+    #[test]
+    fn test_needs_sync() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        db.put(b"a", b"1").unwrap();
+        assert!(!db.needs_sync(b"a").unwrap());
+        drop(db);
+
+        let config = StorageConfig {
+            sync_mode: SyncMode::None,
+            ..Default::default()
+        };
+        let db = Database::open_with_config(dir.path(), config).unwrap();
+        db.put(b"a", b"2").unwrap();
+        db.put(b"b", b"1").unwrap();
+        assert!(db.needs_sync(b"a").unwrap());
+        assert!(db.needs_sync(b"b").unwrap());
+        assert!(!db.needs_sync(b"missing").unwrap());
+
+        // Syncing one key makes every earlier write durable
+        db.sync_key(b"b").unwrap();
+        assert!(!db.needs_sync(b"a").unwrap());
+        assert!(!db.needs_sync(b"b").unwrap());
+
+        let memory = Database::in_memory().unwrap();
+        memory.put(b"a", b"1").unwrap();
+        assert!(!memory.needs_sync(b"a").unwrap());
+    }
+
+    #[test]
+    fn test_close_persists_data() {
+        let dir = tempdir().unwrap();
+
+        let db = Database::open(dir.path()).unwrap();
+        db.put(b"key", b"value").unwrap();
+
+        // Another handle keeps the database open
+        let other = db.clone();
+        match db.close() {
+            Err(Error::InvalidOperation(msg)) => assert_eq!(msg, "database still in use"),
+            other => panic!("Expected InvalidOperation, got {:?}", other),
+        }
+        other.close().unwrap();
+
+        // So does a transaction
+        let db = Database::open(dir.path()).unwrap();
+        let txn = db.begin().unwrap();
+        assert!(matches!(db.close(), Err(Error::InvalidOperation(_))));
+        txn.commit().unwrap();
+
+        let db = Database::open(dir.path()).unwrap();
+        assert_eq!(db.get(b"key").unwrap(), Some(b"value".to_vec()));
+        db.close().unwrap();
+    }
+
+    #[test]
+    fn test_open_is_exclusive() {
+        let dir = tempdir().unwrap();
+
+        let db = Database::open(dir.path()).unwrap();
+        db.put(b"key", b"value").unwrap();
+        match Database::open(dir.path()) {
+            Err(Error::InvalidOperation(msg)) => {
+                assert_eq!(msg, "database already open by another process")
+            }
+            Err(e) => panic!("Expected InvalidOperation, got {:?}", e),
+            Ok(_) => panic!("Expected InvalidOperation, got Ok"),
+        }
+
+        // The lock is released once the database is closed
+        db.close().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        assert_eq!(db.get(b"key").unwrap(), Some(b"value".to_vec()));
+    }
+
+    #[test]
+    fn test_index_definitions_replayed_from_wal() {
+        let dir = tempdir().unwrap();
+
+        let db = Database::open(dir.path()).unwrap();
+        db.create_index("by_name", IndexType::Hash).unwrap();
+        db.create_index("by_age", IndexType::BTree).unwrap();
+        db.create_index("scratch", IndexType::Hash).unwrap();
+        db.put(b"user:1", b"alice").unwrap();
+        db.sync().unwrap();
+        assert!(db.drop_index("scratch").unwrap());
+        db.close().unwrap();
+
+        // Definitions come back from the WAL, including across the flush
+        // made by `sync`
+        let db = Database::open(dir.path()).unwrap();
+        let mut names = db.list_indexes().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["by_age", "by_name"]);
+        assert!(db.create_index("by_name", IndexType::Hash).is_err());
+
+        db.index_insert("by_name", b"alice", 1).unwrap();
+        assert_eq!(db.index_find("by_name", b"alice").unwrap(), vec![1]);
+        assert!(db.index_find("scratch", b"alice").is_err());
+
+        // Indexes with extractors are not persisted and can be redefined
+        db.define_index(
+            "auto",
+            IndexType::Hash,
+            Arc::new(|_: &[u8], v: &[u8]| Some(v.to_vec())),
+        )
+        .unwrap();
+        db.close().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        assert_eq!(db.list_indexes().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_checkpoint_truncates_wal() {
+        let dir = tempdir().unwrap();
+
+        let db = Database::open(dir.path()).unwrap();
+        for i in 0..100 {
+            db.put(format!("key{}", i).as_bytes(), b"value").unwrap();
+        }
+        db.delete(b"key0").unwrap();
+        assert_eq!(db.wal_stats().unwrap().put_records, 100);
+
+        let info = db.checkpoint().unwrap();
+        assert!(info.sequence > 0);
+        assert!(info.segments_removed >= 1);
+        let stats = db.wal_stats().unwrap();
+        assert_eq!(stats.put_records, 0);
+        assert_eq!(stats.delete_records, 0);
+
+        db.put(b"after", b"checkpoint").unwrap();
+        assert_eq!(db.wal_stats().unwrap().put_records, 1);
+        drop(db);
+
+        let db = Database::open(dir.path()).unwrap();
+        assert_eq!(db.get(b"key0").unwrap(), None);
+        for i in 1..100 {
+            assert_eq!(
+                db.get(format!("key{}", i).as_bytes()).unwrap(),
+                Some(b"value".to_vec())
+            );
+        }
+        assert_eq!(db.get(b"after").unwrap(), Some(b"checkpoint".to_vec()));
+    }
+
+    #[test]
+    fn test_checkpoint_keeps_concurrent_writes() {
+        use std::thread;
+
+        let dir = tempdir().unwrap();
+        {
+            let db = Database::open(dir.path()).unwrap();
+            let writer = {
+                let db = db.clone();
+                thread::spawn(move || {
+                    for i in 0..500 {
+                        db.put(format!("key{:03}", i).as_bytes(), b"value").unwrap();
+                    }
+                })
+            };
+            while !writer.is_finished() {
+                db.checkpoint().unwrap();
+            }
+            writer.join().unwrap();
+        }
+
+        let db = Database::open(dir.path()).unwrap();
+        for i in 0..500 {
+            assert_eq!(
+                db.get(format!("key{:03}", i).as_bytes()).unwrap(),
+                Some(b"value".to_vec()),
+                "key{:03} lost",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_or_insert_with_runs_once_per_key() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::thread;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let db = db.clone();
+                let calls = Arc::clone(&calls);
+                thread::spawn(move || {
+                    (0..4)
+                        .map(|k| {
+                            let key = format!("cache:{}", k);
+                            db.get_or_insert_with(key.as_bytes(), || {
+                                calls.fetch_add(1, Ordering::SeqCst);
+                                thread::sleep(std::time::Duration::from_millis(5));
+                                format!("computed {}", k).into_bytes()
+                            })
+                            .unwrap()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let values = handle.join().unwrap();
+            for (k, value) in values.iter().enumerate() {
+                assert_eq!(value, format!("computed {}", k).as_bytes());
+            }
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(db.get(b"cache:0").unwrap(), Some(b"computed 0".to_vec()));
+    }
+
+    #[test]
+    fn test_get_or_insert_with_recovers_from_panic() {
+        let db = Database::in_memory().unwrap();
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            db.get_or_insert_with(b"key", || panic!("compute failed"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(db.get(b"key").unwrap(), None);
+
+        // The panic released the key's lock
+        let value = db.get_or_insert_with(b"key", || b"value".to_vec()).unwrap();
+        assert_eq!(value, b"value".to_vec());
+    }
+
+    #[test]
+    fn test_get_or_insert_with_nested_for_other_keys() {
+        let db = Database::in_memory().unwrap();
+
+        for i in 0..200 {
+            let outer = format!("outer:{}", i);
+            let value = db
+                .get_or_insert_with(outer.as_bytes(), || {
+                    let inner = format!("inner:{}", i);
+                    let mut value = db
+                        .get_or_insert_with(inner.as_bytes(), || b"inner".to_vec())
+                        .unwrap();
+                    value.extend_from_slice(b"+outer");
+                    value
+                })
+                .unwrap();
+            assert_eq!(value, b"inner+outer".to_vec());
+        }
+        assert!(db.inner.key_locks.held.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_or_insert_with_keeps_concurrent_put() {
+        let db = Database::in_memory().unwrap();
+
+        let value = db
+            .get_or_insert_with(b"key", || {
+                db.put(b"key", b"put").unwrap();
+                b"computed".to_vec()
+            })
+            .unwrap();
+        assert_eq!(value, b"put".to_vec());
+        assert_eq!(db.get(b"key").unwrap(), Some(b"put".to_vec()));
+    }
+
+    #[test]
+    fn test_row_ids_only_for_indexed_records() {
+        let db = Database::in_memory().unwrap();
+        db.define_index(
+            "by_email",
+            IndexType::Hash,
+            Arc::new(|key: &[u8], value: &[u8]| key.starts_with(b"user:").then(|| value.to_vec())),
+        )
+        .unwrap();
+        let row_count = || db.inner.auto_indexes.lock().unwrap().row_ids.len();
+
+        for i in 0..100 {
+            db.put(format!("log:{}", i).as_bytes(), b"entry").unwrap();
+        }
+        assert_eq!(row_count(), 0);
+
+        db.put(b"user:1", b"a@example.com").unwrap();
+        db.put(b"user:2", b"a@example.com").unwrap();
+        assert_eq!(row_count(), 2);
+
+        // Removing one row keeps the other under the shared key
+        db.delete(b"user:1").unwrap();
+        assert_eq!(row_count(), 1);
+        assert_eq!(
+            db.index_find_keys("by_email", b"a@example.com").unwrap(),
+            vec![b"user:2".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_concurrent_writes_keep_auto_index_consistent() {
+        let db = Database::in_memory().unwrap();
+        db.define_index(
+            "by_value",
+            IndexType::Hash,
+            Arc::new(|_key: &[u8], value: &[u8]| Some(value.to_vec())),
+        )
+        .unwrap();
+
+        std::thread::scope(|scope| {
+            for t in 0..4 {
+                let db = &db;
+                scope.spawn(move || {
+                    for i in 0..200 {
+                        let key = format!("key:{}", i % 20);
+                        db.put(key.as_bytes(), format!("{}", (t + i) % 3).as_bytes())
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        // Each key is indexed exactly once, under its current value
+        let mut indexed = Vec::new();
+        for value in ["0", "1", "2"] {
+            for key in db.index_find_keys("by_value", value.as_bytes()).unwrap() {
+                assert_eq!(db.get(&key).unwrap(), Some(value.as_bytes().to_vec()));
+                indexed.push(key);
+            }
+        }
+        assert_eq!(indexed.len(), 20);
+        indexed.sort();
+        indexed.dedup();
+        assert_eq!(indexed.len(), 20);
+    }
+
+    #[test]
+    fn test_transactions_rejected_with_auto_indexes() {
+        let db = Database::in_memory().unwrap();
+        let txn = db.begin().unwrap();
+        let extractor: IndexExtractor = Arc::new(|_key: &[u8], value: &[u8]| Some(value.to_vec()));
+        assert!(matches!(
+            db.define_index("by_value", IndexType::Hash, extractor.clone()),
+            Err(Error::InvalidOperation(_))
+        ));
+        txn.rollback().unwrap();
+
+        db.define_index("by_value", IndexType::Hash, extractor)
+            .unwrap();
+        db.put(b"user:1", b"alice").unwrap();
+        assert!(matches!(db.begin(), Err(Error::InvalidOperation(_))));
+        assert_eq!(
+            db.index_find_keys("by_value", b"alice").unwrap(),
+            vec![b"user:1".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_empty_key_when_allowed() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        assert!(db.put(b"", b"root").is_err());
+
+        db.set_resource_limits(ResourceLimits {
+            allow_empty_key: true,
+        })
+        .unwrap();
+        db.put(b"", b"root").unwrap();
+        db.put(b"a", b"1").unwrap();
+        db.sync().unwrap();
+        db.put(b"b", b"2").unwrap();
+
+        // Served once from the SSTable and once from the memtable
+        assert_eq!(db.get(b"").unwrap(), Some(b"root".to_vec()));
+        db.put(b"", b"root2").unwrap();
+        assert_eq!(db.get(b"").unwrap(), Some(b"root2".to_vec()));
+
+        // The empty key sorts first and is a valid range start
+        let keys: Vec<Vec<u8>> = db
+            .scan_range(b"", b"b")
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec![b"".to_vec(), b"a".to_vec()]);
+        assert_eq!(db.scan_keys(b"").unwrap().len(), 3);
+        assert!(db.scan_range(b"a", b"").unwrap().is_empty());
+
+        db.delete_range(b"", b"a").unwrap();
+        assert_eq!(db.get(b"").unwrap(), None);
+        assert_eq!(db.get(b"a").unwrap(), Some(b"1".to_vec()));
+    }
+
+    #[test]
+    fn test_sstable_details() {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig {
+            enable_compaction: false,
+            ..Default::default()
+        };
+        let db = Database::open_with_config(dir.path(), config).unwrap();
+        assert!(db.sstable_details().unwrap().is_empty());
+
+        db.put(b"a1", b"1").unwrap();
+        db.put(b"a2", b"2").unwrap();
+        db.sync().unwrap();
+        db.put(b"m1", b"1").unwrap();
+        db.put(b"m2", b"2").unwrap();
+        db.put(b"m3", b"3").unwrap();
+        db.sync().unwrap();
+
+        let details = db.sstable_details().unwrap();
+        assert_eq!(details.len(), 2);
+        assert_eq!(details[0].min_key, b"a1".to_vec());
+        assert_eq!(details[0].max_key, b"a2".to_vec());
+        assert_eq!(details[0].entry_count, 2);
+        assert_eq!(details[1].min_key, b"m1".to_vec());
+        assert_eq!(details[1].max_key, b"m3".to_vec());
+        assert_eq!(details[1].entry_count, 3);
+        for meta in &details {
+            assert_eq!(meta.level, 0);
+            assert!(meta.path.exists());
+            assert_eq!(meta.file_size, std::fs::metadata(&meta.path).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn test_version_stats_after_gc() {
+        let db = Database::in_memory().unwrap();
+
+        // An open reader keeps every version reachable
+        let reader = db.begin().unwrap();
+        for i in 0..10 {
+            db.put(b"key", format!("v{}", i)).unwrap();
+        }
+        db.put(b"other", b"value").unwrap();
+        let stats = db.version_stats().unwrap();
+        assert_eq!(stats.keys, 2);
+        assert_eq!(stats.max_chain_depth, 10);
+        assert_eq!(stats.versions, 11);
+
+        drop(reader);
+        let watermark = db.version_stats().unwrap().gc_watermark;
+        db.gc().unwrap();
+        let stats = db.version_stats().unwrap();
+        assert_eq!(stats.max_chain_depth, 1);
+        assert_eq!(stats.versions, 2);
+        assert!(stats.gc_watermark > watermark);
+    }
+
+    #[test]
+    fn test_create_snapshot_of_live_database() {
+        let dir = tempdir().unwrap();
+        let backup = tempdir().unwrap();
+        let restored = tempdir().unwrap();
+
+        let db = Database::open(dir.path().join("db")).unwrap();
+        for i in 0..10u32 {
+            db.put(format!("key{}", i), format!("value{}", i)).unwrap();
+        }
+        db.delete(b"key3").unwrap();
+
+        // The writes are still only in the memtable and WAL
+        let snapshot = db.create_snapshot(backup.path().join("snap")).unwrap();
+        assert_eq!(snapshot.sequence, 11);
+
+        // Writes after the snapshot are not part of it
+        db.put(b"later", b"x").unwrap();
+
+        SnapshotManager::new(backup.path())
+            .unwrap()
+            .restore_snapshot(&snapshot, restored.path().join("db"))
+            .unwrap();
+        let copy = Database::open(restored.path().join("db")).unwrap();
+        for i in 0..10u32 {
+            let expected = (i != 3).then(|| format!("value{}", i).into_bytes());
+            assert_eq!(copy.get(format!("key{}", i)).unwrap(), expected);
+        }
+        assert_eq!(copy.get(b"later").unwrap(), None);
+
+        let memory = Database::in_memory().unwrap();
+        assert!(matches!(
+            memory.create_snapshot(backup.path().join("mem")),
+            Err(Error::InvalidOperation(_))
+        ));
+    }
+
+    #[test]
+    fn test_put_get_delete_accept_byte_like_types() {
+        let db = Database::in_memory().unwrap();
+
+        db.put("name", "Alice").unwrap();
+        db.put(String::from("city"), String::from("Paris")).unwrap();
+        db.put(b"id", vec![1u8, 2, 3]).unwrap();
+
+        assert_eq!(db.get("name").unwrap(), Some(b"Alice".to_vec()));
+        assert_eq!(
+            db.get(String::from("city")).unwrap(),
+            Some(b"Paris".to_vec())
+        );
+        let key = b"id".to_vec();
+        assert_eq!(db.get(&key).unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(db.get(key.as_slice()).unwrap(), Some(vec![1, 2, 3]));
+
+        assert!(db.delete("name").unwrap());
+        assert!(db.delete(String::from("city")).unwrap());
+        assert!(db.delete(key).unwrap());
+        assert_eq!(db.get("name").unwrap(), None);
+        assert_eq!(db.get(b"id").unwrap(), None);
+    }
+
+    #[test]
+    fn test_delete() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+
+        db.put(b"key", b"value").unwrap();
+        assert!(db.delete(b"key").unwrap());
+        assert_eq!(db.get(b"key").unwrap(), None);
+        assert!(!db.delete(b"key").unwrap()); // Already deleted
+    }
+
+    #[test]
+    fn test_put_value_too_large() {
+        let db = Database::in_memory().unwrap();
+
+        let large = vec![0u8; security::MAX_VALUE_SIZE + 1];
+        match db.put(b"key", &large) {
+            Err(Error::ValueTooLarge { size, limit }) => {
+                assert_eq!(size, security::MAX_VALUE_SIZE + 1);
+                assert_eq!(limit, security::MAX_VALUE_SIZE);
+            }
+            other => panic!("Expected ValueTooLarge, got {:?}", other),
+        }
+        assert_eq!(db.get(b"key").unwrap(), None);
+    }
+
+    #[test]
+    fn test_len_with_updates_and_deletes() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        assert!(db.is_empty().unwrap());
+
+        for i in 0..20 {
+            db.put(format!("key{:02}", i).as_bytes(), b"v1").unwrap();
+        }
+        db.sync().unwrap();
+
+        for i in 0..5 {
+            db.put(format!("key{:02}", i).as_bytes(), b"v2").unwrap();
+        }
+        for i in 5..8 {
+            db.delete(format!("key{:02}", i).as_bytes()).unwrap();
+        }
+
+        assert_eq!(db.len().unwrap(), 17);
+        assert!(db.approximate_len().unwrap() >= db.len().unwrap());
+
+        let mem = Database::in_memory().unwrap();
+        mem.put(b"a", b"1").unwrap();
+        mem.put(b"a", b"2").unwrap();
+        mem.put(b"b", b"1").unwrap();
+        mem.delete(b"b").unwrap();
+        assert_eq!(mem.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_clear() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+
+        db.put(b"key1", b"value1").unwrap();
+        db.sync().unwrap();
+        db.put(b"key2", b"value2").unwrap();
+
+        db.clear().unwrap();
+        assert_eq!(db.get(b"key1").unwrap(), None);
+        assert_eq!(db.get(b"key2").unwrap(), None);
+        assert!(db.is_empty().unwrap());
+
+        db.put(b"key3", b"value3").unwrap();
+        assert_eq!(db.get(b"key3").unwrap(), Some(b"value3".to_vec()));
+
+        let mem = Database::in_memory().unwrap();
+        mem.put(b"key", b"value").unwrap();
+        mem.clear().unwrap();
+        assert_eq!(mem.get(b"key").unwrap(), None);
+        mem.put(b"key", b"again").unwrap();
+        assert_eq!(mem.get(b"key").unwrap(), Some(b"again".to_vec()));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
+    #[derive(Default)]
+    struct CountingObserver {
+        puts: std::sync::atomic::AtomicUsize,
+        gets: std::sync::atomic::AtomicUsize,
+        hits: std::sync::atomic::AtomicUsize,
+        flushes: std::sync::atomic::AtomicUsize,
+    }
+
+    impl DbObserver for CountingObserver {
+        fn on_put(&self, _key_len: usize, _value_len: usize, _latency: std::time::Duration) {
+            self.puts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+
+        fn on_get(&self, _key_len: usize, hit: bool, _latency: std::time::Duration) {
+            self.gets.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if hit {
+                self.hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        fn on_flush(&self, _sstable: &SSTableMeta, _latency: std::time::Duration) {
+            self.flushes
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
 
     #[test]
-    fn test_version() {
-        assert_eq!(VERSION, "0.7.0");
+    fn test_observer_callbacks() {
+        use std::sync::atomic::Ordering;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        let observer = Arc::new(CountingObserver::default());
+        db.set_observer(observer.clone()).unwrap();
+
+        db.put(b"key1", b"value1").unwrap();
+        db.put(b"key2", b"value2").unwrap();
+        db.put(b"key3", b"value3").unwrap();
+
+        db.get(b"key1").unwrap();
+        db.get(b"missing").unwrap();
+
+        // Rejected writes are not reported
+        assert!(db.put(b"", b"value").is_err());
+
+        db.sync().unwrap();
+
+        assert_eq!(observer.puts.load(Ordering::SeqCst), 3);
+        assert_eq!(observer.gets.load(Ordering::SeqCst), 2);
+        assert_eq!(observer.hits.load(Ordering::SeqCst), 1);
+        assert_eq!(observer.flushes.load(Ordering::SeqCst), 1);
+    }
+
+    struct AddU64;
+
+    impl MergeOperator for AddU64 {
+        fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[Vec<u8>]) -> Vec<u8> {
+            let decode = |b: &[u8]| u64::from_le_bytes(b.try_into().unwrap());
+            let total =
+                operands.iter().map(|o| decode(o)).sum::<u64>() + existing.map_or(0, decode);
+            total.to_le_bytes().to_vec()
+        }
     }
 
     #[test]
-    fn test_in_memory_database() {
-        let db = Database::in_memory().unwrap();
-        db.put(b"key", b"value").unwrap();
-        assert_eq!(db.get(b"key").unwrap(), Some(b"value".to_vec()));
-        assert!(!db.is_persistent());
+    fn test_merge_counter_across_flush() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+
+        // Merging without an operator is rejected
+        assert!(db.merge(b"counter", &1u64.to_le_bytes()).is_err());
+
+        db.register_merge_operator(Arc::new(AddU64)).unwrap();
+
+        db.put(b"counter", 10u64.to_le_bytes()).unwrap();
+        db.merge(b"counter", &1u64.to_le_bytes()).unwrap();
+        db.merge(b"counter", &2u64.to_le_bytes()).unwrap();
+        db.sync().unwrap();
+
+        db.merge(b"counter", &3u64.to_le_bytes()).unwrap();
+        assert_eq!(
+            db.get(b"counter").unwrap(),
+            Some(16u64.to_le_bytes().to_vec())
+        );
+
+        // Operands with no base start from an empty value
+        db.merge(b"fresh", &5u64.to_le_bytes()).unwrap();
+        db.sync().unwrap();
+        db.merge(b"fresh", &5u64.to_le_bytes()).unwrap();
+        assert_eq!(
+            db.get(b"fresh").unwrap(),
+            Some(10u64.to_le_bytes().to_vec())
+        );
+
+        // Pending operands survive a restart via the WAL
+        drop(db);
+        let db = Database::open(dir.path()).unwrap();
+        db.register_merge_operator(Arc::new(AddU64)).unwrap();
+        db.merge(b"counter", &4u64.to_le_bytes()).unwrap();
+        assert_eq!(
+            db.get(b"counter").unwrap(),
+            Some(20u64.to_le_bytes().to_vec())
+        );
+        assert_eq!(db.len().unwrap(), 2);
+
+        let mem = Database::in_memory().unwrap();
+        mem.register_merge_operator(Arc::new(AddU64)).unwrap();
+        mem.merge(b"counter", &1u64.to_le_bytes()).unwrap();
+        mem.merge(b"counter", &2u64.to_le_bytes()).unwrap();
+        assert_eq!(
+            mem.get(b"counter").unwrap(),
+            Some(3u64.to_le_bytes().to_vec())
+        );
     }
 
     #[test]
-    fn test_persistent_database() {
+    fn test_delete_range_across_flush() {
         let dir = tempdir().unwrap();
         let db = Database::open(dir.path()).unwrap();
 
-        db.put(b"persist", b"data").unwrap();
-        assert_eq!(db.get(b"persist").unwrap(), Some(b"data".to_vec()));
-        assert!(db.is_persistent());
+        db.put(b"order:1", b"a").unwrap();
+        db.put(b"order:2", b"b").unwrap();
+        db.put(b"user:1", b"alice").unwrap();
+        db.sync().unwrap();
+
+        db.delete_range(b"order:", b"order;").unwrap();
+        db.put(b"order:3", b"c").unwrap();
+        db.sync().unwrap();
+
+        assert_eq!(db.get(b"order:1").unwrap(), None);
+        assert_eq!(db.get(b"order:2").unwrap(), None);
+        assert_eq!(db.get(b"order:3").unwrap(), Some(b"c".to_vec()));
+        assert_eq!(db.get(b"user:1").unwrap(), Some(b"alice".to_vec()));
+        assert_eq!(db.len().unwrap(), 2);
     }
 
     #[test]
-    fn test_persistence_across_reopens() {
+    fn test_scan_range_rev_honors_tombstones() {
         let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
 
-        // Write data
-        {
-            let db = Database::open(dir.path()).unwrap();
-            db.put(b"key1", b"value1").unwrap();
-            db.put(b"key2", b"value2").unwrap();
-            db.sync().unwrap();
+        for i in 1..=5 {
+            db.put(format!("event:{}", i).as_bytes(), b"v").unwrap();
         }
+        db.sync().unwrap();
+        db.delete(b"event:4").unwrap();
+        db.put(b"other", b"v").unwrap();
 
-        // Reopen and verify
-        {
-            let db = Database::open(dir.path()).unwrap();
-            assert_eq!(db.get(b"key1").unwrap(), Some(b"value1".to_vec()));
-            assert_eq!(db.get(b"key2").unwrap(), Some(b"value2".to_vec()));
-        }
+        let keys: Vec<Vec<u8>> = db
+            .scan_range_rev(b"event:", b"event:9")
+            .unwrap()
+            .map(|entry| entry.unwrap().0)
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                b"event:5".to_vec(),
+                b"event:3".to_vec(),
+                b"event:2".to_vec(),
+                b"event:1".to_vec()
+            ]
+        );
     }
 
     #[test]
-    fn test_delete() {
+    fn test_metrics_snapshot() {
         let dir = tempdir().unwrap();
         let db = Database::open(dir.path()).unwrap();
-
-        db.put(b"key", b"value").unwrap();
-        assert!(db.delete(b"key").unwrap());
-        assert_eq!(db.get(b"key").unwrap(), None);
-        assert!(!db.delete(b"key").unwrap()); // Already deleted
+        assert_eq!(db.metrics().puts, 0);
+
+        db.put(b"key1", b"value1").unwrap();
+        db.put(b"key2", b"value2").unwrap();
+        db.get(b"key1").unwrap();
+        db.get(b"missing").unwrap();
+        db.get(b"other").unwrap();
+        db.sync().unwrap();
+
+        let metrics = db.metrics();
+        assert_eq!(metrics.puts, 2);
+        assert_eq!(metrics.gets, 3);
+        assert_eq!(metrics.get_misses, 2);
+        assert_eq!(metrics.bytes_written, 20);
+        assert_eq!(metrics.flushes, 1);
+        assert_eq!(metrics.compactions, 0);
+        // SyncMode::Sync syncs each WAL append (two puts and the flush
+        // checkpoint), plus once for db.sync()
+        assert_eq!(metrics.wal_syncs, 4);
+        assert_eq!(metrics.sstables_per_level, vec![1]);
+
+        let mem = Database::in_memory().unwrap();
+        mem.put(b"k", b"v").unwrap();
+        let metrics = mem.metrics();
+        assert_eq!(metrics.puts, 1);
+        assert_eq!(metrics.bytes_written, 2);
+        assert_eq!(metrics.flushes, 0);
+        assert!(metrics.sstables_per_level.is_empty());
     }
 
     #[test]
@@ -855,6 +3816,48 @@ mod tests {
             .is_empty());
     }
 
+    #[test]
+    fn test_index_find_all_intersects_lookups() {
+        let db = Database::in_memory().unwrap();
+        db.create_index("products_by_category", IndexType::Hash)
+            .unwrap();
+        db.create_index("products_active", IndexType::BTree)
+            .unwrap();
+
+        for id in [3, 1, 4, 1, 5] {
+            db.index_insert("products_by_category", b"Electronics", id)
+                .unwrap();
+        }
+        db.index_insert("products_by_category", b"Books", 9)
+            .unwrap();
+        for id in [9, 5, 2, 1] {
+            db.index_insert("products_active", b"true", id).unwrap();
+        }
+
+        let ids = db
+            .index_find_all(&[
+                ("products_by_category", b"Electronics"),
+                ("products_active", b"true"),
+            ])
+            .unwrap();
+        assert_eq!(ids, vec![1, 5]);
+
+        assert_eq!(
+            db.index_find_all(&[("products_by_category", b"Electronics")])
+                .unwrap(),
+            vec![1, 3, 4, 5]
+        );
+        assert!(db
+            .index_find_all(&[
+                ("products_by_category", b"Toys"),
+                ("products_active", b"true"),
+            ])
+            .unwrap()
+            .is_empty());
+        assert!(db.index_find_all(&[]).unwrap().is_empty());
+        assert!(db.index_find_all(&[("missing", b"true")]).is_err());
+    }
+
     #[test]
     fn test_index_info() {
         let db = Database::in_memory().unwrap();
@@ -1235,6 +4238,85 @@ mod tests {
         assert_eq!(txn.get(b"key").unwrap(), Some(b"version9".to_vec()));
     }
 
+    #[test]
+    fn test_get_as_of_reads_history() {
+        let db = Database::in_memory().unwrap();
+
+        let mut timestamps = Vec::new();
+        for version in ["v1", "v2", "v3"] {
+            let mut txn = db.begin().unwrap();
+            txn.put(b"doc".to_vec(), version.as_bytes().to_vec())
+                .unwrap();
+            timestamps.push(txn.commit().unwrap());
+        }
+        assert!(timestamps.windows(2).all(|w| w[0] < w[1]));
+
+        for (ts, version) in timestamps.iter().zip(["v1", "v2", "v3"]) {
+            assert_eq!(
+                db.get_as_of(b"doc", *ts).unwrap(),
+                Some(version.as_bytes().to_vec())
+            );
+        }
+
+        // Older than every retained version
+        assert!(matches!(
+            db.get_as_of(b"doc", 0),
+            Err(Error::Transaction(msg)) if msg == "version garbage-collected"
+        ));
+
+        // GC keeps only the latest version, so history is gone
+        db.gc().unwrap();
+        assert!(db.get_as_of(b"doc", timestamps[0]).is_err());
+        assert_eq!(
+            db.get_as_of(b"doc", timestamps[2]).unwrap(),
+            Some(b"v3".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_get_as_of_uses_commit_timestamps() {
+        let db = Database::in_memory().unwrap();
+
+        let mut txn = db.begin().unwrap();
+        txn.put(b"doc".to_vec(), b"v1".to_vec()).unwrap();
+        let first = txn.commit().unwrap();
+
+        // Begins before the second commit, so its snapshot is older
+        let mut writer = db.begin().unwrap();
+        writer.put(b"doc".to_vec(), b"v2".to_vec()).unwrap();
+        let reader = db.begin().unwrap();
+        let second = writer.commit().unwrap();
+
+        assert_eq!(db.get_as_of(b"doc", first).unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(
+            db.get_as_of(b"doc", second - 1).unwrap(),
+            Some(b"v1".to_vec())
+        );
+        assert_eq!(db.get_as_of(b"doc", second).unwrap(), Some(b"v2".to_vec()));
+        assert_eq!(reader.get(b"doc").unwrap(), Some(b"v1".to_vec()));
+
+        let mut txn = db.begin().unwrap();
+        txn.delete(b"doc").unwrap();
+        let deleted = txn.commit().unwrap();
+        assert_eq!(
+            db.get_as_of(b"doc", deleted - 1).unwrap(),
+            Some(b"v2".to_vec())
+        );
+        assert_eq!(db.get_as_of(b"doc", deleted).unwrap(), None);
+    }
+
+    #[test]
+    fn test_get_as_of_without_history_fails() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        db.put(b"key", b"value").unwrap();
+
+        assert!(matches!(
+            db.get_as_of(b"key", u64::MAX),
+            Err(Error::Transaction(msg)) if msg == "no version history for key"
+        ));
+    }
+
     #[test]
     fn test_persistent_transactions() {
         let dir = tempdir().unwrap();
@@ -1267,6 +4349,234 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_persistent_direct_writes_visible_to_transactions() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+
+        db.put(b"direct", b"value").unwrap();
+
+        let mut txn = db.begin().unwrap();
+        assert_eq!(txn.get(b"direct").unwrap(), Some(b"value".to_vec()));
+        assert_eq!(txn.get(b"missing").unwrap(), None);
+
+        // A delete inside the transaction hides the storage engine's value
+        txn.delete(b"direct").unwrap();
+        assert_eq!(txn.get(b"direct").unwrap(), None);
+        txn.commit().unwrap();
+
+        let txn = db.begin().unwrap();
+        assert_eq!(txn.get(b"direct").unwrap(), None);
+    }
+
+    #[test]
+    fn test_persistent_direct_writes_after_transaction_visible() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+
+        let mut txn = db.begin().unwrap();
+        txn.put(b"key".to_vec(), b"txn".to_vec()).unwrap();
+        txn.put(b"gone".to_vec(), b"txn".to_vec()).unwrap();
+        txn.commit().unwrap();
+
+        // A transaction already running keeps its snapshot
+        let running = db.begin().unwrap();
+        db.put(b"key", b"direct").unwrap();
+        db.delete(b"gone").unwrap();
+        assert_eq!(running.get(b"key").unwrap(), Some(b"txn".to_vec()));
+
+        // Later transactions see the direct writes, not the older versions
+        let txn = db.begin().unwrap();
+        assert_eq!(txn.get(b"key").unwrap(), Some(b"direct".to_vec()));
+        assert_eq!(txn.get(b"gone").unwrap(), None);
+        drop(txn);
+        drop(running);
+
+        db.put(b"key", b"again").unwrap();
+        let txn = db.begin().unwrap();
+        assert_eq!(txn.get(b"key").unwrap(), Some(b"again".to_vec()));
+    }
+
+    #[test]
+    fn test_persistent_direct_write_conflicts_with_locked_key() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+        db.put(b"counter", b"1").unwrap();
+
+        // The key has no version chain, only the engine's value
+        let mut txn = db.begin().unwrap();
+        assert_eq!(txn.get_for_update(b"counter").unwrap(), Some(b"1".to_vec()));
+        db.put(b"counter", b"100").unwrap();
+
+        // Committing would overwrite the direct write, so it conflicts
+        txn.put(b"counter".to_vec(), b"2".to_vec()).unwrap();
+        assert!(matches!(txn.commit(), Err(Error::Transaction(_))));
+        assert_eq!(db.get(b"counter").unwrap(), Some(b"100".to_vec()));
+    }
+
+    #[test]
+    fn test_disk_usage() {
+        let dir = tempdir().unwrap();
+        let db = Database::open(dir.path()).unwrap();
+
+        for i in 0..100 {
+            db.put(format!("key{:03}", i).as_bytes(), b"value").unwrap();
+        }
+        let before_flush = db.disk_usage().unwrap();
+        assert!(before_flush > 0, "WAL segments should be counted");
+
+        db.sync().unwrap();
+        let after_flush = db.disk_usage().unwrap();
+        assert!(after_flush > before_flush);
+        assert_eq!(db.metrics().sstables_per_level.iter().sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn test_memtable_pressure() {
+        let dir = tempdir().unwrap();
+        let config = StorageConfig {
+            memtable_size: 64 * 1024,
+            ..Default::default()
+        };
+        let db = Database::open_with_config(dir.path(), config).unwrap();
+        assert_eq!(db.memtable_pressure().unwrap(), 0.0);
+
+        db.put(b"key0", [0u8; 1024]).unwrap();
+        let low = db.memtable_pressure().unwrap();
+        for i in 1..32 {
+            db.put(format!("key{}", i).as_bytes(), [0u8; 1024]).unwrap();
+        }
+        let high = db.memtable_pressure().unwrap();
+        assert!(low > 0.0);
+        assert!(high > low && high < 1.0);
+
+        db.sync().unwrap();
+        assert!(db.memtable_pressure().unwrap() < 0.01);
+    }
+
+    #[test]
+    fn test_wal_stats_reports_incomplete_transactions() {
+        let dir = tempdir().unwrap();
+
+        // Simulate a crash: one committed transaction and one that never
+        // reached its commit marker
+        {
+            let mut wal = WalManager::new(WalConfig {
+                wal_dir: dir.path().join("wal"),
+                ..Default::default()
+            })
+            .unwrap();
+            wal.open().unwrap();
+            wal.append(WalRecord::begin_tx(1)).unwrap();
+            wal.append(WalRecord::put(b"a".to_vec(), b"1".to_vec()))
+                .unwrap();
+            wal.append(WalRecord::commit_tx(1)).unwrap();
+            wal.append(WalRecord::begin_tx(2)).unwrap();
+            wal.append(WalRecord::put(b"b".to_vec(), b"2".to_vec()))
+                .unwrap();
+        }
+
+        let db = Database::open(dir.path()).unwrap();
+        let stats = db.wal_stats().unwrap();
+        assert_eq!(stats.transactions_started, 2);
+        assert_eq!(stats.transactions_committed, 1);
+        assert_eq!(stats.transactions_incomplete, 1);
+        assert_eq!(db.get(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get(b"b").unwrap(), None);
+
+        let memory = Database::in_memory().unwrap();
+        assert_eq!(memory.wal_stats().unwrap().total_records, 0);
+    }
+
+    #[test]
+    fn test_recovery_interleaves_standalone_and_transaction_records() {
+        let dir = tempdir().unwrap();
+
+        // Standalone writes before and after a committed transaction
+        {
+            let mut wal = WalManager::new(WalConfig {
+                wal_dir: dir.path().join("wal"),
+                ..Default::default()
+            })
+            .unwrap();
+            wal.open().unwrap();
+            wal.append(WalRecord::put(b"A".to_vec(), b"standalone".to_vec()))
+                .unwrap();
+            wal.append(WalRecord::begin_tx(1)).unwrap();
+            wal.append(WalRecord::put(b"B".to_vec(), b"transaction".to_vec()))
+                .unwrap();
+            wal.append(WalRecord::commit_tx(1)).unwrap();
+            wal.append(WalRecord::put(b"B".to_vec(), b"standalone".to_vec()))
+                .unwrap();
+        }
+
+        let db = Database::open(dir.path()).unwrap();
+        assert_eq!(db.get(b"A").unwrap(), Some(b"standalone".to_vec()));
+        assert_eq!(db.get(b"B").unwrap(), Some(b"standalone".to_vec()));
+    }
+
+    #[test]
+    fn test_transaction_commit_is_one_wal_batch() {
+        let dir = tempdir().unwrap();
+        {
+            let db = Database::open(dir.path()).unwrap();
+            db.put(b"old", b"value").unwrap();
+
+            let mut txn = db.begin().unwrap();
+            for i in 0..100 {
+                txn.put(format!("key:{:03}", i).into_bytes(), b"v".to_vec())
+                    .unwrap();
+            }
+            txn.delete(b"old").unwrap();
+
+            let syncs = db.metrics().wal_syncs;
+            txn.commit().unwrap();
+            assert_eq!(db.metrics().wal_syncs, syncs + 1);
+            assert_eq!(db.get(b"key:042").unwrap(), Some(b"v".to_vec()));
+        }
+
+        // The committed writes are recovered from the WAL
+        {
+            let db = Database::open(dir.path()).unwrap();
+            assert_eq!(db.get(b"key:000").unwrap(), Some(b"v".to_vec()));
+            assert_eq!(db.get(b"key:099").unwrap(), Some(b"v".to_vec()));
+            assert_eq!(db.get(b"old").unwrap(), None);
+
+            let mut txn = db.begin().unwrap();
+            for i in 0..100 {
+                txn.put(format!("lost:{:03}", i).into_bytes(), b"v".to_vec())
+                    .unwrap();
+            }
+            txn.commit().unwrap();
+        }
+
+        // A crash before the COMMIT_TX record reached disk loses the whole
+        // transaction
+        let mut segments: Vec<_> = std::fs::read_dir(dir.path().join("wal"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        segments.sort();
+        let segment = segments.last().unwrap();
+        // The transaction id is encoded at a fixed width, so any id gives
+        // the length of the record to cut off
+        let commit_len = WalRecord::commit_tx(1).encoded_len().unwrap() as u64;
+        let len = std::fs::metadata(segment).unwrap().len();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(segment)
+            .unwrap()
+            .set_len(len - commit_len)
+            .unwrap();
+
+        let db = Database::open(dir.path()).unwrap();
+        for i in 0..100 {
+            let key = format!("lost:{:03}", i);
+            assert_eq!(db.get(key.as_bytes()).unwrap(), None);
+        }
+        assert_eq!(db.get(b"key:050").unwrap(), Some(b"v".to_vec()));
+    }
+
     #[test]
     fn test_transaction_with_large_dataset() {
         let db = Database::in_memory().unwrap();
@@ -1311,6 +4621,33 @@ mod tests {
         assert_eq!(read_txn.get(b"txn").unwrap(), Some(b"value2".to_vec()));
     }
 
+    #[test]
+    fn test_in_memory_direct_writes_visible_to_transactions() {
+        let db = Database::in_memory().unwrap();
+
+        let earlier = db.begin().unwrap();
+        db.put(b"direct", b"value1").unwrap();
+
+        // A transaction started after the put sees it; an earlier one does not
+        let txn = db.begin().unwrap();
+        assert_eq!(txn.get(b"direct").unwrap(), Some(b"value1".to_vec()));
+        assert_eq!(earlier.get(b"direct").unwrap(), None);
+
+        // Committed transactional writes are visible to direct reads
+        let mut txn = db.begin().unwrap();
+        txn.put(b"txn".to_vec(), b"value2".to_vec()).unwrap();
+        assert_eq!(db.get(b"txn").unwrap(), None);
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"txn").unwrap(), Some(b"value2".to_vec()));
+        assert_eq!(db.len().unwrap(), 2);
+
+        // Direct deletes hide the key from later transactions
+        assert!(db.delete(b"direct").unwrap());
+        let txn = db.begin().unwrap();
+        assert_eq!(txn.get(b"direct").unwrap(), None);
+        assert_eq!(db.len().unwrap(), 1);
+    }
+
     #[test]
     fn test_serializable_isolation() {
         let db = Database::in_memory().unwrap();
@@ -0,0 +1,74 @@
+//! Pull-based metrics for RustLite
+//!
+//! [`DbMetrics`] is a point-in-time snapshot of cumulative counters that can
+//! be exported to Prometheus or any other monitoring system. Counters are
+//! maintained with relaxed atomics on the hot paths, so taking a snapshot is
+//! cheap and does not require installing a `DbObserver`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Snapshot of database counters returned by `Database::metrics`.
+///
+/// All counters are cumulative since the database handle was opened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DbMetrics {
+    /// Number of successful puts
+    pub puts: u64,
+    /// Number of successful gets
+    pub gets: u64,
+    /// Number of gets that found no value
+    pub get_misses: u64,
+    /// Key and value bytes accepted by puts and merges
+    pub bytes_written: u64,
+    /// Number of memtable flushes (persistent databases only)
+    pub flushes: u64,
+    /// Number of compactions (persistent databases only)
+    pub compactions: u64,
+    /// Number of WAL syncs to disk (persistent databases only)
+    pub wal_syncs: u64,
+    /// Current number of SSTables at each level (persistent databases only)
+    pub sstables_per_level: Vec<usize>,
+}
+
+/// Hot-path counters backing [`DbMetrics`]
+#[derive(Debug, Default)]
+pub(crate) struct MetricsCounters {
+    puts: AtomicU64,
+    gets: AtomicU64,
+    get_misses: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl MetricsCounters {
+    /// Record a successful put of `bytes` key and value bytes
+    pub(crate) fn record_put(&self, bytes: usize) {
+        self.puts.fetch_add(1, Ordering::Relaxed);
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Record a successful merge of `bytes` key and operand bytes
+    pub(crate) fn record_merge(&self, bytes: usize) {
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Record a successful get
+    pub(crate) fn record_get(&self, hit: bool) {
+        self.gets.fetch_add(1, Ordering::Relaxed);
+        if !hit {
+            self.get_misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Snapshot the counters into a `DbMetrics` with no storage metrics
+    pub(crate) fn snapshot(&self) -> DbMetrics {
+        DbMetrics {
+            puts: self.puts.load(Ordering::Relaxed),
+            gets: self.gets.load(Ordering::Relaxed),
+            get_misses: self.get_misses.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            ..DbMetrics::default()
+        }
+    }
+}
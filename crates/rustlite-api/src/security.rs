@@ -4,30 +4,56 @@
 /// to prevent security vulnerabilities and resource exhaustion attacks.
 use rustlite_core::error::{Error, Result};
 
+/// Maximum key size accepted by the database (16 MB)
+pub const MAX_KEY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Maximum value size accepted by the database (1 GB)
+pub const MAX_VALUE_SIZE: usize = 1024 * 1024 * 1024;
+
+/// Input limits applied to database operations
+///
+/// # Examples
+///
+/// ```rust
+/// use rustlite::{Database, ResourceLimits};
+///
+/// let db = Database::in_memory()?;
+/// db.set_resource_limits(ResourceLimits {
+///     allow_empty_key: true,
+/// })?;
+/// db.put(b"", b"root")?;
+/// assert_eq!(db.get(b"")?, Some(b"root".to_vec()));
+/// # Ok::<(), rustlite::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResourceLimits {
+    /// Accept the empty key, for example as a root or sentinel entry. It
+    /// sorts before every other key. Defaults to false.
+    pub allow_empty_key: bool,
+}
+
 /// Validates database key
 ///
 /// # Security
 ///
-/// - Prevents empty keys
+/// - Prevents empty keys, unless `limits` allows them
 /// - Prevents oversized keys (>16MB)
 ///
 /// # Errors
 ///
-/// Returns Error::InvalidInput if validation fails
+/// Returns Error::InvalidInput if the key is empty and not allowed, or
+/// Error::KeyTooLarge if it exceeds the size limit
 #[inline]
-pub fn validate_key(key: &[u8]) -> Result<()> {
-    const MAX_KEY_SIZE: usize = 16 * 1024 * 1024; // 16 MB
-
-    if key.is_empty() {
+pub fn validate_key(key: &[u8], limits: &ResourceLimits) -> Result<()> {
+    if key.is_empty() && !limits.allow_empty_key {
         return Err(Error::InvalidInput("Key cannot be empty".to_string()));
     }
 
     if key.len() > MAX_KEY_SIZE {
-        return Err(Error::InvalidInput(format!(
-            "Key size {} exceeds maximum {}",
-            key.len(),
-            MAX_KEY_SIZE
-        )));
+        return Err(Error::KeyTooLarge {
+            size: key.len(),
+            limit: MAX_KEY_SIZE,
+        });
     }
 
     Ok(())
@@ -41,17 +67,14 @@ pub fn validate_key(key: &[u8]) -> Result<()> {
 ///
 /// # Errors
 ///
-/// Returns Error::InvalidInput if validation fails
+/// Returns Error::ValueTooLarge if the value exceeds the size limit
 #[inline]
 pub fn validate_value(value: &[u8]) -> Result<()> {
-    const MAX_VALUE_SIZE: usize = 1024 * 1024 * 1024; // 1 GB
-
     if value.len() > MAX_VALUE_SIZE {
-        return Err(Error::InvalidInput(format!(
-            "Value size {} exceeds maximum {}",
-            value.len(),
-            MAX_VALUE_SIZE
-        )));
+        return Err(Error::ValueTooLarge {
+            size: value.len(),
+            limit: MAX_VALUE_SIZE,
+        });
     }
 
     Ok(())
@@ -174,15 +197,34 @@ mod tests {
 
     #[test]
     fn test_validate_key() {
+        let limits = ResourceLimits::default();
+
         // Valid
-        assert!(validate_key(b"valid").is_ok());
+        assert!(validate_key(b"valid", &limits).is_ok());
 
         // Empty
-        assert!(validate_key(b"").is_err());
+        assert!(validate_key(b"", &limits).is_err());
 
         // Too large
         let large = vec![0u8; 17 * 1024 * 1024];
-        assert!(validate_key(&large).is_err());
+        assert!(matches!(
+            validate_key(&large, &limits),
+            Err(Error::KeyTooLarge { size, limit })
+                if size == large.len() && limit == MAX_KEY_SIZE
+        ));
+    }
+
+    #[test]
+    fn test_validate_key_allow_empty() {
+        let limits = ResourceLimits {
+            allow_empty_key: true,
+        };
+        assert!(validate_key(b"", &limits).is_ok());
+        assert!(validate_key(b"", &ResourceLimits::default()).is_err());
+
+        // The size limit still applies
+        let large = vec![0u8; 17 * 1024 * 1024];
+        assert!(validate_key(&large, &limits).is_err());
     }
 
     #[test]
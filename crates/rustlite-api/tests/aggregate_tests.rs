@@ -1,5 +1,5 @@
 /// Tests for GROUP BY, HAVING, and aggregate functions
-use rustlite::{Column, Database, ExecutionContext, Row, Value};
+use rustlite::{Column, Database, Error, ExecutionContext, Row, Value};
 
 #[test]
 fn test_count_aggregate() {
@@ -510,3 +510,204 @@ fn test_count_with_nulls() {
     assert_eq!(results_column.len(), 1);
     assert_eq!(results_column[0].values[0], Value::Integer(2)); // Only 2 non-NULL values
 }
+
+#[test]
+fn test_ungrouped_column_with_aggregate_errors() {
+    let db = Database::in_memory().unwrap();
+
+    let users = || {
+        vec![Row {
+            columns: vec![
+                Column {
+                    name: "name".to_string(),
+                    alias: None,
+                },
+                Column {
+                    name: "department".to_string(),
+                    alias: None,
+                },
+            ],
+            values: vec![
+                Value::String("Alice".to_string()),
+                Value::String("Engineering".to_string()),
+            ],
+        }]
+    };
+
+    for sql in [
+        "SELECT name, COUNT(*) FROM users",
+        "SELECT department, name, COUNT(*) FROM users GROUP BY department",
+    ] {
+        let plan = db.prepare(sql).unwrap();
+        let mut context = ExecutionContext::new();
+        context.data.insert("users".to_string(), users());
+
+        match db.execute_plan(&plan, context) {
+            Err(Error::InvalidInput(msg)) => assert_eq!(
+                msg,
+                "column 'name' must appear in GROUP BY or be aggregated"
+            ),
+            other => panic!("Expected InvalidInput for {}, got {:?}", sql, other),
+        }
+    }
+}
+
+#[test]
+fn test_count_with_where() {
+    let db = Database::in_memory().unwrap();
+    let row = |age: i64| Row {
+        columns: vec![Column {
+            name: "age".to_string(),
+            alias: None,
+        }],
+        values: vec![Value::Integer(age)],
+    };
+
+    let mut context = ExecutionContext::new();
+    context.data.insert(
+        "users".to_string(),
+        vec![row(12), row(18), row(30), row(45)],
+    );
+
+    // Only rows passing the WHERE condition are counted
+    let plan = db
+        .prepare("SELECT COUNT(*) AS adults FROM users WHERE age > 18")
+        .unwrap();
+    let results = db.execute_plan(&plan, context.clone()).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].values[0], Value::Integer(2));
+
+    // A condition matching nothing counts zero rows
+    let plan = db
+        .prepare("SELECT COUNT(*) FROM users WHERE age > 100")
+        .unwrap();
+    let results = db.execute_plan(&plan, context).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].values[0], Value::Integer(0));
+}
+
+#[test]
+fn test_aggregate_column_names() {
+    let db = Database::in_memory().unwrap();
+    let row = |dept: &str, age: i64| Row {
+        columns: vec![
+            Column {
+                name: "dept".to_string(),
+                alias: None,
+            },
+            Column {
+                name: "age".to_string(),
+                alias: None,
+            },
+        ],
+        values: vec![Value::String(dept.to_string()), Value::Integer(age)],
+    };
+    let mut context = ExecutionContext::new();
+    context
+        .data
+        .insert("users".to_string(), vec![row("a", 20), row("b", 30)]);
+    let names = |sql: &str| -> Vec<String> {
+        let plan = db.prepare(sql).unwrap();
+        let results = db.execute_plan(&plan, context.clone()).unwrap();
+        results[0].columns.iter().map(|c| c.name.clone()).collect()
+    };
+
+    // An alias names the output column
+    assert_eq!(names("SELECT COUNT(*) AS total FROM users"), ["total"]);
+
+    // Generated names are upper case whatever the query's case
+    assert_eq!(
+        names("select count(*), sum(age), max(age) as oldest from users"),
+        ["COUNT(*)", "SUM(age)", "oldest"]
+    );
+    assert_eq!(
+        names("SELECT dept, COUNT(*) AS n, avg(age) FROM users GROUP BY dept"),
+        ["dept", "n", "AVG(age)"]
+    );
+}
+
+#[test]
+fn test_first_last_and_group_concat() {
+    let db = Database::in_memory().unwrap();
+    let row = |category: &str, name: Option<&str>| Row {
+        columns: vec![
+            Column {
+                name: "category".to_string(),
+                alias: None,
+            },
+            Column {
+                name: "name".to_string(),
+                alias: None,
+            },
+        ],
+        values: vec![
+            Value::String(category.to_string()),
+            name.map_or(Value::Null, |n| Value::String(n.to_string())),
+        ],
+    };
+    let mut context = ExecutionContext::new();
+    context.data.insert(
+        "products".to_string(),
+        vec![
+            row("fruit", Some("apple")),
+            row("tools", Some("hammer")),
+            row("fruit", None),
+            row("fruit", Some("cherry")),
+            row("tools", Some("saw")),
+        ],
+    );
+
+    let plan = db
+        .prepare(
+            "SELECT category, FIRST(name) AS first_name, LAST(name), \
+             GROUP_CONCAT(name, ', ') AS names, GROUP_CONCAT(name) \
+             FROM products GROUP BY category",
+        )
+        .unwrap();
+    let results = db.execute_plan(&plan, context).unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        results[0]
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>(),
+        [
+            "category",
+            "first_name",
+            "LAST(name)",
+            "names",
+            "GROUP_CONCAT(name)"
+        ]
+    );
+
+    let group = |category: &str| {
+        results
+            .iter()
+            .find(|r| r.values[0] == Value::String(category.to_string()))
+            .unwrap()
+            .values
+            .clone()
+    };
+    let text = |s: &str| Value::String(s.to_string());
+
+    // NULL names are left out of GROUP_CONCAT
+    assert_eq!(
+        group("fruit")[1..],
+        [
+            text("apple"),
+            text("cherry"),
+            text("apple, cherry"),
+            text("apple,cherry")
+        ]
+    );
+    assert_eq!(
+        group("tools")[1..],
+        [
+            text("hammer"),
+            text("saw"),
+            text("hammer, saw"),
+            text("hammer,saw")
+        ]
+    );
+}
@@ -0,0 +1,146 @@
+/// Tests for databases running on a custom KvBackend
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rustlite::{Database, Error, KvBackend, Result};
+
+/// Ordered map backend whose writes can be made to fail
+#[derive(Default)]
+struct MapBackend {
+    map: Mutex<BTreeMap<Vec<u8>, Vec<u8>>>,
+    fail_writes: AtomicBool,
+    /// Rows returned by scans
+    rows_read: AtomicUsize,
+}
+
+impl MapBackend {
+    fn check_writable(&self) -> Result<()> {
+        if self.fail_writes.load(Ordering::SeqCst) {
+            return Err(Error::Storage("injected write failure".to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl KvBackend for MapBackend {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let map = self.map.lock().map_err(|_| Error::LockPoisoned)?;
+        Ok(map.get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.check_writable()?;
+        let mut map = self.map.lock().map_err(|_| Error::LockPoisoned)?;
+        map.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<()> {
+        self.check_writable()?;
+        let mut map = self.map.lock().map_err(|_| Error::LockPoisoned)?;
+        map.remove(key);
+        Ok(())
+    }
+
+    fn scan_range(&self, start: &[u8], end: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let map = self.map.lock().map_err(|_| Error::LockPoisoned)?;
+        let rows: Vec<_> = map
+            .range(start.to_vec()..end.to_vec())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        self.rows_read.fetch_add(rows.len(), Ordering::SeqCst);
+        Ok(rows)
+    }
+
+    fn entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let map = self.map.lock().map_err(|_| Error::LockPoisoned)?;
+        self.rows_read.fetch_add(map.len(), Ordering::SeqCst);
+        Ok(map.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+}
+
+#[test]
+fn test_custom_backend_basic_operations() {
+    let backend = Arc::new(MapBackend::default());
+    let db = Database::with_backend(Box::new(Arc::clone(&backend)));
+    assert!(!db.is_persistent());
+
+    db.put(b"user:1", b"alice").unwrap();
+    db.put(b"user:2", b"bob").unwrap();
+    db.put(b"order:1", b"book").unwrap();
+    assert_eq!(db.get(b"user:1").unwrap(), Some(b"alice".to_vec()));
+    assert_eq!(db.get(b"missing").unwrap(), None);
+    assert_eq!(backend.get(b"user:2").unwrap(), Some(b"bob".to_vec()));
+
+    db.put(b"user:1", b"alicia").unwrap();
+    assert_eq!(db.get(b"user:1").unwrap(), Some(b"alicia".to_vec()));
+
+    assert!(db.delete(b"user:2").unwrap());
+    assert!(!db.delete(b"user:2").unwrap());
+    assert_eq!(db.get(b"user:2").unwrap(), None);
+
+    assert_eq!(db.scan_keys(b"user:").unwrap(), vec![b"user:1".to_vec()]);
+    assert_eq!(db.scan_range(b"order:", b"order;").unwrap().len(), 1);
+    assert_eq!(db.len().unwrap(), 2);
+
+    db.delete_range(b"user:", b"user;").unwrap();
+    assert_eq!(db.get(b"user:1").unwrap(), None);
+    db.clear().unwrap();
+    assert!(backend.entries().unwrap().is_empty());
+}
+
+#[test]
+fn test_custom_backend_transactions_write_through() {
+    let backend = Arc::new(MapBackend::default());
+    let db = Database::with_backend(Box::new(Arc::clone(&backend)));
+    db.put(b"balance", b"10").unwrap();
+
+    let mut txn = db.begin().unwrap();
+    assert_eq!(txn.get(b"balance").unwrap(), Some(b"10".to_vec()));
+    txn.put(b"balance".to_vec(), b"20".to_vec()).unwrap();
+    txn.commit().unwrap();
+
+    assert_eq!(backend.get(b"balance").unwrap(), Some(b"20".to_vec()));
+    assert_eq!(db.get(b"balance").unwrap(), Some(b"20".to_vec()));
+}
+
+#[test]
+fn test_custom_backend_write_failures_surface() {
+    let backend = Arc::new(MapBackend::default());
+    let db = Database::with_backend(Box::new(Arc::clone(&backend)));
+    db.put(b"key", b"value").unwrap();
+
+    backend.fail_writes.store(true, Ordering::SeqCst);
+    assert!(matches!(db.put(b"key", b"other"), Err(Error::Storage(_))));
+    assert!(db.delete(b"key").is_err());
+
+    let mut txn = db.begin().unwrap();
+    txn.put(b"key".to_vec(), b"txn".to_vec()).unwrap();
+    assert!(txn.commit().is_err());
+
+    backend.fail_writes.store(false, Ordering::SeqCst);
+    assert_eq!(db.get(b"key").unwrap(), Some(b"value".to_vec()));
+}
+
+#[test]
+fn test_custom_backend_iter_prefix_reads_only_prefix() {
+    let backend = Arc::new(MapBackend::default());
+    let db = Database::with_backend(Box::new(Arc::clone(&backend)));
+    for i in 0..100 {
+        db.put(format!("other:{:03}", i).as_bytes(), b"x").unwrap();
+    }
+    for i in 0..10 {
+        db.put(format!("item:{:03}", i).as_bytes(), b"v").unwrap();
+    }
+
+    backend.rows_read.store(0, Ordering::SeqCst);
+    let items: Vec<_> = db
+        .iter_prefix(b"item:")
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(items.len(), 10);
+    assert_eq!(items[0].0, b"item:000");
+    assert_eq!(backend.rows_read.load(Ordering::SeqCst), 10);
+}
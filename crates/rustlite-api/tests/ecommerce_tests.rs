@@ -8,8 +8,12 @@
 //! - Inventory tracking
 //! - Search and filtering
 
+// Values are passed as borrowed `Vec`s, which the `AsRef` signatures accept
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use rustlite::{Database, IndexType, Result};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct Customer {
@@ -587,3 +591,161 @@ fn test_concurrent_cart_operations() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_defined_index_auto_maintenance() -> Result<()> {
+    let db = Database::in_memory()?;
+
+    // Index customers by email without any manual index_insert calls
+    db.define_index(
+        "by_email",
+        IndexType::Hash,
+        Arc::new(|key: &[u8], value: &[u8]| {
+            if !key.starts_with(b"customer:") {
+                return None;
+            }
+            let customer: Customer = bincode::deserialize(value).ok()?;
+            Some(customer.email.into_bytes())
+        }),
+    )?;
+
+    let mut customer = Customer {
+        id: 1,
+        email: "john@example.com".to_string(),
+        name: "John Doe".to_string(),
+        created_at: 1640000000,
+    };
+    db.put(b"customer:1", &bincode::serialize(&customer).unwrap())?;
+
+    // Records the extractor skips are not indexed
+    db.put(b"product:1", b"not a customer")?;
+
+    assert_eq!(db.index_find("by_email", b"john@example.com")?.len(), 1);
+    assert_eq!(
+        db.index_find_keys("by_email", b"john@example.com")?,
+        vec![b"customer:1".to_vec()]
+    );
+
+    // Changing the email moves the index entry
+    customer.email = "johnny@example.com".to_string();
+    db.put(b"customer:1", &bincode::serialize(&customer).unwrap())?;
+    assert!(db.index_find("by_email", b"john@example.com")?.is_empty());
+    assert_eq!(
+        db.index_find_keys("by_email", b"johnny@example.com")?,
+        vec![b"customer:1".to_vec()]
+    );
+
+    // Deleting the customer removes it from the index
+    db.delete(b"customer:1")?;
+    assert!(db.index_find("by_email", b"johnny@example.com")?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_defined_index_update_replaces_stale_entry() -> Result<()> {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Database::open(dir.path())?;
+
+    // Several customers can share an email domain
+    db.define_index(
+        "by_domain",
+        IndexType::BTree,
+        Arc::new(|key: &[u8], value: &[u8]| {
+            if !key.starts_with(b"customer:") {
+                return None;
+            }
+            let customer: Customer = bincode::deserialize(value).ok()?;
+            let (_, domain) = customer.email.split_once('@')?;
+            Some(domain.as_bytes().to_vec())
+        }),
+    )?;
+
+    let mut customers: Vec<Customer> = (1..=2)
+        .map(|id| Customer {
+            id,
+            email: format!("user{}@example.com", id),
+            name: format!("Customer {}", id),
+            created_at: 1640000000 + id,
+        })
+        .collect();
+    for customer in &customers {
+        let key = format!("customer:{}", customer.id);
+        db.put(key.as_bytes(), bincode::serialize(customer).unwrap())?;
+    }
+
+    // The previous value is read back from an SSTable
+    db.sync()?;
+
+    customers[0].email = "user1@example.org".to_string();
+    db.put(b"customer:1", bincode::serialize(&customers[0]).unwrap())?;
+    assert_eq!(
+        db.index_find_keys("by_domain", b"example.com")?,
+        vec![b"customer:2".to_vec()]
+    );
+    assert_eq!(
+        db.index_find_keys("by_domain", b"example.org")?,
+        vec![b"customer:1".to_vec()]
+    );
+
+    // A value the extractor skips drops the entry without adding one
+    db.put(b"customer:2", b"not a customer")?;
+    assert!(db.index_find("by_domain", b"example.com")?.is_empty());
+    assert_eq!(db.index_find("by_domain", b"example.org")?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_rebuild_customer_index_from_data() -> Result<()> {
+    let db = setup_ecommerce_db()?;
+
+    for id in 1..=3 {
+        let customer = Customer {
+            id,
+            email: format!("customer{}@example.com", id),
+            name: format!("Customer {}", id),
+            created_at: 1640000000 + id,
+        };
+        let key = format!("customer:{}", customer.id);
+        db.put(key.as_bytes(), &bincode::serialize(&customer).unwrap())?;
+        db.index_insert("customers_by_email", customer.email.as_bytes(), customer.id)?;
+    }
+    db.put(b"product:1", b"not a customer")?;
+
+    // The index drifts from the data: one entry lost, one stale entry added
+    db.index_remove("customers_by_email", b"customer2@example.com")?;
+    db.index_insert("customers_by_email", b"ghost@example.com", 99)?;
+    assert!(db
+        .index_find("customers_by_email", b"customer2@example.com")?
+        .is_empty());
+
+    db.rebuild_index(
+        "customers_by_email",
+        Arc::new(|key: &[u8], value: &[u8]| {
+            if !key.starts_with(b"customer:") {
+                return None;
+            }
+            let customer: Customer = bincode::deserialize(value).ok()?;
+            Some((customer.email.into_bytes(), customer.id))
+        }),
+    )?;
+
+    for id in 1..=3 {
+        let email = format!("customer{}@example.com", id);
+        assert_eq!(
+            db.index_find("customers_by_email", email.as_bytes())?,
+            vec![id]
+        );
+    }
+    assert!(db
+        .index_find("customers_by_email", b"ghost@example.com")?
+        .is_empty());
+
+    // Rebuilding an index that does not exist fails
+    assert!(db
+        .rebuild_index("missing", Arc::new(|_: &[u8], _: &[u8]| None))
+        .is_err());
+
+    Ok(())
+}
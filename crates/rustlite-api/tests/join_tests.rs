@@ -1,4 +1,4 @@
-use rustlite::{Column, Database, ExecutionContext, Row, Value};
+use rustlite::{Column, Database, Error, ExecutionContext, Row, Value};
 
 #[test]
 fn test_inner_join() {
@@ -480,3 +480,170 @@ fn test_join_empty_tables() {
     let results = db.execute_plan(&plan, context).unwrap();
     assert_eq!(results.len(), 0);
 }
+
+#[test]
+fn test_in_subquery() {
+    let db = Database::in_memory().unwrap();
+    let plan = db
+        .prepare(
+            "SELECT name FROM users WHERE id IN (SELECT user_id FROM orders WHERE amount > 50)",
+        )
+        .unwrap();
+
+    let user_columns = vec![
+        Column {
+            name: "id".to_string(),
+            alias: None,
+        },
+        Column {
+            name: "name".to_string(),
+            alias: None,
+        },
+    ];
+    let order_columns = vec![
+        Column {
+            name: "user_id".to_string(),
+            alias: None,
+        },
+        Column {
+            name: "amount".to_string(),
+            alias: None,
+        },
+    ];
+
+    let mut context = ExecutionContext::new();
+    context.data.insert(
+        "users".to_string(),
+        [(1, "Alice"), (2, "Bob"), (3, "Carol")]
+            .into_iter()
+            .map(|(id, name)| Row {
+                columns: user_columns.clone(),
+                values: vec![Value::Integer(id), Value::String(name.to_string())],
+            })
+            .collect(),
+    );
+    context.data.insert(
+        "orders".to_string(),
+        [(1, 100), (1, 75), (2, 20), (3, 60)]
+            .into_iter()
+            .map(|(user_id, amount)| Row {
+                columns: order_columns.clone(),
+                values: vec![Value::Integer(user_id), Value::Integer(amount)],
+            })
+            .collect(),
+    );
+
+    let results = db.execute_plan(&plan, context).unwrap();
+    let names: Vec<_> = results.iter().map(|r| r.values[0].clone()).collect();
+    assert_eq!(
+        names,
+        vec![
+            Value::String("Alice".to_string()),
+            Value::String("Carol".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_in_subquery_outside_where_not_supported() {
+    let db = Database::in_memory().unwrap();
+    let plan = db
+        .prepare(
+            "SELECT user_id, COUNT(*) FROM orders GROUP BY user_id \
+             HAVING user_id IN (SELECT id FROM users)",
+        )
+        .unwrap();
+
+    let mut context = ExecutionContext::new();
+    context.data.insert(
+        "orders".to_string(),
+        vec![Row {
+            columns: vec![Column {
+                name: "user_id".to_string(),
+                alias: None,
+            }],
+            values: vec![Value::Integer(1)],
+        }],
+    );
+    context.data.insert("users".to_string(), Vec::new());
+
+    assert!(matches!(
+        db.execute_plan(&plan, context),
+        Err(Error::NotSupported(_))
+    ));
+}
+
+#[test]
+fn test_join_qualified_columns_with_same_name() {
+    let db = Database::in_memory().unwrap();
+
+    let column = |name: &str| Column {
+        name: name.to_string(),
+        alias: None,
+    };
+
+    let mut context = ExecutionContext::new();
+    context.data.insert(
+        "users".to_string(),
+        vec![
+            Row {
+                columns: vec![column("id"), column("name")],
+                values: vec![Value::Integer(1), Value::String("Alice".to_string())],
+            },
+            Row {
+                columns: vec![column("id"), column("name")],
+                values: vec![Value::Integer(2), Value::String("Bob".to_string())],
+            },
+        ],
+    );
+    context.data.insert(
+        "orders".to_string(),
+        vec![
+            Row {
+                columns: vec![column("id"), column("user_id")],
+                values: vec![Value::Integer(101), Value::Integer(2)],
+            },
+            Row {
+                columns: vec![column("id"), column("user_id")],
+                values: vec![Value::Integer(102), Value::Integer(1)],
+            },
+        ],
+    );
+
+    let plan = db
+        .prepare(
+            "SELECT users.id, orders.id FROM users INNER JOIN orders ON users.id = orders.user_id \
+             WHERE orders.id = 101",
+        )
+        .unwrap();
+    let results = db.execute_plan(&plan, context.clone()).unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0].values,
+        vec![Value::Integer(2), Value::Integer(101)]
+    );
+    let names: Vec<_> = results[0].columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["users.id", "orders.id"]);
+
+    // Wildcard keeps both `id` columns, and sorting by `orders.id` picks
+    // the orders column rather than the first `id`
+    let plan = db
+        .prepare(
+            "SELECT * FROM users INNER JOIN orders ON users.id = orders.user_id \
+             ORDER BY orders.id DESC",
+        )
+        .unwrap();
+    let results = db.execute_plan(&plan, context).unwrap();
+    let names: Vec<_> = results[0].columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "name", "id", "user_id"]);
+    assert_eq!(
+        results[0].values,
+        vec![
+            Value::Integer(1),
+            Value::String("Alice".to_string()),
+            Value::Integer(102),
+            Value::Integer(1)
+        ]
+    );
+}
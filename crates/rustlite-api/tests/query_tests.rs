@@ -1,4 +1,6 @@
-use rustlite::{Column, Database, ExecutionContext, Row, Value};
+use rustlite::{Column, Database, ExecutionContext, IndexType, Parser, Planner, Row, Value};
+use serde::Deserialize;
+use std::sync::Arc;
 
 #[test]
 fn test_simple_select_all() {
@@ -525,3 +527,456 @@ fn test_complex_query() {
     assert_eq!(results.len(), 3); // Alice, Dave, Bob (Carol filtered out)
     assert_eq!(results[0].values[0], Value::String("Alice".to_string())); // Youngest first
 }
+
+fn product_rows() -> Vec<Row> {
+    let columns = vec![
+        Column {
+            name: "name".to_string(),
+            alias: None,
+        },
+        Column {
+            name: "category".to_string(),
+            alias: None,
+        },
+    ];
+    [
+        ("Widget", Value::String("A".to_string())),
+        ("Gadget", Value::String("B".to_string())),
+        ("Gizmo", Value::String("C".to_string())),
+        ("Doohickey", Value::String("D".to_string())),
+        ("Mystery", Value::Null),
+    ]
+    .into_iter()
+    .map(|(name, category)| Row {
+        columns: columns.clone(),
+        values: vec![Value::String(name.to_string()), category],
+    })
+    .collect()
+}
+
+#[test]
+fn test_select_with_not_in() {
+    let db = Database::in_memory().unwrap();
+    let plan = db
+        .prepare("SELECT name FROM products WHERE category NOT IN ('A', 'B')")
+        .unwrap();
+
+    let mut context = ExecutionContext::new();
+    context.data.insert("products".to_string(), product_rows());
+
+    let results = db.execute_plan(&plan, context).unwrap();
+    let names: Vec<_> = results.iter().map(|r| r.values[0].clone()).collect();
+    // The NULL category is neither in nor not in the list
+    assert_eq!(
+        names,
+        vec![
+            Value::String("Gizmo".to_string()),
+            Value::String("Doohickey".to_string()),
+        ]
+    );
+
+    // A NULL in the list makes NOT IN unknown for every row
+    let plan = db
+        .prepare("SELECT name FROM products WHERE category NOT IN ('A', NULL)")
+        .unwrap();
+    let mut context = ExecutionContext::new();
+    context.data.insert("products".to_string(), product_rows());
+    assert!(db.execute_plan(&plan, context).unwrap().is_empty());
+}
+
+#[test]
+fn test_truncate_and_drop_table() {
+    let db = Database::in_memory().unwrap();
+
+    let mut context = ExecutionContext::new();
+    context.data.insert("products".to_string(), product_rows());
+
+    let results = db.execute("SELECT * FROM products", &mut context).unwrap();
+    assert_eq!(results.len(), 5);
+
+    let results = db.execute("TRUNCATE TABLE products", &mut context).unwrap();
+    assert!(results.is_empty());
+    assert!(context.data.contains_key("products"));
+
+    let results = db.execute("SELECT * FROM products", &mut context).unwrap();
+    assert!(results.is_empty());
+
+    db.execute("DROP TABLE products", &mut context).unwrap();
+    assert!(!context.data.contains_key("products"));
+    assert!(db.execute("DROP TABLE products", &mut context).is_err());
+    assert!(db.execute("TRUNCATE products", &mut context).is_err());
+
+    // The context methods report whether the table existed
+    context.data.insert("products".to_string(), product_rows());
+    context.truncate_table("products");
+    assert!(context.data["products"].is_empty());
+    assert!(context.drop_table("products"));
+    assert!(!context.drop_table("products"));
+}
+
+fn priced_rows(prices: Vec<Value>) -> Vec<Row> {
+    let columns = vec![
+        Column {
+            name: "id".to_string(),
+            alias: None,
+        },
+        Column {
+            name: "price".to_string(),
+            alias: None,
+        },
+    ];
+    prices
+        .into_iter()
+        .enumerate()
+        .map(|(id, price)| Row {
+            columns: columns.clone(),
+            values: vec![Value::Integer(id as i64), price],
+        })
+        .collect()
+}
+
+fn select_ids(db: &Database, sql: &str, rows: Vec<Row>) -> Vec<Value> {
+    let plan = db.prepare(sql).unwrap();
+    let mut context = ExecutionContext::new();
+    context.data.insert("items".to_string(), rows);
+    db.execute_plan(&plan, context)
+        .unwrap()
+        .into_iter()
+        .map(|r| r.values[0].clone())
+        .collect()
+}
+
+#[test]
+fn test_select_with_between() {
+    let db = Database::in_memory().unwrap();
+
+    // String bounds are inclusive at both ends
+    let plan = db
+        .prepare("SELECT name FROM products WHERE name BETWEEN 'Gadget' AND 'Gizmo'")
+        .unwrap();
+    let mut context = ExecutionContext::new();
+    context.data.insert("products".to_string(), product_rows());
+    let names: Vec<_> = db
+        .execute_plan(&plan, context)
+        .unwrap()
+        .into_iter()
+        .map(|r| r.values[0].clone())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            Value::String("Gadget".to_string()),
+            Value::String("Gizmo".to_string()),
+        ]
+    );
+
+    let integer_prices = || {
+        priced_rows(vec![
+            Value::Integer(1),
+            Value::Integer(5),
+            Value::Integer(10),
+            Value::Integer(15),
+        ])
+    };
+
+    // Integer column with integer, float and mixed bounds
+    assert_eq!(
+        select_ids(
+            &db,
+            "SELECT id FROM items WHERE price BETWEEN 5 AND 10",
+            integer_prices()
+        ),
+        vec![Value::Integer(1), Value::Integer(2)]
+    );
+    assert_eq!(
+        select_ids(
+            &db,
+            "SELECT id FROM items WHERE price BETWEEN 1.0 AND 9.9",
+            integer_prices()
+        ),
+        vec![Value::Integer(0), Value::Integer(1)]
+    );
+    assert_eq!(
+        select_ids(
+            &db,
+            "SELECT id FROM items WHERE price BETWEEN 5.0 AND 15",
+            integer_prices()
+        ),
+        vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]
+    );
+    assert_eq!(
+        select_ids(
+            &db,
+            "SELECT id FROM items WHERE price NOT BETWEEN 1.5 AND 10",
+            integer_prices()
+        ),
+        vec![Value::Integer(0), Value::Integer(3)]
+    );
+
+    // Float column with integer bounds
+    let float_prices = priced_rows(vec![
+        Value::Float(0.5),
+        Value::Float(1.0),
+        Value::Float(2.5),
+        Value::Float(3.0),
+        Value::Float(3.5),
+    ]);
+    assert_eq!(
+        select_ids(
+            &db,
+            "SELECT id FROM items WHERE price BETWEEN 1 AND 3",
+            float_prices
+        ),
+        vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]
+    );
+}
+
+#[test]
+fn test_select_with_offset_without_limit() {
+    let db = Database::in_memory().unwrap();
+    let plan = db.prepare("SELECT name FROM products OFFSET 2").unwrap();
+
+    let mut context = ExecutionContext::new();
+    context.data.insert("products".to_string(), product_rows());
+
+    let names: Vec<_> = db
+        .execute_plan(&plan, context)
+        .unwrap()
+        .into_iter()
+        .map(|r| r.values[0].clone())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            Value::String("Gizmo".to_string()),
+            Value::String("Doohickey".to_string()),
+            Value::String("Mystery".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_case_insensitive_identifiers() {
+    let db = Database::in_memory().unwrap();
+    let users = || {
+        let columns = vec![
+            Column {
+                name: "name".to_string(),
+                alias: None,
+            },
+            Column {
+                name: "age".to_string(),
+                alias: None,
+            },
+        ];
+        [("Alice", 30), ("Bob", 17), ("Carol", 45)]
+            .into_iter()
+            .map(|(name, age)| Row {
+                columns: columns.clone(),
+                values: vec![Value::String(name.to_string()), Value::Integer(age)],
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Keywords are matched in any case
+    let plan = db
+        .prepare("select Name from Users where Age > 18 and users.AGE < 100")
+        .unwrap();
+
+    // Identifiers are case-sensitive by default
+    let mut context = ExecutionContext::new();
+    context.data.insert("users".to_string(), users());
+    assert!(db.execute_plan(&plan, context).unwrap().is_empty());
+
+    let mut context = ExecutionContext::new();
+    context.data.insert("users".to_string(), users());
+    context.case_insensitive_identifiers = true;
+    let results = db.execute_plan(&plan, context).unwrap();
+    let names: Vec<_> = results.iter().map(|r| r.values[0].clone()).collect();
+    assert_eq!(
+        names,
+        vec![
+            Value::String("Alice".to_string()),
+            Value::String("Carol".to_string()),
+        ]
+    );
+    // The output column keeps the spelling used in the query
+    assert_eq!(results[0].columns[0].name, "Name");
+}
+
+#[test]
+fn test_execution_context_uses_database_indexes() {
+    let db = Database::in_memory().unwrap();
+    db.define_index(
+        "users_by_value",
+        IndexType::Hash,
+        Arc::new(|key: &[u8], value: &[u8]| key.starts_with(b"users:").then(|| value.to_vec())),
+    )
+    .unwrap();
+    db.put(b"users:1", b"alice").unwrap();
+    db.put(b"users:2", b"bob").unwrap();
+    db.put(b"users:3", b"alice").unwrap();
+    db.put(b"orders:1", b"alice").unwrap();
+
+    let context = db.execution_context(&["users", "orders"]).unwrap();
+    assert_eq!(context.data["users"].len(), 3);
+    assert_eq!(context.data["orders"].len(), 1);
+
+    // The WHERE on the indexed column is planned as an index scan
+    let sql = "SELECT key FROM users WHERE value = 'alice'";
+    let query = Parser::new(sql).unwrap().parse().unwrap();
+    let plan = Planner::with_indexes(context.index_metadata.clone())
+        .plan(&query)
+        .unwrap();
+    assert!(plan.to_string().contains("IndexScan(users.users_by_value)"));
+
+    let rows = db.query(sql, context).unwrap();
+    let mut keys: Vec<Value> = rows.into_iter().map(|r| r.values[0].clone()).collect();
+    keys.sort_by_key(|v| v.to_string());
+    assert_eq!(
+        keys,
+        vec![
+            Value::String("1".to_string()),
+            Value::String("3".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_execution_context_skips_indexes_on_other_tables_or_values() {
+    let db = Database::in_memory().unwrap();
+    db.define_index(
+        "by_value",
+        IndexType::Hash,
+        Arc::new(|_key: &[u8], value: &[u8]| Some(value.to_vec())),
+    )
+    .unwrap();
+    db.define_index(
+        "by_upper_value",
+        IndexType::Hash,
+        Arc::new(|key: &[u8], value: &[u8]| {
+            key.starts_with(b"users:")
+                .then(|| value.to_ascii_uppercase())
+        }),
+    )
+    .unwrap();
+    db.put(b"users:1", b"alice").unwrap();
+    db.put(b"orders:1", b"alice").unwrap();
+    db.put(b"orders:2", b"bob").unwrap();
+
+    let keys = |tables: &[&str], sql: &str| {
+        let context = db.execution_context(tables).unwrap();
+        let query = Parser::new(sql).unwrap().parse().unwrap();
+        let plan = Planner::with_indexes(context.index_metadata.clone())
+            .plan(&query)
+            .unwrap()
+            .to_string();
+        let rows = db.query(sql, context).unwrap();
+        let keys: Vec<String> = rows.iter().map(|r| r.values[0].to_string()).collect();
+        (plan, keys)
+    };
+
+    // An index covering several of the tables is not used for any of them
+    let (plan, rows) = keys(
+        &["users", "orders"],
+        "SELECT key FROM orders WHERE value = 'bob'",
+    );
+    assert!(!plan.contains("IndexScan"));
+    assert_eq!(rows, vec!["2"]);
+
+    // With a single table it is
+    let (plan, rows) = keys(&["orders"], "SELECT key FROM orders WHERE value = 'bob'");
+    assert!(plan.contains("IndexScan(orders.by_value)"));
+    assert_eq!(rows, vec!["2"]);
+
+    // An index whose keys are not the column's values is never used
+    let (plan, rows) = keys(&["users"], "SELECT key FROM users WHERE value = 'alice'");
+    assert!(!plan.contains("by_upper_value"));
+    assert_eq!(rows, vec!["1"]);
+}
+
+#[test]
+fn test_key_like_matches_regardless_of_plan() {
+    let db = Database::in_memory().unwrap();
+    for key in ["order:1", "order:2", "user:1", "xorder:3"] {
+        db.put(format!("kv:{}", key).as_bytes(), b"v").unwrap();
+    }
+
+    for sql in [
+        "SELECT key FROM kv WHERE key LIKE 'order:%'",
+        "SELECT key FROM kv WHERE key LIKE 'order:%' OR key = 'zzz'",
+        "SELECT key FROM kv WHERE key LIKE 'o_der:%'",
+    ] {
+        let context = db.execution_context(&["kv"]).unwrap();
+        let rows = db.query(sql, context).unwrap();
+        let keys: Vec<String> = rows.iter().map(|r| r.values[0].to_string()).collect();
+        assert_eq!(keys, vec!["order:1", "order:2"], "{}", sql);
+    }
+}
+
+#[test]
+fn test_rows_deserialize_into_structs() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct User {
+        name: String,
+        years: i64,
+    }
+
+    let db = Database::in_memory().unwrap();
+    let plan = db
+        .prepare("SELECT name, age AS years FROM users ORDER BY age")
+        .unwrap();
+
+    let column = |name: &str| Column {
+        name: name.to_string(),
+        alias: None,
+    };
+    let user = |id: i64, name: &str, age: i64| Row {
+        columns: vec![column("id"), column("name"), column("age")],
+        values: vec![
+            Value::Integer(id),
+            Value::String(name.to_string()),
+            Value::Integer(age),
+        ],
+    };
+    let mut context = ExecutionContext::new();
+    context.data.insert(
+        "users".to_string(),
+        vec![user(1, "Alice", 30), user(2, "Bob", 25)],
+    );
+
+    let rows = db.execute_plan(&plan, context).unwrap();
+    assert_eq!(
+        rows[0].get_column("name"),
+        Some(&Value::String("Bob".to_string()))
+    );
+    assert_eq!(rows[0].get_column("years"), Some(&Value::Integer(25)));
+    assert_eq!(rows[0].get_column("id"), None);
+
+    let users: Vec<User> = rows
+        .iter()
+        .map(|row| row.try_into_struct())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        users,
+        vec![
+            User {
+                name: "Bob".to_string(),
+                years: 25,
+            },
+            User {
+                name: "Alice".to_string(),
+                years: 30,
+            },
+        ]
+    );
+
+    // A column of the wrong type is reported, not panicked on
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Misnamed {
+        name: i64,
+    }
+    assert!(rows[0].try_into_struct::<Misnamed>().is_err());
+}
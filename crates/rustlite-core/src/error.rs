@@ -26,11 +26,40 @@ pub enum Error {
     /// Invalid input (e.g., invalid SQL query)
     InvalidInput(String),
 
+    /// Valid input using a feature this build does not implement
+    NotSupported(String),
+
+    /// Key exceeds the maximum allowed size
+    KeyTooLarge {
+        /// Size of the rejected key in bytes
+        size: usize,
+        /// Maximum allowed key size in bytes
+        limit: usize,
+    },
+
+    /// Value exceeds the maximum allowed size
+    ValueTooLarge {
+        /// Size of the rejected value in bytes
+        size: usize,
+        /// Maximum allowed value size in bytes
+        limit: usize,
+    },
+
     /// Not found
     NotFound,
 
     /// Data corruption detected
     Corruption(String),
+
+    /// On-disk format version is newer than this build supports
+    UnsupportedVersion {
+        /// File format that was being opened (e.g. "SSTable", "WAL", "Manifest")
+        component: String,
+        /// Version found on disk
+        found: u32,
+        /// Highest version this build can read
+        max_supported: u32,
+    },
 }
 
 impl fmt::Display for Error {
@@ -43,8 +72,24 @@ impl fmt::Display for Error {
             Error::Transaction(msg) => write!(f, "Transaction error: {}", msg),
             Error::InvalidOperation(msg) => write!(f, "Invalid operation: {}", msg),
             Error::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            Error::NotSupported(msg) => write!(f, "Not supported: {}", msg),
+            Error::KeyTooLarge { size, limit } => {
+                write!(f, "Key size {} exceeds maximum {}", size, limit)
+            }
+            Error::ValueTooLarge { size, limit } => {
+                write!(f, "Value size {} exceeds maximum {}", size, limit)
+            }
             Error::NotFound => write!(f, "Not found"),
             Error::Corruption(msg) => write!(f, "Data corruption: {}", msg),
+            Error::UnsupportedVersion {
+                component,
+                found,
+                max_supported,
+            } => write!(
+                f,
+                "Unsupported {} format version: {} (max supported: {})",
+                component, found, max_supported
+            ),
         }
     }
 }
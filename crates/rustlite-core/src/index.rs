@@ -26,10 +26,12 @@
 //! assert_eq!(hash.find(b"session:abc").unwrap(), vec![500]);
 //! ```
 
+use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 /// Index type enumeration
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IndexType {
     /// B-Tree index for ordered data and range queries
     BTree,
@@ -55,6 +57,17 @@ pub trait Index: Send + Sync {
     /// The value is typically a pointer/offset to the actual data.
     fn insert(&mut self, key: &[u8], value: u64) -> crate::Result<()>;
 
+    /// Insert many key-value pairs at once.
+    ///
+    /// Equivalent to calling `insert` for each entry in order. Indexes
+    /// override it to size their storage once for the whole batch.
+    fn insert_many(&mut self, entries: &[(Vec<u8>, u64)]) -> crate::Result<()> {
+        for (key, value) in entries {
+            self.insert(key, *value)?;
+        }
+        Ok(())
+    }
+
     /// Find all values matching the exact key.
     fn find(&self, key: &[u8]) -> crate::Result<Vec<u64>>;
 
@@ -62,6 +75,24 @@ pub trait Index: Send + Sync {
     /// Returns true if any entries were removed.
     fn remove(&mut self, key: &[u8]) -> crate::Result<bool>;
 
+    /// Remove a single value from a key's entries, keeping its other values.
+    /// Returns true if the value was present.
+    ///
+    /// The default rewrites the key's entries; indexes override it to
+    /// remove the value in place.
+    fn remove_value(&mut self, key: &[u8], value: u64) -> crate::Result<bool> {
+        let mut values = self.find(key)?;
+        let Some(position) = values.iter().position(|&v| v == value) else {
+            return Ok(false);
+        };
+        values.remove(position);
+        self.remove(key)?;
+        for value in values {
+            self.insert(key, value)?;
+        }
+        Ok(true)
+    }
+
     /// Returns the number of entries in the index.
     fn len(&self) -> usize;
 
@@ -70,9 +101,24 @@ pub trait Index: Send + Sync {
         self.len() == 0
     }
 
+    /// Returns the number of distinct keys in the index.
+    ///
+    /// Defaults to `len()` for indexes that hold a single value per key.
+    fn key_count(&self) -> usize {
+        self.len()
+    }
+
     /// Clear all entries from the index.
     fn clear(&mut self);
 
+    /// Returns every key in the index with its values.
+    ///
+    /// Defaults to no entries, for indexes that cannot list their contents;
+    /// such indexes are never used to answer queries.
+    fn entries(&self) -> Vec<(Vec<u8>, Vec<u64>)> {
+        Vec::new()
+    }
+
     /// Returns the index type.
     fn index_type(&self) -> IndexType;
 }
@@ -146,6 +192,26 @@ impl BTreeIndex {
         Ok(results)
     }
 
+    /// Reverse range query: find all entries where key is in [start, end]
+    /// inclusive.
+    ///
+    /// Returns a vector of (key, values) pairs in descending key order.
+    pub fn range_rev(&self, start: &[u8], end: &[u8]) -> crate::Result<Vec<(Vec<u8>, Vec<u64>)>> {
+        use std::ops::Bound;
+
+        let results: Vec<_> = self
+            .tree
+            .range((
+                Bound::Included(start.to_vec()),
+                Bound::Included(end.to_vec()),
+            ))
+            .rev()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        Ok(results)
+    }
+
     /// Prefix scan: find all entries where key starts with the given prefix.
     ///
     /// Returns a vector of (key, values) pairs in sorted order.
@@ -189,6 +255,25 @@ impl Index for BTreeIndex {
         Ok(())
     }
 
+    fn insert_many(&mut self, entries: &[(Vec<u8>, u64)]) -> crate::Result<()> {
+        // Group the batch by key (the sort is stable, so each key's values
+        // keep their order) and touch the tree once per distinct key
+        let mut sorted: Vec<&(Vec<u8>, u64)> = entries.iter().collect();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for group in sorted.chunk_by(|a, b| a.0 == b.0) {
+            let values = group.iter().map(|(_, value)| *value);
+            match self.tree.get_mut(&group[0].0) {
+                Some(existing) => existing.extend(values),
+                None => {
+                    self.tree.insert(group[0].0.clone(), values.collect());
+                }
+            }
+        }
+        self.entry_count += entries.len();
+        Ok(())
+    }
+
     fn find(&self, key: &[u8]) -> crate::Result<Vec<u64>> {
         Ok(self.tree.get(key).cloned().unwrap_or_default())
     }
@@ -202,15 +287,38 @@ impl Index for BTreeIndex {
         }
     }
 
+    fn remove_value(&mut self, key: &[u8], value: u64) -> crate::Result<bool> {
+        let Some(values) = self.tree.get_mut(key) else {
+            return Ok(false);
+        };
+        let Some(position) = values.iter().position(|&v| v == value) else {
+            return Ok(false);
+        };
+        values.remove(position);
+        if values.is_empty() {
+            self.tree.remove(key);
+        }
+        self.entry_count -= 1;
+        Ok(true)
+    }
+
     fn len(&self) -> usize {
         self.entry_count
     }
 
+    fn key_count(&self) -> usize {
+        self.tree.len()
+    }
+
     fn clear(&mut self) {
         self.tree.clear();
         self.entry_count = 0;
     }
 
+    fn entries(&self) -> Vec<(Vec<u8>, Vec<u64>)> {
+        self.iter().map(|(k, v)| (k.to_vec(), v.to_vec())).collect()
+    }
+
     fn index_type(&self) -> IndexType {
         IndexType::BTree
     }
@@ -255,6 +363,8 @@ pub struct HashIndex {
     map: HashMap<Vec<u8>, Vec<u64>>,
     /// Total number of key-value pairs
     entry_count: usize,
+    /// Optional bloom filter answering negative `contains_key` lookups
+    bloom: Option<BloomFilter>,
 }
 
 impl HashIndex {
@@ -263,6 +373,7 @@ impl HashIndex {
         Self {
             map: HashMap::new(),
             entry_count: 0,
+            bloom: None,
         }
     }
 
@@ -271,11 +382,31 @@ impl HashIndex {
         Self {
             map: HashMap::with_capacity(capacity),
             entry_count: 0,
+            bloom: None,
+        }
+    }
+
+    /// Create a new Hash index backed by a bloom filter sized for `capacity` keys.
+    ///
+    /// A negative `contains_key` is answered by the filter alone; a positive
+    /// filter hit still falls through to the map. The filter cannot forget
+    /// keys, so removed keys cost a map lookup, and the false-positive rate
+    /// grows once the index holds more than `capacity` distinct keys.
+    pub fn with_bloom(capacity: usize) -> Self {
+        Self {
+            map: HashMap::with_capacity(capacity),
+            entry_count: 0,
+            bloom: Some(BloomFilter::new(capacity)),
         }
     }
 
     /// Check if the index contains a key.
     pub fn contains_key(&self, key: &[u8]) -> bool {
+        if let Some(bloom) = &self.bloom {
+            if !bloom.may_contain(key) {
+                return false;
+            }
+        }
         self.map.contains_key(key)
     }
 
@@ -298,11 +429,32 @@ impl Default for HashIndex {
 
 impl Index for HashIndex {
     fn insert(&mut self, key: &[u8], value: u64) -> crate::Result<()> {
+        if let Some(bloom) = &mut self.bloom {
+            bloom.insert(key);
+        }
         self.map.entry(key.to_vec()).or_default().push(value);
         self.entry_count += 1;
         Ok(())
     }
 
+    fn insert_many(&mut self, entries: &[(Vec<u8>, u64)]) -> crate::Result<()> {
+        // Room for every key being new, so the map rehashes at most once
+        self.map.reserve(entries.len());
+        for (key, value) in entries {
+            if let Some(bloom) = &mut self.bloom {
+                bloom.insert(key);
+            }
+            match self.map.get_mut(key) {
+                Some(values) => values.push(*value),
+                None => {
+                    self.map.insert(key.clone(), vec![*value]);
+                }
+            }
+        }
+        self.entry_count += entries.len();
+        Ok(())
+    }
+
     fn find(&self, key: &[u8]) -> crate::Result<Vec<u64>> {
         Ok(self.map.get(key).cloned().unwrap_or_default())
     }
@@ -316,13 +468,39 @@ impl Index for HashIndex {
         }
     }
 
+    fn remove_value(&mut self, key: &[u8], value: u64) -> crate::Result<bool> {
+        let Some(values) = self.map.get_mut(key) else {
+            return Ok(false);
+        };
+        let Some(position) = values.iter().position(|&v| v == value) else {
+            return Ok(false);
+        };
+        values.remove(position);
+        if values.is_empty() {
+            self.map.remove(key);
+        }
+        self.entry_count -= 1;
+        Ok(true)
+    }
+
     fn len(&self) -> usize {
         self.entry_count
     }
 
+    fn key_count(&self) -> usize {
+        self.map.len()
+    }
+
     fn clear(&mut self) {
         self.map.clear();
         self.entry_count = 0;
+        if let Some(bloom) = &mut self.bloom {
+            bloom.clear();
+        }
+    }
+
+    fn entries(&self) -> Vec<(Vec<u8>, Vec<u64>)> {
+        self.iter().map(|(k, v)| (k.to_vec(), v.to_vec())).collect()
     }
 
     fn index_type(&self) -> IndexType {
@@ -330,6 +508,52 @@ impl Index for HashIndex {
     }
 }
 
+/// Bloom filter over index keys.
+///
+/// Uses ~10 bits per expected key and 7 probes (about 1% false positives at
+/// capacity), deriving the probes from one 64-bit hash by double hashing.
+#[derive(Debug, Clone)]
+struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+}
+
+impl BloomFilter {
+    const BITS_PER_KEY: usize = 10;
+    const NUM_PROBES: u64 = 7;
+
+    fn new(capacity: usize) -> Self {
+        let num_bits = (capacity.max(1) * Self::BITS_PER_KEY).next_multiple_of(64);
+        Self {
+            bits: vec![0; num_bits / 64],
+            num_bits: num_bits as u64,
+        }
+    }
+
+    fn probes(num_bits: u64, key: &[u8]) -> impl Iterator<Item = u64> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+        let (h1, h2) = (hash, hash.rotate_left(32) | 1);
+        (0..Self::NUM_PROBES).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    fn insert(&mut self, key: &[u8]) {
+        for bit in Self::probes(self.num_bits, key) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    fn may_contain(&self, key: &[u8]) -> bool {
+        Self::probes(self.num_bits, key)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+    }
+}
+
 // ============================================================================
 // Index Manager
 // ============================================================================
@@ -376,20 +600,22 @@ impl IndexManager {
             )));
         }
 
-        let index: Box<dyn Index> = match index_type {
-            IndexType::BTree => Box::new(BTreeIndex::new()),
-            IndexType::Hash => Box::new(HashIndex::new()),
-            IndexType::FullText => {
-                return Err(crate::Error::InvalidOperation(
-                    "FullText index not yet implemented".to_string(),
-                ))
-            }
-        };
-
-        self.indexes.insert(name.to_string(), index);
+        self.indexes
+            .insert(name.to_string(), Self::new_index(index_type)?);
         Ok(())
     }
 
+    /// Create an empty index of the given type.
+    fn new_index(index_type: IndexType) -> crate::Result<Box<dyn Index>> {
+        match index_type {
+            IndexType::BTree => Ok(Box::new(BTreeIndex::new())),
+            IndexType::Hash => Ok(Box::new(HashIndex::new())),
+            IndexType::FullText => Err(crate::Error::InvalidOperation(
+                "FullText index not yet implemented".to_string(),
+            )),
+        }
+    }
+
     /// Drop an index by name.
     pub fn drop_index(&mut self, name: &str) -> crate::Result<bool> {
         Ok(self.indexes.remove(name).is_some())
@@ -411,6 +637,12 @@ impl IndexManager {
         index.insert(key, value)
     }
 
+    /// Insert many key-value pairs into a named index.
+    pub fn insert_many(&mut self, name: &str, entries: &[(Vec<u8>, u64)]) -> crate::Result<()> {
+        let index = self.indexes.get_mut(name).ok_or(crate::Error::NotFound)?;
+        index.insert_many(entries)
+    }
+
     /// Find values in a named index.
     pub fn find(&self, name: &str, key: &[u8]) -> crate::Result<Vec<u64>> {
         let index = self.indexes.get(name).ok_or(crate::Error::NotFound)?;
@@ -423,6 +655,31 @@ impl IndexManager {
         index.remove(key)
     }
 
+    /// Remove a single value of a key from a named index.
+    pub fn remove_value(&mut self, name: &str, key: &[u8], value: u64) -> crate::Result<bool> {
+        let index = self.indexes.get_mut(name).ok_or(crate::Error::NotFound)?;
+        index.remove_value(key, value)
+    }
+
+    /// Clear a named index and repopulate it from a data source.
+    ///
+    /// `extractor` maps each stored key-value pair to the `(index_key,
+    /// record_id)` entry it should have, or `None` to leave it out.
+    pub fn rebuild_index<I, F>(&mut self, name: &str, entries: I, extractor: F) -> crate::Result<()>
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+        F: Fn(&[u8], &[u8]) -> Option<(Vec<u8>, u64)>,
+    {
+        let index = self.indexes.get_mut(name).ok_or(crate::Error::NotFound)?;
+        index.clear();
+        for (key, value) in entries {
+            if let Some((index_key, record_id)) = extractor(&key, &value) {
+                index.insert(&index_key, record_id)?;
+            }
+        }
+        Ok(())
+    }
+
     /// List all index names.
     pub fn list_indexes(&self) -> Vec<&str> {
         self.indexes.keys().map(|s| s.as_str()).collect()
@@ -436,9 +693,47 @@ impl IndexManager {
                 name: name.clone(),
                 index_type: index.index_type(),
                 entry_count: index.len(),
+                unique_key_count: index.key_count(),
             })
             .collect()
     }
+
+    /// Copy the type and contents of every index.
+    pub fn snapshot(&self) -> IndexSnapshot {
+        let indexes = self
+            .indexes
+            .iter()
+            .map(|(name, index)| {
+                let mut entries = index.entries();
+                entries.sort();
+                let contents = IndexContents {
+                    index_type: index.index_type(),
+                    entries,
+                };
+                (name.clone(), contents)
+            })
+            .collect();
+        IndexSnapshot { indexes }
+    }
+
+    /// Replace every index with the ones captured in `snapshot`.
+    ///
+    /// Indexes missing from the snapshot are dropped. If an index cannot be
+    /// recreated, the manager is left unchanged.
+    pub fn restore(&mut self, snapshot: IndexSnapshot) -> crate::Result<()> {
+        let mut indexes = HashMap::with_capacity(snapshot.indexes.len());
+        for (name, contents) in snapshot.indexes {
+            let mut index = Self::new_index(contents.index_type)?;
+            for (key, values) in contents.entries {
+                for value in values {
+                    index.insert(&key, value)?;
+                }
+            }
+            indexes.insert(name, index);
+        }
+        self.indexes = indexes;
+        Ok(())
+    }
 }
 
 impl Default for IndexManager {
@@ -456,6 +751,28 @@ pub struct IndexInfo {
     pub index_type: IndexType,
     /// The number of entries in the index.
     pub entry_count: usize,
+    /// The number of distinct keys in the index.
+    pub unique_key_count: usize,
+}
+
+/// A point-in-time copy of every index in an [`IndexManager`].
+///
+/// Produced by [`IndexManager::snapshot`] and loaded with
+/// [`IndexManager::restore`]. It can be serialized to back indexes up
+/// alongside the data they cover.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexSnapshot {
+    /// The contents of each index, by name.
+    pub indexes: BTreeMap<String, IndexContents>,
+}
+
+/// The contents of one index in an [`IndexSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexContents {
+    /// The type of the index.
+    pub index_type: IndexType,
+    /// Each key with its values, in key order.
+    pub entries: Vec<(Vec<u8>, Vec<u64>)>,
 }
 
 // ============================================================================
@@ -506,6 +823,23 @@ mod tests {
         assert_eq!(range[2].0, b"d");
     }
 
+    #[test]
+    fn test_btree_index_range_rev() {
+        let mut index = BTreeIndex::new();
+
+        for (i, key) in [b"a", b"b", b"c", b"d", b"e"].iter().enumerate() {
+            index.insert(*key, i as u64).unwrap();
+        }
+
+        let keys: Vec<_> = index
+            .range_rev(b"b", b"d")
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec![b"d".to_vec(), b"c".to_vec(), b"b".to_vec()]);
+    }
+
     #[test]
     fn test_btree_index_prefix_scan() {
         let mut index = BTreeIndex::new();
@@ -567,6 +901,90 @@ mod tests {
         assert!(index.is_empty());
     }
 
+    #[test]
+    fn test_hash_index_with_bloom() {
+        let mut index = HashIndex::with_bloom(1000);
+
+        for i in 0..1000u64 {
+            index.insert(format!("key:{}", i).as_bytes(), i).unwrap();
+        }
+
+        // No false negatives
+        for i in 0..1000u64 {
+            assert!(index.contains_key(format!("key:{}", i).as_bytes()));
+        }
+
+        // Removed keys still hit the filter but are absent from the map
+        for i in 0..100u64 {
+            assert!(index.remove(format!("key:{}", i).as_bytes()).unwrap());
+        }
+        for i in 0..100u64 {
+            assert!(!index.contains_key(format!("key:{}", i).as_bytes()));
+        }
+        for i in 100..1000u64 {
+            assert!(index.contains_key(format!("key:{}", i).as_bytes()));
+        }
+
+        // Keys never inserted are absent, whether or not the filter rejects them
+        for i in 1000..2000u64 {
+            assert!(!index.contains_key(format!("key:{}", i).as_bytes()));
+        }
+
+        index.clear();
+        assert!(!index.contains_key(b"key:500"));
+        index.insert(b"key:500", 1).unwrap();
+        assert!(index.contains_key(b"key:500"));
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let entries: Vec<(Vec<u8>, u64)> = (0..10_000u64)
+            .map(|i| (format!("key:{}", i % 5_000).into_bytes(), i))
+            .collect();
+
+        let mut indexes: Vec<Box<dyn Index>> = vec![
+            Box::new(BTreeIndex::new()),
+            Box::new(HashIndex::new()),
+            Box::new(HashIndex::with_bloom(5_000)),
+        ];
+        for index in &mut indexes {
+            index.insert(b"key:0", 99_999).unwrap();
+            index.insert_many(&entries).unwrap();
+
+            assert_eq!(index.len(), 10_001);
+            assert_eq!(index.key_count(), 5_000);
+            // Values follow earlier inserts, in batch order
+            assert_eq!(index.find(b"key:0").unwrap(), vec![99_999, 0, 5_000]);
+            for i in 1..5_000u64 {
+                assert_eq!(
+                    index.find(format!("key:{}", i).as_bytes()).unwrap(),
+                    vec![i, i + 5_000]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove_value() {
+        let mut indexes: Vec<Box<dyn Index>> =
+            vec![Box::new(BTreeIndex::new()), Box::new(HashIndex::new())];
+        for index in &mut indexes {
+            index
+                .insert_many(&[(b"a".to_vec(), 1), (b"a".to_vec(), 2), (b"b".to_vec(), 3)])
+                .unwrap();
+
+            assert!(index.remove_value(b"a", 1).unwrap());
+            assert!(!index.remove_value(b"a", 1).unwrap());
+            assert!(!index.remove_value(b"c", 1).unwrap());
+            assert_eq!(index.find(b"a").unwrap(), vec![2]);
+            assert_eq!(index.len(), 2);
+
+            // Removing a key's last value removes the key
+            assert!(index.remove_value(b"b", 3).unwrap());
+            assert_eq!(index.key_count(), 1);
+        }
+    }
+
     #[test]
     fn test_index_manager() {
         let mut manager = IndexManager::new();
@@ -601,6 +1019,66 @@ mod tests {
         assert_eq!(manager.list_indexes().len(), 1);
     }
 
+    #[test]
+    fn test_index_manager_snapshot_restore() {
+        let mut manager = IndexManager::new();
+        manager.create_index("by_status", IndexType::Hash).unwrap();
+        manager.create_index("by_name", IndexType::BTree).unwrap();
+        manager.insert("by_status", b"active", 1).unwrap();
+        manager.insert("by_status", b"active", 2).unwrap();
+        manager.insert("by_name", b"alice", 1).unwrap();
+
+        let snapshot = manager.snapshot();
+
+        manager.remove("by_status", b"active").unwrap();
+        manager.insert("by_name", b"bob", 2).unwrap();
+        manager.drop_index("by_name").unwrap();
+        manager.create_index("extra", IndexType::Hash).unwrap();
+        assert_ne!(manager.snapshot(), snapshot);
+
+        manager.restore(snapshot.clone()).unwrap();
+        assert_eq!(manager.snapshot(), snapshot);
+        assert_eq!(manager.find("by_status", b"active").unwrap(), vec![1, 2]);
+        assert_eq!(manager.find("by_name", b"alice").unwrap(), vec![1]);
+        assert!(manager.find("by_name", b"bob").unwrap().is_empty());
+        assert!(manager.find("extra", b"any").is_err());
+        assert_eq!(
+            manager.get_index("by_name").unwrap().index_type(),
+            IndexType::BTree
+        );
+    }
+
+    #[test]
+    fn test_index_info_unique_key_count() {
+        let mut manager = IndexManager::new();
+        manager.create_index("by_status", IndexType::Hash).unwrap();
+        manager.create_index("by_name", IndexType::BTree).unwrap();
+
+        for id in 0..10 {
+            manager.insert("by_status", b"active", id).unwrap();
+        }
+        manager.insert("by_status", b"inactive", 10).unwrap();
+
+        manager.insert("by_name", b"alice", 1).unwrap();
+        manager.insert("by_name", b"alice", 2).unwrap();
+        manager.insert("by_name", b"bob", 3).unwrap();
+
+        let info = manager.index_info();
+        let status = info.iter().find(|i| i.name == "by_status").unwrap();
+        assert_eq!(status.entry_count, 11);
+        assert_eq!(status.unique_key_count, 2);
+
+        let name = info.iter().find(|i| i.name == "by_name").unwrap();
+        assert_eq!(name.entry_count, 3);
+        assert_eq!(name.unique_key_count, 2);
+
+        manager.remove("by_name", b"alice").unwrap();
+        let info = manager.index_info();
+        let name = info.iter().find(|i| i.name == "by_name").unwrap();
+        assert_eq!(name.entry_count, 1);
+        assert_eq!(name.unique_key_count, 1);
+    }
+
     #[test]
     fn test_index_clear() {
         let mut btree = BTreeIndex::new();
@@ -3,6 +3,17 @@
 /// Defines the structure of parsed queries including SELECT, FROM, WHERE, ORDER BY, LIMIT, and JOIN.
 use std::fmt;
 
+/// A statement accepted by the SQL layer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// SELECT query
+    Select(Query),
+    /// DROP TABLE name
+    DropTable { table: String },
+    /// TRUNCATE TABLE name
+    TruncateTable { table: String },
+}
+
 /// A complete SQL-like query
 #[derive(Debug, Clone, PartialEq)]
 pub struct Query {
@@ -44,6 +55,14 @@ pub enum AggregateFunction {
     Avg,
     Min,
     Max,
+    /// Value of the first row in the group, in input order
+    First,
+    /// Value of the last row in the group, in input order
+    Last,
+    /// Non-NULL values joined with `separator` (`,` if `None`)
+    GroupConcat {
+        separator: Option<String>,
+    },
 }
 
 /// FROM clause specifying tables
@@ -109,21 +128,31 @@ pub enum Expression {
     },
     /// NOT expression
     Not(Box<Expression>),
-    /// LIKE pattern matching
+    /// LIKE pattern matching, or NOT LIKE when `negated`
     Like {
         expr: Box<Expression>,
         pattern: String,
+        negated: bool,
     },
-    /// IN (value1, value2, ...)
+    /// IN (value1, value2, ...), or NOT IN when `negated`
     In {
         expr: Box<Expression>,
         values: Vec<Literal>,
+        negated: bool,
     },
-    /// BETWEEN min AND max
+    /// IN (SELECT ...), or NOT IN when `negated`. The subquery must be
+    /// uncorrelated and select a single column.
+    InSubquery {
+        expr: Box<Expression>,
+        subquery: Box<Query>,
+        negated: bool,
+    },
+    /// BETWEEN min AND max, or NOT BETWEEN when `negated`
     Between {
         expr: Box<Expression>,
         min: Box<Expression>,
         max: Box<Expression>,
+        negated: bool,
     },
 }
 
@@ -178,12 +207,23 @@ pub enum OrderDirection {
 /// LIMIT clause for result limiting
 #[derive(Debug, Clone, PartialEq)]
 pub struct LimitClause {
+    /// Maximum number of rows, `usize::MAX` for an OFFSET with no LIMIT
     pub count: usize,
     pub offset: Option<usize>,
 }
 
 // Display implementations for debugging and error messages
 
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Select(query) => write!(f, "{}", query),
+            Statement::DropTable { table } => write!(f, "DROP TABLE {}", table),
+            Statement::TruncateTable { table } => write!(f, "TRUNCATE TABLE {}", table),
+        }
+    }
+}
+
 impl fmt::Display for Query {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {}", self.select, self.from)?;
@@ -253,6 +293,9 @@ impl fmt::Display for AggregateFunction {
             AggregateFunction::Avg => write!(f, "AVG"),
             AggregateFunction::Min => write!(f, "MIN"),
             AggregateFunction::Max => write!(f, "MAX"),
+            AggregateFunction::First => write!(f, "FIRST"),
+            AggregateFunction::Last => write!(f, "LAST"),
+            AggregateFunction::GroupConcat { .. } => write!(f, "GROUP_CONCAT"),
         }
     }
 }
@@ -325,9 +368,17 @@ impl fmt::Display for Expression {
                 write!(f, "({} {} {})", left, op, right)
             }
             Expression::Not(expr) => write!(f, "NOT ({})", expr),
-            Expression::Like { expr, pattern } => write!(f, "{} LIKE '{}'", expr, pattern),
-            Expression::In { expr, values } => {
-                write!(f, "{} IN (", expr)?;
+            Expression::Like {
+                expr,
+                pattern,
+                negated,
+            } => write!(f, "{} {}LIKE '{}'", expr, not_prefix(*negated), pattern),
+            Expression::In {
+                expr,
+                values,
+                negated,
+            } => {
+                write!(f, "{} {}IN (", expr, not_prefix(*negated))?;
                 for (i, val) in values.iter().enumerate() {
                     if i > 0 {
                         write!(f, ", ")?;
@@ -336,13 +387,38 @@ impl fmt::Display for Expression {
                 }
                 write!(f, ")")
             }
-            Expression::Between { expr, min, max } => {
-                write!(f, "{} BETWEEN {} AND {}", expr, min, max)
+            Expression::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => write!(f, "{} {}IN ({})", expr, not_prefix(*negated), subquery),
+            Expression::Between {
+                expr,
+                min,
+                max,
+                negated,
+            } => {
+                write!(
+                    f,
+                    "{} {}BETWEEN {} AND {}",
+                    expr,
+                    not_prefix(*negated),
+                    min,
+                    max
+                )
             }
         }
     }
 }
 
+fn not_prefix(negated: bool) -> &'static str {
+    if negated {
+        "NOT "
+    } else {
+        ""
+    }
+}
+
 impl fmt::Display for BinaryOperator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -370,7 +446,7 @@ impl fmt::Display for Literal {
         match self {
             Literal::Integer(i) => write!(f, "{}", i),
             Literal::Float(fl) => write!(f, "{}", fl),
-            Literal::String(s) => write!(f, "'{}'", s),
+            Literal::String(s) => write!(f, "'{}'", s.replace('\'', "''")),
             Literal::Boolean(b) => write!(f, "{}", b),
             Literal::Null => write!(f, "NULL"),
         }
@@ -407,6 +483,9 @@ impl fmt::Display for OrderDirection {
 
 impl fmt::Display for LimitClause {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.count == usize::MAX {
+            return write!(f, "OFFSET {}", self.offset.unwrap_or(0));
+        }
         write!(f, "LIMIT {}", self.count)?;
         if let Some(offset) = self.offset {
             write!(f, " OFFSET {}", offset)?;
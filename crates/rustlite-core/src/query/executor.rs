@@ -2,11 +2,13 @@
 ///
 /// Executes physical query plans using iterators.
 use super::ast::*;
-use super::planner::{PhysicalOperator, PhysicalPlan};
-use crate::error::Result;
+use super::planner::{IndexMetadata, PhysicalOperator, PhysicalPlan};
+use crate::error::{Error, Result};
+use serde::de::{self, DeserializeOwned, IntoDeserializer};
 use std::collections::HashMap;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 
 /// Hashable wrapper for group key values
 #[derive(Debug, Clone, Eq)]
@@ -70,6 +72,54 @@ pub enum Value {
 }
 
 impl Value {
+    /// Returns the integer, if this is an `Integer`
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns the float, if this is a `Float`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// Returns the string, if this is a `String`
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if this is a `Boolean`
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this is `Null`
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    /// Name of the value's type, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "INTEGER",
+            Value::Float(_) => "FLOAT",
+            Value::String(_) => "STRING",
+            Value::Boolean(_) => "BOOLEAN",
+            Value::Null => "NULL",
+        }
+    }
+
     /// Convert value to bytes for comparison
     pub fn to_bytes(&self) -> Vec<u8> {
         match self {
@@ -113,12 +163,45 @@ impl Value {
                 BinaryOperator::Ne => a != b,
                 _ => false,
             },
+            // Mixed integer and float operands compare numerically
+            (Value::Integer(a), Value::Float(_)) => Value::Float(*a as f64).compare(other, op),
+            (Value::Float(_), Value::Integer(b)) => self.compare(&Value::Float(*b as f64), op),
             (Value::Null, Value::Null) => matches!(op, BinaryOperator::Eq),
             _ => false,
         }
     }
 }
 
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Integer(i)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -131,6 +214,101 @@ impl fmt::Display for Value {
     }
 }
 
+impl Row {
+    /// Returns the value of the column with the given name or alias
+    pub fn get_column(&self, name: &str) -> Option<&Value> {
+        self.columns
+            .iter()
+            .position(|c| c.name == name || c.alias.as_deref() == Some(name))
+            .and_then(|i| self.values.get(i))
+    }
+
+    /// Deserializes the row into a struct whose fields are named after the
+    /// columns
+    ///
+    /// Columns are keyed by their alias if they have one. `NULL` maps to
+    /// `None` for `Option` fields.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use rustlite_core::query::{Column, Row, Value};
+    ///
+    /// #[derive(serde::Deserialize)]
+    /// struct User {
+    ///     name: String,
+    ///     age: i64,
+    /// }
+    ///
+    /// let column = |name: &str| Column { name: name.to_string(), alias: None };
+    /// let row = Row {
+    ///     columns: vec![column("name"), column("age")],
+    ///     values: vec![Value::String("Alice".to_string()), Value::Integer(30)],
+    /// };
+    ///
+    /// let user: User = row.try_into_struct()?;
+    /// assert_eq!(user.name, "Alice");
+    /// assert_eq!(user.age, 30);
+    /// # Ok::<(), rustlite_core::Error>(())
+    /// ```
+    pub fn try_into_struct<T: DeserializeOwned>(&self) -> Result<T> {
+        let fields = self
+            .columns
+            .iter()
+            .zip(&self.values)
+            .map(|(column, value)| {
+                let name = column.alias.as_deref().unwrap_or(&column.name);
+                (name, ValueDeserializer(value))
+            });
+        T::deserialize(de::value::MapDeserializer::new(fields))
+            .map_err(|e: de::value::Error| Error::Serialization(e.to_string()))
+    }
+}
+
+/// Feeds a single [`Value`] to serde
+struct ValueDeserializer<'a>(&'a Value);
+
+impl<'de> IntoDeserializer<'de, de::value::Error> for ValueDeserializer<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::String(s) => visitor.visit_str(s),
+            Value::Boolean(b) => visitor.visit_bool(*b),
+            Value::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
 /// Query execution context
 #[derive(Clone, Default)]
 pub struct ExecutionContext {
@@ -138,6 +316,16 @@ pub struct ExecutionContext {
     pub data: HashMap<String, Vec<Row>>,
     /// Index access (simplified)
     pub indexes: HashMap<String, HashMap<Vec<u8>, Vec<u64>>>,
+    /// Indexes in `indexes` the planner may turn into index scans
+    pub index_metadata: Vec<IndexMetadata>,
+    /// Match table and column names ignoring ASCII case, so `Users.Name`
+    /// finds the `name` column of table `users`
+    pub case_insensitive_identifiers: bool,
+    /// Column each table's rows are sorted by, ascending, as strings
+    ///
+    /// Prefix scans of a listed table only visit the rows under the prefix.
+    /// The listing must be removed if the rows are reordered.
+    pub sorted_by: HashMap<String, String>,
 }
 
 impl ExecutionContext {
@@ -145,27 +333,113 @@ impl ExecutionContext {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Removes a table and its rows
+    ///
+    /// Returns `false` if the table does not exist.
+    pub fn drop_table(&mut self, name: &str) -> bool {
+        self.sorted_by.remove(name);
+        self.data.remove(name).is_some()
+    }
+
+    /// Removes all rows from a table, keeping the (now empty) table
+    pub fn truncate_table(&mut self, name: &str) {
+        if let Some(rows) = self.data.get_mut(name) {
+            rows.clear();
+        }
+    }
+}
+
+/// Stream of rows produced by an operator
+///
+/// Operators pull rows from their input on demand, so `Filter`, `Project`
+/// and `Limit` never hold more than one input row at a time and a `LIMIT`
+/// stops scanning once it has enough rows. Operators that need their whole
+/// input (sorts, joins and aggregates) collect it first.
+type RowIter<'a> = Box<dyn Iterator<Item = Result<TaggedRow>> + 'a>;
+
+/// Row passed between operators, with the table each column was read from
+///
+/// A join keeps the columns of both tables, so the tables let qualified
+/// references such as `users.id` and `orders.id` tell same-named columns
+/// apart. Columns computed by aggregates have no table.
+#[derive(Debug, Clone)]
+struct TaggedRow {
+    row: Row,
+    /// Table of each column, by position; missing entries have no table
+    tables: Vec<Option<Rc<str>>>,
+}
+
+impl TaggedRow {
+    /// Wrap a row whose columns come from no table
+    fn untagged(row: Row) -> Self {
+        Self {
+            row,
+            tables: Vec::new(),
+        }
+    }
+
+    /// Table of the column at `idx`
+    fn table(&self, idx: usize) -> Option<Rc<str>> {
+        self.tables.get(idx).cloned().flatten()
+    }
+
+    /// Tables of every column, one entry per column
+    fn column_tables(&self) -> impl Iterator<Item = Option<Rc<str>>> + '_ {
+        (0..self.row.columns.len()).map(|idx| self.table(idx))
+    }
+}
+
+impl std::ops::Deref for TaggedRow {
+    type Target = Row;
+
+    fn deref(&self) -> &Row {
+        &self.row
+    }
 }
 
 /// Query executor
 pub struct Executor {
     context: ExecutionContext,
+    /// Rows read from tables, for tests checking that scans stop early
+    #[cfg(test)]
+    rows_scanned: std::cell::Cell<usize>,
 }
 
 impl Executor {
     /// Create new executor
     pub fn new(context: ExecutionContext) -> Self {
-        Self { context }
+        Self {
+            context,
+            #[cfg(test)]
+            rows_scanned: std::cell::Cell::new(0),
+        }
+    }
+
+    /// Consume the executor, returning its context
+    pub fn into_context(self) -> ExecutionContext {
+        self.context
     }
 
     /// Execute a physical plan
     pub fn execute(&mut self, plan: &PhysicalPlan) -> Result<Vec<Row>> {
-        self.execute_operator(&plan.root)
+        let rows = self.collect_rows(&plan.root)?;
+        Ok(rows.into_iter().map(|tagged| tagged.row).collect())
+    }
+
+    /// Run an operator to completion
+    fn collect_rows(&self, op: &PhysicalOperator) -> Result<Vec<TaggedRow>> {
+        self.execute_operator(op)?.collect()
     }
 
-    fn execute_operator(&mut self, op: &PhysicalOperator) -> Result<Vec<Row>> {
+    fn execute_operator<'a>(&'a self, op: &'a PhysicalOperator) -> Result<RowIter<'a>> {
         match op {
             PhysicalOperator::TableScan { table } => self.execute_table_scan(table),
+            PhysicalOperator::PrefixScan {
+                table,
+                column,
+                prefix,
+            } => self.execute_prefix_scan(table, column, prefix),
             PhysicalOperator::IndexScan { table, index, key } => {
                 self.execute_index_scan(table, index, key)
             }
@@ -198,15 +472,82 @@ impl Executor {
             PhysicalOperator::Aggregate { input, aggregates } => {
                 self.execute_aggregate(input, aggregates)
             }
+            PhysicalOperator::SemiJoin {
+                input,
+                expr,
+                subquery,
+                negated,
+            } => self.execute_semi_join(input, expr, subquery, *negated),
         }
     }
 
-    fn execute_table_scan(&mut self, table: &str) -> Result<Vec<Row>> {
-        // Get all rows from table
-        Ok(self.context.data.get(table).cloned().unwrap_or_default())
+    fn execute_table_scan<'a>(&'a self, table: &'a str) -> Result<RowIter<'a>> {
+        let rows = self.table_rows(table);
+        Ok(Box::new(
+            rows.iter().map(move |row| Ok(self.scan_row(row, table))),
+        ))
     }
 
-    fn execute_index_scan(&mut self, table: &str, index: &str, key: &[u8]) -> Result<Vec<Row>> {
+    fn execute_prefix_scan<'a>(
+        &'a self,
+        table: &'a str,
+        column: &'a str,
+        prefix: &'a str,
+    ) -> Result<RowIter<'a>> {
+        let rows = self.table_rows(table);
+        let under_prefix = move |row: &Row| {
+            self.string_column(row, column)
+                .is_some_and(|s| s.starts_with(prefix))
+        };
+
+        if !self.is_sorted_by(table, column) {
+            return Ok(Box::new(
+                rows.iter()
+                    .map(move |row| self.scan_row(row, table))
+                    .filter(move |tagged| under_prefix(&tagged.row))
+                    .map(Ok),
+            ));
+        }
+
+        // Sorted rows under the prefix are contiguous
+        let start =
+            rows.partition_point(|row| self.string_column(row, column).is_some_and(|s| s < prefix));
+        Ok(Box::new(
+            rows[start..]
+                .iter()
+                .take_while(move |row| under_prefix(row))
+                .map(move |row| Ok(self.scan_row(row, table))),
+        ))
+    }
+
+    /// The string value of `column` in `row`, if it has one
+    fn string_column<'r>(&self, row: &'r Row, column: &str) -> Option<&'r str> {
+        let ignore_case = self.context.case_insensitive_identifiers;
+        match column_position(&row.columns, &[], column, ignore_case).map(|idx| &row.values[idx]) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Whether the context lists `table` as sorted by `column`
+    fn is_sorted_by(&self, table: &str, column: &str) -> bool {
+        let name = column.rsplit_once('.').map_or(column, |(_, name)| name);
+        let matches = |a: &str, b: &str| match self.context.case_insensitive_identifiers {
+            true => a.eq_ignore_ascii_case(b),
+            false => a == b,
+        };
+        self.context
+            .sorted_by
+            .iter()
+            .any(|(t, c)| matches(t, table) && matches(c, name))
+    }
+
+    fn execute_index_scan<'a>(
+        &'a self,
+        table: &'a str,
+        index: &str,
+        key: &[u8],
+    ) -> Result<RowIter<'a>> {
         // Look up row IDs from index
         let row_ids = self
             .context
@@ -216,28 +557,19 @@ impl Executor {
             .cloned()
             .unwrap_or_default();
 
-        // Fetch rows by ID
-        let all_rows = self.context.data.get(table).cloned().unwrap_or_default();
-        let result = row_ids
-            .iter()
-            .filter_map(|&id| all_rows.get(id as usize).cloned())
-            .collect();
-
-        Ok(result)
+        Ok(self.fetch_rows(table, row_ids))
     }
 
-    fn execute_index_range_scan(
-        &mut self,
-        table: &str,
+    fn execute_index_range_scan<'a>(
+        &'a self,
+        table: &'a str,
         index: &str,
         start: Option<&[u8]>,
         end: Option<&[u8]>,
-    ) -> Result<Vec<Row>> {
+    ) -> Result<RowIter<'a>> {
         // Get all keys from index in range
-        let index_data = self.context.indexes.get(index).cloned().unwrap_or_default();
-
         let mut row_ids = Vec::new();
-        for (key, ids) in index_data {
+        for (key, ids) in self.context.indexes.get(index).into_iter().flatten() {
             let in_range = match (start, end) {
                 (Some(s), Some(e)) => key.as_slice() >= s && key.as_slice() <= e,
                 (Some(s), None) => key.as_slice() >= s,
@@ -250,42 +582,145 @@ impl Executor {
             }
         }
 
-        // Fetch rows by ID
-        let all_rows = self.context.data.get(table).cloned().unwrap_or_default();
-        let result = row_ids
-            .iter()
-            .filter_map(|&id| all_rows.get(id as usize).cloned())
-            .collect();
+        Ok(self.fetch_rows(table, row_ids))
+    }
 
-        Ok(result)
+    /// Rows stored for a table (empty if the table does not exist)
+    fn table_rows(&self, table: &str) -> &[Row] {
+        let rows = match self.context.data.get(table) {
+            Some(rows) => Some(rows),
+            None if self.context.case_insensitive_identifiers => self
+                .context
+                .data
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(table))
+                .map(|(_, rows)| rows),
+            None => None,
+        };
+        rows.map(Vec::as_slice).unwrap_or_default()
     }
 
-    fn execute_filter(
-        &mut self,
-        input: &PhysicalOperator,
-        condition: &Expression,
-    ) -> Result<Vec<Row>> {
+    /// Find the column a reference names, honouring the context's
+    /// identifier case sensitivity
+    fn column_position(&self, row: &TaggedRow, reference: &str) -> Option<usize> {
+        column_position(
+            &row.columns,
+            &row.tables,
+            reference,
+            self.context.case_insensitive_identifiers,
+        )
+    }
+
+    /// Evaluate a select list against a row
+    fn project_row(&self, row: TaggedRow, columns: &[SelectColumn]) -> TaggedRow {
+        let mut new_columns = Vec::new();
+        let mut new_values = Vec::new();
+        let mut new_tables = Vec::new();
+
+        for col in columns {
+            match col {
+                SelectColumn::Wildcard => {
+                    new_columns.extend(row.columns.clone());
+                    new_values.extend(row.values.clone());
+                    new_tables.extend(row.column_tables());
+                }
+                SelectColumn::Column { name, alias } => {
+                    if let Some(idx) = self.column_position(&row, name) {
+                        new_columns.push(Column {
+                            name: name.clone(),
+                            alias: alias.clone(),
+                        });
+                        new_values.push(row.values[idx].clone());
+                        new_tables.push(row.table(idx));
+                    }
+                }
+                SelectColumn::Aggregate { .. } => {
+                    // Aggregates handled by Aggregate operator
+                }
+            }
+        }
+
+        TaggedRow {
+            row: Row {
+                columns: new_columns,
+                values: new_values,
+            },
+            tables: new_tables,
+        }
+    }
+
+    /// Stream the rows of a table with the given IDs
+    fn fetch_rows<'a>(&'a self, table: &'a str, row_ids: Vec<u64>) -> RowIter<'a> {
+        let rows = self.table_rows(table);
+        Box::new(
+            row_ids
+                .into_iter()
+                .filter_map(move |id| rows.get(id as usize))
+                .map(move |row| Ok(self.scan_row(row, table))),
+        )
+    }
+
+    /// Copy a stored row out of `table`, tagging its columns with the table
+    fn scan_row(&self, row: &Row, table: &str) -> TaggedRow {
+        #[cfg(test)]
+        self.rows_scanned.set(self.rows_scanned.get() + 1);
+        let table: Rc<str> = Rc::from(table);
+        TaggedRow {
+            row: row.clone(),
+            tables: vec![Some(table); row.columns.len()],
+        }
+    }
+
+    fn execute_filter<'a>(
+        &'a self,
+        input: &'a PhysicalOperator,
+        condition: &'a Expression,
+    ) -> Result<RowIter<'a>> {
         let rows = self.execute_operator(input)?;
 
-        let filtered = rows
+        Ok(Box::new(rows.filter_map(move |row| match row {
+            Ok(row) => match self.evaluate_condition(&row, condition) {
+                Ok(true) => Some(Ok(row)),
+                Ok(false) => None,
+                Err(e) => Some(Err(e)),
+            },
+            Err(e) => Some(Err(e)),
+        })))
+    }
+
+    fn execute_semi_join<'a>(
+        &'a self,
+        input: &'a PhysicalOperator,
+        expr: &'a Expression,
+        subquery: &PhysicalOperator,
+        negated: bool,
+    ) -> Result<RowIter<'a>> {
+        // Uncorrelated, so the subquery only needs to run once
+        let values: Vec<Value> = self
+            .collect_rows(subquery)?
             .into_iter()
-            .filter(|row| self.evaluate_condition(row, condition))
+            .filter_map(|tagged| tagged.row.values.into_iter().next())
             .collect();
 
-        Ok(filtered)
+        let rows = self.execute_operator(input)?;
+
+        Ok(Box::new(rows.filter(move |row| match row {
+            Ok(row) => in_values(self.evaluate_expression(row, expr), &values, negated),
+            Err(_) => true,
+        })))
     }
 
     fn execute_sort(
-        &mut self,
+        &self,
         input: &PhysicalOperator,
         columns: &[OrderByColumn],
-    ) -> Result<Vec<Row>> {
-        let mut rows = self.execute_operator(input)?;
+    ) -> Result<RowIter<'static>> {
+        let mut rows = self.collect_rows(input)?;
 
         rows.sort_by(|a, b| {
             for col in columns {
-                let a_idx = a.columns.iter().position(|c| c.name == col.column);
-                let b_idx = b.columns.iter().position(|c| c.name == col.column);
+                let a_idx = self.column_position(a, &col.column);
+                let b_idx = self.column_position(b, &col.column);
 
                 if let (Some(a_idx), Some(b_idx)) = (a_idx, b_idx) {
                     let ordering = match (&a.values[a_idx], &b.values[b_idx]) {
@@ -311,91 +746,60 @@ impl Executor {
             std::cmp::Ordering::Equal
         });
 
-        Ok(rows)
+        Ok(Box::new(rows.into_iter().map(Ok)))
     }
 
-    fn execute_limit(
-        &mut self,
-        input: &PhysicalOperator,
+    fn execute_limit<'a>(
+        &'a self,
+        input: &'a PhysicalOperator,
         count: usize,
         offset: usize,
-    ) -> Result<Vec<Row>> {
+    ) -> Result<RowIter<'a>> {
         let rows = self.execute_operator(input)?;
-        Ok(rows.into_iter().skip(offset).take(count).collect())
+        Ok(Box::new(rows.skip(offset).take(count)))
     }
 
-    fn execute_project(
-        &mut self,
-        input: &PhysicalOperator,
-        columns: &[SelectColumn],
-    ) -> Result<Vec<Row>> {
+    fn execute_project<'a>(
+        &'a self,
+        input: &'a PhysicalOperator,
+        columns: &'a [SelectColumn],
+    ) -> Result<RowIter<'a>> {
         let rows = self.execute_operator(input)?;
-
-        let projected = rows
-            .into_iter()
-            .map(|row| {
-                let mut new_columns = Vec::new();
-                let mut new_values = Vec::new();
-
-                for col in columns {
-                    match col {
-                        SelectColumn::Wildcard => {
-                            new_columns.extend(row.columns.clone());
-                            new_values.extend(row.values.clone());
-                        }
-                        SelectColumn::Column { name, alias } => {
-                            if let Some(idx) = row.columns.iter().position(|c| &c.name == name) {
-                                new_columns.push(Column {
-                                    name: name.clone(),
-                                    alias: alias.clone(),
-                                });
-                                new_values.push(row.values[idx].clone());
-                            }
-                        }
-                        SelectColumn::Aggregate { .. } => {
-                            // Aggregates handled by Aggregate operator
-                        }
-                    }
-                }
-
-                Row {
-                    columns: new_columns,
-                    values: new_values,
-                }
-            })
-            .collect();
-
-        Ok(projected)
+        Ok(Box::new(rows.map(move |row| {
+            row.map(|row| self.project_row(row, columns))
+        })))
     }
 
     fn execute_hash_join(
-        &mut self,
+        &self,
         left: &PhysicalOperator,
         right: &PhysicalOperator,
         join_type: &JoinType,
         condition: &Expression,
-    ) -> Result<Vec<Row>> {
-        let left_rows = self.execute_operator(left)?;
-        let right_rows = self.execute_operator(right)?;
+    ) -> Result<RowIter<'static>> {
+        let left_rows = self.collect_rows(left)?;
+        let right_rows = self.collect_rows(right)?;
 
         // Choose join algorithm based on dataset size
-        if right_rows.len() < 100 {
+        let joined = if right_rows.len() < 100 {
             // Use nested loop join for small datasets
-            self.nested_loop_join(&left_rows, &right_rows, join_type, condition)
+            self.nested_loop_join(&left_rows, &right_rows, join_type, condition)?
         } else {
             // Use hash join for larger datasets
-            self.hash_join_impl(&left_rows, &right_rows, join_type, condition)
-        }
+            self.hash_join_impl(&left_rows, &right_rows, join_type, condition)?
+        };
+
+        Ok(Box::new(joined.into_iter().map(Ok)))
     }
 
     /// Nested loop join - simple but works for small datasets
     fn nested_loop_join(
-        &mut self,
-        left_rows: &[Row],
-        right_rows: &[Row],
+        &self,
+        left_rows: &[TaggedRow],
+        right_rows: &[TaggedRow],
         join_type: &JoinType,
         condition: &Expression,
-    ) -> Result<Vec<Row>> {
+    ) -> Result<Vec<TaggedRow>> {
         let mut result = Vec::new();
 
         match join_type {
@@ -477,14 +881,14 @@ impl Executor {
 
     /// Hash join - efficient for larger datasets
     fn hash_join_impl(
-        &mut self,
-        left_rows: &[Row],
-        right_rows: &[Row],
+        &self,
+        left_rows: &[TaggedRow],
+        right_rows: &[TaggedRow],
         join_type: &JoinType,
         condition: &Expression,
-    ) -> Result<Vec<Row>> {
+    ) -> Result<Vec<TaggedRow>> {
         // Build hash table from right side (build phase)
-        let mut hash_table: HashMap<Vec<u8>, Vec<&Row>> = HashMap::new();
+        let mut hash_table: HashMap<Vec<u8>, Vec<&TaggedRow>> = HashMap::new();
 
         for r_row in right_rows {
             let key = self.extract_join_key(r_row, condition, true);
@@ -538,7 +942,7 @@ impl Executor {
     }
 
     /// Extract join key from row for hashing
-    fn extract_join_key(&self, row: &Row, condition: &Expression, is_right: bool) -> Vec<u8> {
+    fn extract_join_key(&self, row: &TaggedRow, condition: &Expression, is_right: bool) -> Vec<u8> {
         // Simple key extraction - would be more sophisticated in production
         if let Expression::BinaryOp { left, right, .. } = condition {
             if let (Expression::Column(left_col), Expression::Column(right_col)) =
@@ -546,14 +950,7 @@ impl Executor {
             {
                 let col_name = if is_right { right_col } else { left_col };
 
-                // Strip table prefix if present (e.g., "users.id" -> "id")
-                let column_name = if let Some(dot_pos) = col_name.rfind('.') {
-                    &col_name[dot_pos + 1..]
-                } else {
-                    col_name
-                };
-
-                if let Some(idx) = row.columns.iter().position(|c| c.name == column_name) {
+                if let Some(idx) = self.column_position(row, col_name) {
                     return row.values[idx].to_bytes();
                 }
             }
@@ -562,7 +959,12 @@ impl Executor {
     }
 
     /// Evaluate join condition for two rows
-    fn evaluate_join_condition(&self, left: &Row, right: &Row, condition: &Expression) -> bool {
+    fn evaluate_join_condition(
+        &self,
+        left: &TaggedRow,
+        right: &TaggedRow,
+        condition: &Expression,
+    ) -> bool {
         match condition {
             Expression::BinaryOp {
                 left: l_expr,
@@ -598,24 +1000,15 @@ impl Executor {
     fn evaluate_expression_for_row(
         &self,
         expr: &Expression,
-        left_row: &Row,
-        right_row: &Row,
+        left_row: &TaggedRow,
+        right_row: &TaggedRow,
         is_left: bool,
     ) -> Option<Value> {
         match expr {
             Expression::Column(name) => {
-                // Strip table prefix if present (e.g., "users.id" -> "id")
-                let column_name = if let Some(dot_pos) = name.rfind('.') {
-                    &name[dot_pos + 1..]
-                } else {
-                    name
-                };
-
                 // Try to find column in appropriate row
                 let row = if is_left { left_row } else { right_row };
-                row.columns
-                    .iter()
-                    .position(|c| c.name == column_name)
+                self.column_position(row, name)
                     .map(|idx| row.values[idx].clone())
             }
             Expression::Literal(lit) => Some(self.literal_to_value(lit)),
@@ -624,26 +1017,33 @@ impl Executor {
     }
 
     /// Merge two rows into one
-    fn merge_rows(&self, left: &Row, right: &Row) -> Row {
+    fn merge_rows(&self, left: &TaggedRow, right: &TaggedRow) -> TaggedRow {
         let mut columns = left.columns.clone();
         columns.extend(right.columns.clone());
         let mut values = left.values.clone();
         values.extend(right.values.clone());
-        Row { columns, values }
+        let tables = left.column_tables().chain(right.column_tables()).collect();
+        TaggedRow {
+            row: Row { columns, values },
+            tables,
+        }
     }
 
     /// Merge left row with NULL values for right side
-    fn merge_rows_with_null(&self, left: &Row, right_col_count: usize) -> Row {
+    fn merge_rows_with_null(&self, left: &TaggedRow, right_col_count: usize) -> TaggedRow {
         let columns = left.columns.clone();
         let mut values = left.values.clone();
         for _ in 0..right_col_count {
             values.push(Value::Null);
         }
-        Row { columns, values }
+        TaggedRow {
+            row: Row { columns, values },
+            tables: left.tables.clone(),
+        }
     }
 
     /// Merge NULL values for left side with right row
-    fn merge_null_with_row(&self, left_col_count: usize, right: &Row) -> Row {
+    fn merge_null_with_row(&self, left_col_count: usize, right: &TaggedRow) -> TaggedRow {
         let mut columns = Vec::new();
         let mut values = Vec::new();
         for _ in 0..left_col_count {
@@ -651,7 +1051,10 @@ impl Executor {
         }
         columns.extend(right.columns.clone());
         values.extend(right.values.clone());
-        Row { columns, values }
+        TaggedRow {
+            row: Row { columns, values },
+            tables: right.tables.clone(),
+        }
     }
 
     /// Convert literal to value
@@ -666,26 +1069,28 @@ impl Executor {
     }
 
     fn execute_group_by(
-        &mut self,
+        &self,
         input: &PhysicalOperator,
         group_columns: &[String],
         aggregates: &[SelectColumn],
         having: Option<&Expression>,
-    ) -> Result<Vec<Row>> {
-        let rows = self.execute_operator(input)?;
+    ) -> Result<RowIter<'static>> {
+        check_grouped_columns(aggregates, group_columns)?;
+
+        let rows = self.collect_rows(input)?;
 
         if rows.is_empty() {
-            return Ok(Vec::new());
+            return Ok(Box::new(std::iter::empty()));
         }
 
         // Group rows by the specified columns
-        let mut groups: HashMap<GroupKey, Vec<Row>> = HashMap::new();
+        let mut groups: HashMap<GroupKey, Vec<TaggedRow>> = HashMap::new();
 
         for row in rows {
             // Extract group key values
             let mut key_values = Vec::new();
             for group_col in group_columns {
-                if let Some(col_idx) = row.columns.iter().position(|c| &c.name == group_col) {
+                if let Some(col_idx) = self.column_position(&row, group_col) {
                     key_values.push(GroupValue::from(&row.values[col_idx]));
                 } else {
                     key_values.push(GroupValue::Null);
@@ -736,33 +1141,19 @@ impl Executor {
 
                     let value = self.compute_aggregate(function, col_name, &group_rows)?;
 
-                    let display_name = alias
-                        .as_ref()
-                        .cloned()
-                        .unwrap_or_else(|| format!("{}({})", function, col_name));
-
-                    result_columns.push(Column {
-                        name: display_name.clone(),
-                        alias: alias.clone(),
-                    });
+                    result_columns.push(aggregate_column(function, col_name, alias));
                     result_values.push(value);
-                } else if let SelectColumn::Column { name, alias: _ } = agg {
-                    // Non-aggregate column (must be in GROUP BY)
-                    if !group_columns.contains(name) {
-                        // This would be a SQL error - column must be in GROUP BY or be aggregated
-                        continue;
-                    }
                 }
             }
 
-            let row = Row {
+            let row = TaggedRow::untagged(Row {
                 columns: result_columns,
                 values: result_values,
-            };
+            });
 
             // Apply HAVING clause if present
             if let Some(having_condition) = having {
-                if self.evaluate_condition(&row, having_condition) {
+                if self.evaluate_condition(&row, having_condition)? {
                     result_rows.push(row);
                 }
             } else {
@@ -770,17 +1161,21 @@ impl Executor {
             }
         }
 
-        Ok(result_rows)
+        Ok(Box::new(result_rows.into_iter().map(Ok)))
     }
 
     fn compute_aggregate(
         &self,
         function: &AggregateFunction,
         col_name: &str,
-        rows: &[Row],
+        rows: &[TaggedRow],
     ) -> Result<Value> {
+        // COUNT of no rows is 0; every other aggregate is NULL
         if rows.is_empty() {
-            return Ok(Value::Null);
+            return Ok(match function {
+                AggregateFunction::Count => Value::Integer(0),
+                _ => Value::Null,
+            });
         }
 
         match function {
@@ -790,9 +1185,7 @@ impl Executor {
                     Ok(Value::Integer(rows.len() as i64))
                 } else {
                     // COUNT(column): Count non-null values
-                    let col_idx = rows
-                        .iter()
-                        .find_map(|r| r.columns.iter().position(|c| c.name == col_name));
+                    let col_idx = rows.iter().find_map(|r| self.column_position(r, col_name));
 
                     if let Some(idx) = col_idx {
                         let count = rows
@@ -809,23 +1202,12 @@ impl Executor {
             }
             AggregateFunction::Sum => {
                 // Find column index - check all rows if first doesn't have it
-                let col_idx = rows
-                    .iter()
-                    .find_map(|r| r.columns.iter().position(|c| c.name == col_name));
+                let col_idx = rows.iter().find_map(|r| self.column_position(r, col_name));
 
                 if let Some(idx) = col_idx {
                     let sum: i64 = rows
                         .iter()
-                        .filter_map(|r| {
-                            if idx < r.values.len() {
-                                match &r.values[idx] {
-                                    Value::Integer(i) => Some(i),
-                                    _ => None,
-                                }
-                            } else {
-                                None
-                            }
-                        })
+                        .filter_map(|r| r.values.get(idx).and_then(Value::as_i64))
                         .sum();
                     Ok(Value::Integer(sum))
                 } else {
@@ -833,23 +1215,12 @@ impl Executor {
                 }
             }
             AggregateFunction::Avg => {
-                let col_idx = rows
-                    .iter()
-                    .find_map(|r| r.columns.iter().position(|c| c.name == col_name));
+                let col_idx = rows.iter().find_map(|r| self.column_position(r, col_name));
 
                 if let Some(idx) = col_idx {
                     let values: Vec<i64> = rows
                         .iter()
-                        .filter_map(|r| {
-                            if idx < r.values.len() {
-                                match &r.values[idx] {
-                                    Value::Integer(i) => Some(*i),
-                                    _ => None,
-                                }
-                            } else {
-                                None
-                            }
-                        })
+                        .filter_map(|r| r.values.get(idx).and_then(Value::as_i64))
                         .collect();
                     if !values.is_empty() {
                         let sum: i64 = values.iter().sum();
@@ -862,9 +1233,7 @@ impl Executor {
                 }
             }
             AggregateFunction::Min => {
-                let col_idx = rows
-                    .iter()
-                    .find_map(|r| r.columns.iter().position(|c| c.name == col_name));
+                let col_idx = rows.iter().find_map(|r| self.column_position(r, col_name));
 
                 if let Some(idx) = col_idx {
                     Ok(rows
@@ -891,9 +1260,7 @@ impl Executor {
                 }
             }
             AggregateFunction::Max => {
-                let col_idx = rows
-                    .iter()
-                    .find_map(|r| r.columns.iter().position(|c| c.name == col_name));
+                let col_idx = rows.iter().find_map(|r| self.column_position(r, col_name));
 
                 if let Some(idx) = col_idx {
                     Ok(rows
@@ -919,15 +1286,47 @@ impl Executor {
                     Ok(Value::Null)
                 }
             }
+            AggregateFunction::First | AggregateFunction::Last => {
+                let value = |r: &TaggedRow| {
+                    self.column_position(r, col_name)
+                        .and_then(|idx| r.values.get(idx).cloned())
+                        .unwrap_or(Value::Null)
+                };
+                let row = if matches!(function, AggregateFunction::First) {
+                    rows.first()
+                } else {
+                    rows.last()
+                };
+                Ok(row.map(value).unwrap_or(Value::Null))
+            }
+            AggregateFunction::GroupConcat { separator } => {
+                let values: Vec<String> = rows
+                    .iter()
+                    .filter_map(|r| {
+                        let idx = self.column_position(r, col_name)?;
+                        r.values.get(idx).filter(|v| !v.is_null())
+                    })
+                    .map(Value::to_string)
+                    .collect();
+                if values.is_empty() {
+                    Ok(Value::Null)
+                } else {
+                    Ok(Value::String(
+                        values.join(separator.as_deref().unwrap_or(",")),
+                    ))
+                }
+            }
         }
     }
 
     fn execute_aggregate(
-        &mut self,
+        &self,
         input: &PhysicalOperator,
         aggregates: &[SelectColumn],
-    ) -> Result<Vec<Row>> {
-        let rows = self.execute_operator(input)?;
+    ) -> Result<RowIter<'static>> {
+        check_grouped_columns(aggregates, &[])?;
+
+        let rows = self.collect_rows(input)?;
 
         let mut result_columns = Vec::new();
         let mut result_values = Vec::new();
@@ -947,30 +1346,22 @@ impl Executor {
 
                 let value = self.compute_aggregate(function, col_name, &rows)?;
 
-                let display_name = alias
-                    .as_ref()
-                    .cloned()
-                    .unwrap_or_else(|| format!("{}({})", function, col_name));
-
-                result_columns.push(Column {
-                    name: display_name.clone(),
-                    alias: alias.clone(),
-                });
+                result_columns.push(aggregate_column(function, col_name, alias));
                 result_values.push(value);
             }
         }
 
-        Ok(vec![Row {
+        Ok(Box::new(std::iter::once(Ok(TaggedRow::untagged(Row {
             columns: result_columns,
             values: result_values,
-        }])
+        })))))
     }
 
-    fn evaluate_condition(&self, row: &Row, condition: &Expression) -> bool {
-        match condition {
+    fn evaluate_condition(&self, row: &TaggedRow, condition: &Expression) -> Result<bool> {
+        Ok(match condition {
             Expression::Column(name) => {
                 // Column reference - check if exists and is truthy
-                row.columns.iter().any(|c| &c.name == name)
+                self.column_position(row, name).is_some()
             }
             Expression::Literal(lit) => {
                 // Literal value
@@ -990,53 +1381,68 @@ impl Executor {
                 }
             }
             Expression::LogicalOp { left, op, right } => {
-                let left_result = self.evaluate_condition(row, left);
-                let right_result = self.evaluate_condition(row, right);
+                let left_result = self.evaluate_condition(row, left)?;
+                let right_result = self.evaluate_condition(row, right)?;
 
                 match op {
                     LogicalOperator::And => left_result && right_result,
                     LogicalOperator::Or => left_result || right_result,
                 }
             }
-            Expression::Not(expr) => !self.evaluate_condition(row, expr),
-            Expression::Like { expr, pattern } => {
+            Expression::Not(expr) => !self.evaluate_condition(row, expr)?,
+            Expression::Like {
+                expr,
+                pattern,
+                negated,
+            } => {
                 if let Some(Value::String(s)) = self.evaluate_expression(row, expr) {
-                    // Simplified LIKE - just use contains for now
-                    let pattern = pattern.replace('%', "");
-                    s.contains(&pattern)
+                    like_matches(&s, pattern) != *negated
                 } else {
                     false
                 }
             }
-            Expression::In { expr, values } => {
-                self.evaluate_expression(row, expr).is_some_and(|val| {
-                    values.iter().any(|lit| {
-                        let lit_val = literal_to_value(lit);
-                        val == lit_val
-                    })
-                })
+            Expression::In {
+                expr,
+                values,
+                negated,
+            } => {
+                let values: Vec<Value> = values.iter().map(literal_to_value).collect();
+                in_values(self.evaluate_expression(row, expr), &values, *negated)
             }
-            Expression::Between { expr, min, max } => {
+            // WHERE clauses plan these as a SemiJoin; anywhere else is unsupported
+            Expression::InSubquery { .. } => {
+                return Err(Error::NotSupported(
+                    "IN subquery outside of a WHERE clause".into(),
+                ))
+            }
+            Expression::Between {
+                expr,
+                min,
+                max,
+                negated,
+            } => {
                 if let (Some(val), Some(min_v), Some(max_v)) = (
                     self.evaluate_expression(row, expr),
                     self.evaluate_expression(row, min),
                     self.evaluate_expression(row, max),
                 ) {
-                    val.compare(&min_v, &BinaryOperator::Ge)
-                        && val.compare(&max_v, &BinaryOperator::Le)
+                    if val == Value::Null {
+                        return Ok(false);
+                    }
+                    let within = val.compare(&min_v, &BinaryOperator::Ge)
+                        && val.compare(&max_v, &BinaryOperator::Le);
+                    within != *negated
                 } else {
                     false
                 }
             }
-        }
+        })
     }
 
-    fn evaluate_expression(&self, row: &Row, expr: &Expression) -> Option<Value> {
+    fn evaluate_expression(&self, row: &TaggedRow, expr: &Expression) -> Option<Value> {
         match expr {
-            Expression::Column(name) => row
-                .columns
-                .iter()
-                .position(|c| &c.name == name)
+            Expression::Column(name) => self
+                .column_position(row, name)
                 .and_then(|idx| row.values.get(idx).cloned()),
             Expression::Literal(lit) => Some(literal_to_value(lit)),
             _ => None,
@@ -1044,6 +1450,116 @@ impl Executor {
     }
 }
 
+/// Reject plain columns selected alongside aggregates unless they are
+/// grouped on, instead of silently dropping them from the output
+fn check_grouped_columns(columns: &[SelectColumn], group_columns: &[String]) -> Result<()> {
+    for column in columns {
+        if let SelectColumn::Column { name, .. } = column {
+            if !group_columns.contains(name) {
+                return Err(Error::InvalidInput(format!(
+                    "column '{}' must appear in GROUP BY or be aggregated",
+                    name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Output column of an aggregate
+///
+/// Named by the alias if there is one, otherwise `FUNCTION(column)` with the
+/// function in upper case, e.g. `COUNT(*)` or `SUM(amount)`.
+fn aggregate_column(
+    function: &AggregateFunction,
+    col_name: &str,
+    alias: &Option<String>,
+) -> Column {
+    Column {
+        name: alias
+            .clone()
+            .unwrap_or_else(|| format!("{}({})", function, col_name)),
+        alias: alias.clone(),
+    }
+}
+
+/// Find the column a reference names, qualified (`users.id`) or not (`id`)
+///
+/// `tables` holds the table of each column by position. A qualified
+/// reference only matches columns from that table (or columns with no
+/// table). Unqualified references match the first column by name. With
+/// `ignore_case`, names are compared ignoring ASCII case.
+fn column_position(
+    columns: &[Column],
+    tables: &[Option<Rc<str>>],
+    reference: &str,
+    ignore_case: bool,
+) -> Option<usize> {
+    let same = |a: &str, b: &str| {
+        if ignore_case {
+            a.eq_ignore_ascii_case(b)
+        } else {
+            a == b
+        }
+    };
+
+    if let Some((table, name)) = reference.rsplit_once('.') {
+        let qualified = columns.iter().enumerate().position(|(idx, c)| {
+            let column_table = tables.get(idx).and_then(Option::as_deref);
+            same(&c.name, name) && column_table.map_or(true, |t| same(t, table))
+        });
+        if qualified.is_some() {
+            return qualified;
+        }
+    }
+    columns.iter().position(|c| same(&c.name, reference))
+}
+
+/// SQL LIKE matching: `%` matches any run of characters (including
+/// none) and `_` exactly one
+fn like_matches(value: &str, pattern: &str) -> bool {
+    let value: Vec<char> = value.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    // Match greedily, backtracking to the most recent `%` on a mismatch
+    let (mut v, mut p) = (0, 0);
+    let mut last_wildcard = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                last_wildcard = Some((p, v));
+                p += 1;
+            }
+            Some(&c) if c == '_' || c == value[v] => {
+                v += 1;
+                p += 1;
+            }
+            _ => match last_wildcard {
+                Some((wildcard, matched)) => {
+                    // Let the `%` absorb one more character
+                    last_wildcard = Some((wildcard, matched + 1));
+                    p = wildcard + 1;
+                    v = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+/// Evaluate `val IN values`, or `val NOT IN values` when `negated`
+///
+/// NOT IN follows three-valued logic: `NULL NOT IN (...)` and
+/// `x NOT IN (..., NULL)` are unknown unless `x` matches, so they never hold.
+fn in_values(val: Option<Value>, values: &[Value], negated: bool) -> bool {
+    match val {
+        Some(val) if !negated => values.contains(&val),
+        None | Some(Value::Null) => false,
+        Some(val) => values.iter().all(|v| *v != Value::Null && *v != val),
+    }
+}
+
 fn literal_to_value(lit: &Literal) -> Value {
     match lit {
         Literal::Integer(i) => Value::Integer(*i),
@@ -1105,4 +1621,134 @@ mod tests {
         let result = executor.execute(&plan).unwrap();
         assert_eq!(result.len(), 2);
     }
+
+    #[test]
+    fn test_limit_stops_scan_early() {
+        let columns = vec![Column {
+            name: "id".to_string(),
+            alias: None,
+        }];
+        let rows = (0..100_000)
+            .map(|i| Row {
+                columns: columns.clone(),
+                values: vec![Value::Integer(i)],
+            })
+            .collect();
+        let mut context = ExecutionContext::new();
+        context.data.insert("numbers".to_string(), rows);
+
+        let run = |sql: &str| {
+            let mut executor = Executor::new(context.clone());
+            let query = Parser::new(sql).unwrap().parse().unwrap();
+            let plan = Planner::new().plan(&query).unwrap();
+            let result = executor.execute(&plan).unwrap();
+            (result, executor.rows_scanned.get())
+        };
+
+        let (result, scanned) = run("SELECT id FROM numbers LIMIT 10");
+        assert_eq!(result.len(), 10);
+        assert_eq!(scanned, 10);
+
+        // Filtered rows are pulled one at a time until the limit is met
+        let (result, scanned) = run("SELECT id FROM numbers WHERE id >= 500 LIMIT 10 OFFSET 5");
+        assert_eq!(result.len(), 10);
+        assert_eq!(result[0].values[0], Value::Integer(505));
+        assert_eq!(scanned, 515);
+
+        // Sorting needs every row
+        let (_, scanned) = run("SELECT id FROM numbers ORDER BY id DESC LIMIT 10");
+        assert_eq!(scanned, 100_000);
+    }
+
+    #[test]
+    fn test_prefix_scan_skips_other_keys() {
+        let columns = vec![Column {
+            name: "key".to_string(),
+            alias: None,
+        }];
+        let table = |keys: &[&str]| -> Vec<Row> {
+            keys.iter()
+                .map(|key| Row {
+                    columns: columns.clone(),
+                    values: vec![Value::from(*key)],
+                })
+                .collect()
+        };
+        let run = |context: ExecutionContext, sql: &str| {
+            let mut executor = Executor::new(context);
+            let query = Parser::new(sql).unwrap().parse().unwrap();
+            let plan = Planner::new().plan(&query).unwrap();
+            let result = executor.execute(&plan).unwrap();
+            let keys: Vec<_> = result.iter().map(|row| row.values[0].clone()).collect();
+            (keys, executor.rows_scanned.get())
+        };
+        let expected = vec![Value::from("order:1"), Value::from("order:2")];
+
+        // Unsorted rows are all visited
+        let mut context = ExecutionContext::new();
+        context.data.insert(
+            "kv".to_string(),
+            table(&["order:1", "user:1", "order:2", "user:2", "xorder:3"]),
+        );
+        let sql = "SELECT key FROM kv WHERE key LIKE 'order:%'";
+        assert_eq!(run(context.clone(), sql), (expected.clone(), 5));
+
+        // A LIKE the planner cannot turn into a scan matches the same rows
+        let (keys, _) = run(
+            context,
+            "SELECT key FROM kv WHERE key LIKE 'order:%' OR key = 'zzz'",
+        );
+        assert_eq!(keys, expected);
+
+        // Sorted rows outside the prefix are skipped
+        let mut context = ExecutionContext::new();
+        context.data.insert(
+            "kv".to_string(),
+            table(&["a", "order:1", "order:2", "user:1", "user:2", "xorder:3"]),
+        );
+        context
+            .sorted_by
+            .insert("kv".to_string(), "key".to_string());
+        assert_eq!(run(context, sql), (expected, 2));
+    }
+
+    #[test]
+    fn test_like_matches() {
+        assert!(like_matches("order:1", "order:%"));
+        assert!(!like_matches("xorder:3", "order:%"));
+        assert!(like_matches("xorder:3", "%order%"));
+        assert!(like_matches("order", "ord_r"));
+        assert!(!like_matches("ordr", "ord_r"));
+        assert!(like_matches("abcbc", "a%bc"));
+        assert!(like_matches("", "%"));
+        assert!(!like_matches("abc", "ab"));
+        assert!(like_matches("a_c", "a_c"));
+        assert!(like_matches("héllo", "h_llo"));
+    }
+
+    #[test]
+    fn test_value_accessors() {
+        let values = [
+            Value::from(7),
+            Value::from(1.5),
+            Value::from("text"),
+            Value::from(true),
+            Value::Null,
+        ];
+
+        let ints: Vec<_> = values.iter().map(Value::as_i64).collect();
+        assert_eq!(ints, vec![Some(7), None, None, None, None]);
+        let floats: Vec<_> = values.iter().map(Value::as_f64).collect();
+        assert_eq!(floats, vec![None, Some(1.5), None, None, None]);
+        let strs: Vec<_> = values.iter().map(Value::as_str).collect();
+        assert_eq!(strs, vec![None, None, Some("text"), None, None]);
+        let bools: Vec<_> = values.iter().map(Value::as_bool).collect();
+        assert_eq!(bools, vec![None, None, None, Some(true), None]);
+        let nulls: Vec<_> = values.iter().map(Value::is_null).collect();
+        assert_eq!(nulls, vec![false, false, false, false, true]);
+
+        let names: Vec<_> = values.iter().map(Value::type_name).collect();
+        assert_eq!(names, vec!["INTEGER", "FLOAT", "STRING", "BOOLEAN", "NULL"]);
+        assert_eq!(Value::from(String::from("text")), values[2]);
+    }
 }
@@ -29,6 +29,9 @@ pub enum Token {
     Like,
     In,
     Between,
+    Drop,
+    Truncate,
+    Table,
 
     // Aggregate functions
     Count,
@@ -36,6 +39,9 @@ pub enum Token {
     Avg,
     Min,
     Max,
+    First,
+    Last,
+    GroupConcat,
 
     // Operators
     Eq, // =
@@ -94,11 +100,17 @@ impl fmt::Display for Token {
             Token::Like => write!(f, "LIKE"),
             Token::In => write!(f, "IN"),
             Token::Between => write!(f, "BETWEEN"),
+            Token::Drop => write!(f, "DROP"),
+            Token::Truncate => write!(f, "TRUNCATE"),
+            Token::Table => write!(f, "TABLE"),
             Token::Count => write!(f, "COUNT"),
             Token::Sum => write!(f, "SUM"),
             Token::Avg => write!(f, "AVG"),
             Token::Min => write!(f, "MIN"),
             Token::Max => write!(f, "MAX"),
+            Token::First => write!(f, "FIRST"),
+            Token::Last => write!(f, "LAST"),
+            Token::GroupConcat => write!(f, "GROUP_CONCAT"),
             Token::Eq => write!(f, "="),
             Token::Ne => write!(f, "!="),
             Token::Lt => write!(f, "<"),
@@ -194,6 +206,7 @@ impl Lexer {
                 return Err(LexerError::UnexpectedCharacter(ch));
             }
             '\'' => return self.read_string(),
+            '"' => return self.read_quoted_identifier(),
             _ => {}
         }
 
@@ -279,21 +292,41 @@ impl Lexer {
     }
 
     fn read_string(&mut self) -> Result<Token, LexerError> {
+        self.read_quoted('\'')
+            .map(Token::String)
+            .ok_or(LexerError::UnterminatedString)
+    }
+
+    /// Read a double-quoted identifier, which may contain spaces or be a
+    /// reserved word (`"order by"`). A missing closing quote is reported as
+    /// an unterminated string, like for string literals.
+    fn read_quoted_identifier(&mut self) -> Result<Token, LexerError> {
+        self.read_quoted('"')
+            .map(Token::Identifier)
+            .ok_or(LexerError::UnterminatedString)
+    }
+
+    /// Read text enclosed in `quote`, where a doubled quote stands for one
+    /// literal quote character. Returns `None` if the closing quote is
+    /// missing.
+    fn read_quoted(&mut self, quote: char) -> Option<String> {
         self.advance(); // skip opening quote
-        let start = self.position;
+        let mut text = String::new();
 
-        while self.position < self.input.len() && self.current_char() != '\'' {
+        while self.position < self.input.len() {
+            let ch = self.current_char();
             self.advance();
+            if ch != quote {
+                text.push(ch);
+            } else if self.position < self.input.len() && self.current_char() == quote {
+                text.push(quote);
+                self.advance();
+            } else {
+                return Some(text);
+            }
         }
 
-        if self.position >= self.input.len() {
-            return Err(LexerError::UnterminatedString);
-        }
-
-        let string: String = self.input[start..self.position].iter().collect();
-        self.advance(); // skip closing quote
-
-        Ok(Token::String(string))
+        None
     }
 
     fn read_identifier_or_keyword(&mut self) -> Result<Token, LexerError> {
@@ -357,11 +390,17 @@ impl Lexer {
             "LIKE" => Token::Like,
             "IN" => Token::In,
             "BETWEEN" => Token::Between,
+            "DROP" => Token::Drop,
+            "TRUNCATE" => Token::Truncate,
+            "TABLE" => Token::Table,
             "COUNT" => Token::Count,
             "SUM" => Token::Sum,
             "AVG" => Token::Avg,
             "MIN" => Token::Min,
             "MAX" => Token::Max,
+            "FIRST" => Token::First,
+            "LAST" => Token::Last,
+            "GROUP_CONCAT" => Token::GroupConcat,
             "ASC" => Token::Asc,
             "DESC" => Token::Desc,
             "TRUE" => Token::Boolean(true),
@@ -438,6 +477,40 @@ mod tests {
         assert!(tokens.contains(&Token::String("John".to_string())));
     }
 
+    #[test]
+    fn test_escaped_quote_in_string() {
+        let mut lexer = Lexer::new("name = 'O''Brien' OR name = ''''");
+        let tokens = lexer.tokenize().unwrap();
+
+        assert!(tokens.contains(&Token::String("O'Brien".to_string())));
+        assert!(tokens.contains(&Token::String("'".to_string())));
+
+        let mut lexer = Lexer::new("name = 'O''Brien");
+        assert_eq!(lexer.tokenize(), Err(LexerError::UnterminatedString));
+    }
+
+    #[test]
+    fn test_quoted_identifier() {
+        let mut lexer = Lexer::new(r#"SELECT "order by", "say ""hi""" FROM "select""#);
+        let tokens = lexer.tokenize().unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Select,
+                Token::Identifier("order by".to_string()),
+                Token::Comma,
+                Token::Identifier("say \"hi\"".to_string()),
+                Token::From,
+                Token::Identifier("select".to_string()),
+                Token::Eof,
+            ]
+        );
+
+        let mut lexer = Lexer::new(r#"SELECT "name"#);
+        assert_eq!(lexer.tokenize(), Err(LexerError::UnterminatedString));
+    }
+
     #[test]
     fn test_order_by() {
         let mut lexer = Lexer::new("SELECT * FROM users ORDER BY name ASC");
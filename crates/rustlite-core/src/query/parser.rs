@@ -24,6 +24,55 @@ impl Parser {
 
     /// Parse the query into an AST
     pub fn parse(&mut self) -> Result<Query, ParseError> {
+        let query = self.parse_query()?;
+
+        self.expect_token(Token::Eof)?;
+
+        Ok(query)
+    }
+
+    /// Parse a SELECT query or a table statement (DROP TABLE, TRUNCATE TABLE)
+    pub fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        let statement = match self.current_token() {
+            Token::Drop => {
+                self.advance();
+                self.expect_token(Token::Table)?;
+                Statement::DropTable {
+                    table: self.parse_table_name()?,
+                }
+            }
+            Token::Truncate => {
+                self.advance();
+                // TABLE is optional in TRUNCATE
+                if self.current_token() == &Token::Table {
+                    self.advance();
+                }
+                Statement::TruncateTable {
+                    table: self.parse_table_name()?,
+                }
+            }
+            _ => Statement::Select(self.parse_query()?),
+        };
+
+        self.expect_token(Token::Eof)?;
+
+        Ok(statement)
+    }
+
+    fn parse_table_name(&mut self) -> Result<String, ParseError> {
+        if let Token::Identifier(name) = self.current_token().clone() {
+            self.advance();
+            Ok(name)
+        } else {
+            Err(ParseError::UnexpectedToken {
+                expected: "table name".to_string(),
+                found: self.current_token().clone(),
+            })
+        }
+    }
+
+    /// Parse a query, either top-level or nested in a subquery
+    fn parse_query(&mut self) -> Result<Query, ParseError> {
         let select = self.parse_select()?;
         let from = self.parse_from()?;
         let where_clause = self.parse_where()?;
@@ -32,8 +81,6 @@ impl Parser {
         let order_by = self.parse_order_by()?;
         let limit = self.parse_limit()?;
 
-        self.expect_token(Token::Eof)?;
-
         Ok(Query {
             select,
             from,
@@ -56,15 +103,25 @@ impl Parser {
                 columns.push(SelectColumn::Wildcard);
             } else if matches!(
                 self.current_token(),
-                Token::Count | Token::Sum | Token::Avg | Token::Min | Token::Max
+                Token::Count
+                    | Token::Sum
+                    | Token::Avg
+                    | Token::Min
+                    | Token::Max
+                    | Token::First
+                    | Token::Last
+                    | Token::GroupConcat
             ) {
                 // Aggregate function
-                let function = match self.current_token() {
+                let mut function = match self.current_token() {
                     Token::Count => AggregateFunction::Count,
                     Token::Sum => AggregateFunction::Sum,
                     Token::Avg => AggregateFunction::Avg,
                     Token::Min => AggregateFunction::Min,
                     Token::Max => AggregateFunction::Max,
+                    Token::First => AggregateFunction::First,
+                    Token::Last => AggregateFunction::Last,
+                    Token::GroupConcat => AggregateFunction::GroupConcat { separator: None },
                     _ => unreachable!(),
                 };
                 self.advance();
@@ -84,6 +141,22 @@ impl Parser {
                     });
                 };
 
+                // GROUP_CONCAT(column, 'separator')
+                if let AggregateFunction::GroupConcat { separator } = &mut function {
+                    if self.current_token() == &Token::Comma {
+                        self.advance();
+                        if let Token::String(s) = self.current_token().clone() {
+                            self.advance();
+                            *separator = Some(s);
+                        } else {
+                            return Err(ParseError::UnexpectedToken {
+                                expected: "separator string".to_string(),
+                                found: self.current_token().clone(),
+                            });
+                        }
+                    }
+                }
+
                 self.expect_token(Token::RightParen)?;
 
                 let alias = if self.current_token() == &Token::As {
@@ -143,15 +216,7 @@ impl Parser {
     fn parse_from(&mut self) -> Result<FromClause, ParseError> {
         self.expect_token(Token::From)?;
 
-        let table = if let Token::Identifier(name) = self.current_token().clone() {
-            self.advance();
-            name
-        } else {
-            return Err(ParseError::UnexpectedToken {
-                expected: "table name".to_string(),
-                found: self.current_token().clone(),
-            });
-        };
+        let table = self.parse_table_name()?;
 
         let mut joins = Vec::new();
 
@@ -321,6 +386,13 @@ impl Parser {
     fn parse_comparison(&mut self) -> Result<Expression, ParseError> {
         let left = self.parse_primary()?;
 
+        // Handle NOT LIKE / NOT IN / NOT BETWEEN
+        let negated = self.current_token() == &Token::Not
+            && matches!(self.peek_token(), Token::Like | Token::In | Token::Between);
+        if negated {
+            self.advance();
+        }
+
         // Handle LIKE
         if self.current_token() == &Token::Like {
             self.advance();
@@ -329,6 +401,7 @@ impl Parser {
                 return Ok(Expression::Like {
                     expr: Box::new(left),
                     pattern,
+                    negated,
                 });
             } else {
                 return Err(ParseError::UnexpectedToken {
@@ -343,6 +416,18 @@ impl Parser {
             self.advance();
             self.expect_token(Token::LeftParen)?;
 
+            // Handle IN (SELECT ...)
+            if self.current_token() == &Token::Select {
+                let subquery = self.parse_query()?;
+                self.expect_token(Token::RightParen)?;
+
+                return Ok(Expression::InSubquery {
+                    expr: Box::new(left),
+                    subquery: Box::new(subquery),
+                    negated,
+                });
+            }
+
             let mut values = Vec::new();
             loop {
                 let value = self.parse_literal()?;
@@ -360,6 +445,7 @@ impl Parser {
             return Ok(Expression::In {
                 expr: Box::new(left),
                 values,
+                negated,
             });
         }
 
@@ -374,6 +460,7 @@ impl Parser {
                 expr: Box::new(left),
                 min: Box::new(min),
                 max: Box::new(max),
+                negated,
             });
         }
 
@@ -509,41 +596,27 @@ impl Parser {
     }
 
     fn parse_limit(&mut self) -> Result<Option<LimitClause>, ParseError> {
-        if self.current_token() != &Token::Limit {
-            return Ok(None);
-        }
-
-        self.advance();
-
-        let count = if let Token::Integer(n) = self.current_token() {
-            if *n < 0 {
-                return Err(ParseError::InvalidLimitValue(*n));
+        let count = match self.current_token() {
+            Token::Limit => {
+                self.advance();
+                let n = self.parse_count()?;
+                if n < 0 {
+                    return Err(ParseError::InvalidLimitValue(n));
+                }
+                n as usize
             }
-            let count = *n as usize;
-            self.advance();
-            count
-        } else {
-            return Err(ParseError::UnexpectedToken {
-                expected: "integer".to_string(),
-                found: self.current_token().clone(),
-            });
+            // OFFSET on its own skips rows without limiting the rest
+            Token::Offset => usize::MAX,
+            _ => return Ok(None),
         };
 
         let offset = if self.current_token() == &Token::Offset {
             self.advance();
-            if let Token::Integer(n) = self.current_token() {
-                if *n < 0 {
-                    return Err(ParseError::InvalidOffsetValue(*n));
-                }
-                let offset = *n as usize;
-                self.advance();
-                Some(offset)
-            } else {
-                return Err(ParseError::UnexpectedToken {
-                    expected: "integer".to_string(),
-                    found: self.current_token().clone(),
-                });
+            let n = self.parse_count()?;
+            if n < 0 {
+                return Err(ParseError::InvalidOffsetValue(n));
             }
+            Some(n as usize)
         } else {
             None
         };
@@ -551,10 +624,29 @@ impl Parser {
         Ok(Some(LimitClause { count, offset }))
     }
 
+    /// Consume the integer following LIMIT or OFFSET
+    fn parse_count(&mut self) -> Result<i64, ParseError> {
+        if let Token::Integer(n) = self.current_token() {
+            let n = *n;
+            self.advance();
+            Ok(n)
+        } else {
+            Err(ParseError::UnexpectedToken {
+                expected: "integer".to_string(),
+                found: self.current_token().clone(),
+            })
+        }
+    }
+
     fn current_token(&self) -> &Token {
         &self.tokens[self.position]
     }
 
+    fn peek_token(&self) -> &Token {
+        let next = (self.position + 1).min(self.tokens.len() - 1);
+        &self.tokens[next]
+    }
+
     fn advance(&mut self) {
         if self.position < self.tokens.len() - 1 {
             self.position += 1;
@@ -667,6 +759,22 @@ mod tests {
         assert_eq!(limit.offset, Some(5));
     }
 
+    #[test]
+    fn test_select_with_offset_only() {
+        let mut parser = Parser::new("SELECT * FROM users OFFSET 20").unwrap();
+        let query = parser.parse().unwrap();
+
+        let limit = query.limit.unwrap();
+        assert_eq!(limit.count, usize::MAX);
+        assert_eq!(limit.offset, Some(20));
+        assert_eq!(limit.to_string(), "OFFSET 20");
+
+        assert!(Parser::new("SELECT * FROM users OFFSET")
+            .unwrap()
+            .parse()
+            .is_err());
+    }
+
     #[test]
     fn test_complex_where() {
         let mut parser =
@@ -676,6 +784,58 @@ mod tests {
         assert!(query.where_clause.is_some());
     }
 
+    #[test]
+    fn test_negated_predicates() {
+        let mut parser = Parser::new(
+            "SELECT * FROM products WHERE category NOT IN ('A', 'B') \
+             AND name NOT LIKE '%test%' AND price NOT BETWEEN 10 AND 20",
+        )
+        .unwrap();
+        let query = parser.parse().unwrap();
+
+        let condition = query.where_clause.unwrap().condition;
+        assert_eq!(
+            condition.to_string(),
+            "((category NOT IN ('A', 'B') AND name NOT LIKE '%test%') \
+             AND price NOT BETWEEN 10 AND 20)"
+        );
+    }
+
+    #[test]
+    fn test_table_statements() {
+        let statement = Parser::new("DROP TABLE users")
+            .unwrap()
+            .parse_statement()
+            .unwrap();
+        assert_eq!(
+            statement,
+            Statement::DropTable {
+                table: "users".to_string()
+            }
+        );
+
+        for sql in ["TRUNCATE TABLE users", "truncate users"] {
+            let statement = Parser::new(sql).unwrap().parse_statement().unwrap();
+            assert_eq!(
+                statement,
+                Statement::TruncateTable {
+                    table: "users".to_string()
+                }
+            );
+        }
+
+        let statement = Parser::new("SELECT * FROM users")
+            .unwrap()
+            .parse_statement()
+            .unwrap();
+        assert!(matches!(statement, Statement::Select(_)));
+
+        assert!(Parser::new("DROP users")
+            .unwrap()
+            .parse_statement()
+            .is_err());
+    }
+
     #[test]
     fn test_aggregate_function() {
         let mut parser = Parser::new("SELECT COUNT(*) FROM users").unwrap();
@@ -688,6 +848,32 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_group_concat_separator() {
+        let mut parser =
+            Parser::new("SELECT FIRST(name), GROUP_CONCAT(name, '; ') FROM users").unwrap();
+        let query = parser.parse().unwrap();
+
+        assert!(matches!(
+            &query.select.columns[0],
+            SelectColumn::Aggregate {
+                function: AggregateFunction::First,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &query.select.columns[1],
+            SelectColumn::Aggregate {
+                function: AggregateFunction::GroupConcat { separator: Some(s) },
+                ..
+            } if s == "; "
+        ));
+        assert!(Parser::new("SELECT GROUP_CONCAT(name, 1) FROM users")
+            .unwrap()
+            .parse()
+            .is_err());
+    }
+
     #[test]
     fn test_join() {
         let mut parser =
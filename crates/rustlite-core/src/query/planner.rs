@@ -15,6 +15,15 @@ pub struct PhysicalPlan {
 pub enum PhysicalOperator {
     /// Full table scan
     TableScan { table: String },
+    /// Scan of the rows whose string `column` starts with `prefix`
+    ///
+    /// Planned for `WHERE key LIKE 'prefix%'`, so a table backed by ordered
+    /// storage only has to visit keys under the prefix.
+    PrefixScan {
+        table: String,
+        column: String,
+        prefix: String,
+    },
     /// Index scan with exact match
     IndexScan {
         table: String,
@@ -68,6 +77,14 @@ pub enum PhysicalOperator {
         input: Box<PhysicalOperator>,
         aggregates: Vec<SelectColumn>,
     },
+    /// Keep rows whose `expr` is in (or, when `negated`, not in) the single
+    /// column returned by an uncorrelated subquery, which runs once first
+    SemiJoin {
+        input: Box<PhysicalOperator>,
+        expr: Expression,
+        subquery: Box<PhysicalOperator>,
+        negated: bool,
+    },
 }
 
 /// Query planner
@@ -81,6 +98,8 @@ pub struct Planner {
 pub struct IndexMetadata {
     pub name: String,
     pub table: String,
+    /// Column whose values the index keys are
+    pub column: String,
     pub index_type: String, // "BTree" or "Hash"
 }
 
@@ -194,9 +213,34 @@ impl Planner {
         input: PhysicalOperator,
         condition: &Expression,
     ) -> Result<PhysicalOperator, PlanError> {
+        if contains_subquery(condition) {
+            return self.apply_subquery_filter(input, condition);
+        }
+
+        // Fold into an existing filter rather than stacking another one
+        if let PhysicalOperator::Filter {
+            input,
+            condition: existing,
+        } = input
+        {
+            let combined = Expression::LogicalOp {
+                left: Box::new(existing),
+                op: LogicalOperator::And,
+                right: Box::new(condition.clone()),
+            };
+            return self.apply_filter(*input, &combined);
+        }
+
+        // Bound the scan by a key prefix when the predicate allows it
+        if let Some(plan) = self.try_prefix_scan(&input, condition) {
+            return Ok(plan);
+        }
+
         // Try to use index if available
-        if let Some(index_scan) = self.try_index_scan(condition) {
-            return Ok(index_scan);
+        if let PhysicalOperator::TableScan { table } = &input {
+            if let Some(index_scan) = self.try_index_scan(table, condition) {
+                return Ok(index_scan);
+            }
         }
 
         // Otherwise, use filter operator
@@ -206,7 +250,109 @@ impl Planner {
         })
     }
 
-    fn try_index_scan(&self, condition: &Expression) -> Option<PhysicalOperator> {
+    /// Plan a WHERE clause containing IN subqueries
+    ///
+    /// Each subquery must be a top-level AND term; it becomes a `SemiJoin`
+    /// over the plan for the remaining terms.
+    fn apply_subquery_filter(
+        &self,
+        input: PhysicalOperator,
+        condition: &Expression,
+    ) -> Result<PhysicalOperator, PlanError> {
+        let mut terms = Vec::new();
+        split_conjunction(condition, &mut terms);
+
+        let mut semi_joins = Vec::new();
+        let mut rest = Vec::new();
+        for term in terms {
+            match term {
+                Expression::InSubquery {
+                    expr,
+                    subquery,
+                    negated,
+                } => {
+                    let single_column = matches!(
+                        subquery.select.columns.as_slice(),
+                        [SelectColumn::Column { .. }] | [SelectColumn::Aggregate { .. }]
+                    );
+                    if !single_column {
+                        return Err(PlanError::InvalidExpression(
+                            "IN subquery must select exactly one column".to_string(),
+                        ));
+                    }
+                    semi_joins.push((expr.as_ref().clone(), self.plan(subquery)?.root, *negated));
+                }
+                term if contains_subquery(term) => {
+                    return Err(PlanError::UnsupportedOperation(
+                        "IN subquery must be combined with AND".to_string(),
+                    ));
+                }
+                term => rest.push(term),
+            }
+        }
+
+        let mut plan = match conjunction(&rest) {
+            Some(rest) => self.apply_filter(input, &rest)?,
+            None => input,
+        };
+        for (expr, subquery, negated) in semi_joins {
+            plan = PhysicalOperator::SemiJoin {
+                input: Box::new(plan),
+                expr,
+                subquery: Box::new(subquery),
+                negated,
+            };
+        }
+
+        Ok(plan)
+    }
+
+    /// Plan a `key LIKE 'prefix%'` term of a single-table WHERE clause as a
+    /// [`PhysicalOperator::PrefixScan`], filtering on the remaining terms
+    fn try_prefix_scan(
+        &self,
+        input: &PhysicalOperator,
+        condition: &Expression,
+    ) -> Option<PhysicalOperator> {
+        let PhysicalOperator::TableScan { table } = input else {
+            return None;
+        };
+
+        let mut terms = Vec::new();
+        split_conjunction(condition, &mut terms);
+        let (position, column, prefix) = terms.iter().enumerate().find_map(|(i, term)| {
+            let (column, prefix) = key_prefix(term)?;
+            Some((i, column, prefix))
+        })?;
+
+        let scan = PhysicalOperator::PrefixScan {
+            table: table.clone(),
+            column: column.to_string(),
+            prefix: prefix.to_string(),
+        };
+        terms.remove(position);
+
+        Some(match conjunction(&terms) {
+            Some(rest) => PhysicalOperator::Filter {
+                input: Box::new(scan),
+                condition: rest,
+            },
+            None => scan,
+        })
+    }
+
+    /// Indexes on `column` of `table`
+    fn indexes_on<'a>(
+        &'a self,
+        table: &'a str,
+        column: &'a str,
+    ) -> impl Iterator<Item = &'a IndexMetadata> + 'a {
+        self.available_indexes
+            .iter()
+            .filter(move |index| index.table == table && index.column == column)
+    }
+
+    fn try_index_scan(&self, table: &str, condition: &Expression) -> Option<PhysicalOperator> {
         // Check if condition can use an index
         match condition {
             Expression::BinaryOp { left, op, right } => {
@@ -217,53 +363,55 @@ impl Planner {
                 };
 
                 // Find matching index
-                for index in &self.available_indexes {
-                    // Simplified: assume index name contains column name
-                    if index.name.contains(column) {
-                        match index.index_type.as_str() {
-                            "Hash" if *op == BinaryOperator::Eq => {
-                                // Use hash index for exact match
-                                return Some(PhysicalOperator::IndexScan {
-                                    table: index.table.clone(),
-                                    index: index.name.clone(),
-                                    key: literal_to_bytes(value),
-                                });
-                            }
-                            "BTree" => {
-                                // Use B-Tree index for range queries
-                                match op {
-                                    BinaryOperator::Eq => {
-                                        return Some(PhysicalOperator::IndexScan {
-                                            table: index.table.clone(),
-                                            index: index.name.clone(),
-                                            key: literal_to_bytes(value),
-                                        });
-                                    }
-                                    BinaryOperator::Lt | BinaryOperator::Le => {
-                                        return Some(PhysicalOperator::IndexRangeScan {
-                                            table: index.table.clone(),
-                                            index: index.name.clone(),
-                                            start: None,
-                                            end: Some(literal_to_bytes(value)),
-                                        });
-                                    }
-                                    BinaryOperator::Gt | BinaryOperator::Ge => {
-                                        return Some(PhysicalOperator::IndexRangeScan {
-                                            table: index.table.clone(),
-                                            index: index.name.clone(),
-                                            start: Some(literal_to_bytes(value)),
-                                            end: None,
-                                        });
-                                    }
-                                    _ => {}
+                for index in self.indexes_on(table, column) {
+                    match index.index_type.as_str() {
+                        "Hash" if *op == BinaryOperator::Eq => {
+                            // Use hash index for exact match
+                            return Some(PhysicalOperator::IndexScan {
+                                table: index.table.clone(),
+                                index: index.name.clone(),
+                                key: literal_to_bytes(value),
+                            });
+                        }
+                        "BTree" => {
+                            // Use B-Tree index for range queries
+                            match op {
+                                BinaryOperator::Eq => {
+                                    return Some(PhysicalOperator::IndexScan {
+                                        table: index.table.clone(),
+                                        index: index.name.clone(),
+                                        key: literal_to_bytes(value),
+                                    });
+                                }
+                                BinaryOperator::Lt | BinaryOperator::Le => {
+                                    return Some(PhysicalOperator::IndexRangeScan {
+                                        table: index.table.clone(),
+                                        index: index.name.clone(),
+                                        start: None,
+                                        end: Some(literal_to_bytes(value)),
+                                    });
+                                }
+                                BinaryOperator::Gt | BinaryOperator::Ge => {
+                                    return Some(PhysicalOperator::IndexRangeScan {
+                                        table: index.table.clone(),
+                                        index: index.name.clone(),
+                                        start: Some(literal_to_bytes(value)),
+                                        end: None,
+                                    });
                                 }
+                                _ => {}
                             }
-                            _ => {}
                         }
+                        _ => {}
                     }
                 }
             }
-            Expression::Between { expr, min, max } => {
+            Expression::Between {
+                expr,
+                min,
+                max,
+                negated: false,
+            } => {
                 // Extract column name
                 let column = match expr.as_ref() {
                     Expression::Column(col) => col,
@@ -271,8 +419,8 @@ impl Planner {
                 };
 
                 // Find matching B-Tree index
-                for index in &self.available_indexes {
-                    if index.index_type == "BTree" && index.name.contains(column) {
+                for index in self.indexes_on(table, column) {
+                    if index.index_type == "BTree" {
                         let start = match min.as_ref() {
                             Expression::Literal(lit) => Some(literal_to_bytes(lit)),
                             _ => None,
@@ -333,6 +481,75 @@ impl fmt::Display for PlanError {
 
 impl std::error::Error for PlanError {}
 
+/// Whether an expression contains an IN subquery
+fn contains_subquery(expr: &Expression) -> bool {
+    match expr {
+        Expression::InSubquery { .. } => true,
+        Expression::LogicalOp { left, right, .. } | Expression::BinaryOp { left, right, .. } => {
+            contains_subquery(left) || contains_subquery(right)
+        }
+        Expression::Not(inner) => contains_subquery(inner),
+        _ => false,
+    }
+}
+
+/// Column holding each row's primary key
+const KEY_COLUMN: &str = "key";
+
+/// The key column and literal prefix of a `key LIKE 'prefix%'` predicate
+///
+/// Only patterns with a single trailing `%` and no other wildcards qualify,
+/// as the prefix scan then matches exactly the rows the predicate does.
+fn key_prefix(expr: &Expression) -> Option<(&str, &str)> {
+    let Expression::Like {
+        expr,
+        pattern,
+        negated: false,
+    } = expr
+    else {
+        return None;
+    };
+    let Expression::Column(column) = expr.as_ref() else {
+        return None;
+    };
+
+    let name = column
+        .rsplit_once('.')
+        .map_or(column.as_str(), |(_, name)| name);
+    let prefix = pattern.strip_suffix('%')?;
+    if name != KEY_COLUMN || prefix.is_empty() || prefix.contains(['%', '_']) {
+        return None;
+    }
+    Some((column, prefix))
+}
+
+/// AND the terms back together, or `None` if there are none
+fn conjunction(terms: &[&Expression]) -> Option<Expression> {
+    terms
+        .iter()
+        .map(|term| (*term).clone())
+        .reduce(|left, right| Expression::LogicalOp {
+            left: Box::new(left),
+            op: LogicalOperator::And,
+            right: Box::new(right),
+        })
+}
+
+/// Flatten nested ANDs into their terms
+fn split_conjunction<'a>(expr: &'a Expression, terms: &mut Vec<&'a Expression>) {
+    match expr {
+        Expression::LogicalOp {
+            left,
+            op: LogicalOperator::And,
+            right,
+        } => {
+            split_conjunction(left, terms);
+            split_conjunction(right, terms);
+        }
+        _ => terms.push(expr),
+    }
+}
+
 impl fmt::Display for PhysicalPlan {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.root)
@@ -343,6 +560,11 @@ impl fmt::Display for PhysicalOperator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             PhysicalOperator::TableScan { table } => write!(f, "TableScan({})", table),
+            PhysicalOperator::PrefixScan {
+                table,
+                column,
+                prefix,
+            } => write!(f, "PrefixScan({}.{} LIKE '{}%')", table, column, prefix),
             PhysicalOperator::IndexScan { table, index, .. } => {
                 write!(f, "IndexScan({}.{})", table, index)
             }
@@ -424,6 +646,15 @@ impl fmt::Display for PhysicalOperator {
                 }
                 write!(f, ") -> {}", input)
             }
+            PhysicalOperator::SemiJoin {
+                input,
+                expr,
+                subquery,
+                negated,
+            } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "SemiJoin({} {}IN {}) -> {}", expr, not, subquery, input)
+            }
         }
     }
 }
@@ -469,6 +700,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_index_scan_matches_table_and_column() {
+        let planner = Planner::with_indexes(vec![IndexMetadata {
+            name: "by_age".to_string(),
+            table: "users".to_string(),
+            column: "age".to_string(),
+            index_type: "Hash".to_string(),
+        }]);
+        let plan = |sql: &str| {
+            let query = Parser::new(sql).unwrap().parse().unwrap();
+            planner.plan(&query).unwrap().to_string()
+        };
+
+        assert!(plan("SELECT * FROM users WHERE age = 30").contains("IndexScan(users.by_age)"));
+        // Another table, or another column, is filtered instead
+        assert!(!plan("SELECT * FROM orders WHERE age = 30").contains("IndexScan"));
+        assert!(!plan("SELECT * FROM users WHERE name = 30").contains("IndexScan"));
+    }
+
+    #[test]
+    fn test_aggregate_with_filter_plan() {
+        let mut parser = Parser::new("SELECT COUNT(*) FROM users WHERE age > 18").unwrap();
+        let query = parser.parse().unwrap();
+
+        let planner = Planner::new();
+        let plan = planner.plan(&query).unwrap();
+
+        // Should have Aggregate -> Filter -> TableScan
+        match plan.root {
+            PhysicalOperator::Aggregate { input, .. } => match *input {
+                PhysicalOperator::Filter { input, .. } => {
+                    assert!(matches!(*input, PhysicalOperator::TableScan { .. }))
+                }
+                _ => panic!("Expected Filter"),
+            },
+            _ => panic!("Expected Aggregate"),
+        }
+    }
+
     #[test]
     fn test_order_by_plan() {
         let mut parser = Parser::new("SELECT * FROM users ORDER BY name").unwrap();
@@ -494,4 +764,83 @@ mod tests {
         let plan_str = format!("{}", plan);
         assert!(plan_str.contains("Limit"));
     }
+
+    #[test]
+    fn test_key_prefix_plan() {
+        let plan = |sql: &str| {
+            let query = Parser::new(sql).unwrap().parse().unwrap();
+            Planner::new().plan(&query).unwrap().root
+        };
+
+        // The LIKE is absorbed into the scan; the other term still filters
+        let root = plan("SELECT * FROM orders WHERE key LIKE 'order:%' AND total > 10");
+        let PhysicalOperator::Project { input, .. } = root else {
+            panic!("expected projection, got {}", root);
+        };
+        let PhysicalOperator::Filter { input, condition } = *input else {
+            panic!("expected filter, got {}", input);
+        };
+        assert_eq!(condition.to_string(), "(total > 10)");
+        match *input {
+            PhysicalOperator::PrefixScan {
+                ref table,
+                ref column,
+                ref prefix,
+            } => {
+                assert_eq!(table, "orders");
+                assert_eq!(column, "key");
+                assert_eq!(prefix, "order:");
+            }
+            other => panic!("expected prefix scan, got {}", other),
+        }
+
+        // Patterns a prefix cannot express keep the full scan
+        for sql in [
+            "SELECT * FROM orders WHERE key LIKE '%order'",
+            "SELECT * FROM orders WHERE key LIKE 'ord_r:%'",
+            "SELECT * FROM orders WHERE key NOT LIKE 'order:%'",
+            "SELECT * FROM orders WHERE name LIKE 'order:%'",
+            "SELECT * FROM orders WHERE key LIKE 'order:%' OR total > 10",
+        ] {
+            let plan_str = plan(sql).to_string();
+            assert!(
+                plan_str.contains("TableScan(orders)"),
+                "{}: {}",
+                sql,
+                plan_str
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_subquery_plan() {
+        let mut parser = Parser::new(
+            "SELECT name FROM users WHERE age > 18 AND id IN (SELECT user_id FROM orders)",
+        )
+        .unwrap();
+        let query = parser.parse().unwrap();
+
+        let planner = Planner::new();
+        let plan = planner.plan(&query).unwrap();
+
+        // Should have Project -> SemiJoin -> Filter -> TableScan
+        match plan.root {
+            PhysicalOperator::Project { input, .. } => match *input {
+                PhysicalOperator::SemiJoin { input, .. } => {
+                    assert!(matches!(*input, PhysicalOperator::Filter { .. }))
+                }
+                _ => panic!("Expected SemiJoin"),
+            },
+            _ => panic!("Expected Project"),
+        }
+
+        // Subqueries under OR or selecting several columns are rejected
+        for sql in [
+            "SELECT * FROM users WHERE age > 18 OR id IN (SELECT user_id FROM orders)",
+            "SELECT * FROM users WHERE id IN (SELECT * FROM orders)",
+        ] {
+            let query = Parser::new(sql).unwrap().parse().unwrap();
+            assert!(planner.plan(&query).is_err());
+        }
+    }
 }
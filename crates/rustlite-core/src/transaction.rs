@@ -4,17 +4,20 @@
 //! Implements snapshot isolation with timestamp-based versioning.
 
 use crate::{Error, Result};
-use std::collections::{BTreeMap, HashMap};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Transaction isolation levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum IsolationLevel {
-    /// Read uncommitted (lowest isolation)
+    /// Read uncommitted (lowest isolation). Currently behaves like
+    /// `ReadCommitted`: uncommitted versions are never read.
     ReadUncommitted,
-    /// Read committed
+    /// Read committed: each read sees the latest committed value at the
+    /// time of the read
     ReadCommitted,
     /// Repeatable read (snapshot isolation)
     #[default]
@@ -23,12 +26,26 @@ pub enum IsolationLevel {
     Serializable,
 }
 
+impl IsolationLevel {
+    /// Timestamp reads are resolved at for a transaction begun at `snapshot_ts`
+    fn read_timestamp(self, snapshot_ts: Timestamp) -> Timestamp {
+        match self {
+            // Every committed version is visible, so reads see the latest
+            IsolationLevel::ReadUncommitted | IsolationLevel::ReadCommitted => Timestamp::MAX,
+            IsolationLevel::RepeatableRead | IsolationLevel::Serializable => snapshot_ts,
+        }
+    }
+}
+
 /// Transaction ID (monotonically increasing)
 pub type TransactionId = u64;
 
 /// Timestamp for MVCC versioning
 pub type Timestamp = u64;
 
+/// A write to a key: its new value, or `None` for a delete
+pub type KeyWrite = (Vec<u8>, Option<Vec<u8>>);
+
 /// A versioned value in MVCC
 #[derive(Debug, Clone)]
 pub struct VersionedValue {
@@ -69,6 +86,21 @@ impl VersionedValue {
     }
 }
 
+/// Size of the MVCC version chains, for tuning how often to run `gc`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VersionStats {
+    /// Number of keys with at least one version
+    pub keys: usize,
+    /// Number of versions across all keys, committed or not
+    pub versions: usize,
+    /// Number of versions of the key with the most
+    pub max_chain_depth: usize,
+    /// Oldest active snapshot, or the current time if no transaction is
+    /// active. `gc` keeps, for each key, only the newest version visible at
+    /// this timestamp and the versions after it.
+    pub gc_watermark: Timestamp,
+}
+
 /// MVCC version chain for a key
 #[derive(Debug, Clone)]
 pub struct VersionChain {
@@ -103,13 +135,120 @@ impl VersionChain {
         None
     }
 
+    /// Get the value of the most recently committed version, ignoring snapshots
+    ///
+    /// Returns `None` if nothing is committed or the latest commit is a delete.
+    pub fn latest_committed(&self) -> Option<Vec<u8>> {
+        self.versions
+            .iter()
+            .find(|v| v.committed)
+            .and_then(|v| v.value.clone())
+    }
+
+    /// Resolve a key for a snapshot, including deletes
+    ///
+    /// Returns `Some(None)` if the newest version visible to the snapshot
+    /// deletes the key, and `None` if no version is visible at all.
+    fn resolve(
+        &self,
+        snapshot_ts: Timestamp,
+        current_txn_id: TransactionId,
+    ) -> Option<Option<Vec<u8>>> {
+        let version = self.versions.iter().find(|v| {
+            let own = v.txn_id == current_txn_id;
+            own || (v.committed && v.created_at <= snapshot_ts)
+        })?;
+
+        if version.deleted_at.is_some_and(|ts| ts <= snapshot_ts) {
+            return Some(None);
+        }
+        Some(version.value.clone())
+    }
+
+    /// Resolve a key as of a past timestamp from committed versions only
+    ///
+    /// Returns `None` if no committed version is as old as `ts`.
+    fn resolve_as_of(&self, ts: Timestamp) -> Option<Option<Vec<u8>>> {
+        let version = self
+            .versions
+            .iter()
+            .find(|v| v.committed && v.created_at <= ts)?;
+
+        if version.deleted_at.is_some_and(|deleted| deleted <= ts) {
+            return Some(None);
+        }
+        Some(version.value.clone())
+    }
+
+    /// Add an already committed version; a `None` value deletes the key
+    fn add_committed(
+        &mut self,
+        value: Option<Vec<u8>>,
+        txn_id: TransactionId,
+        timestamp: Timestamp,
+    ) {
+        if value.is_none() {
+            self.hide_latest(timestamp);
+        }
+
+        self.add_version(VersionedValue {
+            value,
+            txn_id,
+            created_at: timestamp,
+            deleted_at: None,
+            committed: true,
+        });
+    }
+
+    /// Hide the current committed value from snapshots at or after `timestamp`
+    fn hide_latest(&mut self, timestamp: Timestamp) {
+        if let Some(prev) = self
+            .versions
+            .iter_mut()
+            .find(|v| v.committed && v.value.is_some() && v.deleted_at.is_none())
+        {
+            prev.deleted_at = Some(timestamp);
+        }
+    }
+
     /// Mark all versions created by a transaction as committed
+    ///
+    /// The versions keep the timestamp they were written with.
     pub fn commit_transaction(&mut self, txn_id: TransactionId) {
-        for version in &mut self.versions {
-            if version.txn_id == txn_id {
-                version.committed = true;
-            }
+        if let Some(timestamp) = self
+            .versions
+            .iter()
+            .find(|v| v.txn_id == txn_id)
+            .map(|v| v.created_at)
+        {
+            self.commit_at(txn_id, timestamp);
+        }
+    }
+
+    /// Commit all versions created by a transaction at `commit_ts`
+    ///
+    /// The versions are restamped with the commit timestamp and moved ahead
+    /// of versions committed while the transaction ran, keeping the chain
+    /// newest first. A committed delete hides the value before it.
+    pub fn commit_at(&mut self, txn_id: TransactionId, commit_ts: Timestamp) {
+        if !self.versions.iter().any(|v| v.txn_id == txn_id) {
+            return;
         }
+
+        let (mut own, others): (Vec<_>, Vec<_>) =
+            self.versions.drain(..).partition(|v| v.txn_id == txn_id);
+        self.versions = others;
+
+        for version in &mut own {
+            version.committed = true;
+            version.created_at = commit_ts;
+        }
+        if own[0].value.is_none() {
+            self.hide_latest(commit_ts);
+        }
+
+        own.append(&mut self.versions);
+        self.versions = own;
     }
 
     /// Remove all versions created by a transaction (for rollback)
@@ -140,36 +279,143 @@ impl Default for VersionChain {
     }
 }
 
+/// Store consulted by `MVCCStorage` for keys it holds no visible version of
+///
+/// The base layer is read at its latest state, not at a transaction's snapshot.
+pub trait BaseStore: Send + Sync {
+    /// Get the current value of a key
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+
+    /// Persist the writes of a committing transaction
+    ///
+    /// Called before the transaction's versions become visible; an error
+    /// fails the commit. A `None` value deletes the key. Does nothing by
+    /// default.
+    fn commit_writes(&self, _txn_id: TransactionId, _writes: &[KeyWrite]) -> Result<()> {
+        Ok(())
+    }
+}
+
 /// MVCC storage for versioned data
 pub struct MVCCStorage {
-    /// Version chains for each key
-    data: RwLock<HashMap<Vec<u8>, VersionChain>>,
+    /// Version chains for each key, sorted so key ranges can be read
+    /// without visiting every chain
+    data: RwLock<BTreeMap<Vec<u8>, VersionChain>>,
+    /// Store read below the version chains
+    base: Option<Arc<dyn BaseStore>>,
+    /// Keys each transaction has uncommitted versions of, so committing or
+    /// rolling back visits only those chains. Updated under the `data` lock.
+    pending: RwLock<HashMap<TransactionId, BTreeSet<Vec<u8>>>>,
+    /// Version chains read by range scans, for tests checking that scans
+    /// stay within their range
+    #[cfg(test)]
+    chains_visited: AtomicUsize,
 }
 
 impl MVCCStorage {
     /// Create new MVCC storage
     pub fn new() -> Self {
         Self {
-            data: RwLock::new(HashMap::new()),
+            data: RwLock::new(BTreeMap::new()),
+            base: None,
+            pending: RwLock::new(HashMap::new()),
+            #[cfg(test)]
+            chains_visited: AtomicUsize::new(0),
+        }
+    }
+
+    /// Create MVCC storage layered over `base`
+    ///
+    /// Reads of keys with no version visible to the snapshot fall through to
+    /// `base`; a visible delete still hides the base value.
+    pub fn with_base(base: Arc<dyn BaseStore>) -> Self {
+        Self {
+            data: RwLock::new(BTreeMap::new()),
+            base: Some(base),
+            pending: RwLock::new(HashMap::new()),
+            #[cfg(test)]
+            chains_visited: AtomicUsize::new(0),
         }
     }
 
     /// Read a key with MVCC snapshot isolation
+    ///
+    /// Under `RepeatableRead` and `Serializable` the read sees the snapshot
+    /// taken at `snapshot_ts`; under the weaker levels it sees the latest
+    /// committed version.
     pub fn read(
         &self,
         key: &[u8],
         snapshot_ts: Timestamp,
         txn_id: TransactionId,
+        isolation: IsolationLevel,
     ) -> Result<Option<Vec<u8>>> {
-        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+        let snapshot_ts = isolation.read_timestamp(snapshot_ts);
+        let resolved = {
+            let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+            data.get(key)
+                .and_then(|chain| chain.resolve(snapshot_ts, txn_id))
+        };
 
-        if let Some(chain) = data.get(key) {
-            Ok(chain.get_visible(snapshot_ts, txn_id))
-        } else {
-            Ok(None)
+        match (resolved, &self.base) {
+            (Some(value), _) => Ok(value),
+            (None, Some(base)) => base.get(key),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Read the value a key had at a past timestamp
+    ///
+    /// Fails with `Error::Transaction` if every retained committed version
+    /// of the key is newer than `ts`, since the version visible at `ts` may
+    /// have been garbage collected. With a base store, keys without any
+    /// committed version have no history to read and fail the same way;
+    /// without one, such keys were never written and read as `None`.
+    pub fn read_as_of(&self, key: &[u8], ts: Timestamp) -> Result<Option<Vec<u8>>> {
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+        match data.get(key) {
+            Some(chain) if chain.versions.iter().any(|v| v.committed) => chain
+                .resolve_as_of(ts)
+                .ok_or_else(|| Error::Transaction("version garbage-collected".into())),
+            _ if self.base.is_some() => {
+                Err(Error::Transaction("no version history for key".into()))
+            }
+            _ => Ok(None),
         }
     }
 
+    /// Read several keys with MVCC snapshot isolation under a single lock
+    ///
+    /// Results are returned in the order of `keys`. Visibility follows
+    /// `isolation` as in [`read`](Self::read).
+    pub fn read_many(
+        &self,
+        keys: &[&[u8]],
+        snapshot_ts: Timestamp,
+        txn_id: TransactionId,
+        isolation: IsolationLevel,
+    ) -> Result<Vec<Option<Vec<u8>>>> {
+        let snapshot_ts = isolation.read_timestamp(snapshot_ts);
+        let resolved: Vec<_> = {
+            let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+            keys.iter()
+                .map(|key| {
+                    data.get(*key)
+                        .and_then(|chain| chain.resolve(snapshot_ts, txn_id))
+                })
+                .collect()
+        };
+
+        keys.iter()
+            .zip(resolved)
+            .map(|(key, resolved)| match (resolved, &self.base) {
+                (Some(value), _) => Ok(value),
+                (None, Some(base)) => base.get(key),
+                (None, None) => Ok(None),
+            })
+            .collect()
+    }
+
     /// Write a key (creates a new version)
     pub fn write(
         &self,
@@ -179,6 +425,7 @@ impl MVCCStorage {
         timestamp: Timestamp,
     ) -> Result<()> {
         let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
+        self.track(txn_id, [key.clone()])?;
 
         let chain = data.entry(key).or_insert_with(VersionChain::new);
 
@@ -193,20 +440,40 @@ impl MVCCStorage {
         Ok(())
     }
 
+    /// Write several keys under a single lock (creates a new version of each)
+    pub fn write_batch(
+        &self,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+        txn_id: TransactionId,
+        timestamp: Timestamp,
+    ) -> Result<()> {
+        let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
+        self.track(txn_id, entries.iter().map(|(key, _)| key.clone()))?;
+
+        for (key, value) in entries {
+            data.entry(key)
+                .or_insert_with(VersionChain::new)
+                .add_version(VersionedValue {
+                    value: Some(value),
+                    txn_id,
+                    created_at: timestamp,
+                    deleted_at: None,
+                    committed: false,
+                });
+        }
+
+        Ok(())
+    }
+
     /// Delete a key (creates a delete marker)
     pub fn delete(&self, key: &[u8], txn_id: TransactionId, timestamp: Timestamp) -> Result<()> {
         let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
+        self.track(txn_id, [key.to_vec()])?;
 
         let chain = data.entry(key.to_vec()).or_insert_with(VersionChain::new);
 
-        // Mark previous version as deleted
-        if let Some(prev) = chain.versions.first_mut() {
-            if prev.txn_id != txn_id {
-                prev.deleted_at = Some(timestamp);
-            }
-        }
-
-        // Add delete marker for this transaction
+        // Add delete marker for this transaction; the previous version is
+        // hidden once it commits
         chain.add_version(VersionedValue {
             value: None,
             txn_id,
@@ -218,31 +485,355 @@ impl MVCCStorage {
         Ok(())
     }
 
+    /// Read the latest committed value of a key, ignoring snapshots
+    pub fn read_latest(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+        Ok(data.get(key).and_then(VersionChain::latest_committed))
+    }
+
+    /// Get every key whose latest committed version holds a value, sorted by key
+    pub fn latest_entries(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+
+        Ok(data
+            .iter()
+            .filter_map(|(key, chain)| chain.latest_committed().map(|v| (key.clone(), v)))
+            .collect())
+    }
+
+    /// Get the keys in `[start, end)` whose latest committed version holds a
+    /// value, sorted by key
+    ///
+    /// `None` leaves the range unbounded above. Only the chains in the
+    /// range are visited.
+    pub fn latest_range(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        if end.is_some_and(|end| end <= start) {
+            return Ok(Vec::new());
+        }
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+
+        Ok(data
+            .range(Self::key_bounds(start, end))
+            .inspect(|_| {
+                #[cfg(test)]
+                self.chains_visited.fetch_add(1, Ordering::Relaxed);
+            })
+            .filter_map(|(key, chain)| chain.latest_committed().map(|v| (key.clone(), v)))
+            .collect())
+    }
+
+    /// Bounds of the key range `[start, end)`, unbounded above for `None`
+    fn key_bounds(start: &[u8], end: Option<&[u8]>) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+        (
+            Bound::Included(start.to_vec()),
+            end.map_or(Bound::Unbounded, |end| Bound::Excluded(end.to_vec())),
+        )
+    }
+
+    /// Count the keys whose latest committed version holds a value
+    pub fn latest_len(&self) -> Result<usize> {
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+        Ok(data
+            .values()
+            .filter(|chain| chain.latest_committed().is_some())
+            .count())
+    }
+
+    /// Apply a batch of writes as already-committed versions (autocommit)
+    ///
+    /// A `None` value deletes the key. The whole batch becomes visible at
+    /// once. Each touched chain is then garbage collected against
+    /// `min_active_ts`, and chains left holding only a delete are removed.
+    pub fn write_committed(
+        &self,
+        writes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+        txn_id: TransactionId,
+        timestamp: Timestamp,
+        min_active_ts: Timestamp,
+    ) -> Result<()> {
+        let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
+
+        for (key, value) in writes {
+            Self::apply_committed(&mut data, key, value, txn_id, timestamp, min_active_ts);
+        }
+
+        Ok(())
+    }
+
+    /// Delete every live key in `[start, end)` as already-committed
+    /// versions, like [`write_committed`](Self::write_committed)
+    ///
+    /// The keys are found and deleted under one lock, so no write lands
+    /// between the two. `None` leaves the range unbounded above. Returns the
+    /// deleted keys.
+    pub fn delete_range_committed(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+        txn_id: TransactionId,
+        timestamp: Timestamp,
+        min_active_ts: Timestamp,
+    ) -> Result<Vec<Vec<u8>>> {
+        let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
+
+        if end.is_some_and(|end| end <= start) {
+            return Ok(Vec::new());
+        }
+        let keys: Vec<_> = data
+            .range(Self::key_bounds(start, end))
+            .filter(|(_, chain)| chain.latest_committed().is_some())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &keys {
+            Self::apply_committed(
+                &mut data,
+                key.clone(),
+                None,
+                txn_id,
+                timestamp,
+                min_active_ts,
+            );
+        }
+
+        Ok(keys)
+    }
+
+    /// Add a committed version of `key`, then garbage collect its chain and
+    /// remove it if only a delete is left
+    fn apply_committed(
+        data: &mut BTreeMap<Vec<u8>, VersionChain>,
+        key: Vec<u8>,
+        value: Option<Vec<u8>>,
+        txn_id: TransactionId,
+        timestamp: Timestamp,
+        min_active_ts: Timestamp,
+    ) {
+        let chain = data.entry(key.clone()).or_default();
+        chain.add_committed(value, txn_id, timestamp);
+        chain.gc(min_active_ts);
+
+        if chain
+            .versions
+            .iter()
+            .all(|v| v.committed && v.value.is_none())
+        {
+            data.remove(&key);
+        }
+    }
+
+    /// Bring the version chains of `keys` up to date with writes made
+    /// directly to the base store
+    ///
+    /// Keys without a chain are read from the base anyway and are left
+    /// alone. Every other key gets a committed version at `timestamp`
+    /// holding its current base value, so transactions that begin
+    /// afterwards see the direct write rather than an older transactional
+    /// version. A chain left with only that version is dropped, since the
+    /// base answers reads of the key the same way. Does nothing without a
+    /// base store.
+    pub fn write_through(
+        &self,
+        keys: &[Vec<u8>],
+        txn_id: TransactionId,
+        timestamp: Timestamp,
+        min_active_ts: Timestamp,
+    ) -> Result<()> {
+        let Some(base) = &self.base else {
+            return Ok(());
+        };
+        let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
+
+        for key in keys {
+            let Some(chain) = data.get_mut(key) else {
+                continue;
+            };
+            // Read under the lock, so a later write-through never records
+            // an older base value than an earlier one
+            chain.add_committed(base.get(key)?, txn_id, timestamp);
+            chain.gc(min_active_ts);
+
+            if chain.versions.len() == 1 {
+                data.remove(key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Those of `keys` whose version chains shadow the base store
+    ///
+    /// Only these need [`write_through`](Self::write_through) after a
+    /// direct write. Empty without a base store.
+    pub fn shadowed_keys(&self, mut keys: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+        if self.base.is_none() {
+            return Ok(Vec::new());
+        }
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+        keys.retain(|key| data.contains_key(key));
+        Ok(keys)
+    }
+
+    /// Keys in `[start, end)` whose version chains shadow the base store,
+    /// in no particular order
+    ///
+    /// `None` leaves the range unbounded above. Empty without a base store.
+    pub fn shadowed_range(&self, start: &[u8], end: Option<&[u8]>) -> Result<Vec<Vec<u8>>> {
+        if self.base.is_none() {
+            return Ok(Vec::new());
+        }
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+        Ok(data
+            .keys()
+            .filter(|key| key.as_slice() >= start && end.map_or(true, |end| key.as_slice() < end))
+            .cloned()
+            .collect())
+    }
+
+    /// Record that a transaction has uncommitted versions of `keys`
+    ///
+    /// Must be called with the `data` write lock held.
+    fn track(&self, txn_id: TransactionId, keys: impl IntoIterator<Item = Vec<u8>>) -> Result<()> {
+        let mut pending = self.pending.write().map_err(|_| Error::LockPoisoned)?;
+        pending.entry(txn_id).or_default().extend(keys);
+        Ok(())
+    }
+
+    /// Stop tracking a transaction, returning the keys it wrote
+    ///
+    /// Must be called with the `data` write lock held.
+    fn untrack(&self, txn_id: TransactionId) -> Result<BTreeSet<Vec<u8>>> {
+        let mut pending = self.pending.write().map_err(|_| Error::LockPoisoned)?;
+        Ok(pending.remove(&txn_id).unwrap_or_default())
+    }
+
+    /// Get the keys a transaction has written or deleted, sorted by key
+    pub fn keys_written_by(&self, txn_id: TransactionId) -> Result<Vec<Vec<u8>>> {
+        let pending = self.pending.read().map_err(|_| Error::LockPoisoned)?;
+        Ok(pending
+            .get(&txn_id)
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    /// Get the final value a transaction wrote to each key, sorted by key
+    ///
+    /// A `None` value means the transaction deleted the key.
+    pub fn writes_by(&self, txn_id: TransactionId) -> Result<Vec<KeyWrite>> {
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+        let pending = self.pending.read().map_err(|_| Error::LockPoisoned)?;
+        let Some(keys) = pending.get(&txn_id) else {
+            return Ok(Vec::new());
+        };
+        Ok(keys
+            .iter()
+            .filter_map(|key| {
+                // Versions are newest first
+                let chain = data.get(key)?;
+                let version = chain.versions.iter().find(|v| v.txn_id == txn_id)?;
+                Some((key.clone(), version.value.clone()))
+            })
+            .collect())
+    }
+
     /// Commit all versions for a transaction
+    ///
+    /// The versions keep the timestamp they were written with. With a base
+    /// store, the transaction's writes are first handed to
+    /// [`BaseStore::commit_writes`]; if that fails, nothing is committed.
     pub fn commit(&self, txn_id: TransactionId) -> Result<()> {
+        if self.base.is_some() {
+            self.commit_to_base(txn_id, &self.writes_by(txn_id)?)?;
+        }
+
         let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
 
-        for chain in data.values_mut() {
-            chain.commit_transaction(txn_id);
+        for key in self.untrack(txn_id)? {
+            if let Some(chain) = data.get_mut(&key) {
+                chain.commit_transaction(txn_id);
+            }
         }
 
         Ok(())
     }
 
+    /// Commit all versions for a transaction at a fresh timestamp
+    ///
+    /// Like [`commit`](Self::commit), but the versions are restamped with
+    /// the commit timestamp, which is returned. `writes` must be the
+    /// transaction's final write to each key, as
+    /// [`writes_by`](Self::writes_by) would return them; the caller usually
+    /// has them buffered already. `next_ts` is called under the storage
+    /// lock, so a snapshot taken after the commit timestamp always sees the
+    /// committed versions.
+    pub fn commit_at(
+        &self,
+        txn_id: TransactionId,
+        writes: &[KeyWrite],
+        next_ts: impl FnOnce() -> Timestamp,
+    ) -> Result<Timestamp> {
+        self.commit_to_base(txn_id, writes)?;
+
+        let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
+        let commit_ts = next_ts();
+
+        for key in self.untrack(txn_id)? {
+            if let Some(chain) = data.get_mut(&key) {
+                chain.commit_at(txn_id, commit_ts);
+            }
+        }
+
+        Ok(commit_ts)
+    }
+
+    /// Hand a transaction's writes to the base store, if there is one
+    fn commit_to_base(&self, txn_id: TransactionId, writes: &[KeyWrite]) -> Result<()> {
+        match &self.base {
+            Some(base) if !writes.is_empty() => base.commit_writes(txn_id, writes),
+            _ => Ok(()),
+        }
+    }
+
     /// Rollback all versions for a transaction
     pub fn rollback(&self, txn_id: TransactionId) -> Result<()> {
         let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
 
-        for chain in data.values_mut() {
-            chain.rollback_transaction(txn_id);
+        for key in self.untrack(txn_id)? {
+            if let Some(chain) = data.get_mut(&key) {
+                chain.rollback_transaction(txn_id);
+                // Remove empty chains
+                if chain.versions.is_empty() {
+                    data.remove(&key);
+                }
+            }
         }
 
-        // Remove empty chains
-        data.retain(|_, chain| !chain.versions.is_empty());
-
         Ok(())
     }
 
+    /// Total number of versions held across all keys, committed or not
+    pub fn version_count(&self) -> Result<usize> {
+        Ok(self.version_stats()?.versions)
+    }
+
+    /// Key, version and chain depth counts; `gc_watermark` is left at 0
+    /// since storage does not track snapshots
+    pub fn version_stats(&self) -> Result<VersionStats> {
+        let data = self.data.read().map_err(|_| Error::LockPoisoned)?;
+        let mut stats = VersionStats {
+            keys: data.len(),
+            ..VersionStats::default()
+        };
+        for chain in data.values() {
+            stats.versions += chain.versions.len();
+            stats.max_chain_depth = stats.max_chain_depth.max(chain.versions.len());
+        }
+        Ok(stats)
+    }
+
     /// Garbage collect old versions
     pub fn gc(&self, min_active_ts: Timestamp) -> Result<()> {
         let mut data = self.data.write().map_err(|_| Error::LockPoisoned)?;
@@ -266,7 +857,7 @@ impl MVCCStorage {
         let mut results = Vec::new();
         for (key, chain) in data.iter() {
             if key.starts_with(prefix) {
-                if let Some(value) = chain.get_visible(snapshot_ts, txn_id) {
+                if let Some(Some(value)) = chain.resolve(snapshot_ts, txn_id) {
                     results.push((key.clone(), value));
                 }
             }
@@ -285,10 +876,11 @@ impl Default for MVCCStorage {
 
 /// Active transaction information
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 struct ActiveTransaction {
+    #[allow(dead_code)]
     txn_id: TransactionId,
     snapshot_ts: Timestamp,
+    #[allow(dead_code)]
     isolation: IsolationLevel,
 }
 
@@ -302,6 +894,12 @@ pub struct TransactionManager {
     active_txns: RwLock<BTreeMap<TransactionId, ActiveTransaction>>,
     /// MVCC storage
     storage: Arc<MVCCStorage>,
+    /// Commit timestamp of each key written while other transactions were
+    /// active, for validating keys locked with `Transaction::get_for_update`
+    commit_log: RwLock<HashMap<Vec<u8>, Timestamp>>,
+    /// Most distinct keys a transaction may write, or `usize::MAX` for no
+    /// limit
+    max_transaction_writes: AtomicUsize,
     /// Self reference for creating transactions
     self_ref: RwLock<Option<std::sync::Weak<TransactionManager>>>,
 }
@@ -314,6 +912,8 @@ impl TransactionManager {
             next_timestamp: AtomicU64::new(Self::current_timestamp()),
             active_txns: RwLock::new(BTreeMap::new()),
             storage,
+            commit_log: RwLock::new(HashMap::new()),
+            max_transaction_writes: AtomicUsize::new(usize::MAX),
             self_ref: RwLock::new(None),
         });
 
@@ -340,6 +940,18 @@ impl TransactionManager {
         self.next_timestamp.fetch_add(1, Ordering::SeqCst)
     }
 
+    /// Limit the number of distinct keys each transaction may write
+    ///
+    /// Writes are buffered until commit, so a transaction that keeps writing
+    /// keeps growing. Past the limit, `Transaction::put` and `delete` fail with
+    /// `Error::Transaction("write set limit exceeded")` and the caller should
+    /// commit and continue in a new transaction. `None` removes the limit.
+    /// Applies to transactions begun afterwards.
+    pub fn set_max_transaction_writes(&self, limit: Option<usize>) {
+        self.max_transaction_writes
+            .store(limit.unwrap_or(usize::MAX), Ordering::SeqCst);
+    }
+
     /// Begin a new transaction
     pub fn begin(self: &Arc<Self>, isolation: IsolationLevel) -> Result<Transaction> {
         let txn_id = self.next_txn_id();
@@ -362,18 +974,69 @@ impl TransactionManager {
             isolation,
             storage: Arc::clone(&self.storage),
             manager: Some(Arc::clone(self)),
-            write_set: RwLock::new(HashMap::new()),
+            write_set: RwLock::new(BTreeMap::new()),
+            locked_keys: RwLock::new(HashSet::new()),
+            max_writes: self.max_transaction_writes.load(Ordering::SeqCst),
             committed: false,
+            rolled_back: false,
+            wrote: false,
         })
     }
 
-    /// Commit a transaction
-    pub fn commit(&self, txn_id: TransactionId) -> Result<()> {
-        // Validate no conflicts (simplified - just check write-write conflicts)
-        // In a full implementation, we'd do serializability validation here
+    /// Commit a transaction, returning its commit timestamp
+    ///
+    /// Commit timestamps come from the same clock as snapshot timestamps, so
+    /// they strictly increase across commits.
+    pub fn commit(&self, txn_id: TransactionId) -> Result<Timestamp> {
+        let writes = self.storage.writes_by(txn_id)?;
+        self.commit_with_locks(txn_id, &HashSet::new(), &writes)
+    }
+
+    /// Commit a transaction after checking its locked keys for conflicts
+    ///
+    /// `writes` holds the transaction's final write to each key, sorted by
+    /// key. Fails with `Error::Transaction` and rolls the transaction back
+    /// if another transaction committed a write to one of `locked_keys`
+    /// after this transaction's snapshot was taken.
+    fn commit_with_locks(
+        &self,
+        txn_id: TransactionId,
+        locked_keys: &HashSet<Vec<u8>>,
+        writes: &[KeyWrite],
+    ) -> Result<Timestamp> {
+        // Held until the commit is logged so validation and commit are atomic
+        let mut commit_log = self.commit_log.write().map_err(|_| Error::LockPoisoned)?;
 
-        // Commit in storage
-        self.storage.commit(txn_id)?;
+        let (snapshot_ts, others_active) = {
+            let active = self.active_txns.read().map_err(|_| Error::LockPoisoned)?;
+            let snapshot_ts = active.get(&txn_id).map_or(0, |txn| txn.snapshot_ts);
+            (snapshot_ts, active.keys().any(|&id| id != txn_id))
+        };
+
+        let conflict = locked_keys
+            .iter()
+            .any(|key| commit_log.get(key).is_some_and(|&ts| ts > snapshot_ts));
+        if conflict {
+            drop(commit_log);
+            self.rollback(txn_id)?;
+            return Err(Error::Transaction(
+                "Write conflict on a key locked for update".into(),
+            ));
+        }
+
+        // Commit in storage, stamping the versions with the commit timestamp
+        let commit_ts = self
+            .storage
+            .commit_at(txn_id, writes, || self.next_timestamp())?;
+
+        // Only transactions already running can conflict with this commit
+        if others_active {
+            for (key, _) in writes {
+                commit_log.insert(key.clone(), commit_ts);
+            }
+        } else {
+            commit_log.clear();
+        }
 
         // Remove from active transactions
         {
@@ -381,7 +1044,16 @@ impl TransactionManager {
             active.remove(&txn_id);
         }
 
-        Ok(())
+        Ok(commit_ts)
+    }
+
+    /// Number of transactions begun but not yet committed or rolled back
+    pub fn active_count(&self) -> Result<usize> {
+        Ok(self
+            .active_txns
+            .read()
+            .map_err(|_| Error::LockPoisoned)?
+            .len())
     }
 
     /// Rollback a transaction
@@ -389,29 +1061,147 @@ impl TransactionManager {
         // Rollback in storage
         self.storage.rollback(txn_id)?;
 
-        // Remove from active transactions
-        {
-            let mut active = self.active_txns.write().map_err(|_| Error::LockPoisoned)?;
-            active.remove(&txn_id);
-        }
+        self.deregister(txn_id)
+    }
 
+    /// Remove a transaction from the active set
+    fn deregister(&self, txn_id: TransactionId) -> Result<()> {
+        let mut active = self.active_txns.write().map_err(|_| Error::LockPoisoned)?;
+        active.remove(&txn_id);
         Ok(())
     }
 
+    /// Get the oldest active snapshot, or `fallback` if no transaction is active
+    fn min_active_ts(&self, fallback: Timestamp) -> Result<Timestamp> {
+        let active = self.active_txns.read().map_err(|_| Error::LockPoisoned)?;
+        Ok(active
+            .values()
+            .map(|txn| txn.snapshot_ts)
+            .min()
+            .map_or(fallback, |ts| ts.min(fallback)))
+    }
+
+    /// Version chain statistics, with the watermark the next `gc` would use
+    pub fn version_stats(&self) -> Result<VersionStats> {
+        let now = self.next_timestamp.load(Ordering::SeqCst);
+        Ok(VersionStats {
+            gc_watermark: self.min_active_ts(now)?,
+            ..self.storage.version_stats()?
+        })
+    }
+
     /// Perform garbage collection
     pub fn gc(&self) -> Result<()> {
-        // Find oldest active snapshot
-        let min_active_ts = {
-            let active = self.active_txns.read().map_err(|_| Error::LockPoisoned)?;
-            active
-                .values()
-                .map(|txn| txn.snapshot_ts)
-                .min()
-                .unwrap_or(self.next_timestamp())
-        };
-
+        let min_active_ts = self.min_active_ts(self.next_timestamp())?;
+        {
+            let mut commit_log = self.commit_log.write().map_err(|_| Error::LockPoisoned)?;
+            commit_log.retain(|_, ts| *ts > min_active_ts);
+        }
         self.storage.gc(min_active_ts)
     }
+
+    /// Read the value a key had at a past timestamp
+    ///
+    /// `ts` is typically a commit timestamp returned by `Transaction::commit`.
+    /// See `MVCCStorage::read_as_of`.
+    pub fn read_as_of(&self, key: &[u8], ts: Timestamp) -> Result<Option<Vec<u8>>> {
+        self.storage.read_as_of(key, ts)
+    }
+
+    /// Apply writes outside of any transaction
+    ///
+    /// The writes are committed together at a fresh timestamp, so they are
+    /// visible to transactions that begin afterwards but not to ones already
+    /// running. A `None` value deletes the key. Versions of the written keys
+    /// that no active transaction can see are dropped immediately.
+    pub fn write_committed(&self, writes: Vec<(Vec<u8>, Option<Vec<u8>>)>) -> Result<()> {
+        let mut commit_log = self.commit_log.write().map_err(|_| Error::LockPoisoned)?;
+        let txn_id = self.next_txn_id();
+        let timestamp = self.next_timestamp();
+        let min_active_ts = self.min_active_ts(timestamp)?;
+
+        // Conflicts with transactions that locked any of the keys
+        if min_active_ts < timestamp {
+            for (key, _) in &writes {
+                commit_log.insert(key.clone(), timestamp);
+            }
+        }
+
+        self.storage
+            .write_committed(writes, txn_id, timestamp, min_active_ts)
+    }
+
+    /// Delete every key in `[start, end)` outside of any transaction
+    ///
+    /// Like [`write_committed`](Self::write_committed) with a delete of each
+    /// key in the range, but the keys are listed and deleted atomically.
+    /// `None` leaves the range unbounded above.
+    pub fn delete_range_committed(&self, start: &[u8], end: Option<&[u8]>) -> Result<()> {
+        let mut commit_log = self.commit_log.write().map_err(|_| Error::LockPoisoned)?;
+        let txn_id = self.next_txn_id();
+        let timestamp = self.next_timestamp();
+        let min_active_ts = self.min_active_ts(timestamp)?;
+
+        let deleted =
+            self.storage
+                .delete_range_committed(start, end, txn_id, timestamp, min_active_ts)?;
+
+        // Conflicts with transactions that locked any of the keys
+        if min_active_ts < timestamp {
+            for key in deleted {
+                commit_log.insert(key, timestamp);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record writes already applied directly to the base store
+    ///
+    /// Called after the writes, so transactions that begin afterwards see
+    /// them even for keys an earlier transaction wrote; see
+    /// [`MVCCStorage::write_through`]. Transactions already running keep
+    /// their snapshot of those keys, and those that locked any of them
+    /// fail to commit.
+    pub fn write_through(&self, keys: Vec<Vec<u8>>) -> Result<()> {
+        let shadowed = self.storage.shadowed_keys(keys.clone())?;
+        self.write_through_keys(&keys, &shadowed)
+    }
+
+    /// Record a direct write of every key in `[start, end)`, as in
+    /// [`write_through`](Self::write_through); `None` leaves the range
+    /// unbounded above
+    pub fn write_through_range(&self, start: &[u8], end: Option<&[u8]>) -> Result<()> {
+        let keys = self.storage.shadowed_range(start, end)?;
+        self.write_through_keys(&keys, &keys)
+    }
+
+    /// Record direct writes of `written` for conflict checks, and write
+    /// through those of them in `shadowed`, which have version chains
+    fn write_through_keys(&self, written: &[Vec<u8>], shadowed: &[Vec<u8>]) -> Result<()> {
+        if written.is_empty() {
+            return Ok(());
+        }
+
+        let mut commit_log = self.commit_log.write().map_err(|_| Error::LockPoisoned)?;
+        let txn_id = self.next_txn_id();
+        let timestamp = self.next_timestamp();
+        let min_active_ts = self.min_active_ts(timestamp)?;
+
+        // Conflicts with transactions that locked any of the keys, whether
+        // or not they have version chains
+        if min_active_ts < timestamp {
+            for key in written {
+                commit_log.insert(key.clone(), timestamp);
+            }
+        }
+        if shadowed.is_empty() {
+            return Ok(());
+        }
+
+        self.storage
+            .write_through(shadowed, txn_id, timestamp, min_active_ts)
+    }
 }
 
 /// A database transaction with MVCC support
@@ -426,25 +1216,57 @@ pub struct Transaction {
     storage: Arc<MVCCStorage>,
     /// Reference to transaction manager (for commit/rollback)
     manager: Option<Arc<TransactionManager>>,
-    /// Write set for validation
-    write_set: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+    /// Final write to each key, `None` for a delete; handed to storage
+    /// on commit
+    write_set: RwLock<BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+    /// Keys read with `get_for_update`, checked for concurrent commits
+    locked_keys: RwLock<HashSet<Vec<u8>>>,
+    /// Most distinct keys the write set may hold
+    max_writes: usize,
     /// Whether transaction is committed
     committed: bool,
+    /// Whether transaction was rolled back explicitly
+    rolled_back: bool,
+    /// Whether the transaction has written to storage, so rolling back
+    /// has versions to remove
+    wrote: bool,
 }
 
 impl Transaction {
-    /// Read a value with snapshot isolation
+    /// Read a value
+    ///
+    /// `RepeatableRead` and `Serializable` transactions read from the
+    /// snapshot taken when they began. `ReadCommitted` (and
+    /// `ReadUncommitted`) transactions see the latest committed value on
+    /// every read, including commits made after they began.
     pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
         // Check write set first (read your own writes)
         {
             let write_set = self.write_set.read().map_err(|_| Error::LockPoisoned)?;
             if let Some(value) = write_set.get(key) {
-                return Ok(Some(value.clone()));
+                return Ok(value.clone());
             }
         }
 
-        // Read from MVCC storage with snapshot isolation
-        self.storage.read(key, self.snapshot_ts, self.txn_id)
+        // Read from MVCC storage at this transaction's isolation level
+        self.storage
+            .read(key, self.snapshot_ts, self.txn_id, self.isolation)
+    }
+
+    /// Read a value and lock the key until commit
+    ///
+    /// Behaves like `get`, but the key is validated at commit time like a
+    /// write: if another transaction commits a write to it after this
+    /// transaction began, `commit` fails with `Error::Transaction` and the
+    /// transaction is rolled back. Use this for read-modify-write updates
+    /// that must not lose a concurrent update.
+    pub fn get_for_update(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        {
+            let mut locked = self.locked_keys.write().map_err(|_| Error::LockPoisoned)?;
+            locked.insert(key.to_vec());
+        }
+
+        self.get(key)
     }
 
     /// Write a value (buffered until commit)
@@ -452,57 +1274,146 @@ impl Transaction {
         // Add to write set
         {
             let mut write_set = self.write_set.write().map_err(|_| Error::LockPoisoned)?;
-            write_set.insert(key.clone(), value.clone());
+            let new_keys = usize::from(!write_set.contains_key(&key));
+            self.check_write_limit(&write_set, new_keys)?;
+            write_set.insert(key.clone(), Some(value.clone()));
         }
 
         // Write to MVCC storage (creates uncommitted version with snapshot timestamp)
+        self.wrote = true;
         self.storage
             .write(key, value, self.txn_id, self.snapshot_ts)
     }
 
+    /// Write several values at once (buffered until commit)
+    ///
+    /// Equivalent to calling `put` for each entry in order, but takes the
+    /// write set and storage locks once for the whole batch.
+    pub fn put_batch(&mut self, entries: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        {
+            let mut write_set = self.write_set.write().map_err(|_| Error::LockPoisoned)?;
+            let new_keys: HashSet<&[u8]> = entries
+                .iter()
+                .map(|(key, _)| key.as_slice())
+                .filter(|key| !write_set.contains_key(*key))
+                .collect();
+            self.check_write_limit(&write_set, new_keys.len())?;
+            for (key, value) in &entries {
+                write_set.insert(key.clone(), Some(value.clone()));
+            }
+        }
+
+        self.wrote = true;
+        self.storage
+            .write_batch(entries, self.txn_id, self.snapshot_ts)
+    }
+
+    /// Fail if adding `new_keys` keys would take the write set past the
+    /// limit set with `TransactionManager::set_max_transaction_writes`
+    fn check_write_limit(
+        &self,
+        write_set: &BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+        new_keys: usize,
+    ) -> Result<()> {
+        if write_set.len().saturating_add(new_keys) > self.max_writes {
+            return Err(Error::Transaction("write set limit exceeded".into()));
+        }
+        Ok(())
+    }
+
+    /// Read several values, with the same visibility as `get`
+    ///
+    /// Results are returned in the order of `keys`. Keys in the write set are
+    /// answered from it; the rest are read from storage in one pass.
+    pub fn get_many(&self, keys: &[&[u8]]) -> Result<Vec<Option<Vec<u8>>>> {
+        let mut results = vec![None; keys.len()];
+        let mut missing = Vec::new();
+        {
+            let write_set = self.write_set.read().map_err(|_| Error::LockPoisoned)?;
+            for (i, key) in keys.iter().enumerate() {
+                match write_set.get(*key) {
+                    Some(value) => results[i] = value.clone(),
+                    None => missing.push(i),
+                }
+            }
+        }
+
+        let missing_keys: Vec<&[u8]> = missing.iter().map(|&i| keys[i]).collect();
+        let values =
+            self.storage
+                .read_many(&missing_keys, self.snapshot_ts, self.txn_id, self.isolation)?;
+        for (i, value) in missing.into_iter().zip(values) {
+            results[i] = value;
+        }
+
+        Ok(results)
+    }
+
     /// Delete a key
     pub fn delete(&mut self, key: &[u8]) -> Result<()> {
-        // Remove from write set if present
         {
             let mut write_set = self.write_set.write().map_err(|_| Error::LockPoisoned)?;
-            write_set.remove(key);
+            let new_keys = usize::from(!write_set.contains_key(key));
+            self.check_write_limit(&write_set, new_keys)?;
+            write_set.insert(key.to_vec(), None);
         }
 
+        self.wrote = true;
         self.storage.delete(key, self.txn_id, self.snapshot_ts)
     }
 
     /// Scan keys with prefix
+    ///
+    /// Visibility follows the isolation level, as for `get`.
     pub fn scan(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
-        self.storage
-            .scan_prefix(prefix, self.snapshot_ts, self.txn_id)
+        self.storage.scan_prefix(
+            prefix,
+            self.isolation.read_timestamp(self.snapshot_ts),
+            self.txn_id,
+        )
     }
 
-    /// Commit the transaction
-    pub fn commit(mut self) -> Result<()> {
+    /// Commit the transaction, returning the commit timestamp
+    ///
+    /// Without a transaction manager there is no clock to draw from, and the
+    /// snapshot timestamp is returned instead.
+    pub fn commit(mut self) -> Result<Timestamp> {
         if self.committed {
             return Err(Error::Transaction("Transaction already committed".into()));
         }
 
-        if let Some(manager) = &self.manager {
-            manager.commit(self.txn_id)?;
+        let commit_ts = if let Some(manager) = &self.manager {
+            let write_set = self.write_set.get_mut().map_err(|_| Error::LockPoisoned)?;
+            let writes: Vec<KeyWrite> = std::mem::take(write_set).into_iter().collect();
+            let locked = self.locked_keys.read().map_err(|_| Error::LockPoisoned)?;
+            manager.commit_with_locks(self.txn_id, &locked, &writes)?
         } else {
             self.storage.commit(self.txn_id)?;
-        }
+            self.snapshot_ts
+        };
 
         self.committed = true;
-        Ok(())
+        Ok(commit_ts)
     }
 
     /// Rollback the transaction
-    pub fn rollback(self) -> Result<()> {
+    pub fn rollback(mut self) -> Result<()> {
         if self.committed {
             return Err(Error::Transaction("Transaction already committed".into()));
         }
 
-        if let Some(manager) = &self.manager {
-            manager.rollback(self.txn_id)
-        } else {
-            self.storage.rollback(self.txn_id)
+        self.rolled_back = true;
+        self.abort()
+    }
+
+    /// Discard the transaction's versions and leave the active set, so it
+    /// no longer holds back garbage collection
+    fn abort(&self) -> Result<()> {
+        match &self.manager {
+            // Read-only transactions have no versions to remove
+            Some(manager) if !self.wrote => manager.deregister(self.txn_id),
+            Some(manager) => manager.rollback(self.txn_id),
+            None => self.storage.rollback(self.txn_id),
         }
     }
 
@@ -516,3 +1427,43 @@ impl Transaction {
         self.isolation
     }
 }
+
+impl Drop for Transaction {
+    /// Roll back a transaction dropped without `commit` or `rollback`
+    ///
+    /// Otherwise it would stay registered as active, pinning the oldest
+    /// snapshot and keeping `gc` from reclaiming any newer versions.
+    fn drop(&mut self) {
+        if !self.committed && !self.rolled_back {
+            // Errors cannot be reported from drop
+            let _ = self.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_range_visits_only_keys_in_range() {
+        let storage = MVCCStorage::new();
+        let writes = (0..100)
+            .map(|i| (format!("key{:03}", i).into_bytes(), Some(vec![i as u8])))
+            .collect();
+        storage.write_committed(writes, 1, 1, 1).unwrap();
+
+        let entries = storage.latest_range(b"key010", Some(b"key020")).unwrap();
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[0], (b"key010".to_vec(), vec![10]));
+        assert_eq!(entries[9], (b"key019".to_vec(), vec![19]));
+        assert_eq!(storage.chains_visited.load(Ordering::Relaxed), 10);
+
+        assert!(storage
+            .latest_range(b"key020", Some(b"key010"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(storage.latest_range(b"key095", None).unwrap().len(), 5);
+        assert_eq!(storage.chains_visited.load(Ordering::Relaxed), 15);
+    }
+}
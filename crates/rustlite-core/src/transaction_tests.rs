@@ -492,3 +492,343 @@ fn test_version_chain_ordering() {
     let value = txn.get(b"key").unwrap();
     assert_eq!(value, Some(b"v9".to_vec()));
 }
+
+#[test]
+fn test_write_committed_visibility() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = TransactionManager::new(storage.clone());
+
+    let before = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+
+    manager
+        .write_committed(vec![(b"key".to_vec(), Some(b"v1".to_vec()))])
+        .unwrap();
+    assert_eq!(storage.read_latest(b"key").unwrap(), Some(b"v1".to_vec()));
+
+    // Transactions started earlier keep their snapshot
+    assert_eq!(before.get(b"key").unwrap(), None);
+
+    let after = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(after.get(b"key").unwrap(), Some(b"v1".to_vec()));
+
+    manager
+        .write_committed(vec![(b"key".to_vec(), None)])
+        .unwrap();
+    assert_eq!(storage.read_latest(b"key").unwrap(), None);
+    assert_eq!(after.get(b"key").unwrap(), Some(b"v1".to_vec()));
+    assert_eq!(storage.latest_len().unwrap(), 0);
+
+    let latest = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(latest.get(b"key").unwrap(), None);
+}
+
+#[test]
+fn test_delete_range_committed() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = TransactionManager::new(storage.clone());
+
+    manager
+        .write_committed(
+            ["a", "b1", "b2", "c"]
+                .iter()
+                .map(|key| (key.as_bytes().to_vec(), Some(b"v".to_vec())))
+                .collect(),
+        )
+        .unwrap();
+    let before = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+
+    manager.delete_range_committed(b"b", Some(b"c")).unwrap();
+    assert_eq!(
+        storage.latest_entries().unwrap(),
+        vec![
+            (b"a".to_vec(), b"v".to_vec()),
+            (b"c".to_vec(), b"v".to_vec())
+        ]
+    );
+    assert_eq!(before.get(b"b1").unwrap(), Some(b"v".to_vec()));
+
+    manager.delete_range_committed(&[], None).unwrap();
+    assert_eq!(storage.latest_len().unwrap(), 0);
+    assert_eq!(before.scan(b"").unwrap().len(), 4);
+}
+
+#[test]
+fn test_put_batch_and_get_many() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = TransactionManager::new(storage.clone());
+
+    let mut txn = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    let entries: Vec<_> = (0..100)
+        .map(|i| {
+            (
+                format!("key:{:03}", i).into_bytes(),
+                format!("value:{}", i).into_bytes(),
+            )
+        })
+        .collect();
+    txn.put_batch(entries).unwrap();
+
+    // Uncommitted batch is invisible to others
+    let other = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(other.get(b"key:000").unwrap(), None);
+
+    txn.commit().unwrap();
+
+    let reader = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    let keys: Vec<Vec<u8>> = (0..100)
+        .map(|i| format!("key:{:03}", i).into_bytes())
+        .collect();
+    let key_refs: Vec<&[u8]> = keys.iter().map(|k| k.as_slice()).collect();
+    let values = reader.get_many(&key_refs).unwrap();
+    for (i, value) in values.iter().enumerate() {
+        assert_eq!(value, &Some(format!("value:{}", i).into_bytes()));
+    }
+
+    // Own writes, committed values and missing keys are returned in order
+    let mut writer = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    writer
+        .put(b"key:001".to_vec(), b"updated".to_vec())
+        .unwrap();
+    let values = writer
+        .get_many(&[b"missing", b"key:001", b"key:002"])
+        .unwrap();
+    assert_eq!(
+        values,
+        vec![None, Some(b"updated".to_vec()), Some(b"value:2".to_vec())]
+    );
+}
+
+#[test]
+fn test_commit_timestamps_increase() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = TransactionManager::new(storage.clone());
+
+    let mut txn1 = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    txn1.put(b"key".to_vec(), b"v1".to_vec()).unwrap();
+    let first = txn1.commit().unwrap();
+
+    let mut txn2 = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    txn2.put(b"key".to_vec(), b"v2".to_vec()).unwrap();
+    let second = txn2.commit().unwrap();
+
+    assert!(second > first);
+
+    // The returned timestamps are the ones recorded on the versions
+    assert_eq!(
+        storage.read_as_of(b"key", first).unwrap(),
+        Some(b"v1".to_vec())
+    );
+    assert_eq!(
+        storage.read_as_of(b"key", second - 1).unwrap(),
+        Some(b"v1".to_vec())
+    );
+    assert_eq!(
+        storage.read_as_of(b"key", second).unwrap(),
+        Some(b"v2".to_vec())
+    );
+
+    // Transactions beginning after a commit snapshot past it
+    let reader = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(reader.get(b"key").unwrap(), Some(b"v2".to_vec()));
+}
+
+#[test]
+fn test_get_for_update_conflict() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = TransactionManager::new(storage.clone());
+
+    let mut setup = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    setup.put(b"counter".to_vec(), b"1".to_vec()).unwrap();
+    setup.commit().unwrap();
+
+    // Both transactions read the counter intending to increment it
+    let mut txn1 = manager.begin(IsolationLevel::Serializable).unwrap();
+    let mut txn2 = manager.begin(IsolationLevel::Serializable).unwrap();
+    assert_eq!(
+        txn1.get_for_update(b"counter").unwrap(),
+        Some(b"1".to_vec())
+    );
+    assert_eq!(
+        txn2.get_for_update(b"counter").unwrap(),
+        Some(b"1".to_vec())
+    );
+
+    txn1.put(b"counter".to_vec(), b"2".to_vec()).unwrap();
+    txn1.commit().unwrap();
+
+    // The second increment would be lost, so its commit conflicts
+    txn2.put(b"counter".to_vec(), b"2".to_vec()).unwrap();
+    assert!(matches!(txn2.commit(), Err(crate::Error::Transaction(_))));
+
+    // A transaction started after the commit locks the key without conflict
+    let mut txn3 = manager.begin(IsolationLevel::Serializable).unwrap();
+    assert_eq!(
+        txn3.get_for_update(b"counter").unwrap(),
+        Some(b"2".to_vec())
+    );
+    txn3.put(b"counter".to_vec(), b"3".to_vec()).unwrap();
+    txn3.commit().unwrap();
+
+    let reader = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(reader.get(b"counter").unwrap(), Some(b"3".to_vec()));
+}
+
+#[test]
+fn test_read_committed_sees_concurrent_commit() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = Arc::new(TransactionManager::new(Arc::clone(&storage)));
+
+    let mut setup = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    setup.put(b"key".to_vec(), b"v1".to_vec()).unwrap();
+    setup.commit().unwrap();
+
+    let read_committed = manager.begin(IsolationLevel::ReadCommitted).unwrap();
+    let repeatable = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(read_committed.get(b"key").unwrap(), Some(b"v1".to_vec()));
+    assert_eq!(repeatable.get(b"key").unwrap(), Some(b"v1".to_vec()));
+
+    let mut writer = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    writer.put(b"key".to_vec(), b"v2".to_vec()).unwrap();
+
+    // Uncommitted writes stay invisible to both
+    assert_eq!(read_committed.get(b"key").unwrap(), Some(b"v1".to_vec()));
+    writer.commit().unwrap();
+
+    assert_eq!(read_committed.get(b"key").unwrap(), Some(b"v2".to_vec()));
+    assert_eq!(
+        read_committed.get_many(&[b"key"]).unwrap(),
+        vec![Some(b"v2".to_vec())]
+    );
+    assert_eq!(repeatable.get(b"key").unwrap(), Some(b"v1".to_vec()));
+
+    // Scans see the same values as point reads
+    assert_eq!(
+        read_committed.scan(b"k").unwrap(),
+        vec![(b"key".to_vec(), b"v2".to_vec())]
+    );
+    assert_eq!(
+        repeatable.scan(b"k").unwrap(),
+        vec![(b"key".to_vec(), b"v1".to_vec())]
+    );
+}
+
+#[test]
+fn test_max_transaction_writes() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = TransactionManager::new(Arc::clone(&storage));
+    manager.set_max_transaction_writes(Some(3));
+
+    let mut txn = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    for i in 0..3 {
+        txn.put(format!("key{}", i).into_bytes(), b"v".to_vec())
+            .unwrap();
+    }
+    // Overwriting a buffered key does not grow the write set
+    txn.put(b"key0".to_vec(), b"v2".to_vec()).unwrap();
+    match txn.put(b"key3".to_vec(), b"v".to_vec()) {
+        Err(crate::Error::Transaction(msg)) => assert_eq!(msg, "write set limit exceeded"),
+        other => panic!("Expected write set limit error, got {:?}", other),
+    }
+    assert!(matches!(
+        txn.put_batch(vec![(b"key4".to_vec(), b"v".to_vec())]),
+        Err(crate::Error::Transaction(_))
+    ));
+    // Deletes count towards the limit too, except of keys already written
+    assert!(matches!(
+        txn.delete(b"key5"),
+        Err(crate::Error::Transaction(_))
+    ));
+    txn.delete(b"key1").unwrap();
+    // The writes within the limit still commit
+    txn.commit().unwrap();
+
+    let mut small = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    small.put(b"other".to_vec(), b"v".to_vec()).unwrap();
+    small.commit().unwrap();
+
+    let reader = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(reader.get(b"key0").unwrap(), Some(b"v2".to_vec()));
+    assert_eq!(reader.get(b"key1").unwrap(), None);
+    assert_eq!(reader.get(b"key3").unwrap(), None);
+    assert_eq!(reader.get(b"other").unwrap(), Some(b"v".to_vec()));
+
+    // Without a limit the write set is unbounded
+    manager.set_max_transaction_writes(None);
+    let mut txn = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    for i in 0..10 {
+        txn.put(format!("bulk{}", i).into_bytes(), b"v".to_vec())
+            .unwrap();
+    }
+    txn.commit().unwrap();
+}
+
+#[test]
+fn test_finished_transactions_release_versions() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = TransactionManager::new(Arc::clone(&storage));
+
+    let write_versions = |count: usize| {
+        for i in 0..count {
+            let mut txn = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+            txn.put(b"key".to_vec(), format!("v{}", i).into_bytes())
+                .unwrap();
+            txn.commit().unwrap();
+        }
+    };
+
+    // A long-lived reader pins every version committed after its snapshot
+    let reader = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    write_versions(50);
+    manager.gc().unwrap();
+    assert_eq!(storage.version_count().unwrap(), 50);
+
+    // Rolling the reader back lets gc keep only the latest version
+    reader.rollback().unwrap();
+    manager.gc().unwrap();
+    assert_eq!(storage.version_count().unwrap(), 1);
+
+    // Dropping a transaction without finishing it releases them too
+    let reader = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    let mut writer = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    writer.put(b"pending".to_vec(), b"v".to_vec()).unwrap();
+    write_versions(10);
+    drop(reader);
+    drop(writer);
+    manager.gc().unwrap();
+    assert_eq!(storage.version_count().unwrap(), 1);
+
+    let check = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(check.get(b"key").unwrap(), Some(b"v9".to_vec()));
+    assert_eq!(check.get(b"pending").unwrap(), None);
+}
+
+#[test]
+fn test_keys_written_by_tracks_open_transactions() {
+    let storage = Arc::new(MVCCStorage::new());
+    let manager = TransactionManager::new(Arc::clone(&storage));
+
+    let mut txn1 = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    txn1.put(b"b".to_vec(), b"1".to_vec()).unwrap();
+    txn1.put_batch(vec![(b"a".to_vec(), b"1".to_vec())])
+        .unwrap();
+    txn1.delete(b"c").unwrap();
+    let id1 = txn1.id();
+
+    let mut txn2 = manager.begin(IsolationLevel::RepeatableRead).unwrap();
+    txn2.put(b"d".to_vec(), b"2".to_vec()).unwrap();
+    let id2 = txn2.id();
+
+    assert_eq!(
+        storage.keys_written_by(id1).unwrap(),
+        vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
+    );
+    assert_eq!(storage.keys_written_by(id2).unwrap(), vec![b"d".to_vec()]);
+
+    // Finished transactions are no longer tracked
+    txn1.commit().unwrap();
+    txn2.rollback().unwrap();
+    assert!(storage.keys_written_by(id1).unwrap().is_empty());
+    assert!(storage.keys_written_by(id2).unwrap().is_empty());
+    assert!(storage.writes_by(id1).unwrap().is_empty());
+    assert_eq!(storage.version_count().unwrap(), 3);
+}
@@ -149,6 +149,20 @@ impl SnapshotManager {
 
     /// Create a full snapshot of the database
     pub fn create_snapshot(&mut self, dest: impl AsRef<Path>) -> Result<SnapshotMeta> {
+        let sequence = self.read_sequence()?;
+        self.create_snapshot_at(dest, sequence)
+    }
+
+    /// Create a full snapshot, recording `sequence` as the sequence number
+    /// it captures
+    ///
+    /// Used when the caller knows the exact sequence, e.g. because it holds
+    /// the source database still while the snapshot is taken.
+    pub fn create_snapshot_at(
+        &mut self,
+        dest: impl AsRef<Path>,
+        sequence: u64,
+    ) -> Result<SnapshotMeta> {
         let dest = dest.as_ref().to_path_buf();
 
         // Create destination directory
@@ -197,9 +211,6 @@ impl SnapshotManager {
             }
         }
 
-        // Get sequence number from manifest
-        let sequence = self.read_sequence()?;
-
         // Create metadata
         let meta = SnapshotMeta {
             id: id.clone(),
@@ -240,7 +251,7 @@ impl SnapshotManager {
 
             // Skip certain directories/files
             let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            if name == "lock" || name.starts_with('.') {
+            if name == "LOCK" || name.starts_with('.') {
                 continue;
             }
 
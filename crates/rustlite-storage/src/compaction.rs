@@ -3,21 +3,38 @@
 //! Compaction merges SSTables to reduce read amplification and
 //! reclaim space from deleted entries (tombstones).
 
+use crate::comparator::{self, KeyComparator};
+use crate::integrity::ChecksumAlgorithm;
 use crate::manifest::Manifest;
-use crate::sstable::{delete_sstable, SSTableEntry, SSTableMeta, SSTableReader, SSTableWriter};
+use crate::sstable::{
+    delete_sstable, SSTableEntry, SSTableIterator, SSTableMeta, SSTableReader, SSTableWriter,
+    DEFAULT_BLOCK_SIZE, DEFAULT_WRITE_BUFFER_SIZE,
+};
 use rustlite_core::Result;
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::sync::Arc;
 use tracing::{info, instrument, warn};
 
+/// Default number of level 0 SSTables that triggers a compaction
+const DEFAULT_L0_COMPACTION_TRIGGER: usize = 4;
+
 /// Compaction configuration
 #[derive(Debug, Clone)]
 pub struct CompactionConfig {
-    /// Maximum number of SSTables at level 0 before triggering compaction
+    /// Number of SSTables at level 0 that triggers a level 0 compaction
+    pub l0_compaction_trigger: usize,
+    /// Former name of `l0_compaction_trigger`
+    ///
+    /// Still honoured: if it is changed from the default of 4, it is used
+    /// in place of `l0_compaction_trigger`.
+    #[deprecated(since = "0.8.0", note = "Use `l0_compaction_trigger`")]
     pub level0_trigger: usize,
+    /// Maximum number of SSTables at each level above 0. A level with more
+    /// files is picked for compaction even if it is under its size limit.
+    pub max_files_per_level: usize,
     /// Size multiplier between levels (e.g., 10 means level N+1 is 10x larger)
     pub level_multiplier: usize,
     /// Maximum size for level 1 in bytes
@@ -29,9 +46,12 @@ pub struct CompactionConfig {
 }
 
 impl Default for CompactionConfig {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
-            level0_trigger: 4,
+            l0_compaction_trigger: DEFAULT_L0_COMPACTION_TRIGGER,
+            level0_trigger: DEFAULT_L0_COMPACTION_TRIGGER,
+            max_files_per_level: 10,
             level_multiplier: 10,
             level1_max_size: 10 * 1024 * 1024, // 10MB
             max_levels: 7,
@@ -40,6 +60,26 @@ impl Default for CompactionConfig {
     }
 }
 
+impl CompactionConfig {
+    /// Number of level 0 SSTables that triggers a compaction, taking the
+    /// deprecated `level0_trigger` into account
+    #[allow(deprecated)]
+    fn effective_l0_trigger(&self) -> usize {
+        if self.level0_trigger != DEFAULT_L0_COMPACTION_TRIGGER {
+            self.level0_trigger
+        } else {
+            self.l0_compaction_trigger
+        }
+    }
+}
+
+/// Extension appended to compaction outputs while they are being written.
+///
+/// Outputs are renamed to their final `.sst` name only once every output of
+/// the compaction is complete, so a `.sst.tmp` file left in the SSTable
+/// directory is the residue of an interrupted compaction.
+pub const COMPACTION_TEMP_EXTENSION: &str = "tmp";
+
 /// Statistics for compaction
 #[derive(Debug, Clone, Default)]
 pub struct CompactionStats {
@@ -59,11 +99,12 @@ struct MergeEntry {
     key: Vec<u8>,
     entry: SSTableEntry,
     source_idx: usize,
+    comparator: Arc<dyn KeyComparator>,
 }
 
 impl PartialEq for MergeEntry {
     fn eq(&self, other: &Self) -> bool {
-        self.key == other.key && self.source_idx == other.source_idx
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -79,13 +120,98 @@ impl Ord for MergeEntry {
     fn cmp(&self, other: &Self) -> Ordering {
         // BinaryHeap is a max-heap, so we reverse key comparison for min-heap behavior
         // For equal keys, higher source_idx (newer files) should come first
-        match other.key.cmp(&self.key) {
+        match self.comparator.compare(&other.key, &self.key) {
             Ordering::Equal => self.source_idx.cmp(&other.source_idx),
             ord => ord,
         }
     }
 }
 
+/// Streaming k-way merge over SSTable iterators
+///
+/// Buffers at most one entry per input, so memory use is bounded by the
+/// number of inputs rather than their size. Inputs are ordered oldest first;
+/// when a key appears in several inputs only the entry from the newest one is
+/// yielded.
+struct MergingIterator<'a> {
+    iterators: Vec<SSTableIterator<'a>>,
+    heap: BinaryHeap<MergeEntry>,
+    /// Number of older entries skipped because a newer input had the key
+    shadowed: u64,
+    comparator: Arc<dyn KeyComparator>,
+}
+
+impl<'a> MergingIterator<'a> {
+    fn new(
+        mut iterators: Vec<SSTableIterator<'a>>,
+        comparator: Arc<dyn KeyComparator>,
+    ) -> Result<Self> {
+        // Prime the heap with first entry from each SSTable
+        let mut heap = BinaryHeap::with_capacity(iterators.len());
+        for (idx, iter) in iterators.iter_mut().enumerate() {
+            if let Some(entry) = iter.next_entry()? {
+                heap.push(MergeEntry {
+                    key: entry.key.clone(),
+                    entry,
+                    source_idx: idx,
+                    comparator: Arc::clone(&comparator),
+                });
+            }
+        }
+
+        Ok(Self {
+            iterators,
+            heap,
+            shadowed: 0,
+            comparator,
+        })
+    }
+
+    /// Next entry in key order with the index of the input it came from
+    fn next_entry(&mut self) -> Result<Option<(usize, SSTableEntry)>> {
+        let Some(top) = self.heap.pop() else {
+            return Ok(None);
+        };
+        self.advance(top.source_idx)?;
+
+        // Older versions of the same key sort right behind the newest one
+        while self
+            .heap
+            .peek()
+            .is_some_and(|next| self.comparator.compare(&next.key, &top.key) == Ordering::Equal)
+        {
+            if let Some(older) = self.heap.pop() {
+                self.shadowed += 1;
+                self.advance(older.source_idx)?;
+            }
+        }
+
+        Ok(Some((top.source_idx, top.entry)))
+    }
+
+    /// Push the next entry of an input onto the heap
+    fn advance(&mut self, source_idx: usize) -> Result<()> {
+        if let Some(entry) = self.iterators[source_idx].next_entry()? {
+            self.heap.push(MergeEntry {
+                key: entry.key.clone(),
+                entry,
+                source_idx,
+                comparator: Arc::clone(&self.comparator),
+            });
+        }
+        Ok(())
+    }
+
+    /// Number of SSTable blocks read from disk so far, across all inputs
+    #[cfg(test)]
+    fn blocks_read(&self) -> usize {
+        self.iterators
+            .iter()
+            .map(SSTableIterator::blocks_read)
+            .sum()
+    }
+}
+
 /// Compaction worker
 pub struct CompactionWorker {
     /// Database directory
@@ -94,10 +220,16 @@ pub struct CompactionWorker {
     config: CompactionConfig,
     /// Statistics
     stats: CompactionStats,
-    /// Counter for generating unique SSTable names
-    file_counter: AtomicU64,
     /// Flag to stop compaction
     stop_flag: Arc<AtomicBool>,
+    /// Data block size of output SSTables
+    block_size: usize,
+    /// Write buffer capacity of output SSTables
+    write_buffer_size: usize,
+    /// Key ordering of input and output SSTables
+    comparator: Arc<dyn KeyComparator>,
+    /// Block checksum of output SSTables
+    checksum: ChecksumAlgorithm,
 }
 
 impl CompactionWorker {
@@ -107,40 +239,64 @@ impl CompactionWorker {
             dir: dir.as_ref().to_path_buf(),
             config,
             stats: CompactionStats::default(),
-            file_counter: AtomicU64::new(0),
             stop_flag: Arc::new(AtomicBool::new(false)),
+            block_size: DEFAULT_BLOCK_SIZE,
+            write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
+            comparator: comparator::bytewise(),
+            checksum: ChecksumAlgorithm::default(),
         }
     }
 
+    /// Set the data block size of SSTables written by compaction
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Set the write buffer capacity of SSTables written by compaction
+    pub fn with_write_buffer_size(mut self, write_buffer_size: usize) -> Self {
+        self.write_buffer_size = write_buffer_size;
+        self
+    }
+
+    /// Set the key ordering of the SSTables being compacted
+    pub fn with_comparator(mut self, comparator: Arc<dyn KeyComparator>) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Set the block checksum of SSTables written by compaction
+    pub fn with_checksum(mut self, checksum: ChecksumAlgorithm) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
     /// Get the stop flag for external control
     pub fn stop_flag(&self) -> Arc<AtomicBool> {
         Arc::clone(&self.stop_flag)
     }
 
-    /// Check if compaction is needed for level 0
+    /// Check if any level needs compaction
     pub fn needs_compaction(&self, manifest: &Manifest) -> bool {
-        let level0_count = manifest.sstables_at_level(0).len();
-        level0_count >= self.config.level0_trigger
+        self.pick_compaction_level(manifest).is_some()
     }
 
     /// Check which level needs compaction
     pub fn pick_compaction_level(&self, manifest: &Manifest) -> Option<u32> {
         // Check level 0 first
         let level0_count = manifest.sstables_at_level(0).len();
-        if level0_count >= self.config.level0_trigger {
+        if level0_count >= self.config.effective_l0_trigger() {
             return Some(0);
         }
 
-        // Check other levels by size
-        for level in 1..self.config.max_levels {
-            let level_size: u64 = manifest
-                .sstables_at_level(level)
-                .iter()
-                .map(|s| s.file_size)
-                .sum();
+        // Check other levels by size and file count. The last level has
+        // nowhere to compact into.
+        for level in 1..self.config.max_levels.saturating_sub(1) {
+            let sstables = manifest.sstables_at_level(level);
+            let level_size: u64 = sstables.iter().map(|s| s.file_size).sum();
 
             let max_size = self.max_size_for_level(level);
-            if level_size > max_size {
+            if level_size > max_size || sstables.len() > self.config.max_files_per_level {
                 return Some(level);
             }
         }
@@ -161,17 +317,16 @@ impl CompactionWorker {
         size
     }
 
-    /// Generate a unique SSTable path
-    fn next_sstable_path(&self, level: u32) -> PathBuf {
-        let counter = self.file_counter.fetch_add(1, AtomicOrdering::SeqCst);
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-
-        self.dir
-            .join("sst")
-            .join(format!("L{}_{}_{}.sst", level, timestamp, counter))
+    /// Generate a unique temporary path for a compaction output
+    ///
+    /// The name is built from a file number allocated by the manifest, as
+    /// for flushed SSTables, so it never matches a live SSTable.
+    fn next_sstable_path(&self, manifest: &mut Manifest, level: u32) -> Result<PathBuf> {
+        let file_number = manifest.next_file_number()?;
+        Ok(self.dir.join("sst").join(format!(
+            "L{}_{:08}.sst.{}",
+            level, file_number, COMPACTION_TEMP_EXTENSION
+        )))
     }
 
     /// Compact level 0 to level 1
@@ -196,30 +351,35 @@ impl CompactionWorker {
         // Find overlapping level 1 SSTables
         let level1_sstables = manifest.sstables_at_level(1);
 
-        // For simplicity, merge all level 0 with overlapping level 1
-        let mut all_inputs: Vec<PathBuf> = input_paths.clone();
+        // For simplicity, merge all level 0 with overlapping level 1. Inputs
+        // go oldest first: level 1, then level 0 in flush order.
+        let mut all_inputs: Vec<PathBuf> = Vec::new();
 
         // Get min/max key range from level 0
+        let cmp = self.comparator.as_ref();
         let min_key: Vec<u8> = level0_sstables
             .iter()
             .map(|s| s.min_key.clone())
-            .min()
+            .min_by(|a, b| cmp.compare(a, b))
             .unwrap_or_default();
         let max_key: Vec<u8> = level0_sstables
             .iter()
             .map(|s| s.max_key.clone())
-            .max()
+            .max_by(|a, b| cmp.compare(a, b))
             .unwrap_or_default();
 
         // Add overlapping level 1 SSTables
         for sst in level1_sstables {
-            if sst.max_key >= min_key && sst.min_key <= max_key {
+            if cmp.compare(&sst.max_key, &min_key) != Ordering::Less
+                && cmp.compare(&sst.min_key, &max_key) != Ordering::Greater
+            {
                 all_inputs.push(PathBuf::from(&sst.path));
             }
         }
+        all_inputs.extend(input_paths);
 
         // Perform the merge
-        let outputs = self.merge_sstables(&all_inputs, 1)?;
+        let outputs = self.merge_sstables(manifest, &all_inputs, 1, None)?;
 
         // Update manifest
         manifest.record_compaction(0, all_inputs.clone(), outputs)?;
@@ -234,16 +394,172 @@ impl CompactionWorker {
         Ok(())
     }
 
+    /// Compact one SSTable of `level` (1 or above) into the next level
+    ///
+    /// The oldest SSTable at `level` is merged with the SSTables it overlaps
+    /// at `level + 1`, and the outputs go to `level + 1`.
+    #[instrument(skip(self, manifest))]
+    pub fn compact_level(&mut self, manifest: &mut Manifest, level: u32) -> Result<()> {
+        let Some(input) = manifest
+            .sstables_at_level(level)
+            .into_iter()
+            .min_by_key(|s| s.sequence)
+        else {
+            return Ok(());
+        };
+
+        info!(level, "Starting level compaction");
+
+        // Inputs go oldest first: the overlapping next level SSTables, then
+        // the SSTable being pushed down
+        let cmp = self.comparator.as_ref();
+        let mut all_inputs: Vec<PathBuf> = manifest
+            .sstables_at_level(level + 1)
+            .into_iter()
+            .filter(|sst| {
+                cmp.compare(&sst.max_key, &input.min_key) != Ordering::Less
+                    && cmp.compare(&sst.min_key, &input.max_key) != Ordering::Greater
+            })
+            .map(|sst| PathBuf::from(&sst.path))
+            .collect();
+        all_inputs.push(PathBuf::from(&input.path));
+
+        let outputs = self.merge_sstables(manifest, &all_inputs, level + 1, None)?;
+        manifest.record_compaction(level, all_inputs.clone(), outputs)?;
+        for path in all_inputs {
+            let _ = delete_sstable(&path);
+        }
+
+        self.stats.compaction_count += 1;
+
+        Ok(())
+    }
+
+    /// Compact the SSTables whose key ranges overlap `[start, end]`
+    ///
+    /// SSTables overlapping the inputs are pulled in too, at every level,
+    /// until nothing else overlaps them. No older version of an input key is
+    /// then left outside the merge, so point tombstones for keys in
+    /// `[start, end]` and range tombstones within it are dropped rather than
+    /// carried over. Outputs go to the deepest input level, or level 1 if
+    /// every input is at level 0. Returns the statistics of this compaction
+    /// alone.
+    #[instrument(skip(self, manifest, start, end))]
+    pub fn compact_range(
+        &mut self,
+        manifest: &mut Manifest,
+        start: &[u8],
+        end: &[u8],
+    ) -> Result<CompactionStats> {
+        let cmp = Arc::clone(&self.comparator);
+        let cmp = cmp.as_ref();
+        if cmp.compare(start, end) == Ordering::Greater {
+            return Ok(CompactionStats::default());
+        }
+        let overlaps = |min: &[u8], max: &[u8], lo: &[u8], hi: &[u8]| {
+            cmp.compare(max, lo) != Ordering::Less && cmp.compare(min, hi) != Ordering::Greater
+        };
+
+        // Grow the key range until no unselected SSTable overlaps it
+        let sstables = manifest.all_sstables();
+        let mut selected = vec![false; sstables.len()];
+        let (mut lo, mut hi) = (start.to_vec(), end.to_vec());
+        loop {
+            let mut grew = false;
+            for (sst, selected) in sstables.iter().zip(selected.iter_mut()) {
+                if !*selected && overlaps(&sst.min_key, &sst.max_key, &lo, &hi) {
+                    *selected = true;
+                    grew = true;
+                    if cmp.compare(&sst.min_key, &lo) == Ordering::Less {
+                        lo = sst.min_key.clone();
+                    }
+                    if cmp.compare(&sst.max_key, &hi) == Ordering::Greater {
+                        hi = sst.max_key.clone();
+                    }
+                }
+            }
+            if !grew {
+                break;
+            }
+        }
+
+        let chosen: HashSet<&str> = sstables
+            .iter()
+            .zip(&selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(sst, _)| sst.path.as_str())
+            .collect();
+        if chosen.is_empty() {
+            return Ok(CompactionStats::default());
+        }
+        let bottom = sstables
+            .iter()
+            .filter(|sst| chosen.contains(sst.path.as_str()))
+            .map(|sst| sst.level)
+            .max()
+            .unwrap_or(0);
+
+        // Inputs go oldest first: deepest level first, level 0 in flush order
+        let inputs: Vec<PathBuf> = (0..=bottom)
+            .rev()
+            .flat_map(|level| manifest.sstables_at_level(level))
+            .filter(|sst| chosen.contains(sst.path.as_str()))
+            .map(|sst| PathBuf::from(&sst.path))
+            .collect();
+
+        info!(
+            inputs = inputs.len(),
+            level = bottom,
+            "Starting range compaction"
+        );
+
+        let before = self.stats.clone();
+        let outputs = self.merge_sstables(manifest, &inputs, bottom.max(1), Some((start, end)))?;
+        manifest.record_compaction(bottom, inputs.clone(), outputs)?;
+        for path in inputs {
+            let _ = delete_sstable(&path);
+        }
+        self.stats.compaction_count += 1;
+
+        Ok(CompactionStats {
+            bytes_read: self.stats.bytes_read - before.bytes_read,
+            bytes_written: self.stats.bytes_written - before.bytes_written,
+            compaction_count: 1,
+            entries_removed: self.stats.entries_removed - before.entries_removed,
+        })
+    }
+
     /// Merge multiple SSTables into new SSTables at the target level
+    ///
+    /// If `drop_range` is given, nothing older than the inputs holds keys
+    /// in that inclusive range: point tombstones for those keys and range
+    /// tombstones within it are left out of the outputs.
+    ///
+    /// The outputs take the highest sequence of the inputs listed in
+    /// `manifest`.
     fn merge_sstables(
         &mut self,
+        manifest: &mut Manifest,
         inputs: &[PathBuf],
         target_level: u32,
+        drop_range: Option<(&[u8], &[u8])>,
     ) -> Result<Vec<SSTableMeta>> {
         if inputs.is_empty() {
             return Ok(Vec::new());
         }
 
+        let input_paths: HashSet<String> = inputs
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let sequence = manifest
+            .all_sstables()
+            .iter()
+            .filter(|sst| input_paths.contains(&sst.path))
+            .map(|sst| sst.sequence)
+            .max()
+            .unwrap_or(0);
+
         // Create SST directory if needed
         let sst_dir = self.dir.join("sst");
         std::fs::create_dir_all(&sst_dir)?;
@@ -254,6 +570,7 @@ impl CompactionWorker {
             if path.exists() {
                 match SSTableReader::open(path) {
                     Ok(reader) => {
+                        let reader = reader.with_comparator(Arc::clone(&self.comparator));
                         self.stats.bytes_read += reader.metadata().file_size;
                         readers.push(reader);
                     }
@@ -266,42 +583,84 @@ impl CompactionWorker {
             return Ok(Vec::new());
         }
 
-        // Initialize merge heap
-        let mut heap: BinaryHeap<MergeEntry> = BinaryHeap::new();
-        let mut iterators: Vec<_> = readers
+        // Range tombstones with the index of the input they came from. They
+        // hide entries from older inputs (lower index) and are carried over
+        // to the outputs so they keep hiding data in SSTables not part of
+        // this compaction.
+        let range_tombstones: Vec<(usize, Vec<u8>, Vec<u8>)> = readers
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, r)| {
+                r.range_tombstones()
+                    .iter()
+                    .map(move |(start, end)| (idx, start.clone(), end.clone()))
+            })
+            .collect();
+        let cmp = self.comparator.as_ref();
+        let droppable = |key: &[u8]| {
+            drop_range.is_some_and(|(start, end)| {
+                cmp.compare(key, start) != Ordering::Less
+                    && cmp.compare(key, end) != Ordering::Greater
+            })
+        };
+        // Tombstones written to the outputs
+        let kept_tombstones: Vec<(Vec<u8>, Vec<u8>)> = range_tombstones
+            .iter()
+            .filter(|(_, start, end)| {
+                !(droppable(start)
+                    && drop_range
+                        .is_some_and(|(_, last)| cmp.compare(end, last) != Ordering::Greater))
+            })
+            .map(|(_, start, end)| (start.clone(), end.clone()))
+            .collect();
+        let new_writer = |path: &Path| -> Result<SSTableWriter> {
+            Ok(
+                SSTableWriter::with_buffer_sizes(path, self.block_size, self.write_buffer_size)?
+                    .with_comparator(Arc::clone(&self.comparator))
+                    .with_checksum(self.checksum),
+            )
+        };
+        // Each output gets the part of every tombstone between its first key
+        // and the next output's first key, so no output's tombstones cover
+        // the entries of another output at the same level
+        let finish = |mut writer: SSTableWriter, lower: Option<&[u8]>, upper: Option<&[u8]>| {
+            for (start, end) in &kept_tombstones {
+                let start = match lower {
+                    Some(lower) if cmp.compare(lower, start) == Ordering::Greater => lower,
+                    _ => start.as_slice(),
+                };
+                let end = match upper {
+                    Some(upper) if cmp.compare(upper, end) == Ordering::Less => upper,
+                    _ => end.as_slice(),
+                };
+                if cmp.compare(start, end) == Ordering::Less {
+                    writer.add_range_tombstone(start.to_vec(), end.to_vec());
+                }
+            }
+            writer.finish()
+        };
+
+        let iterators = readers
             .iter_mut()
             .map(|r| r.iter())
             .collect::<Result<Vec<_>>>()?;
-
-        // Prime the heap with first entry from each SSTable
-        for (idx, iter) in iterators.iter_mut().enumerate() {
-            if let Some(entry) = iter.next_entry()? {
-                heap.push(MergeEntry {
-                    key: entry.key.clone(),
-                    entry,
-                    source_idx: idx,
-                });
-            }
-        }
+        let mut merging = MergingIterator::new(iterators, Arc::clone(&self.comparator))?;
 
         // Output SSTables
         let mut outputs: Vec<SSTableMeta> = Vec::new();
         let mut current_writer: Option<SSTableWriter> = None;
         let mut current_size: u64 = 0;
-        let mut last_key: Option<Vec<u8>> = None;
-
-        while let Some(merge_entry) = heap.pop() {
-            // Skip duplicate keys (keep the newest - higher source_idx)
-            if last_key.as_ref() == Some(&merge_entry.key) {
+        // First key of the current output, unless it is the first output
+        let mut current_lower: Option<Vec<u8>> = None;
+
+        while let Some((source_idx, entry)) = merging.next_entry()? {
+            // Skip entries deleted by a range tombstone from a newer input
+            let range_deleted = range_tombstones.iter().any(|(idx, start, end)| {
+                *idx > source_idx
+                    && comparator::in_range(self.comparator.as_ref(), &entry.key, start, end)
+            });
+            if range_deleted || (entry.is_tombstone() && droppable(&entry.key)) {
                 self.stats.entries_removed += 1;
-                // Advance the iterator that provided this entry
-                if let Some(next) = iterators[merge_entry.source_idx].next_entry()? {
-                    heap.push(MergeEntry {
-                        key: next.key.clone(),
-                        entry: next,
-                        source_idx: merge_entry.source_idx,
-                    });
-                }
                 continue;
             }
 
@@ -309,51 +668,53 @@ impl CompactionWorker {
             if current_writer.is_none() || current_size >= self.config.target_file_size {
                 // Finish current writer
                 if let Some(writer) = current_writer.take() {
-                    let meta = writer.finish()?;
+                    let meta = finish(writer, current_lower.as_deref(), Some(&entry.key))?;
                     self.stats.bytes_written += meta.file_size;
                     outputs.push(meta);
+                    current_lower = Some(entry.key.clone());
                 }
 
                 // Start new writer
-                let path = self.next_sstable_path(target_level);
-                current_writer = Some(SSTableWriter::new(&path)?);
+                let path = self.next_sstable_path(manifest, target_level)?;
+                current_writer = Some(new_writer(&path)?);
                 current_size = 0;
             }
 
             // Write entry
             if let Some(ref mut writer) = current_writer {
-                let entry_size = merge_entry.entry.key.len() + merge_entry.entry.value.len() + 10;
-                writer.add(merge_entry.entry.clone())?;
+                let entry_size = entry.key.len() + entry.value.len() + 10;
+                writer.add(entry)?;
                 current_size += entry_size as u64;
             }
+        }
+        // Duplicate keys (only the newest is kept)
+        self.stats.entries_removed += merging.shadowed;
 
-            last_key = Some(merge_entry.key);
-
-            // Advance the iterator that provided this entry
-            if let Some(next) = iterators[merge_entry.source_idx].next_entry()? {
-                heap.push(MergeEntry {
-                    key: next.key.clone(),
-                    entry: next,
-                    source_idx: merge_entry.source_idx,
-                });
-            }
+        // Keep range tombstones even if every entry was dropped
+        if current_writer.is_none() && !kept_tombstones.is_empty() {
+            let path = self.next_sstable_path(manifest, target_level)?;
+            current_writer = Some(new_writer(&path)?);
         }
 
         // Finish last writer
         if let Some(writer) = current_writer {
-            let meta = writer.finish()?;
+            let meta = finish(writer, current_lower.as_deref(), None)?;
             self.stats.bytes_written += meta.file_size;
             outputs.push(meta);
         }
 
-        // Update level in output metadata
+        // Every output is complete; give them their final names and levels
         let outputs: Vec<SSTableMeta> = outputs
             .into_iter()
             .map(|mut m| {
+                let final_path = m.path.with_extension("");
+                std::fs::rename(&m.path, &final_path)?;
+                m.path = final_path;
                 m.level = target_level;
-                m
+                m.sequence = sequence;
+                Ok(m)
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         Ok(outputs)
     }
@@ -369,15 +730,13 @@ impl CompactionWorker {
             return Ok(false);
         }
 
-        if let Some(level) = self.pick_compaction_level(manifest) {
-            if level == 0 {
-                self.compact_level0(manifest)?;
-                return Ok(true);
-            }
-            // TODO: Implement higher level compaction
+        match self.pick_compaction_level(manifest) {
+            Some(0) => self.compact_level0(manifest)?,
+            Some(level) => self.compact_level(manifest, level)?,
+            None => return Ok(false),
         }
 
-        Ok(false)
+        Ok(true)
     }
 }
 
@@ -390,7 +749,8 @@ mod tests {
     #[test]
     fn test_compaction_config_default() {
         let config = CompactionConfig::default();
-        assert_eq!(config.level0_trigger, 4);
+        assert_eq!(config.l0_compaction_trigger, 4);
+        assert_eq!(config.max_files_per_level, 10);
         assert_eq!(config.max_levels, 7);
     }
 
@@ -400,11 +760,13 @@ mod tests {
             key: b"a".to_vec(),
             entry: SSTableEntry::value(b"a".to_vec(), b"1".to_vec()),
             source_idx: 0,
+            comparator: comparator::bytewise(),
         };
         let e2 = MergeEntry {
             key: b"b".to_vec(),
             entry: SSTableEntry::value(b"b".to_vec(), b"2".to_vec()),
             source_idx: 0,
+            comparator: comparator::bytewise(),
         };
 
         // In a max-heap, larger values come first
@@ -416,7 +778,7 @@ mod tests {
     fn test_needs_compaction() {
         let dir = tempdir().unwrap();
         let config = CompactionConfig {
-            level0_trigger: 2,
+            l0_compaction_trigger: 2,
             ..Default::default()
         };
         let worker = CompactionWorker::new(dir.path(), config);
@@ -441,6 +803,90 @@ mod tests {
         assert!(worker.needs_compaction(&manifest));
     }
 
+    #[test]
+    fn test_pick_compaction_level_by_file_count() {
+        let dir = tempdir().unwrap();
+        let config = CompactionConfig {
+            max_files_per_level: 2,
+            ..Default::default()
+        };
+        let worker = CompactionWorker::new(dir.path(), config);
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+
+        // Level 1 files far below the size limit
+        for i in 0..3 {
+            let meta = SSTableMeta {
+                path: PathBuf::from(format!("test{}.sst", i)),
+                min_key: vec![],
+                max_key: vec![],
+                entry_count: 0,
+                file_size: 1,
+                level: 1,
+                sequence: 0,
+            };
+            manifest.add_sstable(&meta).unwrap();
+            let expected = if i < 2 { None } else { Some(1) };
+            assert_eq!(worker.pick_compaction_level(&manifest), expected);
+        }
+    }
+
+    #[test]
+    fn test_run_once_compacts_level_over_file_limit() {
+        let dir = tempdir().unwrap();
+        let sst_dir = dir.path().join("sst");
+        std::fs::create_dir_all(&sst_dir).unwrap();
+        let config = CompactionConfig {
+            max_files_per_level: 2,
+            ..Default::default()
+        };
+        let mut worker = CompactionWorker::new(dir.path(), config);
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+
+        for (i, key) in [b"a", b"b", b"c"].iter().enumerate() {
+            let mut writer = SSTableWriter::new(sst_dir.join(format!("L1_{}.sst", i))).unwrap();
+            writer
+                .add(SSTableEntry::value(key.to_vec(), b"v".to_vec()))
+                .unwrap();
+            let mut meta = writer.finish().unwrap();
+            meta.level = 1;
+            meta.sequence = i as u64;
+            manifest.add_sstable(&meta).unwrap();
+        }
+
+        // The oldest level 1 SSTable is pushed down to level 2
+        assert!(worker.run_once(&mut manifest).unwrap());
+        assert_eq!(manifest.sstables_at_level(1).len(), 2);
+        let level2 = manifest.sstables_at_level(2);
+        assert_eq!(level2.len(), 1);
+        assert_eq!(level2[0].min_key, b"a".to_vec());
+
+        assert!(!worker.run_once(&mut manifest).unwrap());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_level0_trigger() {
+        let dir = tempdir().unwrap();
+        let config = CompactionConfig {
+            level0_trigger: 1,
+            ..Default::default()
+        };
+        let worker = CompactionWorker::new(dir.path(), config);
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+
+        let meta = SSTableMeta {
+            path: PathBuf::from("test.sst"),
+            min_key: vec![],
+            max_key: vec![],
+            entry_count: 0,
+            file_size: 0,
+            level: 0,
+            sequence: 0,
+        };
+        manifest.add_sstable(&meta).unwrap();
+        assert_eq!(worker.pick_compaction_level(&manifest), Some(0));
+    }
+
     #[test]
     fn test_merge_sstables() {
         let dir = tempdir().unwrap();
@@ -471,7 +917,10 @@ mod tests {
         // Merge
         let config = CompactionConfig::default();
         let mut worker = CompactionWorker::new(dir.path(), config);
-        let outputs = worker.merge_sstables(&[path1, path2], 1).unwrap();
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        let outputs = worker
+            .merge_sstables(&mut manifest, &[path1, path2], 1, None)
+            .unwrap();
 
         assert!(!outputs.is_empty());
 
@@ -482,4 +931,147 @@ mod tests {
         // "c" should have the newer value from the second SSTable
         assert_eq!(reader.get(b"c").unwrap().unwrap().value, b"3-new".to_vec());
     }
+
+    #[test]
+    fn test_merge_outputs_named_from_manifest_file_numbers() {
+        let dir = tempdir().unwrap();
+        let sst_dir = dir.path().join("sst");
+        std::fs::create_dir_all(&sst_dir).unwrap();
+
+        let input = sst_dir.join("input.sst");
+        let mut writer = SSTableWriter::new(&input).unwrap();
+        writer
+            .add(SSTableEntry::value(b"a".to_vec(), b"1".to_vec()))
+            .unwrap();
+        writer.finish().unwrap();
+
+        let mut names = Vec::new();
+        let mut inputs = vec![input];
+        for _ in 0..2 {
+            // A fresh worker and manifest each time, as after a restart
+            let mut worker = CompactionWorker::new(dir.path(), CompactionConfig::default());
+            let mut manifest = Manifest::open(dir.path()).unwrap();
+            let outputs = worker
+                .merge_sstables(&mut manifest, &inputs, 1, None)
+                .unwrap();
+            assert_eq!(outputs.len(), 1);
+            names.push(outputs[0].path.file_name().unwrap().to_owned());
+            inputs = vec![outputs[0].path.clone()];
+        }
+
+        assert_eq!(names, ["L1_00000001.sst", "L1_00000002.sst"]);
+    }
+
+    #[test]
+    fn test_merge_sstables_streaming_large_inputs() {
+        let dir = tempdir().unwrap();
+        let sst_dir = dir.path().join("sst");
+        std::fs::create_dir_all(&sst_dir).unwrap();
+
+        // The older SSTable has every key, the newer one overwrites even keys
+        let count = 20_000u32;
+        let path1 = sst_dir.join("old.sst");
+        let mut writer1 = SSTableWriter::new(&path1).unwrap();
+        for i in 0..count {
+            let key = format!("key{:08}", i).into_bytes();
+            writer1
+                .add(SSTableEntry::value(key, b"old".to_vec()))
+                .unwrap();
+        }
+        writer1.finish().unwrap();
+
+        let path2 = sst_dir.join("new.sst");
+        let mut writer2 = SSTableWriter::new(&path2).unwrap();
+        for i in (0..count).step_by(2) {
+            let key = format!("key{:08}", i).into_bytes();
+            writer2
+                .add(SSTableEntry::value(key, b"new".to_vec()))
+                .unwrap();
+        }
+        writer2.finish().unwrap();
+
+        // The merge reads blocks only as it reaches them
+        {
+            let mut readers = [
+                SSTableReader::open(&path1).unwrap(),
+                SSTableReader::open(&path2).unwrap(),
+            ];
+            let total_blocks: usize = readers.iter().map(SSTableReader::block_count).sum();
+            assert!(total_blocks > 10);
+
+            let iterators = readers.iter_mut().map(|r| r.iter().unwrap()).collect();
+            let mut merging = MergingIterator::new(iterators, comparator::bytewise()).unwrap();
+            assert_eq!(merging.blocks_read(), 2);
+
+            let mut merged = 0u32;
+            while let Some((_, entry)) = merging.next_entry().unwrap() {
+                assert_eq!(entry.key, format!("key{:08}", merged).into_bytes());
+                merged += 1;
+                if merged == count / 2 {
+                    // Both inputs are half read, give or take a block each
+                    assert!(merging.blocks_read() <= total_blocks / 2 + 2);
+                }
+            }
+            assert_eq!(merged, count);
+            assert_eq!(merging.blocks_read(), total_blocks);
+            assert_eq!(merging.shadowed, u64::from(count / 2));
+        }
+
+        let mut worker = CompactionWorker::new(dir.path(), CompactionConfig::default());
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        let outputs = worker
+            .merge_sstables(&mut manifest, &[path1, path2], 1, None)
+            .unwrap();
+        let total: u64 = outputs.iter().map(|m| m.entry_count).sum();
+        assert_eq!(total, u64::from(count));
+        assert_eq!(worker.stats().entries_removed, u64::from(count / 2));
+
+        let mut reader = SSTableReader::open(&outputs[0].path).unwrap();
+        let mut iter = reader.iter().unwrap();
+        let mut i = 0u32;
+        while let Some(entry) = iter.next_entry().unwrap() {
+            let expected: &[u8] = if i % 2 == 0 { b"new" } else { b"old" };
+            assert_eq!(entry.value, expected);
+            i += 1;
+        }
+        assert_eq!(i, count);
+    }
+
+    #[test]
+    fn test_merge_sstables_range_tombstone() {
+        let dir = tempdir().unwrap();
+        let sst_dir = dir.path().join("sst");
+        std::fs::create_dir_all(&sst_dir).unwrap();
+
+        let path1 = sst_dir.join("old.sst");
+        let mut writer1 = SSTableWriter::new(&path1).unwrap();
+        for key in [b"a", b"b", b"c"] {
+            writer1
+                .add(SSTableEntry::value(key.to_vec(), b"old".to_vec()))
+                .unwrap();
+        }
+        writer1.finish().unwrap();
+
+        // Newer SSTable deletes [a, c) and rewrites "b" afterwards
+        let path2 = sst_dir.join("new.sst");
+        let mut writer2 = SSTableWriter::new(&path2).unwrap();
+        writer2.add_range_tombstone(b"a".to_vec(), b"c".to_vec());
+        writer2
+            .add(SSTableEntry::value(b"b".to_vec(), b"new".to_vec()))
+            .unwrap();
+        writer2.finish().unwrap();
+
+        let mut worker = CompactionWorker::new(dir.path(), CompactionConfig::default());
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        let outputs = worker
+            .merge_sstables(&mut manifest, &[path1, path2], 1, None)
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+
+        let mut reader = SSTableReader::open(&outputs[0].path).unwrap();
+        assert!(reader.get(b"a").unwrap().is_none());
+        assert_eq!(reader.get(b"b").unwrap().unwrap().value, b"new".to_vec());
+        assert_eq!(reader.get(b"c").unwrap().unwrap().value, b"old".to_vec());
+        assert!(reader.is_range_deleted(b"a"));
+    }
 }
@@ -0,0 +1,108 @@
+//! Key comparators for custom key ordering
+//!
+//! A comparator defines the order of keys in memtables, SSTables and
+//! compaction output. The default [`BytewiseComparator`] orders keys
+//! lexicographically by their raw bytes.
+//!
+//! The ordering is not recorded on disk, so a database must always be
+//! reopened with the same comparator it was written with.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+/// Defines a total order over keys.
+///
+/// # Examples
+///
+/// Order keys with a numeric suffix naturally, so `key2` sorts before `key10`:
+///
+/// ```rust
+/// use rustlite_storage::KeyComparator;
+/// use std::cmp::Ordering;
+///
+/// struct NaturalOrder;
+///
+/// impl KeyComparator for NaturalOrder {
+///     fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+///         fn split(k: &[u8]) -> (&[u8], &[u8]) {
+///             let digits = k.iter().rev().take_while(|b| b.is_ascii_digit()).count();
+///             k.split_at(k.len() - digits)
+///         }
+///         let ((a_prefix, a_num), (b_prefix, b_num)) = (split(a), split(b));
+///         a_prefix
+///             .cmp(b_prefix)
+///             .then(a_num.len().cmp(&b_num.len()))
+///             .then(a_num.cmp(b_num))
+///     }
+///
+///     fn name(&self) -> &str {
+///         "natural"
+///     }
+/// }
+/// ```
+pub trait KeyComparator: Send + Sync {
+    /// Compare two keys
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering;
+
+    /// Name of the comparator, used for debugging
+    fn name(&self) -> &str;
+
+    /// Returns true if the keys starting with any prefix form a contiguous
+    /// run that begins at the prefix itself, as in bytewise order
+    ///
+    /// Prefix scans then only visit the keys in that run; otherwise they
+    /// have to check every key.
+    fn supports_prefix_seek(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Debug for dyn KeyComparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("KeyComparator").field(&self.name()).finish()
+    }
+}
+
+/// Orders keys lexicographically by their raw bytes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BytewiseComparator;
+
+impl KeyComparator for BytewiseComparator {
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        a.cmp(b)
+    }
+
+    fn name(&self) -> &str {
+        "bytewise"
+    }
+
+    fn supports_prefix_seek(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the default bytewise comparator
+pub fn bytewise() -> Arc<dyn KeyComparator> {
+    Arc::new(BytewiseComparator)
+}
+
+/// Smallest key greater than every key starting with `prefix` in bytewise
+/// order, or `None` if there is none (the prefix is empty or all `0xFF`)
+pub(crate) fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|&b| b != 0xFF)?;
+    let mut successor = prefix[..=last].to_vec();
+    successor[last] += 1;
+    Some(successor)
+}
+
+/// Returns true if `key` lies in `[start, end)` under `comparator`
+pub(crate) fn in_range(
+    comparator: &dyn KeyComparator,
+    key: &[u8],
+    start: &[u8],
+    end: &[u8],
+) -> bool {
+    comparator.compare(key, start) != Ordering::Less
+        && comparator.compare(key, end) == Ordering::Less
+}
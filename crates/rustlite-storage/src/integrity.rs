@@ -0,0 +1,16 @@
+//! Checksums protecting SSTable data blocks and WAL records
+//!
+//! Each SSTable data block and each WAL record is followed by a checksum of
+//! its contents. The algorithm is recorded in the SSTable footer and in the
+//! WAL segment header, so existing files stay readable when the
+//! configuration changes.
+
+pub use rustlite_wal::ChecksumAlgorithm;
+
+/// Data integrity settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IntegrityConfig {
+    /// Checksum written after each SSTable data block and each WAL record.
+    /// Defaults to CRC32, which older versions can read.
+    pub checksum: ChecksumAlgorithm,
+}
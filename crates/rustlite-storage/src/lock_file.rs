@@ -0,0 +1,81 @@
+//! Exclusive lock on a database directory
+//!
+//! Two engines writing the same directory would interleave WAL appends and
+//! manifest edits and corrupt both. The engine holds an advisory lock on a
+//! `LOCK` file for as long as it is open; a second open fails instead of
+//! waiting. The operating system drops the lock when the file is closed,
+//! including when the process dies, so a crash never leaves it held.
+//!
+//! Locking uses `flock` and is only available on Unix; elsewhere opening a
+//! database fails rather than running unguarded.
+
+use rustlite_core::{Error, Result};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+/// Lock file name
+const LOCK_FILE: &str = "LOCK";
+
+/// Held lock on a database directory, released on drop
+#[derive(Debug)]
+pub(crate) struct LockFile {
+    _file: File,
+}
+
+impl LockFile {
+    /// Lock `dir`, failing if another handle already holds it
+    pub(crate) fn acquire(dir: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOCK_FILE))?;
+        try_lock(&file)?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Take an exclusive, non-blocking `flock` on the file
+#[cfg(unix)]
+fn try_lock(file: &File) -> Result<()> {
+    use rustix::fs::{flock, FlockOperation};
+
+    match flock(file, FlockOperation::NonBlockingLockExclusive) {
+        Ok(()) => Ok(()),
+        Err(rustix::io::Errno::WOULDBLOCK) => Err(Error::InvalidOperation(
+            "database already open by another process".to_string(),
+        )),
+        Err(errno) => Err(Error::Io(errno.into())),
+    }
+}
+
+/// Advisory locks are not available on this platform
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> Result<()> {
+    Err(Error::NotSupported(
+        "locking the database directory on this platform".to_string(),
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_file_is_exclusive() {
+        let dir = tempdir().unwrap();
+
+        let lock = LockFile::acquire(dir.path()).unwrap();
+        match LockFile::acquire(dir.path()) {
+            Err(Error::InvalidOperation(msg)) => {
+                assert_eq!(msg, "database already open by another process")
+            }
+            other => panic!("Expected InvalidOperation, got {:?}", other),
+        }
+
+        drop(lock);
+        LockFile::acquire(dir.path()).unwrap();
+    }
+}
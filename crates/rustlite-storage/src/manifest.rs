@@ -3,18 +3,39 @@
 //! The manifest tracks which SSTable files are currently active,
 //! their levels, and the current sequence number. It is used for
 //! recovery and compaction coordination.
+//!
+//! On disk the `MANIFEST` file holds a base snapshot followed by an
+//! append-only edit log of length-prefixed [`ManifestRecord`]s. Opening the
+//! manifest replays the log on top of the snapshot; once the log grows past a
+//! threshold it is folded back into a fresh snapshot, so each flush only pays
+//! for appending one small edit.
+//!
+//! In memory, level 0 SSTables are kept oldest first and every other level is
+//! kept sorted by minimum key. Compaction leaves the SSTables of levels 1 and
+//! up with disjoint key ranges, so a point lookup there can binary search for
+//! the single SSTable that may hold the key.
 
+use crate::comparator::{self, KeyComparator};
 use crate::sstable::SSTableMeta;
 use rustlite_core::{Error, Result};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Manifest file name
 const MANIFEST_FILE: &str = "MANIFEST";
-/// Manifest backup file name
+/// Manifest backup file name, left behind by older versions' rewrites
 const MANIFEST_BACKUP: &str = "MANIFEST.bak";
+/// Temporary file a new manifest snapshot is written to before it is renamed
+/// over `MANIFEST`
+const MANIFEST_TEMP: &str = "MANIFEST.tmp";
+/// Manifest snapshot format version
+///
+/// Version 2 appended `next_file_number` to the snapshot.
+const MANIFEST_VERSION: u32 = 2;
 
 /// Record type for manifest log entries
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,12 +54,14 @@ pub enum ManifestRecord {
     RemoveSSTable { path: String },
     /// Update the current sequence number
     UpdateSequence { sequence: u64 },
-    /// Compaction completed
+    /// Compaction completed: the inputs are replaced by the outputs
     CompactionDone {
         level: u32,
         inputs: Vec<String>,
-        outputs: Vec<String>,
+        outputs: Vec<ManifestSSTable>,
     },
+    /// A file number was allocated; the next one is `next_file_number`
+    AllocateFileNumber { next_file_number: u64 },
 }
 
 /// SSTable entry in the manifest
@@ -84,6 +107,55 @@ pub struct ManifestSnapshot {
     pub sstables: Vec<ManifestSSTable>,
     /// Version number for compatibility
     pub version: u32,
+    /// Number given to the next SSTable file
+    pub next_file_number: u64,
+}
+
+/// Leading fields shared by every snapshot version, used to read the version
+/// before decoding the rest
+#[derive(Serialize, Deserialize)]
+struct ManifestSnapshotV1 {
+    sequence: u64,
+    sstables: Vec<ManifestSSTable>,
+    version: u32,
+}
+
+impl ManifestSnapshot {
+    /// Apply an edit from the manifest log
+    fn apply(&mut self, record: &ManifestRecord) {
+        match record {
+            ManifestRecord::AddSSTable {
+                level,
+                path,
+                min_key,
+                max_key,
+                entry_count,
+                file_size,
+                sequence,
+            } => self.sstables.push(ManifestSSTable {
+                level: *level,
+                path: path.clone(),
+                min_key: min_key.clone(),
+                max_key: max_key.clone(),
+                entry_count: *entry_count,
+                file_size: *file_size,
+                sequence: *sequence,
+            }),
+            ManifestRecord::RemoveSSTable { path } => {
+                self.sstables.retain(|s| &s.path != path);
+            }
+            ManifestRecord::UpdateSequence { sequence } => self.sequence = *sequence,
+            ManifestRecord::CompactionDone {
+                inputs, outputs, ..
+            } => {
+                self.sstables.retain(|s| !inputs.contains(&s.path));
+                self.sstables.extend(outputs.iter().cloned());
+            }
+            ManifestRecord::AllocateFileNumber { next_file_number } => {
+                self.next_file_number = *next_file_number;
+            }
+        }
+    }
 }
 
 impl Default for ManifestSnapshot {
@@ -91,7 +163,8 @@ impl Default for ManifestSnapshot {
         Self {
             sequence: 0,
             sstables: Vec::new(),
-            version: 1,
+            version: MANIFEST_VERSION,
+            next_file_number: 1,
         }
     }
 }
@@ -108,6 +181,12 @@ pub struct Manifest {
     log_entries: usize,
     /// Threshold for rewriting manifest
     log_threshold: usize,
+    /// Key ordering of the SSTables, used to sort levels by minimum key
+    comparator: Arc<dyn KeyComparator>,
+    /// Per level, whether its SSTables' key ranges are known not to overlap
+    disjoint_levels: Vec<bool>,
+    /// Whether the manifest held no state when opened
+    is_new: bool,
 }
 
 impl Manifest {
@@ -118,31 +197,154 @@ impl Manifest {
 
         let manifest_path = dir.join(MANIFEST_FILE);
 
-        let snapshot = if manifest_path.exists() {
-            Self::load_snapshot(&manifest_path)?
+        // A leftover temp file is a rewrite that never reached its rename;
+        // the current MANIFEST is still complete
+        let temp_path = dir.join(MANIFEST_TEMP);
+        if temp_path.exists() {
+            fs::remove_file(&temp_path)?;
+        }
+
+        // Older versions could crash between copying the manifest to the
+        // backup and writing the new one
+        let backup_path = dir.join(MANIFEST_BACKUP);
+        if backup_path.exists() && !manifest_path.exists() {
+            fs::rename(&backup_path, &manifest_path)?;
+        }
+
+        let (snapshot, log_entries, clean) = if manifest_path.exists() {
+            match Self::load(&manifest_path) {
+                // The backup is an older but intact copy; the rewrite below
+                // replaces the corrupt manifest with it
+                Err(Error::Corruption(msg)) if backup_path.exists() => {
+                    tracing::warn!(error = %msg, "MANIFEST is corrupt, recovering from MANIFEST.bak");
+                    let (snapshot, log_entries, _) = Self::load(&backup_path)?;
+                    (snapshot, log_entries, false)
+                }
+                loaded => loaded?,
+            }
         } else {
-            ManifestSnapshot::default()
+            (ManifestSnapshot::default(), 0, false)
         };
+        let is_new = snapshot.sstables.is_empty() && snapshot.next_file_number <= 1;
 
-        // Open log file for appending
-        let log_writer = Some(BufWriter::new(
-            OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&manifest_path)?,
-        ));
-
-        Ok(Self {
+        let mut manifest = Self {
             dir,
             snapshot,
-            log_writer,
-            log_entries: 0,
-            log_threshold: 100, // Rewrite after 100 incremental entries
-        })
+            log_writer: None,
+            log_entries,
+            log_threshold: 100, // Compact the log after 100 edits
+            comparator: comparator::bytewise(),
+            disjoint_levels: Vec::new(),
+            is_new,
+        };
+        manifest.sort_sstables();
+
+        if clean {
+            // Open log file for appending
+            manifest.log_writer = Some(BufWriter::new(
+                OpenOptions::new().append(true).open(&manifest_path)?,
+            ));
+        } else {
+            // New manifest, or one whose log ends in a torn edit that later
+            // appends would be stranded behind: start from a fresh snapshot
+            manifest.rewrite()?;
+        }
+        if backup_path.exists() {
+            fs::remove_file(&backup_path)?;
+        }
+
+        Ok(manifest)
+    }
+
+    /// The file number the next flush will be given, without allocating it
+    pub fn peek_next_file_number(&self) -> u64 {
+        self.snapshot.next_file_number
     }
 
-    /// Load a manifest snapshot from disk
-    fn load_snapshot(path: &Path) -> Result<ManifestSnapshot> {
+    /// Returns true if the manifest held no state when opened
+    ///
+    /// Such a manifest does not know which SSTables exist, so files on disk
+    /// must not be judged by it.
+    pub fn is_new(&self) -> bool {
+        self.is_new
+    }
+
+    /// Point every SSTable entry at its file name under `sst_dir`
+    ///
+    /// Entries store full paths, which go stale when the database directory
+    /// is moved. Persists the updated paths if any changed.
+    pub fn relocate(&mut self, sst_dir: &Path) -> Result<()> {
+        let mut moved = false;
+        for sst in &mut self.snapshot.sstables {
+            let Some(name) = Path::new(&sst.path).file_name() else {
+                continue;
+            };
+            let path = sst_dir.join(name).to_string_lossy().to_string();
+            if path != sst.path {
+                sst.path = path;
+                moved = true;
+            }
+        }
+        if moved {
+            self.rewrite()?;
+        }
+        Ok(())
+    }
+
+    /// Order keys with `comparator` instead of bytewise
+    ///
+    /// Must match the comparator the SSTables were written with.
+    pub fn with_comparator(mut self, comparator: Arc<dyn KeyComparator>) -> Self {
+        self.comparator = comparator;
+        self.sort_sstables();
+        self
+    }
+
+    /// Restore the in-memory order of the SSTables after an edit
+    ///
+    /// Level 0 is sorted oldest first, the other levels by minimum key, and
+    /// each level is checked for overlapping key ranges.
+    fn sort_sstables(&mut self) {
+        let cmp = Arc::clone(&self.comparator);
+        self.snapshot.sstables.sort_by(|a, b| {
+            a.level.cmp(&b.level).then_with(|| {
+                if a.level == 0 {
+                    a.sequence.cmp(&b.sequence)
+                } else {
+                    cmp.compare(&a.min_key, &b.min_key)
+                        .then(a.sequence.cmp(&b.sequence))
+                }
+            })
+        });
+
+        let max_level = self.snapshot.sstables.last().map_or(0, |s| s.level);
+        self.disjoint_levels = (0..=max_level)
+            .map(|level| {
+                level > 0
+                    && self
+                        .level_slice(level)
+                        .windows(2)
+                        .all(|w| cmp.compare(&w[0].max_key, &w[1].min_key) == Ordering::Less)
+            })
+            .collect();
+    }
+
+    /// The SSTables at `level`, in their sorted order
+    fn level_slice(&self, level: u32) -> &[ManifestSSTable] {
+        let sstables = &self.snapshot.sstables;
+        let start = sstables.partition_point(|s| s.level < level);
+        let end = sstables.partition_point(|s| s.level <= level);
+        &sstables[start..end]
+    }
+
+    /// Load the base snapshot and replay the edit log after it
+    ///
+    /// Returns the resulting state, the number of edits replayed, and whether
+    /// the file was fully consumed. Replay stops at the first incomplete or
+    /// undecodable edit, which is what a crash mid-append leaves behind. An
+    /// undecodable base snapshot fails with `Error::Corruption`: the file is
+    /// the only record of which SSTables are live.
+    fn load(path: &Path) -> Result<(ManifestSnapshot, usize, bool)> {
         let file = File::open(path)?;
         let mut reader = BufReader::new(file);
 
@@ -150,78 +352,149 @@ impl Manifest {
         reader.read_to_end(&mut contents)?;
 
         if contents.is_empty() {
-            return Ok(ManifestSnapshot::default());
+            return Ok((ManifestSnapshot::default(), 0, false));
         }
 
-        // Try to deserialize as snapshot
-        match bincode::deserialize::<ManifestSnapshot>(&contents) {
-            Ok(snapshot) => Ok(snapshot),
-            Err(_) => {
-                // Fall back to empty manifest
-                Ok(ManifestSnapshot::default())
+        // Deserialize the base snapshot, starting with the fields common to
+        // every version
+        let header = match bincode::deserialize::<ManifestSnapshotV1>(&contents) {
+            Ok(header) if header.version > MANIFEST_VERSION => {
+                return Err(Error::UnsupportedVersion {
+                    component: "Manifest".to_string(),
+                    found: header.version,
+                    max_supported: MANIFEST_VERSION,
+                })
+            }
+            Ok(header) => header,
+            Err(e) => {
+                return Err(Error::Corruption(format!(
+                    "Manifest snapshot cannot be decoded: {}",
+                    e
+                )))
             }
+        };
+        let mut offset = bincode::serialized_size(&header)
+            .map_err(|e| Error::Serialization(e.to_string()))? as usize;
+
+        // Version 1 snapshots have no file number; reporting the load as
+        // unclean rewrites them in the current format
+        let upgraded = header.version < 2;
+        let next_file_number = if upgraded {
+            1
+        } else {
+            match bincode::deserialize::<u64>(&contents[offset..]) {
+                Ok(number) => {
+                    offset += 8;
+                    number
+                }
+                Err(e) => {
+                    return Err(Error::Corruption(format!(
+                        "Manifest file number cannot be decoded: {}",
+                        e
+                    )))
+                }
+            }
+        };
+        let mut snapshot = ManifestSnapshot {
+            sequence: header.sequence,
+            sstables: header.sstables,
+            version: MANIFEST_VERSION,
+            next_file_number,
+        };
+
+        // Replay the edit log
+        let mut replayed = 0;
+        while offset + 4 <= contents.len() {
+            let len_bytes = [
+                contents[offset],
+                contents[offset + 1],
+                contents[offset + 2],
+                contents[offset + 3],
+            ];
+            let start = offset + 4;
+            let end = start + u32::from_le_bytes(len_bytes) as usize;
+            if end > contents.len() {
+                break;
+            }
+            match bincode::deserialize::<ManifestRecord>(&contents[start..end]) {
+                Ok(record) => snapshot.apply(&record),
+                Err(_) => break,
+            }
+            offset = end;
+            replayed += 1;
         }
+
+        Ok((snapshot, replayed, offset == contents.len() && !upgraded))
     }
 
-    /// Write a record to the manifest log
-    fn write_record(&mut self, record: &ManifestRecord) -> Result<()> {
+    /// Apply an edit and append it to the manifest log
+    fn write_record(&mut self, record: ManifestRecord) -> Result<()> {
+        self.snapshot.apply(&record);
+        self.sort_sstables();
+
         if let Some(ref mut writer) = self.log_writer {
             let encoded =
-                bincode::serialize(record).map_err(|e| Error::Serialization(e.to_string()))?;
+                bincode::serialize(&record).map_err(|e| Error::Serialization(e.to_string()))?;
             let len = encoded.len() as u32;
 
-            writer.write_all(&len.to_le_bytes())?;
-            writer.write_all(&encoded)?;
+            let mut frame = Vec::with_capacity(4 + encoded.len());
+            frame.extend_from_slice(&len.to_le_bytes());
+            frame.extend_from_slice(&encoded);
+            writer.write_all(&frame)?;
             writer.flush()?;
+            writer.get_ref().sync_data()?;
 
             self.log_entries += 1;
 
-            // Rewrite manifest if threshold reached
+            // Fold the log into a new snapshot if threshold reached
             if self.log_entries >= self.log_threshold {
-                self.rewrite()?;
+                self.compact_log()?;
             }
         }
 
         Ok(())
     }
 
+    /// Fold the edit log into a fresh base snapshot
+    pub fn compact_log(&mut self) -> Result<()> {
+        self.rewrite()
+    }
+
+    /// Number of edits in the log since the last snapshot
+    pub fn log_len(&self) -> usize {
+        self.log_entries
+    }
+
     /// Rewrite the manifest as a fresh snapshot
+    ///
+    /// The snapshot is written to a temporary file, synced, and atomically
+    /// renamed over `MANIFEST`, so a crash at any point leaves either the
+    /// old or the new manifest intact.
     pub fn rewrite(&mut self) -> Result<()> {
         // Close current log writer
         self.log_writer = None;
 
         let manifest_path = self.dir.join(MANIFEST_FILE);
-        let backup_path = self.dir.join(MANIFEST_BACKUP);
+        let temp_path = self.dir.join(MANIFEST_TEMP);
 
-        // Backup current manifest
-        if manifest_path.exists() {
-            fs::copy(&manifest_path, &backup_path)?;
-        }
-
-        // Write new snapshot
+        // Write new snapshot to the temp file
         let encoded =
             bincode::serialize(&self.snapshot).map_err(|e| Error::Serialization(e.to_string()))?;
+        {
+            let mut file = File::create(&temp_path)?;
+            file.write_all(&encoded)?;
+            file.sync_all()?;
+        }
 
-        fs::write(&manifest_path, &encoded)?;
-
-        // Remove backup
-        let _ = fs::remove_file(&backup_path);
+        // Atomically replace the manifest and persist the rename
+        fs::rename(&temp_path, &manifest_path)?;
+        sync_dir(&self.dir)?;
 
         // Reopen log writer
         self.log_writer = Some(BufWriter::new(
-            OpenOptions::new()
-                .create(true)
-                .write(true)
-                .truncate(true)
-                .open(&manifest_path)?,
+            OpenOptions::new().append(true).open(&manifest_path)?,
         ));
 
-        // Write the snapshot to the new file
-        if let Some(ref mut writer) = self.log_writer {
-            writer.write_all(&encoded)?;
-            writer.flush()?;
-        }
-
         self.log_entries = 0;
 
         Ok(())
@@ -229,7 +502,7 @@ impl Manifest {
 
     /// Add an SSTable to the manifest
     pub fn add_sstable(&mut self, meta: &SSTableMeta) -> Result<()> {
-        let sstable = ManifestSSTable {
+        self.write_record(ManifestRecord::AddSSTable {
             level: meta.level,
             path: meta.path.to_string_lossy().to_string(),
             min_key: meta.min_key.clone(),
@@ -237,41 +510,41 @@ impl Manifest {
             entry_count: meta.entry_count,
             file_size: meta.file_size,
             sequence: meta.sequence,
-        };
-
-        self.snapshot.sstables.push(sstable);
-
-        self.write_record(&ManifestRecord::AddSSTable {
-            level: meta.level,
-            path: meta.path.to_string_lossy().to_string(),
-            min_key: meta.min_key.clone(),
-            max_key: meta.max_key.clone(),
-            entry_count: meta.entry_count,
-            file_size: meta.file_size,
-            sequence: meta.sequence,
-        })?;
-
-        Ok(())
+        })
     }
 
     /// Remove an SSTable from the manifest
     pub fn remove_sstable(&mut self, path: &Path) -> Result<()> {
-        let path_str = path.to_string_lossy().to_string();
-
-        self.snapshot.sstables.retain(|s| s.path != path_str);
-
-        self.write_record(&ManifestRecord::RemoveSSTable { path: path_str })?;
-
-        Ok(())
+        let path = path.to_string_lossy().to_string();
+        self.write_record(ManifestRecord::RemoveSSTable { path })
     }
 
     /// Update the sequence number
     pub fn update_sequence(&mut self, sequence: u64) -> Result<()> {
-        self.snapshot.sequence = sequence;
+        self.write_record(ManifestRecord::UpdateSequence { sequence })
+    }
 
-        self.write_record(&ManifestRecord::UpdateSequence { sequence })?;
+    /// Remove all SSTables from the manifest and persist the empty state
+    ///
+    /// The sequence number is preserved. Returns the entries that were removed
+    /// so the caller can delete the underlying files.
+    pub fn clear(&mut self) -> Result<Vec<ManifestSSTable>> {
+        let removed = std::mem::take(&mut self.snapshot.sstables);
+        self.disjoint_levels.clear();
+        self.rewrite()?;
+        Ok(removed)
+    }
 
-        Ok(())
+    /// Allocate a number for a new SSTable file
+    ///
+    /// Numbers increase monotonically across restarts, so file names built
+    /// from them never collide regardless of the system clock.
+    pub fn next_file_number(&mut self) -> Result<u64> {
+        let number = self.snapshot.next_file_number;
+        self.write_record(ManifestRecord::AllocateFileNumber {
+            next_file_number: number + 1,
+        })?;
+        Ok(number)
     }
 
     /// Get the current sequence number
@@ -280,12 +553,50 @@ impl Manifest {
     }
 
     /// Get all SSTables at a given level
+    ///
+    /// Level 0 is listed oldest first, other levels by minimum key.
     pub fn sstables_at_level(&self, level: u32) -> Vec<&ManifestSSTable> {
-        self.snapshot
-            .sstables
-            .iter()
-            .filter(|s| s.level == level)
-            .collect()
+        self.level_slice(level).iter().collect()
+    }
+
+    /// SSTables at `level` whose key range covers `key`, newest first
+    ///
+    /// At a level whose SSTables do not overlap this is a binary search
+    /// yielding at most one SSTable. Level 0, and any level where range
+    /// tombstones widened key ranges into each other, are scanned in full.
+    pub fn sstables_for_key(&self, level: u32, key: &[u8]) -> Vec<&ManifestSSTable> {
+        let cmp = self.comparator.as_ref();
+        let covers = |s: &ManifestSSTable| {
+            cmp.compare(key, &s.min_key) != Ordering::Less
+                && cmp.compare(key, &s.max_key) != Ordering::Greater
+        };
+        let sstables = self.level_slice(level);
+
+        if self.is_disjoint(level) {
+            // The last SSTable starting at or before `key` is the only one
+            // that can cover it
+            let idx =
+                sstables.partition_point(|s| cmp.compare(&s.min_key, key) != Ordering::Greater);
+            return idx
+                .checked_sub(1)
+                .map(|idx| &sstables[idx])
+                .filter(|s| covers(s))
+                .into_iter()
+                .collect();
+        }
+
+        let mut candidates: Vec<&ManifestSSTable> = sstables.iter().filter(|s| covers(s)).collect();
+        candidates.sort_by_key(|s| std::cmp::Reverse(s.sequence));
+        candidates
+    }
+
+    /// Whether the SSTables at `level` are known to have disjoint key
+    /// ranges (never true for level 0)
+    pub fn is_disjoint(&self, level: u32) -> bool {
+        self.disjoint_levels
+            .get(level as usize)
+            .copied()
+            .unwrap_or(false)
     }
 
     /// Get all SSTables
@@ -323,29 +634,7 @@ impl Manifest {
         inputs: Vec<PathBuf>,
         outputs: Vec<SSTableMeta>,
     ) -> Result<()> {
-        // Remove input files from manifest
-        for input in &inputs {
-            self.snapshot
-                .sstables
-                .retain(|s| s.path != input.to_string_lossy());
-        }
-
-        // Add output files to manifest
-        for output in &outputs {
-            let sstable = ManifestSSTable {
-                level: output.level,
-                path: output.path.to_string_lossy().to_string(),
-                min_key: output.min_key.clone(),
-                max_key: output.max_key.clone(),
-                entry_count: output.entry_count,
-                file_size: output.file_size,
-                sequence: output.sequence,
-            };
-            self.snapshot.sstables.push(sstable);
-        }
-
-        // Write record
-        self.write_record(&ManifestRecord::CompactionDone {
+        self.write_record(ManifestRecord::CompactionDone {
             level,
             inputs: inputs
                 .iter()
@@ -353,19 +642,104 @@ impl Manifest {
                 .collect(),
             outputs: outputs
                 .iter()
-                .map(|p| p.path.to_string_lossy().to_string())
+                .map(|output| ManifestSSTable {
+                    level: output.level,
+                    path: output.path.to_string_lossy().to_string(),
+                    min_key: output.min_key.clone(),
+                    max_key: output.max_key.clone(),
+                    entry_count: output.entry_count,
+                    file_size: output.file_size,
+                    sequence: output.sequence,
+                })
                 .collect(),
-        })?;
-
-        Ok(())
+        })
     }
 }
 
+/// Sync a directory so that renames within it are durable
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Directories cannot be opened as files on this platform; renames are
+/// durable once the rename call returns
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_manifest_sstables_for_key() {
+        let dir = tempdir().unwrap();
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        let sst = |level: u32, path: &str, min: &[u8], max: &[u8], sequence: u64| SSTableMeta {
+            path: PathBuf::from(path),
+            min_key: min.to_vec(),
+            max_key: max.to_vec(),
+            entry_count: 1,
+            file_size: 100,
+            level,
+            sequence,
+        };
+
+        // Added out of key order
+        manifest
+            .add_sstable(&sst(1, "g.sst", b"g", b"i", 0))
+            .unwrap();
+        manifest
+            .add_sstable(&sst(1, "a.sst", b"a", b"c", 0))
+            .unwrap();
+        manifest
+            .add_sstable(&sst(1, "d.sst", b"d", b"f", 0))
+            .unwrap();
+        manifest
+            .add_sstable(&sst(0, "new.sst", b"a", b"z", 2))
+            .unwrap();
+        manifest
+            .add_sstable(&sst(0, "old.sst", b"a", b"z", 1))
+            .unwrap();
+
+        let paths = |ssts: Vec<&ManifestSSTable>| -> Vec<String> {
+            ssts.iter().map(|s| s.path.clone()).collect()
+        };
+        assert_eq!(
+            paths(manifest.sstables_at_level(1)),
+            vec!["a.sst", "d.sst", "g.sst"]
+        );
+        assert_eq!(
+            paths(manifest.sstables_at_level(0)),
+            vec!["old.sst", "new.sst"]
+        );
+
+        assert!(manifest.is_disjoint(1));
+        assert!(!manifest.is_disjoint(0));
+        assert_eq!(paths(manifest.sstables_for_key(1, b"e")), vec!["d.sst"]);
+        assert_eq!(paths(manifest.sstables_for_key(1, b"i")), vec!["g.sst"]);
+        assert!(manifest.sstables_for_key(1, b"cc").is_empty());
+        assert!(manifest.sstables_for_key(1, b"z").is_empty());
+        assert_eq!(
+            paths(manifest.sstables_for_key(0, b"e")),
+            vec!["new.sst", "old.sst"]
+        );
+
+        // Overlapping ranges fall back to checking every SSTable
+        manifest
+            .add_sstable(&sst(1, "e.sst", b"e", b"h", 3))
+            .unwrap();
+        assert!(!manifest.is_disjoint(1));
+        assert_eq!(
+            paths(manifest.sstables_for_key(1, b"e")),
+            vec!["e.sst", "d.sst"]
+        );
+    }
+
     #[test]
     fn test_manifest_create() {
         let dir = tempdir().unwrap();
@@ -419,6 +793,246 @@ mod tests {
         assert!(manifest.all_sstables().is_empty());
     }
 
+    #[test]
+    fn test_manifest_clear() {
+        let dir = tempdir().unwrap();
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+
+        let meta = SSTableMeta {
+            path: PathBuf::from("test.sst"),
+            min_key: b"a".to_vec(),
+            max_key: b"z".to_vec(),
+            entry_count: 100,
+            file_size: 1024,
+            level: 0,
+            sequence: 1,
+        };
+        manifest.add_sstable(&meta).unwrap();
+        manifest.update_sequence(42).unwrap();
+
+        let removed = manifest.clear().unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(manifest.all_sstables().is_empty());
+        assert_eq!(manifest.sequence(), 42);
+
+        let reopened = Manifest::open(dir.path()).unwrap();
+        assert!(reopened.all_sstables().is_empty());
+        assert_eq!(reopened.sequence(), 42);
+    }
+
+    #[test]
+    fn test_manifest_interrupted_rewrite() {
+        let dir = tempdir().unwrap();
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+
+        let meta = SSTableMeta {
+            path: PathBuf::from("test.sst"),
+            min_key: b"a".to_vec(),
+            max_key: b"z".to_vec(),
+            entry_count: 100,
+            file_size: 1024,
+            level: 0,
+            sequence: 1,
+        };
+        manifest.add_sstable(&meta).unwrap();
+        manifest.update_sequence(7).unwrap();
+        manifest.rewrite().unwrap();
+        assert!(!dir.path().join(MANIFEST_TEMP).exists());
+        drop(manifest);
+
+        // Crash partway through the next rewrite: a truncated snapshot in
+        // the temp file that never got renamed
+        let next = ManifestSnapshot {
+            sequence: 99,
+            ..Default::default()
+        };
+        let encoded = bincode::serialize(&next).unwrap();
+        fs::write(
+            dir.path().join(MANIFEST_TEMP),
+            &encoded[..encoded.len() / 2],
+        )
+        .unwrap();
+
+        let reopened = Manifest::open(dir.path()).unwrap();
+        assert_eq!(reopened.sequence(), 7);
+        assert_eq!(reopened.all_sstables().len(), 1);
+        assert_eq!(reopened.all_sstables()[0].path, "test.sst");
+        assert!(!dir.path().join(MANIFEST_TEMP).exists());
+    }
+
+    #[test]
+    fn test_manifest_corrupt_snapshot_is_an_error() {
+        let dir = tempdir().unwrap();
+        let manifest_path = dir.path().join(MANIFEST_FILE);
+        {
+            let mut manifest = Manifest::open(dir.path()).unwrap();
+            manifest.update_sequence(7).unwrap();
+            manifest.rewrite().unwrap();
+        }
+        let intact = fs::read(&manifest_path).unwrap();
+
+        // The corrupt file is reported, not replaced by an empty manifest
+        fs::write(&manifest_path, b"\xff\xff\xff").unwrap();
+        assert!(matches!(
+            Manifest::open(dir.path()),
+            Err(Error::Corruption(_))
+        ));
+        assert_eq!(fs::read(&manifest_path).unwrap(), b"\xff\xff\xff");
+
+        // An intact backup is used instead
+        fs::write(dir.path().join(MANIFEST_BACKUP), &intact).unwrap();
+        let manifest = Manifest::open(dir.path()).unwrap();
+        assert_eq!(manifest.sequence(), 7);
+        assert!(!dir.path().join(MANIFEST_BACKUP).exists());
+        drop(manifest);
+        assert_eq!(Manifest::open(dir.path()).unwrap().sequence(), 7);
+    }
+
+    #[test]
+    fn test_manifest_edit_log_replay_and_compaction() {
+        let dir = tempdir().unwrap();
+        let meta = |i: u32| SSTableMeta {
+            path: PathBuf::from(format!("sst_{}.sst", i)),
+            min_key: b"a".to_vec(),
+            max_key: b"z".to_vec(),
+            entry_count: 10,
+            file_size: 512,
+            level: 0,
+            sequence: u64::from(i),
+        };
+
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        for i in 0..300 {
+            manifest.add_sstable(&meta(i)).unwrap();
+            manifest.update_sequence(u64::from(i)).unwrap();
+            if i >= 5 {
+                manifest
+                    .remove_sstable(Path::new(&format!("sst_{}.sst", i - 5)))
+                    .unwrap();
+            }
+        }
+        assert!(manifest.log_len() < 100);
+        drop(manifest);
+
+        // 895 edits were made, but the file only holds the compacted
+        // snapshot plus a bounded tail of the log
+        let record_len = bincode::serialized_size(&ManifestRecord::AddSSTable {
+            level: 0,
+            path: "sst_000.sst".to_string(),
+            min_key: b"a".to_vec(),
+            max_key: b"z".to_vec(),
+            entry_count: 10,
+            file_size: 512,
+            sequence: 0,
+        })
+        .unwrap();
+        let file_len = fs::metadata(dir.path().join(MANIFEST_FILE)).unwrap().len();
+        assert!(file_len < 110 * (record_len + 4));
+
+        let reopened = Manifest::open(dir.path()).unwrap();
+        assert_eq!(reopened.sequence(), 299);
+        let mut paths: Vec<_> = reopened
+            .all_sstables()
+            .iter()
+            .map(|s| s.path.clone())
+            .collect();
+        paths.sort();
+        let expected: Vec<_> = (295..300).map(|i| format!("sst_{}.sst", i)).collect();
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn test_manifest_replays_log_without_rewrite() {
+        let dir = tempdir().unwrap();
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        manifest
+            .add_sstable(&SSTableMeta {
+                path: PathBuf::from("a.sst"),
+                min_key: vec![],
+                max_key: vec![],
+                entry_count: 0,
+                file_size: 0,
+                level: 0,
+                sequence: 3,
+            })
+            .unwrap();
+        manifest.update_sequence(3).unwrap();
+        drop(manifest);
+
+        // Append a torn edit, as if the process crashed mid-write
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(MANIFEST_FILE))
+            .unwrap();
+        file.write_all(&[200, 0, 0, 0, 1]).unwrap();
+        drop(file);
+
+        let mut reopened = Manifest::open(dir.path()).unwrap();
+        assert_eq!(reopened.sequence(), 3);
+        assert_eq!(reopened.all_sstables().len(), 1);
+
+        // Edits after the torn one are not lost on the next open
+        reopened.update_sequence(4).unwrap();
+        drop(reopened);
+        assert_eq!(Manifest::open(dir.path()).unwrap().sequence(), 4);
+    }
+
+    #[test]
+    fn test_manifest_file_numbers() {
+        let dir = tempdir().unwrap();
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        assert_eq!(manifest.next_file_number().unwrap(), 1);
+        assert_eq!(manifest.next_file_number().unwrap(), 2);
+        drop(manifest);
+
+        // Numbers survive both log replay and a snapshot rewrite
+        let mut reopened = Manifest::open(dir.path()).unwrap();
+        assert_eq!(reopened.next_file_number().unwrap(), 3);
+        reopened.rewrite().unwrap();
+        drop(reopened);
+        assert_eq!(
+            Manifest::open(dir.path())
+                .unwrap()
+                .next_file_number()
+                .unwrap(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_manifest_upgrades_version_1() {
+        let dir = tempdir().unwrap();
+        let v1 = ManifestSnapshotV1 {
+            sequence: 7,
+            sstables: vec![ManifestSSTable {
+                level: 0,
+                path: "L0_0_0.sst".to_string(),
+                min_key: b"a".to_vec(),
+                max_key: b"b".to_vec(),
+                entry_count: 2,
+                file_size: 64,
+                sequence: 7,
+            }],
+            version: 1,
+        };
+        let mut contents = bincode::serialize(&v1).unwrap();
+        let edit = bincode::serialize(&ManifestRecord::UpdateSequence { sequence: 9 }).unwrap();
+        contents.extend_from_slice(&(edit.len() as u32).to_le_bytes());
+        contents.extend_from_slice(&edit);
+        fs::write(dir.path().join(MANIFEST_FILE), contents).unwrap();
+
+        let mut manifest = Manifest::open(dir.path()).unwrap();
+        assert_eq!(manifest.sequence(), 9);
+        assert_eq!(manifest.all_sstables().len(), 1);
+        assert_eq!(manifest.next_file_number().unwrap(), 1);
+        drop(manifest);
+
+        let reopened = Manifest::open(dir.path()).unwrap();
+        assert_eq!(reopened.sequence(), 9);
+        assert_eq!(reopened.all_sstables().len(), 1);
+        assert_eq!(reopened.snapshot.next_file_number, 2);
+    }
+
     #[test]
     fn test_manifest_sequence() {
         let dir = tempdir().unwrap();
@@ -463,4 +1077,30 @@ mod tests {
         assert_eq!(counts[0], 3);
         assert_eq!(counts[1], 2);
     }
+
+    #[test]
+    fn test_manifest_unsupported_version() {
+        let dir = tempdir().unwrap();
+
+        let snapshot = ManifestSnapshot {
+            version: MANIFEST_VERSION + 1,
+            ..Default::default()
+        };
+        let encoded = bincode::serialize(&snapshot).unwrap();
+        fs::write(dir.path().join(MANIFEST_FILE), encoded).unwrap();
+
+        match Manifest::open(dir.path()) {
+            Err(Error::UnsupportedVersion {
+                component,
+                found,
+                max_supported,
+            }) => {
+                assert_eq!(component, "Manifest");
+                assert_eq!(found, MANIFEST_VERSION + 1);
+                assert_eq!(max_supported, MANIFEST_VERSION);
+            }
+            Err(e) => panic!("Expected UnsupportedVersion, got {}", e),
+            Ok(_) => panic!("Expected UnsupportedVersion, got Ok"),
+        }
+    }
 }
@@ -4,8 +4,12 @@
 //! before they are flushed to disk as SSTables. It uses a BTreeMap for
 //! sorted key order, which enables efficient range scans and ordered iteration.
 
+use crate::comparator::{self, KeyComparator};
+use std::cmp;
 use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 /// Entry value in the memtable - can be a value or a tombstone (deletion marker)
 #[derive(Debug, Clone, PartialEq)]
@@ -14,6 +18,14 @@ pub enum MemtableEntry {
     Value(Vec<u8>),
     /// A tombstone marking deletion
     Tombstone,
+    /// Merge operands (oldest first) not yet folded into a value
+    Merge {
+        /// Value or tombstone the operands apply on top of, or `None` if the
+        /// base lives in older memtables or SSTables
+        base: Option<Box<MemtableEntry>>,
+        /// Pending merge operands, oldest first
+        operands: Vec<Vec<u8>>,
+    },
 }
 
 impl MemtableEntry {
@@ -22,10 +34,41 @@ impl MemtableEntry {
         match self {
             MemtableEntry::Value(v) => v.len() + 1, // +1 for type tag
             MemtableEntry::Tombstone => 1,
+            MemtableEntry::Merge { base, operands } => {
+                1 + base.as_ref().map_or(0, |b| b.size())
+                    + operands.iter().map(|o| o.len()).sum::<usize>()
+            }
         }
     }
 }
 
+/// Memtable key ordered by the memtable's comparator
+#[derive(Debug, Clone)]
+struct OrderedKey {
+    key: Vec<u8>,
+    comparator: Arc<dyn KeyComparator>,
+}
+
+impl PartialEq for OrderedKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for OrderedKey {}
+
+impl PartialOrd for OrderedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedKey {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.comparator.compare(&self.key, &other.key)
+    }
+}
+
 /// Memtable - an in-memory sorted write buffer
 ///
 /// Provides O(log n) insert, lookup, and delete operations.
@@ -34,11 +77,15 @@ impl MemtableEntry {
 #[derive(Debug)]
 pub struct Memtable {
     /// The underlying sorted map
-    data: BTreeMap<Vec<u8>, MemtableEntry>,
+    data: BTreeMap<OrderedKey, MemtableEntry>,
+    /// Key ordering
+    comparator: Arc<dyn KeyComparator>,
     /// Approximate size in bytes (for flush threshold checking)
     size_bytes: AtomicU64,
     /// Sequence number for MVCC (future use)
     sequence: AtomicU64,
+    /// Range tombstones as `[start, end)` pairs, hiding older data
+    range_tombstones: Vec<(Vec<u8>, Vec<u8>)>,
 }
 
 impl Memtable {
@@ -46,8 +93,10 @@ impl Memtable {
     pub fn new() -> Self {
         Self {
             data: BTreeMap::new(),
+            comparator: comparator::bytewise(),
             size_bytes: AtomicU64::new(0),
             sequence: AtomicU64::new(0),
+            range_tombstones: Vec::new(),
         }
     }
 
@@ -55,8 +104,43 @@ impl Memtable {
     pub fn with_sequence(sequence: u64) -> Self {
         Self {
             data: BTreeMap::new(),
+            comparator: comparator::bytewise(),
             size_bytes: AtomicU64::new(0),
             sequence: AtomicU64::new(sequence),
+            range_tombstones: Vec::new(),
+        }
+    }
+
+    /// Orders keys with `comparator` instead of bytewise
+    ///
+    /// Existing entries are re-sorted under the new ordering.
+    pub fn with_comparator(mut self, comparator: Arc<dyn KeyComparator>) -> Self {
+        self.data = std::mem::take(&mut self.data)
+            .into_iter()
+            .map(|(k, v)| {
+                (
+                    OrderedKey {
+                        key: k.key,
+                        comparator: Arc::clone(&comparator),
+                    },
+                    v,
+                )
+            })
+            .collect();
+        self.comparator = comparator;
+        self
+    }
+
+    /// Returns the comparator that orders this memtable's keys
+    pub fn comparator(&self) -> &Arc<dyn KeyComparator> {
+        &self.comparator
+    }
+
+    /// Wraps a key for lookup in the sorted map
+    fn ordered(&self, key: &[u8]) -> OrderedKey {
+        OrderedKey {
+            key: key.to_vec(),
+            comparator: Arc::clone(&self.comparator),
         }
     }
 
@@ -66,29 +150,74 @@ impl Memtable {
         let value_size = value.len() as u64 + 1; // +1 for entry type
 
         // Remove old entry size if exists
+        let key = self.ordered(&key);
         if let Some(old) = self.data.get(&key) {
             let old_size = old.size() as u64;
             self.size_bytes
                 .fetch_sub(key_size + old_size, Ordering::Relaxed);
         }
 
-        self.data.insert(key.clone(), MemtableEntry::Value(value));
+        self.data.insert(key, MemtableEntry::Value(value));
         self.size_bytes
             .fetch_add(key_size + value_size, Ordering::Relaxed);
         self.sequence.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Appends a merge operand for a key
+    ///
+    /// The operand is stacked on top of any existing entry for the key and is
+    /// folded into a value later by a merge operator.
+    pub fn merge(&mut self, key: Vec<u8>, operand: Vec<u8>) {
+        let key_size = key.len() as u64;
+        let key = self.ordered(&key);
+
+        let entry = match self.data.remove(&key) {
+            Some(old) => {
+                self.size_bytes
+                    .fetch_sub(key_size + old.size() as u64, Ordering::Relaxed);
+                match old {
+                    MemtableEntry::Merge { base, mut operands } => {
+                        operands.push(operand);
+                        MemtableEntry::Merge { base, operands }
+                    }
+                    other => MemtableEntry::Merge {
+                        base: Some(Box::new(other)),
+                        operands: vec![operand],
+                    },
+                }
+            }
+            None => MemtableEntry::Merge {
+                base: None,
+                operands: vec![operand],
+            },
+        };
+
+        self.size_bytes
+            .fetch_add(key_size + entry.size() as u64, Ordering::Relaxed);
+        self.data.insert(key, entry);
+        self.sequence.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Retrieves a value by key
     ///
     /// Returns:
     /// - `Some(Some(value))` if the key exists with a value
-    /// - `Some(None)` if the key was deleted (tombstone)
-    /// - `None` if the key is not in the memtable
+    /// - `Some(None)` if the key was deleted (tombstone or range tombstone)
+    /// - `None` if the key is not in the memtable or only has pending merge
+    ///   operands (use [`Memtable::get_entry`] to see those)
     pub fn get(&self, key: &[u8]) -> Option<Option<&[u8]>> {
-        self.data.get(key).map(|entry| match entry {
-            MemtableEntry::Value(v) => Some(v.as_slice()),
-            MemtableEntry::Tombstone => None,
-        })
+        match self.data.get(&self.ordered(key)) {
+            Some(MemtableEntry::Value(v)) => Some(Some(v.as_slice())),
+            Some(MemtableEntry::Tombstone) => Some(None),
+            Some(MemtableEntry::Merge { .. }) => None,
+            None if self.is_range_deleted(key) => Some(None),
+            None => None,
+        }
+    }
+
+    /// Retrieves the raw entry for a key, including pending merge operands
+    pub fn get_entry(&self, key: &[u8]) -> Option<&MemtableEntry> {
+        self.data.get(&self.ordered(key))
     }
 
     /// Marks a key as deleted with a tombstone
@@ -96,17 +225,61 @@ impl Memtable {
         let key_size = key.len() as u64;
 
         // Remove old entry size if exists
+        let key = self.ordered(&key);
         if let Some(old) = self.data.get(&key) {
             let old_size = old.size() as u64;
             self.size_bytes
                 .fetch_sub(key_size + old_size, Ordering::Relaxed);
         }
 
-        self.data.insert(key.clone(), MemtableEntry::Tombstone);
+        self.data.insert(key, MemtableEntry::Tombstone);
         self.size_bytes.fetch_add(key_size + 1, Ordering::Relaxed); // +1 for tombstone
         self.sequence.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Deletes all keys in `[start, end)`
+    ///
+    /// Entries in the range are dropped from the memtable and a range
+    /// tombstone is recorded to hide older data for the range in immutable
+    /// memtables and SSTables. Keys written afterwards are not affected.
+    pub fn delete_range(&mut self, start: Vec<u8>, end: Vec<u8>) {
+        if self.comparator.compare(&start, &end) != cmp::Ordering::Less {
+            return;
+        }
+
+        let doomed: Vec<OrderedKey> = self
+            .data
+            .range(self.ordered(&start)..self.ordered(&end))
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in doomed {
+            if let Some(old) = self.data.remove(&key) {
+                self.size_bytes
+                    .fetch_sub(key.key.len() as u64 + old.size() as u64, Ordering::Relaxed);
+            }
+        }
+
+        self.size_bytes
+            .fetch_add((start.len() + end.len()) as u64, Ordering::Relaxed);
+        self.range_tombstones.push((start, end));
+        self.sequence.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns true if a range tombstone in this memtable covers the key
+    ///
+    /// Entries still present in the memtable were written after any
+    /// covering tombstone and take precedence over it.
+    pub fn is_range_deleted(&self, key: &[u8]) -> bool {
+        self.range_tombstones
+            .iter()
+            .any(|(start, end)| comparator::in_range(self.comparator.as_ref(), key, start, end))
+    }
+
+    /// Returns the range tombstones recorded in this memtable
+    pub fn range_tombstones(&self) -> &[(Vec<u8>, Vec<u8>)] {
+        &self.range_tombstones
+    }
+
     /// Returns the approximate size of the memtable in bytes
     pub fn size_bytes(&self) -> u64 {
         self.size_bytes.load(Ordering::Relaxed)
@@ -117,9 +290,9 @@ impl Memtable {
         self.data.len()
     }
 
-    /// Returns true if the memtable is empty
+    /// Returns true if the memtable holds no entries or range tombstones
     pub fn is_empty(&self) -> bool {
-        self.data.is_empty()
+        self.data.is_empty() && self.range_tombstones.is_empty()
     }
 
     /// Returns the current sequence number
@@ -129,26 +302,48 @@ impl Memtable {
 
     /// Returns an iterator over all entries in sorted order
     pub fn iter(&self) -> impl Iterator<Item = (&Vec<u8>, &MemtableEntry)> {
-        self.data.iter()
+        self.data.iter().map(|(k, v)| (&k.key, v))
     }
 
-    /// Returns an iterator over a range of keys
-    pub fn range<R>(&self, range: R) -> impl Iterator<Item = (&Vec<u8>, &MemtableEntry)>
+    /// Returns an iterator over a range of keys, in sorted order
+    ///
+    /// Tombstones and pending merges are included. Only the entries in the
+    /// range are visited, and an inverted range yields nothing.
+    pub fn range<R>(&self, range: R) -> impl DoubleEndedIterator<Item = (&Vec<u8>, &MemtableEntry)>
     where
-        R: std::ops::RangeBounds<Vec<u8>>,
+        R: RangeBounds<Vec<u8>>,
     {
-        self.data.range(range)
+        let bound = |b: Bound<&Vec<u8>>| b.map(|k| self.ordered(k));
+        let bounds = (bound(range.start_bound()), bound(range.end_bound()));
+        // BTreeMap::range panics on an inverted range
+        let valid = match &bounds {
+            (
+                Bound::Included(start) | Bound::Excluded(start),
+                Bound::Included(end) | Bound::Excluded(end),
+            ) => match start.cmp(end) {
+                cmp::Ordering::Less => true,
+                cmp::Ordering::Equal => !matches!(bounds, (Bound::Excluded(_), Bound::Excluded(_))),
+                cmp::Ordering::Greater => false,
+            },
+            _ => true,
+        };
+        valid
+            .then_some(bounds)
+            .into_iter()
+            .flat_map(|bounds| self.data.range(bounds))
+            .map(|(k, v)| (&k.key, v))
     }
 
     /// Clears the memtable
     pub fn clear(&mut self) {
         self.data.clear();
+        self.range_tombstones.clear();
         self.size_bytes.store(0, Ordering::Relaxed);
     }
 
     /// Consumes the memtable and returns all entries sorted by key
     pub fn drain(self) -> impl Iterator<Item = (Vec<u8>, MemtableEntry)> {
-        self.data.into_iter()
+        self.data.into_iter().map(|(k, v)| (k.key, v))
     }
 }
 
@@ -233,6 +428,34 @@ mod tests {
         assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
     }
 
+    #[test]
+    fn test_memtable_range() {
+        let mut mt = Memtable::new();
+        for key in ["e", "a", "c", "d", "b", "f"] {
+            mt.put(key.as_bytes().to_vec(), key.as_bytes().to_vec());
+        }
+        mt.delete(b"c".to_vec());
+        mt.delete(b"x".to_vec());
+
+        let entries: Vec<_> = mt.range(b"b".to_vec()..b"e".to_vec()).collect();
+        assert_eq!(
+            entries,
+            vec![
+                (&b"b".to_vec(), &MemtableEntry::Value(b"b".to_vec())),
+                (&b"c".to_vec(), &MemtableEntry::Tombstone),
+                (&b"d".to_vec(), &MemtableEntry::Value(b"d".to_vec())),
+            ]
+        );
+
+        // Bounds need not be present keys
+        let keys: Vec<_> = mt.range(b"dd".to_vec()..).map(|(k, _)| k.clone()).collect();
+        assert_eq!(keys, vec![b"e".to_vec(), b"f".to_vec(), b"x".to_vec()]);
+
+        assert_eq!(mt.range(b"e".to_vec()..b"b".to_vec()).count(), 0);
+        assert_eq!(mt.range(b"b".to_vec()..b"b".to_vec()).count(), 0);
+        assert_eq!(mt.range(b"b".to_vec()..=b"b".to_vec()).count(), 1);
+    }
+
     #[test]
     fn test_memtable_sequence() {
         let mut mt = Memtable::with_sequence(100);
@@ -259,4 +482,57 @@ mod tests {
         assert!(mt.is_empty());
         assert_eq!(mt.size_bytes(), 0);
     }
+
+    #[test]
+    fn test_memtable_merge() {
+        let mut mt = Memtable::new();
+
+        mt.merge(b"fresh".to_vec(), b"1".to_vec());
+        mt.merge(b"fresh".to_vec(), b"2".to_vec());
+        assert_eq!(mt.get(b"fresh"), None);
+        assert_eq!(
+            mt.get_entry(b"fresh"),
+            Some(&MemtableEntry::Merge {
+                base: None,
+                operands: vec![b"1".to_vec(), b"2".to_vec()],
+            })
+        );
+
+        mt.put(b"based".to_vec(), b"base".to_vec());
+        mt.merge(b"based".to_vec(), b"3".to_vec());
+        assert_eq!(
+            mt.get_entry(b"based"),
+            Some(&MemtableEntry::Merge {
+                base: Some(Box::new(MemtableEntry::Value(b"base".to_vec()))),
+                operands: vec![b"3".to_vec()],
+            })
+        );
+
+        // Overwriting with a put discards pending operands
+        mt.put(b"fresh".to_vec(), b"v".to_vec());
+        assert_eq!(mt.get(b"fresh"), Some(Some(b"v".as_slice())));
+    }
+
+    #[test]
+    fn test_memtable_delete_range() {
+        let mut mt = Memtable::new();
+
+        mt.put(b"a".to_vec(), b"1".to_vec());
+        mt.put(b"b".to_vec(), b"2".to_vec());
+        mt.put(b"c".to_vec(), b"3".to_vec());
+
+        mt.delete_range(b"a".to_vec(), b"c".to_vec());
+        assert_eq!(mt.get(b"a"), Some(None));
+        assert_eq!(mt.get(b"b"), Some(None));
+        assert_eq!(mt.get(b"c"), Some(Some(b"3".as_slice())));
+        assert_eq!(mt.len(), 1);
+
+        // Writes after the range tombstone are visible
+        mt.put(b"b".to_vec(), b"new".to_vec());
+        assert_eq!(mt.get(b"b"), Some(Some(b"new".as_slice())));
+
+        mt.clear();
+        assert!(mt.is_empty());
+        assert!(!mt.is_range_deleted(b"a"));
+    }
 }
@@ -0,0 +1,35 @@
+//! Merge operators for read-modify-write updates
+//!
+//! A merge operator lets callers record an update (an "operand") for a key
+//! without reading the current value first. Operands are appended to the WAL
+//! and memtable, and folded into the base value when the key is read or when
+//! the memtable is flushed, so SSTables only ever contain resolved values.
+
+/// Folds merge operands into an existing value.
+///
+/// # Examples
+///
+/// A counter that adds little-endian `u64` operands:
+///
+/// ```rust
+/// use rustlite_storage::MergeOperator;
+///
+/// struct AddU64;
+///
+/// impl MergeOperator for AddU64 {
+///     fn merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[Vec<u8>]) -> Vec<u8> {
+///         let decode = |b: &[u8]| u64::from_le_bytes(b.try_into().unwrap_or([0; 8]));
+///         let mut total = existing.map(decode).unwrap_or(0);
+///         for op in operands {
+///             total += decode(op);
+///         }
+///         total.to_le_bytes().to_vec()
+///     }
+/// }
+/// ```
+pub trait MergeOperator: Send + Sync {
+    /// Apply `operands` (oldest first) on top of `existing` and return the new value
+    ///
+    /// `existing` is `None` if the key has no value or was deleted.
+    fn merge(&self, key: &[u8], existing: Option<&[u8]>, operands: &[Vec<u8>]) -> Vec<u8>;
+}
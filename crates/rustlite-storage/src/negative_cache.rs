@@ -0,0 +1,118 @@
+//! Cache of keys recently confirmed to be absent
+//!
+//! Lookups of missing keys have to consult every memtable and every SSTable
+//! whose key range covers the key. Remembering recent misses lets repeated
+//! probes for the same missing keys skip that work. A write to a key
+//! invalidates it once the write is in the memtable; together with the
+//! generation check in [`NegativeCache::insert`] this keeps a lookup that
+//! raced with the write from caching a stale miss.
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Least-recently-used set of absent keys
+pub(crate) struct NegativeCache {
+    /// Maximum number of keys remembered
+    capacity: usize,
+    /// Last-use tick of each cached key
+    entries: HashMap<Vec<u8>, u64>,
+    /// Cached keys by last-use tick, oldest first
+    by_use: BTreeMap<u64, Vec<u8>>,
+    /// Source of last-use ticks
+    tick: u64,
+    /// Bumped by every invalidation. A lookup only caches its miss if no
+    /// write happened while it was running.
+    generation: u64,
+}
+
+impl NegativeCache {
+    /// Create a cache holding up to `capacity` keys
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            by_use: BTreeMap::new(),
+            tick: 0,
+            generation: 0,
+        }
+    }
+
+    /// Returns true if `key` is known to be absent, marking it recently used
+    pub(crate) fn contains(&mut self, key: &[u8]) -> bool {
+        let Some(last_use) = self.entries.get_mut(key) else {
+            return false;
+        };
+        self.tick += 1;
+        let key = self.by_use.remove(last_use).unwrap_or_else(|| key.to_vec());
+        *last_use = self.tick;
+        self.by_use.insert(self.tick, key);
+        true
+    }
+
+    /// Current generation, to pass to [`insert`](Self::insert) once a
+    /// lookup started now has found nothing
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Remember that `key` is absent
+    ///
+    /// Ignored if any key was invalidated since `generation` was read, as
+    /// the miss may predate a write of this key.
+    pub(crate) fn insert(&mut self, key: Vec<u8>, generation: u64) {
+        if self.capacity == 0 || generation != self.generation || self.contains(&key) {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.by_use.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.tick += 1;
+        self.entries.insert(key.clone(), self.tick);
+        self.by_use.insert(self.tick, key);
+    }
+
+    /// Forget `key`, which is about to be written
+    pub(crate) fn invalidate(&mut self, key: &[u8]) {
+        self.generation += 1;
+        if let Some(last_use) = self.entries.remove(key) {
+            self.by_use.remove(&last_use);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_cache_evicts_least_recently_used() {
+        let mut cache = NegativeCache::new(2);
+        let generation = cache.generation();
+        cache.insert(b"a".to_vec(), generation);
+        cache.insert(b"b".to_vec(), generation);
+
+        // Touch "a" so "b" is the oldest
+        assert!(cache.contains(b"a"));
+        cache.insert(b"c".to_vec(), generation);
+        assert!(cache.contains(b"a"));
+        assert!(!cache.contains(b"b"));
+        assert!(cache.contains(b"c"));
+    }
+
+    #[test]
+    fn test_negative_cache_invalidation() {
+        let mut cache = NegativeCache::new(4);
+        cache.insert(b"a".to_vec(), cache.generation());
+        cache.invalidate(b"a");
+        assert!(!cache.contains(b"a"));
+
+        // A miss found before a write is not cached
+        let stale = cache.generation();
+        cache.invalidate(b"b");
+        cache.insert(b"b".to_vec(), stale);
+        assert!(!cache.contains(b"b"));
+    }
+}
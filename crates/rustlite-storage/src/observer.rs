@@ -0,0 +1,28 @@
+//! Observer hooks for metrics and tracing integration
+//!
+//! A [`DbObserver`] receives callbacks for database operations so that
+//! applications can export metrics (e.g. Prometheus counters and
+//! histograms) without modifying the storage engine.
+
+use crate::compaction::CompactionStats;
+use crate::sstable::SSTableMeta;
+use std::time::Duration;
+
+/// Callbacks invoked by the database and storage engine.
+///
+/// All methods have no-op default implementations, so implementors only
+/// need to override the events they care about. Callbacks run inline on
+/// the calling thread and should return quickly.
+pub trait DbObserver: Send + Sync {
+    /// Called after a successful put
+    fn on_put(&self, _key_len: usize, _value_len: usize, _latency: Duration) {}
+
+    /// Called after a successful get; `hit` is true if the key was found
+    fn on_get(&self, _key_len: usize, _hit: bool, _latency: Duration) {}
+
+    /// Called after a memtable has been flushed to an SSTable
+    fn on_flush(&self, _sstable: &SSTableMeta, _latency: Duration) {}
+
+    /// Called after a compaction completes with the cumulative compaction stats
+    fn on_compaction(&self, _stats: &CompactionStats) {}
+}
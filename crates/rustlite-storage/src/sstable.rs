@@ -7,20 +7,24 @@
 //!
 //! ```text
 //! +------------------+
-//! | Data Blocks      |  <- Key-value pairs grouped in blocks
+//! | Data Blocks      |  <- Key-value pairs grouped in blocks, each followed by a checksum
 //! +------------------+
 //! | Index Block      |  <- Sparse index pointing to data blocks
 //! +------------------+
-//! | Footer           |  <- Index offset + magic number + CRC
+//! | Footer           |  <- Index offset + range tombstones + checksum tag + magic number + CRC
 //! +------------------+
 //! ```
 
+use crate::comparator::{self, KeyComparator};
+use crate::integrity::ChecksumAlgorithm;
 use crate::memtable::MemtableEntry;
 use rustlite_core::{Error, Result};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Magic number for SSTable files ("RSSL" = RustLite SSTable)
 const SSTABLE_MAGIC_HEADER: [u8; 4] = *b"RSSL";
@@ -30,10 +34,15 @@ const SSTABLE_MAGIC: u64 = 0x53_53_54_42_4C_49_54;
 
 /// SSTable format version (v1.0.0+)
 /// Increment this when making incompatible format changes
-const SSTABLE_FORMAT_VERSION: u16 = 1;
+/// - 2: footer carries range tombstones
+/// - 3: footer records the block checksum algorithm
+const SSTABLE_FORMAT_VERSION: u16 = 3;
 
 /// Default block size (4KB)
-const DEFAULT_BLOCK_SIZE: usize = 4096;
+pub(crate) const DEFAULT_BLOCK_SIZE: usize = 4096;
+
+/// Default capacity of the buffer in front of the output file (64KB)
+pub(crate) const DEFAULT_WRITE_BUFFER_SIZE: usize = 64 * 1024;
 
 /// Entry type tags
 const ENTRY_TYPE_VALUE: u8 = 0;
@@ -101,12 +110,78 @@ pub struct SSTableFooter {
     pub min_key: Vec<u8>,
     /// Maximum key in the SSTable
     pub max_key: Vec<u8>,
+    /// Range tombstones as `[start, end)` pairs (format version 2+)
+    pub range_tombstones: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Tag of the data block checksum algorithm (format version 3+; CRC32
+    /// before)
+    pub checksum: u8,
     /// Magic number for validation (kept for backward compat with footer)
     pub magic: u64,
     /// CRC32 of the footer data
     pub crc: u32,
 }
 
+/// Footer layout of format version 2, before the checksum tag was added
+#[derive(Debug, Clone, Deserialize)]
+struct SSTableFooterV2 {
+    format_version: u16,
+    index_offset: u64,
+    index_size: u32,
+    entry_count: u64,
+    min_key: Vec<u8>,
+    max_key: Vec<u8>,
+    range_tombstones: Vec<(Vec<u8>, Vec<u8>)>,
+    magic: u64,
+    crc: u32,
+}
+
+impl From<SSTableFooterV2> for SSTableFooter {
+    fn from(v2: SSTableFooterV2) -> Self {
+        Self {
+            format_version: v2.format_version,
+            index_offset: v2.index_offset,
+            index_size: v2.index_size,
+            entry_count: v2.entry_count,
+            min_key: v2.min_key,
+            max_key: v2.max_key,
+            range_tombstones: v2.range_tombstones,
+            checksum: ChecksumAlgorithm::Crc32.tag(),
+            magic: v2.magic,
+            crc: v2.crc,
+        }
+    }
+}
+
+/// Footer layout of format version 1, before range tombstones were added
+#[derive(Debug, Clone, Deserialize)]
+struct SSTableFooterV1 {
+    format_version: u16,
+    index_offset: u64,
+    index_size: u32,
+    entry_count: u64,
+    min_key: Vec<u8>,
+    max_key: Vec<u8>,
+    magic: u64,
+    crc: u32,
+}
+
+impl From<SSTableFooterV1> for SSTableFooter {
+    fn from(v1: SSTableFooterV1) -> Self {
+        Self {
+            format_version: v1.format_version,
+            index_offset: v1.index_offset,
+            index_size: v1.index_size,
+            entry_count: v1.entry_count,
+            min_key: v1.min_key,
+            max_key: v1.max_key,
+            range_tombstones: Vec::new(),
+            checksum: ChecksumAlgorithm::Crc32.tag(),
+            magic: v1.magic,
+            crc: v1.crc,
+        }
+    }
+}
+
 /// File header written at the start of SSTable files (v1.0+)
 #[derive(Debug, Clone)]
 pub struct SSTableHeader {
@@ -152,16 +227,23 @@ impl SSTableHeader {
         let version = u16::from_le_bytes(version_bytes);
 
         if version > SSTABLE_FORMAT_VERSION {
-            return Err(Error::Corruption(format!(
-                "Unsupported SSTable version: {} (current: {})",
-                version, SSTABLE_FORMAT_VERSION
-            )));
+            return Err(Error::UnsupportedVersion {
+                component: "SSTable".to_string(),
+                found: version as u32,
+                max_supported: SSTABLE_FORMAT_VERSION as u32,
+            });
         }
 
         Ok(Self { magic, version })
     }
 }
 
+impl Default for SSTableHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// SSTable metadata (in-memory representation)
 #[derive(Debug, Clone)]
 pub struct SSTableMeta {
@@ -203,6 +285,12 @@ pub struct SSTableWriter {
     min_key: Option<Vec<u8>>,
     /// Maximum key
     max_key: Option<Vec<u8>>,
+    /// Range tombstones as `[start, end)` pairs
+    range_tombstones: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Key ordering, used to widen the key range over range tombstones
+    comparator: Arc<dyn KeyComparator>,
+    /// Checksum written after each data block
+    checksum: ChecksumAlgorithm,
 }
 
 impl SSTableWriter {
@@ -213,9 +301,22 @@ impl SSTableWriter {
 
     /// Create a new SSTable writer with custom block size
     pub fn with_block_size(path: impl AsRef<Path>, block_size: usize) -> Result<Self> {
+        Self::with_buffer_sizes(path, block_size, DEFAULT_WRITE_BUFFER_SIZE)
+    }
+
+    /// Create a new SSTable writer with custom block size that buffers up
+    /// to `write_buffer_size` bytes before writing to the file
+    ///
+    /// The buffer size only affects how often the file is written to, not
+    /// the file's contents.
+    pub fn with_buffer_sizes(
+        path: impl AsRef<Path>,
+        block_size: usize,
+        write_buffer_size: usize,
+    ) -> Result<Self> {
         let path = path.as_ref().to_path_buf();
         let file = File::create(&path)?;
-        let mut writer = BufWriter::new(file);
+        let mut writer = BufWriter::with_capacity(write_buffer_size, file);
 
         // Write file header (v1.0+)
         let header = SSTableHeader::new();
@@ -233,11 +334,42 @@ impl SSTableWriter {
             entry_count: 0,
             min_key: None,
             max_key: None,
+            range_tombstones: Vec::new(),
+            comparator: comparator::bytewise(),
+            checksum: ChecksumAlgorithm::default(),
         })
     }
 
+    /// Order keys with `comparator` instead of bytewise
+    ///
+    /// Entries must be added in ascending order under the comparator.
+    pub fn with_comparator(mut self, comparator: Arc<dyn KeyComparator>) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Protect data blocks with `checksum` instead of CRC32
+    ///
+    /// The choice is recorded in the footer, so readers need no
+    /// configuration to verify the file.
+    pub fn with_checksum(mut self, checksum: ChecksumAlgorithm) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
     /// Add an entry to the SSTable
+    ///
+    /// Keys must be added in strictly ascending order under the comparator;
+    /// otherwise `Error::InvalidOperation` is returned and nothing is added.
     pub fn add(&mut self, entry: SSTableEntry) -> Result<()> {
+        if let Some(previous) = &self.max_key {
+            if self.comparator.compare(&entry.key, previous) != Ordering::Greater {
+                return Err(Error::InvalidOperation(
+                    "keys must be added in ascending order".to_string(),
+                ));
+            }
+        }
+
         // Track min/max keys
         if self.min_key.is_none() {
             self.min_key = Some(entry.key.clone());
@@ -268,21 +400,32 @@ impl SSTableWriter {
         Ok(())
     }
 
+    /// Add a range tombstone deleting keys in `[start, end)` from older
+    /// SSTables
+    ///
+    /// Entries added to this SSTable are assumed to be newer than its range
+    /// tombstones and are not suppressed by them.
+    pub fn add_range_tombstone(&mut self, start: Vec<u8>, end: Vec<u8>) {
+        self.range_tombstones.push((start, end));
+    }
+
     /// Flush the current block to disk
     fn flush_block(&mut self) -> Result<()> {
         if self.block_buffer.is_empty() {
             return Ok(());
         }
 
-        // Calculate CRC
-        let crc = crc32fast::hash(&self.block_buffer);
+        // Calculate checksum
+        let checksum = self
+            .checksum
+            .encode(self.checksum.checksum(&self.block_buffer));
 
         // Create index entry
         if let Some(first_key) = self.current_block_first_key.take() {
             self.index.push(IndexEntry {
                 first_key,
                 offset: self.position,
-                size: self.block_buffer.len() as u32 + 4, // +4 for CRC
+                size: (self.block_buffer.len() + checksum.len()) as u32,
             });
         }
 
@@ -290,9 +433,9 @@ impl SSTableWriter {
         self.writer.write_all(&self.block_buffer)?;
         self.position += self.block_buffer.len() as u64;
 
-        // Write block CRC
-        self.writer.write_all(&crc.to_le_bytes())?;
-        self.position += 4;
+        // Write block checksum
+        self.writer.write_all(&checksum)?;
+        self.position += checksum.len() as u64;
 
         self.block_buffer.clear();
 
@@ -313,9 +456,26 @@ impl SSTableWriter {
         self.writer.write_all(&index_encoded)?;
         self.position += index_size as u64;
 
-        // Write footer
-        let min_key = self.min_key.clone().unwrap_or_default();
-        let max_key = self.max_key.clone().unwrap_or_default();
+        // Write footer, widening the key range to cover range tombstones
+        let mut min_key = self.min_key.clone();
+        let mut max_key = self.max_key.clone();
+        let cmp = self.comparator.as_ref();
+        for (start, end) in &self.range_tombstones {
+            if min_key
+                .as_ref()
+                .map_or(true, |k| cmp.compare(start, k) == Ordering::Less)
+            {
+                min_key = Some(start.clone());
+            }
+            if max_key
+                .as_ref()
+                .map_or(true, |k| cmp.compare(end, k) == Ordering::Greater)
+            {
+                max_key = Some(end.clone());
+            }
+        }
+        let min_key = min_key.unwrap_or_default();
+        let max_key = max_key.unwrap_or_default();
 
         let footer_data = SSTableFooter {
             format_version: SSTABLE_FORMAT_VERSION,
@@ -324,6 +484,8 @@ impl SSTableWriter {
             entry_count: self.entry_count,
             min_key: min_key.clone(),
             max_key: max_key.clone(),
+            range_tombstones: std::mem::take(&mut self.range_tombstones),
+            checksum: self.checksum.tag(),
             magic: SSTABLE_MAGIC,
             crc: 0, // Will be set after computing CRC
         };
@@ -360,17 +522,42 @@ impl SSTableWriter {
         })
     }
 
-    /// Build an SSTable from a memtable
-    pub fn from_memtable<I>(path: impl AsRef<Path>, iter: I) -> Result<SSTableMeta>
+    /// Build an SSTable from a memtable, using data blocks of `block_size` bytes
+    pub fn from_memtable<I>(
+        path: impl AsRef<Path>,
+        iter: I,
+        block_size: usize,
+    ) -> Result<SSTableMeta>
     where
         I: Iterator<Item = (Vec<u8>, MemtableEntry)>,
     {
-        let mut writer = SSTableWriter::new(path)?;
+        Self::from_memtable_with_range_tombstones(path, iter, &[], block_size)
+    }
+
+    /// Build an SSTable from a memtable's entries and range tombstones
+    pub fn from_memtable_with_range_tombstones<I>(
+        path: impl AsRef<Path>,
+        iter: I,
+        range_tombstones: &[(Vec<u8>, Vec<u8>)],
+        block_size: usize,
+    ) -> Result<SSTableMeta>
+    where
+        I: Iterator<Item = (Vec<u8>, MemtableEntry)>,
+    {
+        let mut writer = SSTableWriter::with_block_size(path, block_size)?;
+        for (start, end) in range_tombstones {
+            writer.add_range_tombstone(start.clone(), end.clone());
+        }
 
         for (key, entry) in iter {
             let sstable_entry = match entry {
                 MemtableEntry::Value(v) => SSTableEntry::value(key, v),
                 MemtableEntry::Tombstone => SSTableEntry::tombstone(key),
+                MemtableEntry::Merge { .. } => {
+                    return Err(Error::InvalidOperation(
+                        "Unresolved merge entry cannot be written to an SSTable".into(),
+                    ));
+                }
             };
             writer.add(sstable_entry)?;
         }
@@ -393,6 +580,16 @@ pub struct SSTableReader {
     file_size: u64,
     /// Header offset (0 for legacy files, SSTableHeader::SIZE for v1.0+)
     header_offset: u64,
+    /// Key ordering the SSTable was written with
+    comparator: Arc<dyn KeyComparator>,
+    /// Checksum protecting each data block, from the footer
+    checksum: ChecksumAlgorithm,
+    /// Key comparisons made by in-block searches
+    #[cfg(test)]
+    key_comparisons: usize,
+    /// Data blocks read from disk
+    #[cfg(test)]
+    blocks_read: usize,
 }
 
 impl SSTableReader {
@@ -440,21 +637,38 @@ impl SSTableReader {
         let mut footer_buf = vec![0u8; footer_len as usize];
         file.read_exact(&mut footer_buf)?;
 
-        let footer: SSTableFooter =
-            bincode::deserialize(&footer_buf).map_err(|e| Error::Serialization(e.to_string()))?;
+        // The format version is the first footer field and selects the layout
+        if footer_buf.len() < 2 {
+            return Err(Error::Corruption("SSTable footer too small".into()));
+        }
+        let format_version = u16::from_le_bytes([footer_buf[0], footer_buf[1]]);
+
+        // Validate format version (v1.0.0+)
+        if format_version > SSTABLE_FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion {
+                component: "SSTable".to_string(),
+                found: format_version as u32,
+                max_supported: SSTABLE_FORMAT_VERSION as u32,
+            });
+        }
+
+        let footer: SSTableFooter = if format_version >= 3 {
+            bincode::deserialize(&footer_buf).map_err(|e| Error::Serialization(e.to_string()))?
+        } else if format_version == 2 {
+            bincode::deserialize::<SSTableFooterV2>(&footer_buf)
+                .map_err(|e| Error::Serialization(e.to_string()))?
+                .into()
+        } else {
+            bincode::deserialize::<SSTableFooterV1>(&footer_buf)
+                .map_err(|e| Error::Serialization(e.to_string()))?
+                .into()
+        };
 
         // Validate magic number
         if footer.magic != SSTABLE_MAGIC {
             return Err(Error::Corruption("Invalid SSTable magic number".into()));
         }
-
-        // Validate format version (v1.0.0+)
-        if footer.format_version > SSTABLE_FORMAT_VERSION {
-            return Err(Error::Corruption(format!(
-                "Unsupported SSTable format version: {} (current: {})",
-                footer.format_version, SSTABLE_FORMAT_VERSION
-            )));
-        }
+        let checksum = ChecksumAlgorithm::from_tag(footer.checksum)?;
 
         // Read index (index_offset is already absolute from file start for v1.0+, or from data start for legacy)
         let index_offset = if header_offset > 0 {
@@ -478,20 +692,35 @@ impl SSTableReader {
             footer,
             file_size,
             header_offset,
+            comparator: comparator::bytewise(),
+            checksum,
+            #[cfg(test)]
+            key_comparisons: 0,
+            #[cfg(test)]
+            blocks_read: 0,
         })
     }
 
+    /// Order keys with `comparator` instead of bytewise
+    ///
+    /// Must match the comparator the SSTable was written with.
+    pub fn with_comparator(mut self, comparator: Arc<dyn KeyComparator>) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
     /// Get a value by key
     pub fn get(&mut self, key: &[u8]) -> Result<Option<SSTableEntry>> {
         // Binary search to find the block that might contain the key
+        let cmp = Arc::clone(&self.comparator);
         let block_idx = self
             .index
-            .partition_point(|entry| entry.first_key.as_slice() <= key);
+            .partition_point(|entry| cmp.compare(&entry.first_key, key) != Ordering::Greater);
 
         // The key would be in the previous block (if any)
         if block_idx == 0 {
             // Key is smaller than all keys in the SSTable
-            if key < self.footer.min_key.as_slice() {
+            if cmp.compare(key, &self.footer.min_key) == Ordering::Less {
                 return Ok(None);
             }
         }
@@ -503,27 +732,110 @@ impl SSTableReader {
             return Ok(None);
         }
 
-        // Read and search the block
-        let block = self.read_block(block_idx)?;
+        // Read the block and binary search its sorted entries
+        let mut block = self.read_block(block_idx)?;
 
-        for entry in block {
-            if entry.key.as_slice() == key {
-                return Ok(Some(entry));
+        let found = block.binary_search_by(|entry| {
+            #[cfg(test)]
+            {
+                self.key_comparisons += 1;
             }
-            if entry.key.as_slice() > key {
+            cmp.compare(&entry.key, key)
+        });
+
+        Ok(found.ok().map(|idx| block.swap_remove(idx)))
+    }
+
+    /// Get the entries with keys in `[start, end)`, in key order
+    ///
+    /// Uses the block index to start at the block that may hold `start` and
+    /// stops once a block begins at or after `end`, so only the blocks
+    /// overlapping the range are read. Tombstones are included.
+    pub fn get_range(&mut self, start: &[u8], end: &[u8]) -> Result<Vec<SSTableEntry>> {
+        self.get_range_limited(start, end, usize::MAX)
+    }
+
+    /// Get the first `limit` entries with keys in `[start, end)`, in key
+    /// order
+    ///
+    /// Like [`get_range`](Self::get_range), but stops reading blocks once
+    /// `limit` entries have been found.
+    pub fn get_range_limited(
+        &mut self,
+        start: &[u8],
+        end: &[u8],
+        limit: usize,
+    ) -> Result<Vec<SSTableEntry>> {
+        let cmp = Arc::clone(&self.comparator);
+        if cmp.compare(start, end) != Ordering::Less {
+            return Ok(Vec::new());
+        }
+
+        let mut iter = self.iter_range(Some(start), Some(end))?;
+        let mut entries = Vec::new();
+        while entries.len() < limit {
+            match iter.next_entry()? {
+                Some(entry) => entries.push(entry),
+                None => break,
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Get the last `limit` entries with keys in `[start, end)`, in
+    /// descending key order
+    ///
+    /// Reads blocks backwards from the last one starting before `end`, and
+    /// stops once `limit` entries have been found or a block starting at or
+    /// before `start` has been read. Tombstones are included.
+    pub fn get_range_rev_limited(
+        &mut self,
+        start: &[u8],
+        end: &[u8],
+        limit: usize,
+    ) -> Result<Vec<SSTableEntry>> {
+        let cmp = Arc::clone(&self.comparator);
+        if cmp.compare(start, end) != Ordering::Less {
+            return Ok(Vec::new());
+        }
+
+        // Blocks starting at or after `end` hold nothing in the range
+        let end_block = self
+            .index
+            .partition_point(|entry| cmp.compare(&entry.first_key, end) == Ordering::Less);
+
+        let mut entries = Vec::new();
+        for block_idx in (0..end_block).rev() {
+            if entries.len() >= limit {
+                break;
+            }
+            entries.extend(
+                self.read_block(block_idx)?
+                    .into_iter()
+                    .rev()
+                    .filter(|e| comparator::in_range(cmp.as_ref(), &e.key, start, end)),
+            );
+            if cmp.compare(&self.index[block_idx].first_key, start) != Ordering::Greater {
                 break;
             }
         }
+        entries.truncate(limit);
 
-        Ok(None)
+        Ok(entries)
     }
 
     /// Read a data block by index
     fn read_block(&mut self, block_idx: usize) -> Result<Vec<SSTableEntry>> {
+        #[cfg(test)]
+        {
+            self.blocks_read += 1;
+        }
         let index_entry = &self.index[block_idx];
 
         // Block offsets are already absolute for v1.0+ files (include header)
         // For legacy files, they start at position 0 (no header)
+        #[allow(clippy::if_same_then_else)]
         let absolute_offset = if self.header_offset > 0 {
             index_entry.offset // Already absolute
         } else {
@@ -531,17 +843,20 @@ impl SSTableReader {
         };
         self.file.seek(SeekFrom::Start(absolute_offset))?;
 
-        let data_size = index_entry.size as usize - 4; // Subtract CRC size
+        let checksum_len = self.checksum.size();
+        let data_size = (index_entry.size as usize)
+            .checked_sub(checksum_len)
+            .ok_or_else(|| Error::Corruption("Block smaller than its checksum".into()))?;
         let mut data_buf = vec![0u8; data_size];
         self.file.read_exact(&mut data_buf)?;
 
-        // Read and verify CRC
-        let mut crc_buf = [0u8; 4];
-        self.file.read_exact(&mut crc_buf)?;
-        let stored_crc = u32::from_le_bytes(crc_buf);
-        let computed_crc = crc32fast::hash(&data_buf);
+        // Read and verify checksum
+        let mut checksum_buf = [0u8; 8];
+        self.file.read_exact(&mut checksum_buf[..checksum_len])?;
+        let stored = self.checksum.decode(&checksum_buf);
+        let computed = self.checksum.checksum(&data_buf);
 
-        if stored_crc != computed_crc {
+        if stored != computed {
             return Err(Error::Corruption("Block CRC mismatch".into()));
         }
 
@@ -575,6 +890,11 @@ impl SSTableReader {
         Ok(entries)
     }
 
+    /// Number of data blocks (one index entry each) in this SSTable
+    pub fn block_count(&self) -> usize {
+        self.index.len()
+    }
+
     /// Get metadata about this SSTable
     pub fn metadata(&self) -> SSTableMeta {
         SSTableMeta {
@@ -588,18 +908,70 @@ impl SSTableReader {
         }
     }
 
+    /// Range tombstones stored in this SSTable as `[start, end)` pairs
+    pub fn range_tombstones(&self) -> &[(Vec<u8>, Vec<u8>)] {
+        &self.footer.range_tombstones
+    }
+
+    /// Check if a key is deleted by one of this SSTable's range tombstones
+    ///
+    /// Only applies to data in older SSTables; entries stored in this
+    /// SSTable take precedence.
+    pub fn is_range_deleted(&self, key: &[u8]) -> bool {
+        self.footer
+            .range_tombstones
+            .iter()
+            .any(|(start, end)| comparator::in_range(self.comparator.as_ref(), key, start, end))
+    }
+
     /// Check if a key might be in this SSTable (range check)
     pub fn might_contain(&self, key: &[u8]) -> bool {
-        key >= self.footer.min_key.as_slice() && key <= self.footer.max_key.as_slice()
+        self.comparator.compare(key, &self.footer.min_key) != Ordering::Less
+            && self.comparator.compare(key, &self.footer.max_key) != Ordering::Greater
     }
 
     /// Iterate over all entries in the SSTable
     pub fn iter(&mut self) -> Result<SSTableIterator<'_>> {
+        self.iter_range(None, None)
+    }
+
+    /// Iterate over the entries with keys in `[start, end)`, in key order
+    ///
+    /// A missing bound leaves that side of the range open. Iteration begins
+    /// at the block that may hold `start` and no block is read once one
+    /// begins at or after `end`, so exporting a slice of a large SSTable
+    /// only reads the blocks overlapping it. Tombstones are included.
+    pub fn iter_range(
+        &mut self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> Result<SSTableIterator<'_>> {
+        let cmp = Arc::clone(&self.comparator);
+        // The last block starting at or before `start` may hold it
+        let block_idx = start.map_or(0, |start| {
+            self.index
+                .partition_point(|entry| cmp.compare(&entry.first_key, start) != Ordering::Greater)
+                .saturating_sub(1)
+        });
+
         Ok(SSTableIterator {
             reader: self,
-            block_idx: 0,
-            block_entries: Vec::new(),
-            entry_idx: 0,
+            block_idx,
+            block_entries: Vec::new().into_iter(),
+            start: start.map(<[u8]>::to_vec),
+            end: end.map(<[u8]>::to_vec),
+            comparator: cmp,
+        })
+    }
+
+    /// Iterate over the values in the SSTable, skipping tombstones
+    ///
+    /// For tooling and export. Range tombstones only cover older SSTables,
+    /// so they do not hide any of these entries. Compaction must see the
+    /// tombstones and uses [`iter`](Self::iter) instead.
+    pub fn live_iter(&mut self) -> Result<LiveSSTableIterator<'_>> {
+        Ok(LiveSSTableIterator {
+            inner: self.iter()?,
         })
     }
 }
@@ -608,8 +980,13 @@ impl SSTableReader {
 pub struct SSTableIterator<'a> {
     reader: &'a mut SSTableReader,
     block_idx: usize,
-    block_entries: Vec<SSTableEntry>,
-    entry_idx: usize,
+    /// Remaining entries of the current block; only one block is buffered
+    block_entries: std::vec::IntoIter<SSTableEntry>,
+    /// Entries before this key are skipped
+    start: Option<Vec<u8>>,
+    /// Iteration ends at the first key at or after this one
+    end: Option<Vec<u8>>,
+    comparator: Arc<dyn KeyComparator>,
 }
 
 impl SSTableIterator<'_> {
@@ -617,22 +994,69 @@ impl SSTableIterator<'_> {
     pub fn next_entry(&mut self) -> Result<Option<SSTableEntry>> {
         loop {
             // If we have entries in the current block, return the next one
-            if self.entry_idx < self.block_entries.len() {
-                let entry = self.block_entries[self.entry_idx].clone();
-                self.entry_idx += 1;
+            if let Some(entry) = self.block_entries.next() {
+                let cmp = self.comparator.as_ref();
+                if self
+                    .start
+                    .as_ref()
+                    .is_some_and(|start| cmp.compare(&entry.key, start) == Ordering::Less)
+                {
+                    continue;
+                }
+                if self
+                    .end
+                    .as_ref()
+                    .is_some_and(|end| cmp.compare(&entry.key, end) != Ordering::Less)
+                {
+                    self.finish();
+                    return Ok(None);
+                }
                 return Ok(Some(entry));
             }
 
-            // Load the next block
-            if self.block_idx >= self.reader.index.len() {
+            // Load the next block, unless it starts past the end
+            let past_end = self.end.as_ref().is_some_and(|end| {
+                self.reader.index.get(self.block_idx).is_some_and(|block| {
+                    self.comparator.compare(&block.first_key, end) != Ordering::Less
+                })
+            });
+            if past_end || self.block_idx >= self.reader.index.len() {
                 return Ok(None);
             }
 
-            self.block_entries = self.reader.read_block(self.block_idx)?;
+            self.block_entries = self.reader.read_block(self.block_idx)?.into_iter();
             self.block_idx += 1;
-            self.entry_idx = 0;
         }
     }
+
+    /// Number of blocks read so far
+    #[cfg(test)]
+    pub(crate) fn blocks_read(&self) -> usize {
+        self.block_idx
+    }
+
+    /// Stop reading any further entries
+    fn finish(&mut self) {
+        self.block_idx = self.reader.index.len();
+        self.block_entries = Vec::new().into_iter();
+    }
+}
+
+/// Iterator over the non-tombstone entries of an SSTable
+pub struct LiveSSTableIterator<'a> {
+    inner: SSTableIterator<'a>,
+}
+
+impl LiveSSTableIterator<'_> {
+    /// Get the next value entry
+    pub fn next_entry(&mut self) -> Result<Option<SSTableEntry>> {
+        while let Some(entry) = self.inner.next_entry()? {
+            if !entry.is_tombstone() {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
 }
 
 /// Delete an SSTable file
@@ -707,6 +1131,29 @@ mod tests {
         assert!(entry.is_tombstone());
     }
 
+    #[test]
+    fn test_sstable_rejects_out_of_order_keys() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.sst");
+
+        let mut writer = SSTableWriter::new(&path).unwrap();
+        writer
+            .add(SSTableEntry::value(b"b".to_vec(), b"1".to_vec()))
+            .unwrap();
+        for key in [b"a", b"b"] {
+            let result = writer.add(SSTableEntry::value(key.to_vec(), b"2".to_vec()));
+            assert!(matches!(
+                result,
+                Err(Error::InvalidOperation(msg)) if msg == "keys must be added in ascending order"
+            ));
+        }
+        writer
+            .add(SSTableEntry::value(b"c".to_vec(), b"3".to_vec()))
+            .unwrap();
+        let meta = writer.finish().unwrap();
+        assert_eq!(meta.entry_count, 2);
+    }
+
     #[test]
     fn test_sstable_iterator() {
         let dir = tempdir().unwrap();
@@ -733,6 +1180,87 @@ mod tests {
         assert_eq!(count, 100);
     }
 
+    #[test]
+    fn test_sstable_crc64_blocks() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.sst");
+
+        let mut writer = SSTableWriter::with_block_size(&path, 64)
+            .unwrap()
+            .with_checksum(ChecksumAlgorithm::Crc64);
+        for i in 0..20 {
+            writer
+                .add(SSTableEntry::value(
+                    format!("key{:02}", i).into_bytes(),
+                    b"value".to_vec(),
+                ))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        // The algorithm comes from the footer, not from the reader
+        let mut reader = SSTableReader::open(&path).unwrap();
+        assert!(reader.block_count() > 1);
+        assert_eq!(reader.checksum, ChecksumAlgorithm::Crc64);
+        for i in 0..20 {
+            let entry = reader
+                .get(format!("key{:02}", i).as_bytes())
+                .unwrap()
+                .unwrap();
+            assert_eq!(entry.value, b"value".to_vec());
+        }
+
+        // Flip one byte inside the first block
+        let offset = reader.index[0].offset as usize + 8;
+        drop(reader);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[offset] ^= 0xFF;
+        fs::write(&path, bytes).unwrap();
+
+        let mut reader = SSTableReader::open(&path).unwrap();
+        assert!(matches!(reader.get(b"key00"), Err(Error::Corruption(_))));
+        // Other blocks are still readable
+        assert!(reader.get(b"key19").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_sstable_live_iterator() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.sst");
+
+        let mut writer = SSTableWriter::new(&path).unwrap();
+        writer
+            .add(SSTableEntry::value(b"a".to_vec(), b"1".to_vec()))
+            .unwrap();
+        writer.add(SSTableEntry::tombstone(b"b".to_vec())).unwrap();
+        writer
+            .add(SSTableEntry::value(b"c".to_vec(), b"3".to_vec()))
+            .unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = SSTableReader::open(&path).unwrap();
+        let mut live = Vec::new();
+        let mut iter = reader.live_iter().unwrap();
+        while let Some(entry) = iter.next_entry().unwrap() {
+            live.push((entry.key, entry.value));
+        }
+        assert_eq!(
+            live,
+            vec![
+                (b"a".to_vec(), b"1".to_vec()),
+                (b"c".to_vec(), b"3".to_vec())
+            ]
+        );
+
+        // The full iterator still reports the tombstone
+        let mut iter = reader.iter().unwrap();
+        let mut count = 0;
+        while iter.next_entry().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
     #[test]
     fn test_sstable_from_memtable() {
         use crate::memtable::Memtable;
@@ -745,7 +1273,7 @@ mod tests {
         mt.put(b"b".to_vec(), b"2".to_vec());
         mt.delete(b"c".to_vec());
 
-        let meta = SSTableWriter::from_memtable(&path, mt.drain()).unwrap();
+        let meta = SSTableWriter::from_memtable(&path, mt.drain(), DEFAULT_BLOCK_SIZE).unwrap();
 
         assert_eq!(meta.entry_count, 3);
 
@@ -754,6 +1282,211 @@ mod tests {
         assert!(reader.get(b"c").unwrap().unwrap().is_tombstone());
     }
 
+    #[test]
+    fn test_sstable_range_tombstones() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.sst");
+
+        let mut writer = SSTableWriter::new(&path).unwrap();
+        writer.add_range_tombstone(b"a".to_vec(), b"m".to_vec());
+        writer
+            .add(SSTableEntry::value(b"c".to_vec(), b"3".to_vec()))
+            .unwrap();
+        let meta = writer.finish().unwrap();
+
+        // Key range is widened to cover the tombstone
+        assert_eq!(meta.min_key, b"a".to_vec());
+        assert_eq!(meta.max_key, b"m".to_vec());
+        assert_eq!(meta.entry_count, 1);
+
+        let reader = SSTableReader::open(&path).unwrap();
+        assert_eq!(reader.range_tombstones(), &[(b"a".to_vec(), b"m".to_vec())]);
+        assert!(reader.is_range_deleted(b"a"));
+        assert!(reader.is_range_deleted(b"l"));
+        assert!(!reader.is_range_deleted(b"m"));
+    }
+
+    #[test]
+    fn test_sstable_get_binary_searches_block() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.sst");
+
+        // One block large enough to hold every entry
+        let count = 1024;
+        let mut writer = SSTableWriter::with_block_size(&path, 1024 * 1024).unwrap();
+        for i in 0..count {
+            let key = format!("key{:05}", i * 2).into_bytes();
+            writer
+                .add(SSTableEntry::value(key, format!("v{}", i).into_bytes()))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut reader = SSTableReader::open(&path).unwrap();
+        assert_eq!(reader.block_count(), 1);
+
+        for i in 0..count {
+            let key = format!("key{:05}", i * 2).into_bytes();
+            let entry = reader.get(&key).unwrap().unwrap();
+            assert_eq!(entry.value, format!("v{}", i).into_bytes());
+
+            // Odd keys fall between entries and are absent
+            let missing = format!("key{:05}", i * 2 + 1).into_bytes();
+            assert!(reader.get(&missing).unwrap().is_none());
+        }
+
+        // log2(1024) = 10 comparisons per lookup, far below a linear scan
+        let lookups = 2 * count;
+        assert!(
+            reader.key_comparisons <= lookups * 11,
+            "{} comparisons for {} lookups",
+            reader.key_comparisons,
+            lookups
+        );
+    }
+
+    #[test]
+    fn test_sstable_write_buffer_size_does_not_change_output() {
+        let dir = tempdir().unwrap();
+        let write = |name: &str, write_buffer_size: usize| {
+            let path = dir.path().join(name);
+            let mut writer =
+                SSTableWriter::with_buffer_sizes(&path, DEFAULT_BLOCK_SIZE, write_buffer_size)
+                    .unwrap();
+            for i in 0..20_000 {
+                let key = format!("key{:06}", i).into_bytes();
+                writer
+                    .add(SSTableEntry::value(key, vec![i as u8; 100]))
+                    .unwrap();
+            }
+            writer.finish().unwrap();
+            std::fs::read(&path).unwrap()
+        };
+
+        let default = write("default.sst", DEFAULT_WRITE_BUFFER_SIZE);
+        assert!(default.len() > 2 * 1024 * 1024);
+        assert_eq!(write("large.sst", 4 * 1024 * 1024), default);
+        assert_eq!(write("tiny.sst", 1), default);
+    }
+
+    /// Write `key000`..`key099` with values `value000`..`value099` in
+    /// 256-byte blocks, so the SSTable spans many blocks
+    fn write_small_block_sstable(path: &Path) {
+        let mut writer = SSTableWriter::with_block_size(path, 256).unwrap();
+        for i in 0..100 {
+            let key = format!("key{:03}", i).into_bytes();
+            writer
+                .add(SSTableEntry::value(
+                    key,
+                    format!("value{:03}", i).into_bytes(),
+                ))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    /// Keys of [`write_small_block_sstable`] numbered `from..to`
+    fn keys_between(from: usize, to: usize) -> Vec<Vec<u8>> {
+        (from..to)
+            .map(|i| format!("key{:03}", i).into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_sstable_get_range_reads_only_overlapping_blocks() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.sst");
+        write_small_block_sstable(&path);
+
+        let mut reader = SSTableReader::open(&path).unwrap();
+        let blocks = reader.block_count();
+        assert!(blocks >= 10, "expected many blocks, got {}", blocks);
+
+        let entries = reader.get_range(b"key040", b"key045").unwrap();
+        let keys: Vec<_> = entries.iter().map(|e| e.key.clone()).collect();
+        assert_eq!(keys, keys_between(40, 45));
+        assert_eq!(entries[0].value, b"value040");
+        assert!(
+            reader.blocks_read <= 2,
+            "read {} of {} blocks",
+            reader.blocks_read,
+            blocks
+        );
+
+        // Ranges outside the table or empty ranges read nothing useful
+        assert!(reader.get_range(b"a", b"key").unwrap().is_empty());
+        assert!(reader.get_range(b"z", b"zz").unwrap().is_empty());
+        assert!(reader.get_range(b"key050", b"key050").unwrap().is_empty());
+        assert_eq!(reader.get_range(b"", b"zz").unwrap().len(), 100);
+
+        // Reverse reads walk back from the end of the range
+        reader.blocks_read = 0;
+        let entries = reader
+            .get_range_rev_limited(b"key040", b"key090", 3)
+            .unwrap();
+        let keys: Vec<_> = entries.iter().map(|e| e.key.clone()).collect();
+        assert_eq!(
+            keys,
+            vec![b"key089".to_vec(), b"key088".to_vec(), b"key087".to_vec()]
+        );
+        assert!(
+            reader.blocks_read <= 2,
+            "read {} blocks",
+            reader.blocks_read
+        );
+        let entries = reader
+            .get_range_rev_limited(b"key040", b"key045", 100)
+            .unwrap();
+        let mut keys: Vec<_> = entries.iter().map(|e| e.key.clone()).collect();
+        keys.reverse();
+        assert_eq!(keys, keys_between(40, 45));
+        assert!(reader
+            .get_range_rev_limited(b"key050", b"key050", 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_sstable_iter_range() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("test.sst");
+        write_small_block_sstable(&path);
+
+        let collect = |reader: &mut SSTableReader, start: Option<&[u8]>, end: Option<&[u8]>| {
+            let mut iter = reader.iter_range(start, end).unwrap();
+            let mut keys = Vec::new();
+            while let Some(entry) = iter.next_entry().unwrap() {
+                keys.push(entry.key);
+            }
+            keys
+        };
+
+        let mut reader = SSTableReader::open(&path).unwrap();
+        let blocks = reader.block_count();
+        assert_eq!(
+            collect(&mut reader, Some(b"key040"), Some(b"key060")),
+            keys_between(40, 60)
+        );
+        assert!(
+            reader.blocks_read < blocks / 2,
+            "read {} of {} blocks",
+            reader.blocks_read,
+            blocks
+        );
+
+        assert_eq!(
+            collect(&mut reader, None, Some(b"key005")),
+            keys_between(0, 5)
+        );
+        assert_eq!(
+            collect(&mut reader, Some(b"key095"), None),
+            keys_between(95, 100)
+        );
+        assert_eq!(collect(&mut reader, None, None).len(), 100);
+        assert!(collect(&mut reader, Some(b"key050"), Some(b"key050")).is_empty());
+        assert!(collect(&mut reader, Some(b"z"), None).is_empty());
+    }
+
     #[test]
     fn test_sstable_might_contain() {
         let dir = tempdir().unwrap();
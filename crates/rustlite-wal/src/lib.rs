@@ -23,17 +23,19 @@
 use rustlite_core::Result;
 use serde::{Deserialize, Serialize};
 
+pub mod checksum;
 pub mod reader;
 pub mod record;
 pub mod recovery;
 pub mod segment;
 pub mod writer;
 
-pub use reader::WalReader;
+pub use checksum::ChecksumAlgorithm;
+pub use reader::{ReadOutcome, WalPosition, WalReader};
 pub use record::{RecordPayload, RecordType, WalRecord};
-pub use recovery::{RecoveryManager, RecoveryStats};
+pub use recovery::{RecordSummary, RecoveryManager, RecoveryStats};
 pub use segment::{SegmentInfo, SegmentManager};
-pub use writer::WalWriter;
+pub use writer::{AppendOutcome, WalWriter};
 
 /// WAL configuration options
 #[derive(Debug, Clone)]
@@ -44,6 +46,21 @@ pub struct WalConfig {
     pub max_segment_size: u64,
     /// Directory path for WAL segments
     pub wal_dir: std::path::PathBuf,
+    /// Directory that truncated segments are moved into instead of being
+    /// deleted (`None` deletes them)
+    pub archive_dir: Option<std::path::PathBuf>,
+    /// Cap on the combined size of all segments in bytes (`None` is
+    /// unlimited). Appends that would exceed it fail; the storage engine
+    /// flushes and truncates the WAL before that happens.
+    pub max_total_wal_size: Option<u64>,
+    /// Bytes of records buffered in memory before they are written to the
+    /// segment file
+    pub write_buffer_bytes: usize,
+    /// How recovery treats a corrupt or torn record
+    pub recovery_policy: RecoveryPolicy,
+    /// Checksum written after each record in new segments. Existing
+    /// segments keep the algorithm recorded in their header.
+    pub checksum: ChecksumAlgorithm,
 }
 
 impl Default for WalConfig {
@@ -52,10 +69,26 @@ impl Default for WalConfig {
             sync_mode: SyncMode::Sync,
             max_segment_size: 64 * 1024 * 1024, // 64 MB
             wal_dir: std::path::PathBuf::from("wal"),
+            archive_dir: None,
+            max_total_wal_size: None,
+            write_buffer_bytes: writer::DEFAULT_WRITE_BUFFER_SIZE,
+            recovery_policy: RecoveryPolicy::default(),
+            checksum: ChecksumAlgorithm::default(),
         }
     }
 }
 
+/// How recovery treats a record that fails CRC validation or was torn by
+/// a crash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecoveryPolicy {
+    /// Stop at the damaged record and keep every record before it
+    #[default]
+    Lenient,
+    /// Fail recovery with [`Error::Corruption`](rustlite_core::Error::Corruption)
+    Strict,
+}
+
 /// Sync mode for WAL writes
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SyncMode {
@@ -71,6 +104,8 @@ pub enum SyncMode {
 pub struct WalManager {
     config: WalConfig,
     writer: Option<WalWriter>,
+    /// Syncs performed by writers that have since been closed
+    retired_sync_count: u64,
 }
 
 impl WalManager {
@@ -78,6 +113,7 @@ impl WalManager {
         Ok(Self {
             config,
             writer: None,
+            retired_sync_count: 0,
         })
     }
 
@@ -85,23 +121,105 @@ impl WalManager {
     ///
     /// This creates or opens the current WAL segment for appending records.
     pub fn open(&mut self) -> Result<()> {
-        let writer = WalWriter::new(
+        let writer = WalWriter::with_checksum(
             &self.config.wal_dir,
             self.config.max_segment_size,
             self.config.sync_mode,
+            self.config.write_buffer_bytes,
+            self.config.checksum,
         )?;
-        self.writer = Some(writer);
+        if let Some(old) = self.writer.replace(writer) {
+            self.retired_sync_count += old.sync_count();
+        }
 
         Ok(())
     }
 
     /// Append a record to the WAL
-    pub fn append(&mut self, record: WalRecord) -> Result<u64> {
+    ///
+    /// Fails if the record would take the WAL past `max_total_wal_size`.
+    /// Checkpoint records are always accepted, since they are what allows
+    /// older segments to be truncated.
+    pub fn append(&mut self, record: WalRecord) -> Result<AppendOutcome> {
+        self.append_ref(&record)
+    }
+
+    /// Append a record the caller keeps ownership of
+    ///
+    /// Behaves like [`append`](Self::append), for callers that still need
+    /// the record's data afterwards, such as a memtable insert.
+    pub fn append_ref(&mut self, record: &WalRecord) -> Result<AppendOutcome> {
+        if record.record_type != RecordType::Checkpoint && self.exceeds_size_limit(record)? {
+            return Err(rustlite_core::Error::Storage(format!(
+                "WAL size limit of {} bytes exceeded",
+                self.config.max_total_wal_size.unwrap_or_default()
+            )));
+        }
+
         let writer = self
             .writer
             .as_mut()
             .ok_or_else(|| rustlite_core::Error::InvalidOperation("WAL not opened".to_string()))?;
-        writer.append(record)
+        writer.append_ref(record)
+    }
+
+    /// Append a batch of records with a single sync
+    ///
+    /// Returns the LSN of each record, in order.
+    pub fn append_batch(&mut self, records: Vec<WalRecord>) -> Result<Vec<u64>> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| rustlite_core::Error::InvalidOperation("WAL not opened".to_string()))?;
+        writer.append_batch(records)
+    }
+
+    /// Combined size in bytes of all WAL segments
+    ///
+    /// The active segment is measured by the writer, so records still
+    /// buffered in memory are included.
+    pub fn total_size(&self) -> Result<u64> {
+        let Some(writer) = &self.writer else {
+            return self.segment_manager().total_size();
+        };
+
+        let closed: u64 = self
+            .segment_manager()
+            .list_segments()?
+            .iter()
+            .filter(|s| &s.path != writer.current_segment_path())
+            .map(|s| s.size)
+            .sum();
+        Ok(closed + writer.current_segment_size())
+    }
+
+    /// Whether appending `record` would take the WAL past
+    /// `max_total_wal_size`
+    pub fn exceeds_size_limit(&self, record: &WalRecord) -> Result<bool> {
+        self.batch_exceeds_size_limit(std::slice::from_ref(record))
+    }
+
+    /// Whether appending every record in `records` would take the WAL past
+    /// `max_total_wal_size`
+    pub fn batch_exceeds_size_limit(&self, records: &[WalRecord]) -> Result<bool> {
+        let Some(limit) = self.config.max_total_wal_size else {
+            return Ok(false);
+        };
+        let mut batch_size = 0u64;
+        for record in records {
+            batch_size += record.encoded_len_with(self.config.checksum)? as u64;
+        }
+        Ok(self.total_size()? + batch_size > limit)
+    }
+
+    /// Start a new active segment
+    ///
+    /// Lets `truncate_before` drop every segment written so far.
+    pub fn rotate(&mut self) -> Result<()> {
+        match &mut self.writer {
+            Some(writer) => writer.rotate_segment(),
+            None => Ok(()),
+        }
     }
 
     /// Sync the WAL to disk
@@ -117,10 +235,59 @@ impl WalManager {
     pub fn close(&mut self) -> Result<()> {
         if let Some(mut writer) = self.writer.take() {
             writer.sync()?;
+            self.retired_sync_count += writer.sync_count();
         }
         Ok(())
     }
 
+    /// Block until every record up to and including `lsn` is durable
+    ///
+    /// Cheaper than [`sync`](Self::sync) when the records were already
+    /// synced, which is common under `SyncMode::Async` and `SyncMode::None`
+    /// when several callers wait on nearby LSNs.
+    pub fn sync_through(&mut self, lsn: u64) -> Result<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| rustlite_core::Error::InvalidOperation("WAL not opened".to_string()))?;
+        writer.sync_through(lsn)
+    }
+
+    /// Highest LSN known to be durable, or 0 if the WAL is not open
+    pub fn durable_lsn(&self) -> u64 {
+        self.writer.as_ref().map_or(0, |w| w.durable_sequence())
+    }
+
+    /// Total number of WAL syncs to disk since this manager was created
+    pub fn sync_count(&self) -> u64 {
+        self.retired_sync_count + self.writer.as_ref().map_or(0, |w| w.sync_count())
+    }
+
+    /// Sequence number of the segment new records are appended to
+    ///
+    /// Returns `None` if the WAL is not open.
+    pub fn active_segment(&self) -> Option<u64> {
+        self.writer
+            .as_ref()
+            .and_then(|w| SegmentManager::sequence_from_path(w.current_segment_path()))
+    }
+
+    /// Remove segments older than the given segment sequence number
+    ///
+    /// Used after a checkpoint to drop segments whose records are already
+    /// persisted elsewhere. If `archive_dir` is configured the segments are
+    /// moved there instead of being deleted. The active segment is never
+    /// removed. Returns the number of segments removed.
+    pub fn truncate_before(&self, sequence: u64) -> Result<usize> {
+        let sequence = self.active_segment().map_or(sequence, |a| sequence.min(a));
+
+        let segments = self.segment_manager();
+        match &self.config.archive_dir {
+            Some(archive_dir) => segments.archive_before(sequence, archive_dir),
+            None => segments.cleanup_before(sequence),
+        }
+    }
+
     /// Recover records from the WAL
     ///
     /// This reads all segments and returns committed records for replay.
@@ -130,6 +297,15 @@ impl WalManager {
         recovery.recover()
     }
 
+    /// Recover records written after the newest checkpoint at or below
+    /// `flushed_sequence`
+    ///
+    /// See [`RecoveryManager::recover_since_checkpoint`].
+    pub fn recover_since_checkpoint(&self, flushed_sequence: u64) -> Result<Vec<WalRecord>> {
+        let recovery = RecoveryManager::new(self.config.clone())?;
+        recovery.recover_since_checkpoint(flushed_sequence)
+    }
+
     /// Recover records with transaction markers included
     ///
     /// Unlike `recover()`, this includes BEGIN_TX and COMMIT_TX markers.
@@ -145,7 +321,14 @@ impl WalManager {
     }
 
     /// Create a reader for the WAL
+    ///
+    /// Records still buffered by the writer are first flushed to the OS (not
+    /// fsynced), so the reader sees everything appended so far, whatever
+    /// the sync mode.
     pub fn reader(&self) -> Result<WalReader> {
+        if let Some(writer) = &self.writer {
+            writer.flush()?;
+        }
         WalReader::new(&self.config.wal_dir)
     }
 
@@ -179,6 +362,11 @@ mod tests {
             wal_dir: wal_path,
             sync_mode: SyncMode::Sync,
             max_segment_size: 64 * 1024 * 1024,
+            archive_dir: None,
+            max_total_wal_size: None,
+            write_buffer_bytes: writer::DEFAULT_WRITE_BUFFER_SIZE,
+            recovery_policy: RecoveryPolicy::Lenient,
+            checksum: ChecksumAlgorithm::Crc32,
         };
 
         (temp_dir, config)
@@ -211,6 +399,98 @@ mod tests {
         assert!(!manager.is_open());
     }
 
+    #[test]
+    fn test_wal_manager_sync_count() {
+        let (_temp_dir, config) = setup_test_config();
+
+        let mut manager = WalManager::new(config).expect("Failed to create manager");
+        manager.open().expect("Failed to open");
+
+        // SyncMode::Sync syncs on every append
+        for i in 0..3 {
+            let record = WalRecord::put(format!("key{}", i).into_bytes(), b"value".to_vec());
+            manager.append(record).expect("Failed to append");
+        }
+        assert_eq!(manager.sync_count(), 3);
+
+        manager.sync().expect("Failed to sync");
+        manager.close().expect("Failed to close");
+        assert_eq!(manager.sync_count(), 5);
+    }
+
+    #[test]
+    fn test_wal_manager_truncate_to_archive() {
+        let (temp_dir, mut config) = setup_test_config();
+        let archive_dir = temp_dir.path().join("archive");
+        config.max_segment_size = 100;
+        config.archive_dir = Some(archive_dir.clone());
+
+        let mut manager = WalManager::new(config.clone()).expect("Failed to create manager");
+        manager.open().expect("Failed to open");
+
+        let mut original = Vec::new();
+        for i in 0..10 {
+            let record = WalRecord::put(
+                format!("key{}", i).into_bytes(),
+                format!("value{}", i).into_bytes(),
+            );
+            original.push(record.clone());
+            manager.append(record).expect("Failed to append");
+        }
+
+        let latest = manager
+            .segment_manager()
+            .latest_segment()
+            .unwrap()
+            .expect("Expected a segment");
+        let total = manager.segment_manager().segment_count().unwrap();
+        assert!(total > 1);
+
+        let archived = manager.truncate_before(latest.sequence).unwrap();
+        assert_eq!(archived, total - 1);
+        assert_eq!(manager.segment_manager().segment_count().unwrap(), 1);
+        assert_eq!(
+            SegmentManager::new(archive_dir.clone())
+                .segment_count()
+                .unwrap(),
+            archived
+        );
+
+        // Archive followed by the live WAL yields the original records
+        let recovery = RecoveryManager::new(config).unwrap();
+        let mut replayed = recovery.replay_archive(&archive_dir).unwrap();
+        replayed.extend(manager.recover().unwrap());
+        assert_eq!(replayed, original);
+    }
+
+    #[test]
+    fn test_wal_manager_size_limit() {
+        let (_temp_dir, mut config) = setup_test_config();
+        config.max_segment_size = 200;
+        config.max_total_wal_size = Some(1000);
+
+        let mut manager = WalManager::new(config).expect("Failed to create manager");
+        manager.open().expect("Failed to open");
+
+        let record = WalRecord::put(b"key".to_vec(), vec![0u8; 50]);
+        let mut appended = 0;
+        while manager.append(record.clone()).is_ok() {
+            appended += 1;
+        }
+        assert!(appended > 0);
+        assert!(manager.exceeds_size_limit(&record).unwrap());
+        assert!(manager.total_size().unwrap() <= 1000);
+
+        // Checkpoints still go through so the WAL can be truncated
+        manager.append(WalRecord::checkpoint(1)).unwrap();
+
+        manager.rotate().unwrap();
+        let active = manager.segment_manager().latest_segment().unwrap().unwrap();
+        manager.truncate_before(active.sequence).unwrap();
+        assert!(!manager.exceeds_size_limit(&record).unwrap());
+        manager.append(record).unwrap();
+    }
+
     #[test]
     fn test_wal_manager_write_and_recover() {
         let (_temp_dir, config) = setup_test_config();
@@ -241,6 +521,124 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_wal_manager_append_batch() {
+        let (_temp_dir, config) = setup_test_config();
+
+        let mut manager = WalManager::new(config.clone()).expect("Failed to create manager");
+        manager.open().expect("Failed to open");
+        manager
+            .append(WalRecord::put(b"first".to_vec(), b"value".to_vec()))
+            .expect("Failed to append");
+
+        let batch: Vec<WalRecord> = (0..5)
+            .map(|i| WalRecord::put(format!("key{}", i).into_bytes(), b"value".to_vec()))
+            .collect();
+        let lsns = manager
+            .append_batch(batch.clone())
+            .expect("Failed to append batch");
+
+        assert_eq!(lsns.len(), 5);
+        assert!(lsns.windows(2).all(|w| w[0] < w[1]));
+        // One sync for the single append and one for the whole batch
+        assert_eq!(manager.sync_count(), 2);
+        manager.close().expect("Failed to close");
+
+        let records = manager.recover().expect("Failed to recover");
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[1..], batch[..]);
+    }
+
+    #[test]
+    fn test_wal_manager_sync_through() {
+        let (_temp_dir, mut config) = setup_test_config();
+        config.sync_mode = SyncMode::Async;
+
+        let mut manager = WalManager::new(config.clone()).expect("Failed to create WAL manager");
+        manager.open().expect("Failed to open WAL");
+        let record = |i: usize| WalRecord::put(format!("key{}", i).into_bytes(), b"v".to_vec());
+        let lsns: Vec<u64> = (0..5)
+            .map(|i| manager.append(record(i)).expect("Failed to append").lsn)
+            .collect();
+        assert_eq!(manager.sync_count(), 0);
+
+        manager.sync_through(lsns[2]).expect("Failed to sync");
+        assert!(manager.durable_lsn() >= lsns[2]);
+        assert_eq!(manager.sync_count(), 1);
+
+        // Already durable, so no further sync
+        manager.sync_through(lsns[1]).expect("Failed to sync");
+        assert_eq!(manager.sync_count(), 1);
+        assert!(manager.sync_through(lsns[4] + 1).is_err());
+
+        // Crash without closing the WAL
+        std::mem::forget(manager);
+
+        let mut manager = WalManager::new(config).expect("Failed to create WAL manager");
+        let records = manager.recover().expect("Failed to recover");
+        assert!(records.len() >= 3);
+        for (i, r) in records.iter().take(3).enumerate() {
+            assert_eq!(*r, record(i));
+        }
+        manager.open().expect("Failed to open WAL");
+    }
+
+    #[test]
+    fn test_wal_manager_reader_sees_unsynced_records() {
+        let (_temp_dir, mut config) = setup_test_config();
+        config.sync_mode = SyncMode::None;
+
+        let mut manager = WalManager::new(config).expect("Failed to create WAL manager");
+        manager.open().expect("Failed to open WAL");
+        for i in 0..3 {
+            manager
+                .append(WalRecord::put(
+                    format!("key{}", i).into_bytes(),
+                    b"value".to_vec(),
+                ))
+                .expect("Failed to append");
+        }
+        assert_eq!(manager.sync_count(), 0);
+
+        let records = manager
+            .reader()
+            .expect("Failed to create reader")
+            .read_all()
+            .expect("Failed to read");
+        assert_eq!(records.len(), 3);
+        // Flushing for the reader is not a sync
+        assert_eq!(manager.sync_count(), 0);
+    }
+
+    #[test]
+    fn test_wal_manager_record_timestamps() {
+        let (_temp_dir, config) = setup_test_config();
+
+        let mut manager = WalManager::new(config).expect("Failed to create WAL manager");
+        manager.open().expect("Failed to open WAL");
+        for i in 0..5 {
+            manager
+                .append(WalRecord::put(
+                    format!("key{}", i).into_bytes(),
+                    b"value".to_vec(),
+                ))
+                .expect("Failed to append");
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        manager.close().expect("Failed to close");
+
+        let records = manager.recover().expect("Failed to recover");
+        assert_eq!(records.len(), 5);
+        assert!(records.iter().all(|r| r.timestamp_ms() > 0));
+        assert!(records
+            .windows(2)
+            .all(|w| w[0].timestamp_ms() < w[1].timestamp_ms()));
+
+        let stats = manager.stats().expect("Failed to get stats");
+        assert_eq!(stats.oldest_timestamp_ms(), Some(records[0].timestamp_ms()));
+        assert_eq!(stats.newest_timestamp_ms(), Some(records[4].timestamp_ms()));
+    }
+
     #[test]
     fn test_wal_manager_stats() {
         let (_temp_dir, config) = setup_test_config();
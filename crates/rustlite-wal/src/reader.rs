@@ -5,13 +5,64 @@
 // 2. Record reading - iterating through records in each segment
 // 3. CRC validation - verifying data integrity of each record
 
+use crate::checksum::ChecksumAlgorithm;
 use crate::record::WalRecord;
+use crate::segment::SegmentManager;
 use crate::writer::WalHeader;
 use rustlite_core::{Error, Result};
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
-use tracing::debug;
+use tracing::{debug, warn};
+
+/// Position of a record in the WAL
+///
+/// Identifies a segment by its sequence number (from `wal-{seq}.log`) and a
+/// byte offset within that segment. Positions obtained from
+/// [`WalReader::position`] always fall on a record boundary and can be
+/// passed to [`WalReader::open_from`] to resume reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WalPosition {
+    /// Sequence number of the segment
+    pub segment: u64,
+    /// Byte offset within the segment
+    pub offset: u64,
+}
+
+/// Outcome of reading the next record with [`WalReader::next_outcome`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadOutcome {
+    /// A complete record that passed CRC validation
+    Record(WalRecord),
+    /// The last segment ends with a partially written record, typically a
+    /// write torn by a crash. The position is where the partial record
+    /// starts; every record before it is intact.
+    TornTail(WalPosition),
+    /// There are no more records
+    End,
+}
+
+/// Outcome of reading the next undecoded frame with
+/// [`WalReader::next_frame`]
+pub(crate) enum FrameOutcome {
+    /// A complete record frame, the position it starts at and the checksum
+    /// algorithm of its segment
+    Frame(WalPosition, ChecksumAlgorithm, Vec<u8>),
+    /// See [`ReadOutcome::TornTail`]
+    TornTail(WalPosition),
+    /// There are no more records
+    End,
+}
+
+/// Result of reading one record frame from a segment
+enum FrameRead {
+    /// The bytes of a complete frame, CRC included
+    Frame(Vec<u8>),
+    /// Clean end of segment
+    Eof,
+    /// Fewer bytes remain than the record frame requires
+    Torn,
+}
 
 /// WAL reader for reading records from log segments
 pub struct WalReader {
@@ -23,6 +74,10 @@ pub struct WalReader {
     reader: Option<BufReader<File>>,
     /// Current byte offset within segment
     current_offset: u64,
+    /// Length of the current segment file
+    segment_len: u64,
+    /// Checksum algorithm of the current segment's records
+    checksum: ChecksumAlgorithm,
 }
 
 impl WalReader {
@@ -35,6 +90,8 @@ impl WalReader {
             current_segment_index: 0,
             reader: None,
             current_offset: 0,
+            segment_len: 0,
+            checksum: ChecksumAlgorithm::default(),
         };
 
         // Open first segment if available
@@ -45,6 +102,102 @@ impl WalReader {
         Ok(reader)
     }
 
+    /// Create a WAL reader positioned at a record boundary
+    ///
+    /// `segment` is the sequence number of a segment file and `offset` the
+    /// byte offset within it, typically taken from [`WalReader::position`]
+    /// after an earlier read. Records before the position are skipped
+    /// without being decoded. Returns an error if the segment does not exist
+    /// or the offset is not the start of a record (or the end of the
+    /// segment).
+    pub fn open_from(wal_dir: &Path, segment: u64, offset: u64) -> Result<Self> {
+        let mut reader = Self::new(wal_dir)?;
+        reader.seek_to(WalPosition { segment, offset })?;
+        Ok(reader)
+    }
+
+    /// Position of the next record to be read
+    ///
+    /// Returns `None` if there are no segments to read.
+    pub fn position(&self) -> Option<WalPosition> {
+        let path = self.segments.get(self.current_segment_index)?;
+        Some(WalPosition {
+            segment: SegmentManager::sequence_from_path(path)?,
+            offset: self.current_offset,
+        })
+    }
+
+    /// Move the reader to a record boundary
+    ///
+    /// See [`WalReader::open_from`] for the validation performed.
+    pub fn seek_to(&mut self, position: WalPosition) -> Result<()> {
+        let index = self
+            .segments
+            .iter()
+            .position(|p| SegmentManager::sequence_from_path(p) == Some(position.segment))
+            .ok_or_else(|| {
+                Error::InvalidInput(format!("WAL segment {:016x} not found", position.segment))
+            })?;
+
+        self.open_segment(index)?;
+        if position.offset < self.current_offset {
+            return Err(Error::InvalidInput(format!(
+                "WAL offset {} is inside the segment header",
+                position.offset
+            )));
+        }
+
+        let reader = self
+            .reader
+            .as_mut()
+            .ok_or_else(|| Error::Storage("WAL segment not open".to_string()))?;
+        let segment_len = self.segment_len;
+        if position.offset > segment_len {
+            return Err(Error::InvalidInput(format!(
+                "WAL offset {} is past the end of segment {:016x}",
+                position.offset, position.segment
+            )));
+        }
+
+        // Validate that a complete record starts at the offset by decoding
+        // it, then rewind so it is returned by the next read
+        reader.seek(SeekFrom::Start(position.offset))?;
+        let remaining = segment_len - position.offset;
+        let is_record = match Self::read_frame(reader, remaining, self.checksum) {
+            Ok(FrameRead::Frame(frame)) => WalRecord::decode_with(&frame, self.checksum).is_ok(),
+            _ => false,
+        };
+        if remaining > 0 && !is_record {
+            return Err(Error::InvalidInput(format!(
+                "WAL offset {} is not at a record boundary",
+                position.offset
+            )));
+        }
+        reader.seek(SeekFrom::Start(position.offset))?;
+        self.current_offset = position.offset;
+
+        Ok(())
+    }
+
+    /// Count the complete records in a single segment file
+    pub(crate) fn count_segment_records(path: &Path) -> Result<usize> {
+        let mut reader = Self {
+            segments: vec![path.to_path_buf()],
+            current_segment_index: 0,
+            reader: None,
+            current_offset: 0,
+            segment_len: 0,
+            checksum: ChecksumAlgorithm::default(),
+        };
+        reader.open_segment(0)?;
+
+        let mut count = 0;
+        while reader.next_record()?.is_some() {
+            count += 1;
+        }
+        Ok(count)
+    }
+
     /// Discover and sort all WAL segment files in the directory
     fn discover_segments(wal_dir: &Path) -> Result<Vec<PathBuf>> {
         if !wal_dir.exists() {
@@ -74,18 +227,23 @@ impl WalReader {
         let file = File::open(path)
             .map_err(|e| Error::Storage(format!("Failed to open segment {:?}: {}", path, e)))?;
 
+        let segment_len = file
+            .metadata()
+            .map_err(|e| Error::Storage(format!("Failed to stat segment {:?}: {}", path, e)))?
+            .len();
         let mut reader = BufReader::new(file);
 
         // Try to read header (v1.0+)
         // If header is missing or invalid, assume legacy format (v0.x)
-        let header_offset = match WalHeader::read_from(&mut reader) {
+        let (header_offset, checksum) = match WalHeader::read_from(&mut reader) {
             Ok(header) => {
                 debug!(
                     segment = ?path,
                     version = header.version,
+                    checksum = ?header.checksum,
                     "Opened WAL segment with header"
                 );
-                WalHeader::SIZE as u64
+                (header.encoded_len() as u64, header.checksum)
             }
             Err(_) => {
                 // No valid header, must be legacy format - reopen to reset position
@@ -94,13 +252,15 @@ impl WalReader {
                 })?;
                 reader = BufReader::new(file);
                 debug!(segment = ?path, "Opened legacy WAL segment (pre-v1.0)");
-                0
+                (0, ChecksumAlgorithm::Crc32)
             }
         };
 
         self.reader = Some(reader);
         self.current_segment_index = index;
         self.current_offset = header_offset;
+        self.segment_len = segment_len;
+        self.checksum = checksum;
 
         Ok(())
     }
@@ -122,61 +282,98 @@ impl WalReader {
     /// Returns `Ok(Some(record))` if a record was read successfully,
     /// `Ok(None)` if we've reached the end of all segments,
     /// or an error if reading/parsing failed.
+    ///
+    /// A torn record at the end of the last segment is treated as the end of
+    /// the log; use [`next_outcome`](Self::next_outcome) to detect it.
     pub fn next_record(&mut self) -> Result<Option<WalRecord>> {
+        match self.next_outcome()? {
+            ReadOutcome::Record(record) => Ok(Some(record)),
+            ReadOutcome::TornTail(_) | ReadOutcome::End => Ok(None),
+        }
+    }
+
+    /// Read the next record, reporting a torn trailing record explicitly
+    ///
+    /// A partial record is detected structurally: fewer bytes remain in the
+    /// segment than its length field (or the length field itself) needs. At
+    /// the end of the last segment this yields [`ReadOutcome::TornTail`] and
+    /// the reader stops. A partial record at the end of an earlier segment
+    /// is skipped and reading continues with the next segment.
+    pub fn next_outcome(&mut self) -> Result<ReadOutcome> {
+        match self.next_frame()? {
+            FrameOutcome::Frame(_, checksum, frame) => {
+                // Decode record (includes CRC validation)
+                let (record, _) = WalRecord::decode_with(&frame, checksum)?;
+                Ok(ReadOutcome::Record(record))
+            }
+            FrameOutcome::TornTail(position) => Ok(ReadOutcome::TornTail(position)),
+            FrameOutcome::End => Ok(ReadOutcome::End),
+        }
+    }
+
+    /// Read the next record frame without decoding it
+    ///
+    /// Segment boundaries and torn records are handled as in
+    /// [`next_outcome`](Self::next_outcome).
+    pub(crate) fn next_frame(&mut self) -> Result<FrameOutcome> {
         loop {
+            let position = Self::position(self);
             let reader = match &mut self.reader {
                 Some(r) => r,
-                None => return Ok(None), // No more segments
+                None => return Ok(FrameOutcome::End), // No more segments
             };
 
-            // Try to read a record from current segment
-            match Self::read_record(reader) {
-                Ok(Some((record, bytes_read))) => {
-                    self.current_offset += bytes_read as u64;
-                    return Ok(Some(record));
+            // Try to read a frame from current segment
+            let remaining = self.segment_len.saturating_sub(self.current_offset);
+            match Self::read_frame(reader, remaining, self.checksum)? {
+                FrameRead::Frame(frame) => {
+                    self.current_offset += frame.len() as u64;
+                    let position = position
+                        .ok_or_else(|| Error::Storage("WAL segment not open".to_string()))?;
+                    return Ok(FrameOutcome::Frame(position, self.checksum, frame));
                 }
-                Ok(None) => {
+                FrameRead::Eof => {
                     // End of current segment, try next
                     if !self.advance_segment()? {
-                        return Ok(None);
+                        return Ok(FrameOutcome::End);
                     }
                     // Continue loop to read from new segment
                 }
-                Err(e) => {
-                    // Check if this is an incomplete record at end of file
-                    // (possible crash during write)
-                    if Self::is_truncation_error(&e) {
-                        // Try to advance to next segment
-                        if !self.advance_segment()? {
-                            return Ok(None);
+                FrameRead::Torn => {
+                    if self.current_segment_index + 1 >= self.segments.len() {
+                        self.reader = None;
+                        if let Some(position) = position {
+                            return Ok(FrameOutcome::TornTail(position));
                         }
-                        // Continue loop to read from new segment
-                    } else {
-                        return Err(e);
+                        return Ok(FrameOutcome::End);
                     }
+
+                    warn!(?position, "Skipping partial record at end of WAL segment");
+                    self.advance_segment()?;
                 }
             }
         }
     }
 
-    /// Read a single record from a reader
-    ///
-    /// Returns the record and number of bytes consumed
-    fn read_record(reader: &mut BufReader<File>) -> Result<Option<(WalRecord, usize)>> {
+    /// Read a single record frame from a reader with `remaining` bytes left
+    /// in the segment, whose records end with a `checksum` checksum
+    fn read_frame(
+        reader: &mut BufReader<File>,
+        remaining: u64,
+        checksum: ChecksumAlgorithm,
+    ) -> Result<FrameRead> {
+        if remaining == 0 {
+            return Ok(FrameRead::Eof);
+        }
+        if remaining < 4 {
+            return Ok(FrameRead::Torn);
+        }
+
         // Read length field (4 bytes)
         let mut len_buf = [0u8; 4];
-        match reader.read_exact(&mut len_buf) {
-            Ok(()) => {}
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                return Ok(None); // End of file
-            }
-            Err(e) => {
-                return Err(Error::Storage(format!(
-                    "Failed to read record length: {}",
-                    e
-                )));
-            }
-        }
+        reader
+            .read_exact(&mut len_buf)
+            .map_err(|e| Error::Storage(format!("Failed to read record length: {}", e)))?;
 
         let content_len = u32::from_le_bytes(len_buf) as usize;
 
@@ -189,34 +386,22 @@ impl WalReader {
         }
 
         // Read content (type + payload) and CRC
-        let total_data_len = content_len + 4; // content + crc
-        let mut data = vec![0u8; total_data_len];
+        let total_data_len = content_len + checksum.size(); // content + crc
+        if remaining < 4 + total_data_len as u64 {
+            return Ok(FrameRead::Torn);
+        }
 
-        reader.read_exact(&mut data).map_err(|e| {
-            if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                Error::Serialization("Incomplete record: truncated".to_string())
-            } else {
-                Error::Storage(format!("Failed to read record data: {}", e))
-            }
-        })?;
+        let mut data = vec![0u8; total_data_len];
+        reader
+            .read_exact(&mut data)
+            .map_err(|e| Error::Storage(format!("Failed to read record data: {}", e)))?;
 
         // Build full frame for decoding
         let mut frame = Vec::with_capacity(4 + total_data_len);
         frame.extend_from_slice(&len_buf);
         frame.extend_from_slice(&data);
 
-        // Decode record (includes CRC validation)
-        let (record, bytes_consumed) = WalRecord::decode(&frame)?;
-
-        Ok(Some((record, bytes_consumed)))
-    }
-
-    /// Check if an error indicates a truncated/incomplete record
-    fn is_truncation_error(err: &Error) -> bool {
-        match err {
-            Error::Serialization(msg) => msg.contains("Incomplete") || msg.contains("truncated"),
-            _ => false,
-        }
+        Ok(FrameRead::Frame(frame))
     }
 
     /// Get the number of segments discovered
@@ -381,6 +566,46 @@ mod tests {
         assert!(reader.segment_count() > 1, "Expected multiple segments");
     }
 
+    #[test]
+    fn test_open_from_position() {
+        let (_temp_dir, wal_path) = setup_test_wal();
+
+        // Small segments so the saved position is not in the first segment
+        {
+            let mut writer =
+                WalWriter::new(&wal_path, 100, SyncMode::Sync).expect("Failed to create writer");
+            for i in 0..20 {
+                let record = WalRecord::put(
+                    format!("key{}", i).into_bytes(),
+                    format!("value{}", i).into_bytes(),
+                );
+                writer.append(record).expect("Failed to append");
+            }
+        }
+
+        let mut reader = WalReader::new(&wal_path).expect("Failed to create reader");
+        for _ in 0..12 {
+            reader.next_record().unwrap().expect("Expected record");
+        }
+        let position = reader.position().expect("Expected position");
+        assert!(reader.current_segment() > 0);
+        let expected = reader.read_all().unwrap();
+        assert_eq!(expected.len(), 8);
+
+        let mut resumed = WalReader::open_from(&wal_path, position.segment, position.offset)
+            .expect("Failed to open from position");
+        let records = resumed.read_all().unwrap();
+        assert_eq!(records, expected);
+        assert_eq!(
+            records[0],
+            WalRecord::put(b"key12".to_vec(), b"value12".to_vec())
+        );
+
+        // Offsets that are not record boundaries are rejected
+        assert!(WalReader::open_from(&wal_path, position.segment, position.offset + 1).is_err());
+        assert!(WalReader::open_from(&wal_path, 0xdead, 0).is_err());
+    }
+
     #[test]
     fn test_reader_reset() {
         let (_temp_dir, wal_path) = setup_test_wal();
@@ -1,7 +1,14 @@
 // WAL record format and encoding/decoding
 //
 // Record format (binary):
-// [length: u32 LE] [type: u8] [payload bytes] [crc32: u32 LE]
+// [length: u32 LE] [type: u8] [timestamp_ms: u64 LE]? [payload bytes] [crc: u32 or u64 LE]
+//
+// The CRC is CRC32 (4 bytes) unless the segment header names another
+// algorithm.
+//
+// The timestamp is present when the high bit of the type byte is set.
+// Records written before timestamps were introduced have no timestamp and
+// decode with `timestamp_ms` 0.
 //
 // Types:
 // - PUT (1): key-value insert/update
@@ -9,11 +16,19 @@
 // - BEGIN_TX (3): transaction start marker
 // - COMMIT_TX (4): transaction commit marker
 // - CHECKPOINT (5): checkpoint marker
+// - MERGE (6): merge operand for a key
+// - DELETE_RANGE (7): deletion of all keys in [start, end)
+// - CREATE_INDEX (8): index definition
+// - DROP_INDEX (9): index removal
 
-use crc32fast::Hasher;
+use crate::checksum::ChecksumAlgorithm;
+use rustlite_core::index::IndexType;
 use rustlite_core::{Error, Result};
 use serde::{Deserialize, Serialize};
 
+/// Type byte flag marking a record that carries a timestamp
+const TIMESTAMP_FLAG: u8 = 0x80;
+
 /// WAL record types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
@@ -23,6 +38,10 @@ pub enum RecordType {
     BeginTx = 3,
     CommitTx = 4,
     Checkpoint = 5,
+    Merge = 6,
+    DeleteRange = 7,
+    CreateIndex = 8,
+    DropIndex = 9,
 }
 
 impl TryFrom<u8> for RecordType {
@@ -35,6 +54,10 @@ impl TryFrom<u8> for RecordType {
             3 => Ok(RecordType::BeginTx),
             4 => Ok(RecordType::CommitTx),
             5 => Ok(RecordType::Checkpoint),
+            6 => Ok(RecordType::Merge),
+            7 => Ok(RecordType::DeleteRange),
+            8 => Ok(RecordType::CreateIndex),
+            9 => Ok(RecordType::DropIndex),
             _ => Err(Error::InvalidOperation(format!(
                 "Unknown WAL record type: {}",
                 value
@@ -51,13 +74,27 @@ pub enum RecordPayload {
     BeginTx { tx_id: u64 },
     CommitTx { tx_id: u64 },
     Checkpoint { sequence: u64 },
+    Merge { key: Vec<u8>, operand: Vec<u8> },
+    DeleteRange { start: Vec<u8>, end: Vec<u8> },
+    CreateIndex { name: String, index_type: IndexType },
+    DropIndex { name: String },
 }
 
 /// A WAL record
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Records compare equal when their type and payload match; the append
+/// timestamp is not compared.
+#[derive(Debug, Clone, Eq)]
 pub struct WalRecord {
     pub record_type: RecordType,
     pub payload: RecordPayload,
+    timestamp_ms: u64,
+}
+
+impl PartialEq for WalRecord {
+    fn eq(&self, other: &Self) -> bool {
+        self.record_type == other.record_type && self.payload == other.payload
+    }
 }
 
 impl WalRecord {
@@ -70,6 +107,13 @@ impl WalRecord {
             RecordType::BeginTx => Self::begin_tx(0), // Default tx_id
             RecordType::CommitTx => Self::commit_tx(0),
             RecordType::Checkpoint => Self::checkpoint(0),
+            RecordType::Merge => Self::merge(key, value),
+            RecordType::DeleteRange => Self::delete_range(key, value),
+            RecordType::CreateIndex => Self::create_index(
+                String::from_utf8_lossy(&key).into_owned(),
+                IndexType::BTree, // Default index type
+            ),
+            RecordType::DropIndex => Self::drop_index(String::from_utf8_lossy(&key).into_owned()),
         }
     }
 
@@ -78,6 +122,7 @@ impl WalRecord {
         Self {
             record_type: RecordType::Put,
             payload: RecordPayload::Put { key, value },
+            timestamp_ms: 0,
         }
     }
 
@@ -86,6 +131,7 @@ impl WalRecord {
         Self {
             record_type: RecordType::Delete,
             payload: RecordPayload::Delete { key },
+            timestamp_ms: 0,
         }
     }
 
@@ -94,6 +140,7 @@ impl WalRecord {
         Self {
             record_type: RecordType::BeginTx,
             payload: RecordPayload::BeginTx { tx_id },
+            timestamp_ms: 0,
         }
     }
 
@@ -102,49 +149,183 @@ impl WalRecord {
         Self {
             record_type: RecordType::CommitTx,
             payload: RecordPayload::CommitTx { tx_id },
+            timestamp_ms: 0,
         }
     }
 
     /// Create a CHECKPOINT record
+    ///
+    /// `sequence` is the last sequence whose data is durably flushed once the
+    /// checkpoint takes effect; see `RecoveryManager::recover_since_checkpoint`.
     pub fn checkpoint(sequence: u64) -> Self {
         Self {
             record_type: RecordType::Checkpoint,
             payload: RecordPayload::Checkpoint { sequence },
+            timestamp_ms: 0,
         }
     }
 
+    /// Create a MERGE record
+    pub fn merge(key: Vec<u8>, operand: Vec<u8>) -> Self {
+        Self {
+            record_type: RecordType::Merge,
+            payload: RecordPayload::Merge { key, operand },
+            timestamp_ms: 0,
+        }
+    }
+
+    /// Create a DELETE_RANGE record covering keys in [start, end)
+    pub fn delete_range(start: Vec<u8>, end: Vec<u8>) -> Self {
+        Self {
+            record_type: RecordType::DeleteRange,
+            payload: RecordPayload::DeleteRange { start, end },
+            timestamp_ms: 0,
+        }
+    }
+
+    /// Create a CREATE_INDEX record defining index `name`
+    pub fn create_index(name: String, index_type: IndexType) -> Self {
+        Self {
+            record_type: RecordType::CreateIndex,
+            payload: RecordPayload::CreateIndex { name, index_type },
+            timestamp_ms: 0,
+        }
+    }
+
+    /// Create a DROP_INDEX record removing index `name`
+    pub fn drop_index(name: String) -> Self {
+        Self {
+            record_type: RecordType::DropIndex,
+            payload: RecordPayload::DropIndex { name },
+            timestamp_ms: 0,
+        }
+    }
+
+    /// Returns the record with its timestamp set
+    pub fn with_timestamp(mut self, timestamp_ms: u64) -> Self {
+        self.timestamp_ms = timestamp_ms;
+        self
+    }
+
+    /// Wall-clock time in milliseconds since the UNIX epoch when the record
+    /// was appended, or 0 if unknown. Set by the WAL writer.
+    pub fn timestamp_ms(&self) -> u64 {
+        self.timestamp_ms
+    }
+
     /// Encode record to bytes with framing and CRC
-    /// Format: [length: u32 LE] [type: u8] [payload bytes] [crc32: u32 LE]
+    /// Format: [length: u32 LE] [type: u8] [timestamp_ms: u64 LE]? [payload bytes] [crc32: u32 LE]
     pub fn encode(&self) -> Result<Vec<u8>> {
-        // Serialize payload
-        let payload_bytes = bincode::serialize(&self.payload)
-            .map_err(|e| Error::Serialization(format!("Failed to serialize payload: {}", e)))?;
+        self.encode_with_timestamp(self.timestamp_ms, ChecksumAlgorithm::Crc32)
+    }
 
-        let type_byte = self.record_type as u8;
+    /// Size in bytes of the record as the WAL writer frames it, without
+    /// encoding it
+    ///
+    /// The writer stamps every record it appends, so the timestamp is
+    /// always counted, even if this record's own timestamp is still unset.
+    pub fn encoded_len(&self) -> Result<usize> {
+        self.encoded_len_with(ChecksumAlgorithm::Crc32)
+    }
 
-        // Calculate length (type byte + payload)
-        let content_len = 1 + payload_bytes.len();
+    /// Like [`encoded_len`](Self::encoded_len), for a segment whose records
+    /// end with a `checksum` checksum
+    pub fn encoded_len_with(&self, checksum: ChecksumAlgorithm) -> Result<usize> {
+        Ok(4 + 1 + 8 + self.payload_len()? + checksum.size())
+    }
 
-        // Calculate CRC over type + payload
-        let mut hasher = Hasher::new();
-        hasher.update(&[type_byte]);
-        hasher.update(&payload_bytes);
-        let crc = hasher.finalize();
+    /// Serialized size of the payload
+    fn payload_len(&self) -> Result<usize> {
+        bincode::serialized_size(&self.payload)
+            .map(|len| len as usize)
+            .map_err(|e| Error::Serialization(format!("Failed to size payload: {}", e)))
+    }
 
-        // Build frame: [length][type][payload][crc]
-        let mut frame = Vec::with_capacity(4 + content_len + 4);
+    /// Encode the record as if its timestamp were `timestamp_ms`, ending
+    /// the frame with a `checksum` checksum
+    ///
+    /// Lets the writer stamp a borrowed record without cloning it. The
+    /// payload is serialized straight into the frame, so encoding makes a
+    /// single allocation.
+    pub(crate) fn encode_with_timestamp(
+        &self,
+        timestamp_ms: u64,
+        checksum: ChecksumAlgorithm,
+    ) -> Result<Vec<u8>> {
+        // A zero timestamp is left out, matching the pre-timestamp format
+        let timestamp = (timestamp_ms != 0).then(|| timestamp_ms.to_le_bytes());
+        let type_byte = match timestamp {
+            Some(_) => self.record_type as u8 | TIMESTAMP_FLAG,
+            None => self.record_type as u8,
+        };
+        let timestamp: &[u8] = timestamp.as_ref().map_or(&[], |t| t.as_slice());
+
+        // Calculate length (type byte + timestamp + payload)
+        let content_len = 1 + timestamp.len() + self.payload_len()?;
+
+        // Build frame: [length][type][timestamp][payload][crc]
+        let mut frame = Vec::with_capacity(4 + content_len + checksum.size());
         frame.extend_from_slice(&(content_len as u32).to_le_bytes());
         frame.push(type_byte);
-        frame.extend_from_slice(&payload_bytes);
-        frame.extend_from_slice(&crc.to_le_bytes());
+        frame.extend_from_slice(timestamp);
+        bincode::serialize_into(&mut frame, &self.payload)
+            .map_err(|e| Error::Serialization(format!("Failed to serialize payload: {}", e)))?;
+
+        // CRC over type + timestamp + payload
+        let crc = checksum.checksum(&frame[4..]);
+        frame.extend_from_slice(&checksum.encode(crc));
 
         Ok(frame)
     }
 
     /// Decode record from bytes with validation
+    ///
+    /// The CRC is checked before anything inside the frame is interpreted,
+    /// so a damaged record is always reported as a CRC mismatch rather than
+    /// as whatever garbage its type byte or payload happen to decode to.
     pub fn decode(data: &[u8]) -> Result<(Self, usize)> {
-        if data.len() < 9 {
-            // Minimum: 4 (length) + 1 (type) + 0 (payload) + 4 (crc)
+        Self::decode_with(data, ChecksumAlgorithm::Crc32)
+    }
+
+    /// Like [`decode`](Self::decode), for a frame that ends with a
+    /// `checksum` checksum
+    pub fn decode_with(data: &[u8], checksum: ChecksumAlgorithm) -> Result<(Self, usize)> {
+        let (content, expected_crc, total_size) = Self::split_frame(data, checksum)?;
+        let actual_crc = checksum.checksum(content);
+        if actual_crc != expected_crc {
+            return Err(Error::Storage(format!(
+                "CRC mismatch: expected {}, got {}",
+                expected_crc, actual_crc
+            )));
+        }
+        Ok((Self::decode_content(content)?, total_size))
+    }
+
+    /// Decode a record without rejecting a CRC mismatch
+    ///
+    /// Returns the record, the number of bytes it occupied and its stored
+    /// and computed checksums. Only meant for inspecting damaged records
+    /// whose payload still deserializes; everything else uses
+    /// [`decode`](Self::decode).
+    pub(crate) fn decode_unverified(
+        data: &[u8],
+        checksum: ChecksumAlgorithm,
+    ) -> Result<(Self, usize, FrameCrc)> {
+        let (content, expected_crc, total_size) = Self::split_frame(data, checksum)?;
+        let crc = FrameCrc {
+            expected: expected_crc,
+            actual: checksum.checksum(content),
+        };
+        Ok((Self::decode_content(content)?, total_size, crc))
+    }
+
+    /// Split a frame into its checksummed content and stored CRC
+    ///
+    /// Returns the content (type, timestamp and payload), the stored CRC
+    /// and the total number of bytes the frame occupies.
+    fn split_frame(data: &[u8], checksum: ChecksumAlgorithm) -> Result<(&[u8], u64, usize)> {
+        if data.len() < 5 + checksum.size() {
+            // Minimum: 4 (length) + 1 (type) + 0 (payload) + crc
             return Err(Error::Serialization("Incomplete record frame".to_string()));
         }
 
@@ -152,7 +333,7 @@ impl WalRecord {
         let length = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
 
         // Check if we have the full record
-        let total_size = 4 + length + 4; // length field + content + crc
+        let total_size = 4 + length + checksum.size(); // length field + content + crc
         if data.len() < total_size {
             return Err(Error::Serialization(format!(
                 "Incomplete record: expected {} bytes, got {}",
@@ -161,49 +342,59 @@ impl WalRecord {
             )));
         }
 
-        // Read type
-        let type_byte = data[4];
-        let record_type = RecordType::try_from(type_byte)?;
-
-        // Read payload
-        let payload_bytes = &data[5..4 + length];
-
         // Read CRC
         let crc_offset = 4 + length;
-        let expected_crc = u32::from_le_bytes([
-            data[crc_offset],
-            data[crc_offset + 1],
-            data[crc_offset + 2],
-            data[crc_offset + 3],
-        ]);
-
-        // Validate CRC
-        let mut hasher = Hasher::new();
-        hasher.update(&[type_byte]);
-        hasher.update(payload_bytes);
-        let actual_crc = hasher.finalize();
+        let expected_crc = checksum.decode(&data[crc_offset..]);
 
-        if actual_crc != expected_crc {
-            return Err(Error::Storage(format!(
-                "CRC mismatch: expected {}, got {}",
-                expected_crc, actual_crc
-            )));
+        Ok((&data[4..crc_offset], expected_crc, total_size))
+    }
+
+    /// Decode the type, timestamp and payload of a frame's content
+    fn decode_content(content: &[u8]) -> Result<Self> {
+        if content.is_empty() {
+            return Err(Error::Serialization("Record has no type byte".to_string()));
+        }
+
+        // Read type
+        let type_byte = content[0];
+        let record_type = RecordType::try_from(type_byte & !TIMESTAMP_FLAG)?;
+
+        // Read timestamp, if present
+        let mut payload_start = 1;
+        let mut timestamp_ms = 0;
+        if type_byte & TIMESTAMP_FLAG != 0 {
+            if content.len() < 9 {
+                return Err(Error::Serialization(
+                    "Record too short for its timestamp".to_string(),
+                ));
+            }
+            let mut timestamp = [0u8; 8];
+            timestamp.copy_from_slice(&content[1..9]);
+            timestamp_ms = u64::from_le_bytes(timestamp);
+            payload_start = 9;
         }
 
         // Deserialize payload
-        let payload: RecordPayload = bincode::deserialize(payload_bytes)
+        let payload: RecordPayload = bincode::deserialize(&content[payload_start..])
             .map_err(|e| Error::Serialization(format!("Failed to deserialize payload: {}", e)))?;
 
-        Ok((
-            WalRecord {
-                record_type,
-                payload,
-            },
-            total_size,
-        ))
+        Ok(WalRecord {
+            record_type,
+            payload,
+            timestamp_ms,
+        })
     }
 }
 
+/// Stored and computed checksums of a decoded record frame
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FrameCrc {
+    /// CRC stored in the frame
+    pub(crate) expected: u64,
+    /// CRC computed over the frame contents
+    pub(crate) actual: u64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -262,6 +453,83 @@ mod tests {
         assert_eq!(decoded, record);
     }
 
+    #[test]
+    fn test_merge_record_encode_decode() {
+        let record = WalRecord::merge(b"counter".to_vec(), 1u64.to_le_bytes().to_vec());
+
+        let encoded = record.encode().unwrap();
+        let (decoded, _) = WalRecord::decode(&encoded).unwrap();
+
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.record_type, RecordType::Merge);
+    }
+
+    #[test]
+    fn test_delete_range_record_encode_decode() {
+        let record = WalRecord::delete_range(b"a".to_vec(), b"m".to_vec());
+
+        let encoded = record.encode().unwrap();
+        let (decoded, _) = WalRecord::decode(&encoded).unwrap();
+
+        assert_eq!(decoded, record);
+        assert_eq!(RecordType::try_from(7).unwrap(), RecordType::DeleteRange);
+    }
+
+    #[test]
+    fn test_index_records_encode_decode() {
+        let create = WalRecord::create_index("by_name".to_string(), IndexType::Hash);
+        let drop = WalRecord::drop_index("by_name".to_string());
+
+        for record in [create, drop] {
+            let encoded = record.encode().unwrap();
+            let (decoded, _) = WalRecord::decode(&encoded).unwrap();
+            assert_eq!(decoded, record);
+        }
+        assert_eq!(RecordType::try_from(8).unwrap(), RecordType::CreateIndex);
+        assert_eq!(RecordType::try_from(9).unwrap(), RecordType::DropIndex);
+    }
+
+    #[test]
+    fn test_encoded_len_matches_encode() {
+        let records = [
+            WalRecord::put(b"key".to_vec(), b"value".to_vec()),
+            WalRecord::put(b"key".to_vec(), vec![7u8; 1000]).with_timestamp(42),
+            WalRecord::delete(b"key".to_vec()),
+            WalRecord::commit_tx(9),
+            WalRecord::delete_range(b"a".to_vec(), b"z".to_vec()).with_timestamp(1),
+        ];
+        for record in &records {
+            // Unstamped records are stamped by the writer when appended
+            let stamped = record.clone().with_timestamp(record.timestamp_ms.max(1));
+            assert_eq!(
+                record.encoded_len().unwrap(),
+                stamped.encode().unwrap().len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_timestamp_encode_decode() {
+        let record =
+            WalRecord::put(b"key".to_vec(), b"value".to_vec()).with_timestamp(1_700_000_000_000);
+
+        let encoded = record.encode().unwrap();
+        let (decoded, size) = WalRecord::decode(&encoded).unwrap();
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.timestamp_ms(), 1_700_000_000_000);
+        assert_eq!(size, encoded.len());
+
+        // Records without a timestamp keep the original frame layout
+        let legacy = WalRecord::put(b"key".to_vec(), b"value".to_vec());
+        let legacy_encoded = legacy.encode().unwrap();
+        assert_eq!(legacy_encoded.len() + 8, encoded.len());
+        assert_eq!(legacy_encoded[4], RecordType::Put as u8);
+        assert_eq!(
+            WalRecord::decode(&legacy_encoded).unwrap().0.timestamp_ms,
+            0
+        );
+    }
+
     #[test]
     fn test_crc_validation() {
         let record = WalRecord::put(b"key".to_vec(), b"value".to_vec());
@@ -277,6 +545,19 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_crc_checked_before_parsing() {
+        let record = WalRecord::put(b"key".to_vec(), b"value".to_vec());
+        let mut encoded = record.encode().unwrap();
+
+        // An invalid type byte is still reported as a CRC mismatch
+        encoded[4] = 0x7F;
+        match WalRecord::decode(&encoded) {
+            Err(Error::Storage(msg)) => assert!(msg.contains("CRC mismatch")),
+            other => panic!("Expected a CRC mismatch, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_incomplete_record() {
         let record = WalRecord::put(b"key".to_vec(), b"value".to_vec());
@@ -6,10 +6,13 @@
 // 3. Only returning committed records (incomplete transactions are rolled back)
 // 4. Handling corrupted or truncated records gracefully
 
+use crate::reader::{FrameOutcome, ReadOutcome};
 use crate::record::RecordPayload;
-use crate::{WalConfig, WalReader, WalRecord};
+use crate::{RecordType, RecoveryPolicy, WalConfig, WalReader, WalRecord};
 use rustlite_core::{Error, Result};
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tracing::warn;
 
 /// Manages WAL recovery after crash or restart
 pub struct RecoveryManager {
@@ -19,8 +22,8 @@ pub struct RecoveryManager {
 /// Represents a transaction's state during recovery
 #[derive(Debug, Clone)]
 struct TransactionState {
-    /// Records belonging to this transaction
-    records: Vec<WalRecord>,
+    /// ID from the transaction's BEGIN_TX record
+    tx_id: u64,
     /// Whether the transaction was committed
     committed: bool,
 }
@@ -31,6 +34,20 @@ impl RecoveryManager {
         Ok(Self { config })
     }
 
+    /// Recover records from archived WAL segments
+    ///
+    /// Reads segments previously moved into `archive_dir` by
+    /// `WalManager::truncate_before` and applies the same transaction
+    /// handling as [`recover`](Self::recover). Replaying the archive followed
+    /// by the live WAL reconstructs the full history.
+    pub fn replay_archive(&self, archive_dir: &Path) -> Result<Vec<WalRecord>> {
+        let config = WalConfig {
+            wal_dir: archive_dir.to_path_buf(),
+            ..self.config.clone()
+        };
+        RecoveryManager::new(config)?.recover()
+    }
+
     /// Recover records from WAL
     ///
     /// This method:
@@ -41,78 +58,148 @@ impl RecoveryManager {
     ///
     /// Returns a vector of recovered records in order
     pub fn recover(&self) -> Result<Vec<WalRecord>> {
+        self.recover_records(None)
+    }
+
+    /// Recover records written after the newest durable checkpoint
+    ///
+    /// `flushed_sequence` is the sequence already persisted by the caller
+    /// (for the storage engine, the sequence recorded in its manifest). Any
+    /// checkpoint with a sequence at or below it marks data that is already
+    /// captured on disk, so records preceding the newest such checkpoint are
+    /// not returned. Checkpoints above `flushed_sequence` belong to flushes
+    /// that never completed and are ignored.
+    pub fn recover_since_checkpoint(&self, flushed_sequence: u64) -> Result<Vec<WalRecord>> {
+        self.recover_records(Some(flushed_sequence))
+    }
+
+    fn recover_records(&self, flushed_sequence: Option<u64>) -> Result<Vec<WalRecord>> {
+        Ok(self
+            .committed_records(flushed_sequence)?
+            .into_iter()
+            .filter(|record| {
+                !matches!(
+                    record.payload,
+                    RecordPayload::BeginTx { .. }
+                        | RecordPayload::CommitTx { .. }
+                        | RecordPayload::Checkpoint { .. }
+                )
+            })
+            .collect())
+    }
+
+    /// Recover records with transaction markers included
+    ///
+    /// Unlike `recover()`, this method returns all records including
+    /// BEGIN_TX and COMMIT_TX markers for committed transactions.
+    /// This is useful for replaying the exact WAL state.
+    pub fn recover_with_markers(&self) -> Result<Vec<WalRecord>> {
+        self.committed_records(None)
+    }
+
+    /// Every record outside a transaction or in a committed one, in log
+    /// order
+    ///
+    /// Records are never regrouped by transaction: when transactions commit
+    /// writes to the same key, the write that comes last in the log wins on
+    /// replay. With `flushed_sequence`, records before the newest checkpoint
+    /// at or below it are dropped (see
+    /// [`recover_since_checkpoint`](Self::recover_since_checkpoint)), except
+    /// those of transactions still open at that checkpoint.
+    fn committed_records(&self, flushed_sequence: Option<u64>) -> Result<Vec<WalRecord>> {
         let mut reader = WalReader::new(&self.config.wal_dir)?;
 
         if reader.segment_count() == 0 {
             return Ok(Vec::new());
         }
 
-        // Track active transactions
-        let mut transactions: HashMap<u64, TransactionState> = HashMap::new();
-        let mut committed_tx_ids: HashSet<u64> = HashSet::new();
+        // Every transaction seen, in BEGIN_TX order; IDs may be reused once
+        // a transaction has finished, so each BEGIN_TX starts a new entry
+        let mut transactions: Vec<TransactionState> = Vec::new();
+        // Index into `transactions` of each transaction not yet committed
+        let mut open: HashMap<u64, usize> = HashMap::new();
 
-        // Records outside of any transaction
-        let mut standalone_records: Vec<WalRecord> = Vec::new();
+        // Records in log order, each with the index of its transaction
+        // (`None` outside of any transaction)
+        let mut records: Vec<(Option<usize>, WalRecord)> = Vec::new();
 
         // Current transaction context (for records that don't specify tx_id)
-        let mut current_tx_id: Option<u64> = None;
+        let mut current_tx: Option<usize> = None;
 
         // Read all records
         loop {
-            match reader.next_record() {
-                Ok(Some(record)) => {
-                    match &record.payload {
+            match reader.next_outcome() {
+                Ok(ReadOutcome::Record(record)) => {
+                    let tx = match &record.payload {
                         RecordPayload::BeginTx { tx_id } => {
                             // Start tracking a new transaction
-                            transactions.insert(
-                                *tx_id,
-                                TransactionState {
-                                    records: Vec::new(),
-                                    committed: false,
-                                },
-                            );
-                            current_tx_id = Some(*tx_id);
+                            transactions.push(TransactionState {
+                                tx_id: *tx_id,
+                                committed: false,
+                            });
+                            let idx = transactions.len() - 1;
+                            open.insert(*tx_id, idx);
+                            current_tx = Some(idx);
+                            Some(idx)
                         }
                         RecordPayload::CommitTx { tx_id } => {
                             // Mark transaction as committed
-                            if let Some(tx_state) = transactions.get_mut(tx_id) {
-                                tx_state.committed = true;
-                                committed_tx_ids.insert(*tx_id);
+                            let idx = open.remove(tx_id);
+                            if let Some(idx) = idx {
+                                transactions[idx].committed = true;
                             }
                             // Clear current tx if it matches
-                            if current_tx_id == Some(*tx_id) {
-                                current_tx_id = None;
+                            if current_tx.is_some_and(|idx| transactions[idx].tx_id == *tx_id) {
+                                current_tx = None;
                             }
+                            idx
                         }
-                        RecordPayload::Put { .. } | RecordPayload::Delete { .. } => {
-                            // Data records - add to current transaction or standalone
-                            if let Some(tx_id) = current_tx_id {
-                                if let Some(tx_state) = transactions.get_mut(&tx_id) {
-                                    tx_state.records.push(record);
-                                } else {
-                                    // Transaction not found, treat as standalone
-                                    standalone_records.push(record);
-                                }
-                            } else {
-                                // No active transaction
-                                standalone_records.push(record);
-                            }
+                        RecordPayload::Put { .. }
+                        | RecordPayload::Delete { .. }
+                        | RecordPayload::Merge { .. }
+                        | RecordPayload::DeleteRange { .. }
+                        | RecordPayload::CreateIndex { .. }
+                        | RecordPayload::DropIndex { .. } => {
+                            // Data records - belong to the current transaction
+                            // or stand alone
+                            current_tx
                         }
-                        RecordPayload::Checkpoint { .. } => {
-                            // Checkpoint records can be used for optimization
-                            // For now, we just skip them during recovery
+                        RecordPayload::Checkpoint { sequence } => {
+                            // Everything committed before a durable checkpoint
+                            // is already on disk and must not be replayed
+                            if flushed_sequence.is_some_and(|flushed| *sequence <= flushed) {
+                                records.retain(|(tx, _)| {
+                                    tx.is_some_and(|idx| !transactions[idx].committed)
+                                });
+                            }
+                            None
                         }
+                    };
+                    records.push((tx, record));
+                }
+                Ok(ReadOutcome::TornTail(position)) => {
+                    // A write torn by a crash; everything before it is valid
+                    if self.config.recovery_policy == RecoveryPolicy::Strict {
+                        return Err(Error::Corruption(format!(
+                            "Torn record at end of WAL at {:?}",
+                            position
+                        )));
                     }
+                    warn!(?position, "Ignoring torn record at end of WAL");
+                    break;
                 }
-                Ok(None) => {
+                Ok(ReadOutcome::End) => {
                     // End of WAL
                     break;
                 }
                 Err(e) => {
                     // Handle errors gracefully
-                    // CRC errors or truncation means we stop here
+                    // CRC errors mean we stop here
                     // Records up to this point are still valid
                     if Self::is_recoverable_error(&e) {
+                        if self.config.recovery_policy == RecoveryPolicy::Strict {
+                            return Err(Error::Corruption(format!("Invalid WAL record: {}", e)));
+                        }
                         break;
                     }
                     return Err(e);
@@ -120,107 +207,89 @@ impl RecoveryManager {
             }
         }
 
-        // Collect results: standalone records + committed transaction records
-        let mut result = standalone_records;
-
-        // Add records from committed transactions in order
-        // Sort by tx_id for deterministic ordering
-        let mut committed_txs: Vec<_> = transactions
+        // Drop records of transactions that never committed
+        Ok(records
             .into_iter()
-            .filter(|(_, state)| state.committed)
-            .collect();
-        committed_txs.sort_by_key(|(tx_id, _)| *tx_id);
-
-        for (_, tx_state) in committed_txs {
-            result.extend(tx_state.records);
-        }
-
-        Ok(result)
+            .filter(|(tx, _)| tx.map_or(true, |idx| transactions[idx].committed))
+            .map(|(_, record)| record)
+            .collect())
     }
 
-    /// Recover records with transaction markers included
+    /// Summarize every record in the WAL, in log order
     ///
-    /// Unlike `recover()`, this method returns all records including
-    /// BEGIN_TX and COMMIT_TX markers for committed transactions.
-    /// This is useful for replaying the exact WAL state.
-    pub fn recover_with_markers(&self) -> Result<Vec<WalRecord>> {
+    /// Unlike [`recover`](Self::recover), nothing is filtered: transaction
+    /// markers, records of uncommitted transactions and records failing CRC
+    /// validation are all listed. A record with a bad CRC is still
+    /// summarized if its payload can be decoded; listing stops at the first
+    /// frame that cannot be decoded at all, or at a torn trailing record.
+    pub fn dump(&self) -> Result<Vec<RecordSummary>> {
         let mut reader = WalReader::new(&self.config.wal_dir)?;
-
-        if reader.segment_count() == 0 {
-            return Ok(Vec::new());
-        }
-
-        // First pass: identify committed transactions
-        let mut committed_tx_ids: HashSet<u64> = HashSet::new();
-        let mut all_records: Vec<WalRecord> = Vec::new();
+        let mut summaries = Vec::new();
+        let mut current_tx_id: Option<u64> = None;
 
         loop {
-            match reader.next_record() {
-                Ok(Some(record)) => {
-                    if let RecordPayload::CommitTx { tx_id } = &record.payload {
-                        committed_tx_ids.insert(*tx_id);
-                    }
-                    all_records.push(record);
+            let (position, checksum, frame) = match reader.next_frame()? {
+                FrameOutcome::Frame(position, checksum, frame) => (position, checksum, frame),
+                FrameOutcome::TornTail(position) => {
+                    warn!(?position, "Torn record at end of WAL");
+                    break;
                 }
-                Ok(None) => break,
+                FrameOutcome::End => break,
+            };
+            let (record, _, crc) = match WalRecord::decode_unverified(&frame, checksum) {
+                Ok(decoded) => decoded,
                 Err(e) => {
-                    if Self::is_recoverable_error(&e) {
-                        break;
-                    }
-                    return Err(e);
+                    warn!(?position, error = %e, "Undecodable WAL record");
+                    break;
                 }
-            }
-        }
-
-        // Second pass: filter to only include committed transactions and standalone records
-        let mut result: Vec<WalRecord> = Vec::new();
-        let mut current_tx_id: Option<u64> = None;
-        let mut in_committed_tx = false;
+            };
 
-        for record in all_records {
-            let payload = &record.payload;
-            let should_include = match payload {
+            let timestamp_ms = record.timestamp_ms();
+            let (key, value_len, tx_id) = match record.payload {
+                RecordPayload::Put { key, value } => (Some(key), value.len(), current_tx_id),
+                RecordPayload::Delete { key } => (Some(key), 0, current_tx_id),
+                RecordPayload::Merge { key, operand } => (Some(key), operand.len(), current_tx_id),
+                RecordPayload::DeleteRange { start, end } => {
+                    (Some(start), end.len(), current_tx_id)
+                }
                 RecordPayload::BeginTx { tx_id } => {
-                    in_committed_tx = committed_tx_ids.contains(tx_id);
-                    current_tx_id = Some(*tx_id);
-                    in_committed_tx
+                    current_tx_id = Some(tx_id);
+                    (None, 0, Some(tx_id))
                 }
                 RecordPayload::CommitTx { tx_id } => {
-                    let include = committed_tx_ids.contains(tx_id);
-                    if current_tx_id == Some(*tx_id) {
+                    if current_tx_id == Some(tx_id) {
                         current_tx_id = None;
-                        in_committed_tx = false;
-                    }
-                    include
-                }
-                RecordPayload::Put { .. } | RecordPayload::Delete { .. } => {
-                    if current_tx_id.is_some() {
-                        // In a transaction
-                        in_committed_tx
-                    } else {
-                        // Standalone record
-                        true
                     }
+                    (None, 0, Some(tx_id))
                 }
-                RecordPayload::Checkpoint { .. } => {
-                    // Include checkpoint markers
-                    true
+                RecordPayload::CreateIndex { name, .. } | RecordPayload::DropIndex { name } => {
+                    (Some(name.into_bytes()), 0, current_tx_id)
                 }
+                RecordPayload::Checkpoint { .. } => (None, 0, None),
             };
 
-            if should_include {
-                result.push(record);
-            }
+            summaries.push(RecordSummary {
+                segment: position.segment,
+                offset: position.offset,
+                record_type: record.record_type,
+                key,
+                value_len,
+                tx_id,
+                timestamp_ms,
+                crc_valid: crc.expected == crc.actual,
+            });
         }
 
-        Ok(result)
+        Ok(summaries)
     }
 
     /// Check if an error is recoverable (we can continue without the corrupted data)
+    ///
+    /// Torn trailing records are reported structurally by the reader and
+    /// never reach this check.
     fn is_recoverable_error(err: &Error) -> bool {
         match err {
             Error::Storage(msg) => msg.contains("CRC mismatch"),
-            Error::Serialization(msg) => msg.contains("Incomplete") || msg.contains("truncated"),
             _ => false,
         }
     }
@@ -234,10 +303,14 @@ impl RecoveryManager {
             total_records: 0,
             put_records: 0,
             delete_records: 0,
+            merge_records: 0,
+            delete_range_records: 0,
             transactions_started: 0,
             transactions_committed: 0,
             transactions_incomplete: 0,
             checkpoints: 0,
+            oldest_timestamp_ms: None,
+            newest_timestamp_ms: None,
         };
 
         let mut active_transactions: HashSet<u64> = HashSet::new();
@@ -246,9 +319,18 @@ impl RecoveryManager {
             match reader.next_record() {
                 Ok(Some(record)) => {
                     stats.total_records += 1;
+                    if record.timestamp_ms() != 0 {
+                        let ts = record.timestamp_ms();
+                        stats.oldest_timestamp_ms =
+                            Some(stats.oldest_timestamp_ms.map_or(ts, |t| t.min(ts)));
+                        stats.newest_timestamp_ms =
+                            Some(stats.newest_timestamp_ms.map_or(ts, |t| t.max(ts)));
+                    }
                     match &record.payload {
                         RecordPayload::Put { .. } => stats.put_records += 1,
                         RecordPayload::Delete { .. } => stats.delete_records += 1,
+                        RecordPayload::Merge { .. } => stats.merge_records += 1,
+                        RecordPayload::DeleteRange { .. } => stats.delete_range_records += 1,
                         RecordPayload::BeginTx { tx_id } => {
                             stats.transactions_started += 1;
                             active_transactions.insert(*tx_id);
@@ -258,6 +340,7 @@ impl RecoveryManager {
                             active_transactions.remove(tx_id);
                         }
                         RecordPayload::Checkpoint { .. } => stats.checkpoints += 1,
+                        RecordPayload::CreateIndex { .. } | RecordPayload::DropIndex { .. } => {}
                     }
                 }
                 Ok(None) => break,
@@ -271,6 +354,31 @@ impl RecoveryManager {
     }
 }
 
+/// Structural summary of one WAL record, as returned by
+/// [`RecoveryManager::dump`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordSummary {
+    /// Sequence number of the segment holding the record
+    pub segment: u64,
+    /// Byte offset of the record within the segment
+    pub offset: u64,
+    /// Type of the record
+    pub record_type: RecordType,
+    /// Key of a data record; the range start for DELETE_RANGE and the index
+    /// name for CREATE_INDEX and DROP_INDEX
+    pub key: Option<Vec<u8>>,
+    /// Length of the value or merge operand; the length of the range end
+    /// for DELETE_RANGE and 0 for records without a value
+    pub value_len: usize,
+    /// Transaction of a BEGIN_TX or COMMIT_TX marker, or the open
+    /// transaction a data record was written in
+    pub tx_id: Option<u64>,
+    /// Append timestamp (ms since the UNIX epoch), or 0 if not recorded
+    pub timestamp_ms: u64,
+    /// Whether the stored CRC matches the record contents
+    pub crc_valid: bool,
+}
+
 /// Statistics about the WAL state
 #[derive(Debug, Clone, Default)]
 pub struct RecoveryStats {
@@ -282,6 +390,10 @@ pub struct RecoveryStats {
     pub put_records: usize,
     /// Number of DELETE records
     pub delete_records: usize,
+    /// Number of MERGE records
+    pub merge_records: usize,
+    /// Number of DELETE_RANGE records
+    pub delete_range_records: usize,
     /// Number of transactions started
     pub transactions_started: usize,
     /// Number of transactions committed
@@ -290,12 +402,28 @@ pub struct RecoveryStats {
     pub transactions_incomplete: usize,
     /// Number of checkpoint records
     pub checkpoints: usize,
+    oldest_timestamp_ms: Option<u64>,
+    newest_timestamp_ms: Option<u64>,
+}
+
+impl RecoveryStats {
+    /// Earliest append timestamp (ms since the UNIX epoch) among records
+    /// that carry one
+    pub fn oldest_timestamp_ms(&self) -> Option<u64> {
+        self.oldest_timestamp_ms
+    }
+
+    /// Latest append timestamp (ms since the UNIX epoch) among records
+    /// that carry one
+    pub fn newest_timestamp_ms(&self) -> Option<u64> {
+        self.newest_timestamp_ms
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{RecordType, SyncMode, WalWriter};
+    use crate::{ChecksumAlgorithm, RecordType, SyncMode, WalWriter};
     use tempfile::TempDir;
 
     fn setup_test_wal() -> (TempDir, WalConfig) {
@@ -307,6 +435,11 @@ mod tests {
             wal_dir: wal_path,
             sync_mode: SyncMode::Sync,
             max_segment_size: 64 * 1024 * 1024,
+            archive_dir: None,
+            max_total_wal_size: None,
+            write_buffer_bytes: crate::writer::DEFAULT_WRITE_BUFFER_SIZE,
+            recovery_policy: RecoveryPolicy::Lenient,
+            checksum: ChecksumAlgorithm::Crc32,
         };
 
         (temp_dir, config)
@@ -483,6 +616,107 @@ mod tests {
         assert_eq!(records[2].record_type, RecordType::CommitTx);
     }
 
+    #[test]
+    fn test_recovery_preserves_log_order() {
+        let (_temp_dir, config) = setup_test_wal();
+
+        // Transaction 2 commits before transaction 1 writes the same key,
+        // and a standalone write comes last
+        {
+            let mut writer =
+                WalWriter::new(&config.wal_dir, config.max_segment_size, config.sync_mode)
+                    .expect("Failed to create writer");
+            for record in [
+                WalRecord::begin_tx(2),
+                WalRecord::put(b"key".to_vec(), b"tx2".to_vec()),
+                WalRecord::commit_tx(2),
+                WalRecord::begin_tx(1),
+                WalRecord::put(b"key".to_vec(), b"tx1".to_vec()),
+                WalRecord::commit_tx(1),
+                WalRecord::put(b"other".to_vec(), b"first".to_vec()),
+                WalRecord::begin_tx(3),
+                WalRecord::put(b"other".to_vec(), b"aborted".to_vec()),
+            ] {
+                writer.append(record).expect("Failed to append");
+            }
+            writer.sync().expect("Failed to sync");
+        }
+
+        let recovery = RecoveryManager::new(config).expect("Failed to create recovery manager");
+        let values: Vec<Vec<u8>> = recovery
+            .recover()
+            .expect("Failed to recover")
+            .into_iter()
+            .map(|record| match record.payload {
+                RecordPayload::Put { value, .. } => value,
+                other => panic!("unexpected record {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![b"tx2".to_vec(), b"tx1".to_vec(), b"first".to_vec()]
+        );
+
+        let types: Vec<RecordType> = recovery
+            .recover_with_markers()
+            .expect("Failed to recover")
+            .into_iter()
+            .map(|record| record.record_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                RecordType::BeginTx,
+                RecordType::Put,
+                RecordType::CommitTx,
+                RecordType::BeginTx,
+                RecordType::Put,
+                RecordType::CommitTx,
+                RecordType::Put,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recovery_since_checkpoint() {
+        let (_temp_dir, config) = setup_test_wal();
+
+        {
+            let mut writer =
+                WalWriter::new(&config.wal_dir, config.max_segment_size, config.sync_mode)
+                    .expect("Failed to create writer");
+
+            writer
+                .append(WalRecord::put(b"flushed".to_vec(), b"v".to_vec()))
+                .unwrap();
+            writer.append(WalRecord::checkpoint(1)).unwrap();
+            writer
+                .append(WalRecord::put(b"after".to_vec(), b"v".to_vec()))
+                .unwrap();
+            // Checkpoint of a flush that never completed
+            writer.append(WalRecord::checkpoint(2)).unwrap();
+            writer
+                .append(WalRecord::put(b"latest".to_vec(), b"v".to_vec()))
+                .unwrap();
+            writer.sync().unwrap();
+        }
+
+        let recovery = RecoveryManager::new(config).expect("Failed to create recovery manager");
+        assert_eq!(recovery.recover().unwrap().len(), 3);
+
+        let records = recovery.recover_since_checkpoint(1).unwrap();
+        assert_eq!(
+            records,
+            vec![
+                WalRecord::put(b"after".to_vec(), b"v".to_vec()),
+                WalRecord::put(b"latest".to_vec(), b"v".to_vec()),
+            ]
+        );
+
+        assert_eq!(recovery.recover_since_checkpoint(2).unwrap().len(), 1);
+        assert_eq!(recovery.recover_since_checkpoint(0).unwrap().len(), 3);
+    }
+
     #[test]
     fn test_recovery_stats() {
         let (_temp_dir, config) = setup_test_wal();
@@ -525,4 +759,81 @@ mod tests {
         assert_eq!(stats.transactions_committed, 1);
         assert_eq!(stats.transactions_incomplete, 1);
     }
+
+    #[test]
+    fn test_dump_mixed_segment() {
+        let (_temp_dir, config) = setup_test_wal();
+
+        let written = vec![
+            WalRecord::put(b"a".to_vec(), b"alpha".to_vec()),
+            WalRecord::begin_tx(7),
+            WalRecord::merge(b"b".to_vec(), b"op".to_vec()),
+            WalRecord::delete(b"c".to_vec()),
+            WalRecord::commit_tx(7),
+            WalRecord::checkpoint(3),
+            WalRecord::delete_range(b"d".to_vec(), b"f".to_vec()),
+            WalRecord::put(b"z".to_vec(), b"last".to_vec()),
+        ];
+        {
+            let mut writer =
+                WalWriter::new(&config.wal_dir, config.max_segment_size, config.sync_mode)
+                    .expect("Failed to create writer");
+            for record in &written {
+                writer.append_ref(record).expect("Failed to append");
+            }
+            writer.sync().unwrap();
+        }
+
+        let recovery = RecoveryManager::new(config.clone()).unwrap();
+        let summaries = recovery.dump().unwrap();
+        assert_eq!(summaries.len(), written.len());
+
+        let expected = [
+            (RecordType::Put, Some(&b"a"[..]), 5, None),
+            (RecordType::BeginTx, None, 0, Some(7)),
+            (RecordType::Merge, Some(b"b"), 2, Some(7)),
+            (RecordType::Delete, Some(b"c"), 0, Some(7)),
+            (RecordType::CommitTx, None, 0, Some(7)),
+            (RecordType::Checkpoint, None, 0, None),
+            (RecordType::DeleteRange, Some(b"d"), 1, None),
+            (RecordType::Put, Some(b"z"), 4, None),
+        ];
+        let mut offset = crate::writer::WalHeader::SIZE as u64;
+        for ((summary, record), (record_type, key, value_len, tx_id)) in
+            summaries.iter().zip(&written).zip(expected)
+        {
+            assert_eq!(summary.offset, offset);
+            assert_eq!(summary.segment, summaries[0].segment);
+            assert_eq!(summary.record_type, record_type);
+            assert_eq!(summary.key.as_deref(), key);
+            assert_eq!(summary.value_len, value_len);
+            assert_eq!(summary.tx_id, tx_id);
+            assert!(summary.timestamp_ms > 0);
+            assert!(summary.crc_valid);
+
+            let stamped = record.clone().with_timestamp(summary.timestamp_ms);
+            offset += stamped.encoded_len().unwrap() as u64;
+        }
+
+        // Damage the last byte of the final value; the record is still
+        // listed but flagged
+        let segment = std::fs::read_dir(&config.wal_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let mut bytes = std::fs::read(&segment).unwrap();
+        assert_eq!(bytes.len() as u64, offset);
+        let crc_start = bytes.len() - 4;
+        bytes[crc_start - 1] ^= 0xff;
+        std::fs::write(&segment, bytes).unwrap();
+
+        let summaries = recovery.dump().unwrap();
+        assert_eq!(summaries.len(), written.len());
+        let last = summaries.last().unwrap();
+        assert!(!last.crc_valid);
+        assert_eq!(last.key.as_deref(), Some(&b"z"[..]));
+        assert!(summaries[..summaries.len() - 1].iter().all(|s| s.crc_valid));
+    }
 }
@@ -3,10 +3,32 @@
 // Segments are named: wal-{sequence:016x}.log
 // Where sequence is a monotonically increasing hex number
 
+use crate::reader::WalReader;
 use rustlite_core::{Error, Result};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 
+/// Move a file, copying it when `to` is on another file system, where
+/// `fs::rename` fails
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if is_cross_device(&e) => {
+            fs::copy(from, to)?;
+            fs::File::open(to)?.sync_all()?;
+            fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+/// Whether a rename failed because source and target are on different
+/// file systems (`EXDEV` on Unix, `ERROR_NOT_SAME_DEVICE` on Windows)
+fn is_cross_device(err: &io::Error) -> bool {
+    const CROSS_DEVICE: i32 = if cfg!(windows) { 17 } else { 18 };
+    err.raw_os_error() == Some(CROSS_DEVICE)
+}
+
 /// Manages WAL segment files
 pub struct SegmentManager {
     wal_dir: PathBuf,
@@ -21,6 +43,10 @@ pub struct SegmentInfo {
     pub sequence: u64,
     /// File size in bytes
     pub size: u64,
+    /// Number of complete records in the segment, if it has been scanned
+    ///
+    /// Only populated by [`SegmentManager::segment_infos`].
+    pub record_count: Option<usize>,
 }
 
 impl SegmentManager {
@@ -47,8 +73,20 @@ impl SegmentManager {
         Ok(segments)
     }
 
-    /// Parse segment info from a file path
-    fn parse_segment_info(&self, path: &Path) -> Option<SegmentInfo> {
+    /// Find a segment by its sequence number
+    ///
+    /// Returns the segment's position in the ordered list from
+    /// [`list_segments`](Self::list_segments) along with its info.
+    pub fn find_segment(&self, sequence: u64) -> Result<Option<(usize, SegmentInfo)>> {
+        let segments = self.list_segments()?;
+        Ok(segments
+            .into_iter()
+            .enumerate()
+            .find(|(_, s)| s.sequence == sequence))
+    }
+
+    /// Extract the sequence number from a segment path (`wal-{hex}.log`)
+    pub fn sequence_from_path(path: &Path) -> Option<u64> {
         let name = path.file_name()?.to_str()?;
 
         // Must match pattern: wal-{hex}.log
@@ -57,7 +95,12 @@ impl SegmentManager {
         }
 
         let seq_str = name.strip_prefix("wal-")?.strip_suffix(".log")?;
-        let sequence = u64::from_str_radix(seq_str, 16).ok()?;
+        u64::from_str_radix(seq_str, 16).ok()
+    }
+
+    /// Parse segment info from a file path
+    fn parse_segment_info(&self, path: &Path) -> Option<SegmentInfo> {
+        let sequence = Self::sequence_from_path(path)?;
 
         let size = fs::metadata(path).ok()?.len();
 
@@ -65,9 +108,23 @@ impl SegmentManager {
             path: path.to_path_buf(),
             sequence,
             size,
+            record_count: None,
         })
     }
 
+    /// List all segment files in order, with record counts
+    ///
+    /// Unlike [`list_segments`](Self::list_segments), this reads every
+    /// segment to count its records, so it costs a full scan of the WAL.
+    /// A torn record at the end of a segment is not counted.
+    pub fn segment_infos(&self) -> Result<Vec<SegmentInfo>> {
+        let mut segments = self.list_segments()?;
+        for segment in &mut segments {
+            segment.record_count = Some(WalReader::count_segment_records(&segment.path)?);
+        }
+        Ok(segments)
+    }
+
     /// Get the total size of all segments
     pub fn total_size(&self) -> Result<u64> {
         let segments = self.list_segments()?;
@@ -102,6 +159,40 @@ impl SegmentManager {
         Ok(deleted)
     }
 
+    /// Move segments older than the given sequence number into an archive
+    /// directory
+    ///
+    /// The archive directory is created if needed and segments keep their
+    /// file names, so it can be read like a WAL directory. It may be on
+    /// another file system, in which case segments are copied and then
+    /// removed. Returns the number of segments archived.
+    pub fn archive_before(&self, sequence: u64, archive_dir: &Path) -> Result<usize> {
+        fs::create_dir_all(archive_dir).map_err(|e| {
+            Error::Storage(format!("Failed to create WAL archive directory: {}", e))
+        })?;
+
+        let segments = self.list_segments()?;
+        let mut archived = 0;
+
+        for segment in segments {
+            if segment.sequence < sequence {
+                let file_name = segment.path.file_name().ok_or_else(|| {
+                    Error::Storage(format!("Invalid segment path {:?}", segment.path))
+                })?;
+                let target = archive_dir.join(file_name);
+                move_file(&segment.path, &target).map_err(|e| {
+                    Error::Storage(format!(
+                        "Failed to archive segment {:?}: {}",
+                        segment.path, e
+                    ))
+                })?;
+                archived += 1;
+            }
+        }
+
+        Ok(archived)
+    }
+
     /// Delete all segment files
     ///
     /// Use with caution - this removes all WAL data!
@@ -199,6 +290,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_segment_infos() {
+        let (_temp_dir, wal_path) = setup_test_wal();
+
+        let record = |i: usize| WalRecord::put(format!("key{}", i).into_bytes(), vec![0u8; 40]);
+        // Appended records carry a timestamp, which is part of their size
+        let record_size = record(0).with_timestamp(1).encode().unwrap().len() as u64;
+
+        // Room for three records per segment, so five records span two
+        {
+            let max_size = crate::writer::WalHeader::SIZE as u64 + 3 * record_size;
+            let mut writer = WalWriter::new(&wal_path, max_size, SyncMode::Sync)
+                .expect("Failed to create writer");
+            for i in 0..5 {
+                writer.append(record(i)).expect("Failed to append");
+            }
+        }
+
+        let manager = SegmentManager::new(wal_path);
+        let infos = manager
+            .segment_infos()
+            .expect("Failed to get segment infos");
+
+        assert_eq!(infos.len(), 2);
+        assert!(infos.iter().all(|s| s.size > 0));
+        assert!(infos[1].sequence > infos[0].sequence);
+        assert_eq!(infos[0].record_count, Some(3));
+        assert_eq!(infos[1].record_count, Some(2));
+    }
+
     #[test]
     fn test_total_size() {
         let (_temp_dir, wal_path) = setup_test_wal();
@@ -1,17 +1,29 @@
 // WAL writer module - handles appending records to the log
+use crate::checksum::ChecksumAlgorithm;
 use crate::record::WalRecord;
 use crate::SyncMode;
 use rustlite_core::{Error, Result};
 use std::fs::{File, OpenOptions};
 use std::io::{BufWriter, Read, Write};
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info, instrument};
 
 /// Magic bytes for WAL segment files ("RLWL" = RustLite WAL)
 const WAL_MAGIC_HEADER: [u8; 4] = *b"RLWL";
 
 /// WAL format version (v1.0.0+)
-const WAL_FORMAT_VERSION: u16 = 1;
+///
+/// Version 2 adds a checksum algorithm tag to the header. CRC32 segments
+/// keep writing version 1 headers so older versions can still read them.
+const WAL_FORMAT_VERSION: u16 = 2;
+
+/// First format version whose header carries a checksum algorithm tag
+const CHECKSUM_TAG_VERSION: u16 = 2;
+
+/// Default capacity of the buffer in front of the segment file (64KB)
+pub const DEFAULT_WRITE_BUFFER_SIZE: usize = 64 * 1024;
 
 /// File header written at the start of WAL segment files (v1.0+)
 #[derive(Debug, Clone)]
@@ -20,17 +32,39 @@ pub struct WalHeader {
     pub magic: [u8; 4],
     /// Format version
     pub version: u16,
+    /// Checksum algorithm of the segment's records (version 2+; CRC32 in
+    /// older segments)
+    pub checksum: ChecksumAlgorithm,
 }
 
 impl WalHeader {
-    /// Size of header in bytes
+    /// Size of a CRC32 segment's header in bytes
     pub const SIZE: usize = 6; // 4 bytes magic + 2 bytes version
 
-    /// Create a new header with current version
+    /// Create a new header for a CRC32 segment
     pub fn new() -> Self {
+        Self::with_checksum(ChecksumAlgorithm::Crc32)
+    }
+
+    /// Create a new header for a segment whose records use `checksum`
+    pub fn with_checksum(checksum: ChecksumAlgorithm) -> Self {
+        let version = match checksum {
+            ChecksumAlgorithm::Crc32 => 1,
+            _ => WAL_FORMAT_VERSION,
+        };
         Self {
             magic: WAL_MAGIC_HEADER,
-            version: WAL_FORMAT_VERSION,
+            version,
+            checksum,
+        }
+    }
+
+    /// Size of this header in bytes
+    pub fn encoded_len(&self) -> usize {
+        if self.version >= CHECKSUM_TAG_VERSION {
+            Self::SIZE + 1 // checksum tag
+        } else {
+            Self::SIZE
         }
     }
 
@@ -38,6 +72,9 @@ impl WalHeader {
     pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
         writer.write_all(&self.magic)?;
         writer.write_all(&self.version.to_le_bytes())?;
+        if self.version >= CHECKSUM_TAG_VERSION {
+            writer.write_all(&[self.checksum.tag()])?;
+        }
         Ok(())
     }
 
@@ -58,28 +95,108 @@ impl WalHeader {
         let version = u16::from_le_bytes(version_bytes);
 
         if version > WAL_FORMAT_VERSION {
-            return Err(Error::Corruption(format!(
-                "Unsupported WAL version: {} (current: {})",
-                version, WAL_FORMAT_VERSION
-            )));
+            return Err(Error::UnsupportedVersion {
+                component: "WAL".to_string(),
+                found: version as u32,
+                max_supported: WAL_FORMAT_VERSION as u32,
+            });
         }
 
-        Ok(Self { magic, version })
+        let checksum = if version >= CHECKSUM_TAG_VERSION {
+            let mut tag = [0u8; 1];
+            reader.read_exact(&mut tag)?;
+            ChecksumAlgorithm::from_tag(tag[0])?
+        } else {
+            ChecksumAlgorithm::Crc32
+        };
+
+        Ok(Self {
+            magic,
+            version,
+            checksum,
+        })
     }
 }
+
+impl Default for WalHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where an appended record stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppendOutcome {
+    /// Sequence number assigned to the record
+    pub lsn: u64,
+    /// Whether the record was synced to disk by the append. Always true
+    /// under `SyncMode::Sync`. Otherwise it stays buffered until a later
+    /// [`sync`](WalWriter::sync) or `sync_through(lsn)`.
+    pub durable: bool,
+}
+
 pub struct WalWriter {
-    file: BufWriter<File>,
+    /// Locked so buffered records can be flushed through a shared reference
+    file: Mutex<BufWriter<File>>,
     current_segment: PathBuf,
     current_size: u64,
     max_segment_size: u64,
     sync_mode: SyncMode,
     sequence: u64,
     wal_dir: PathBuf,
+    sync_count: u64,
+    last_timestamp_ms: u64,
+    /// Highest sequence known to be on disk
+    durable_sequence: u64,
+    /// Capacity of the buffer in front of each segment file
+    buffer_size: usize,
+    /// Checksum algorithm of the records this writer appends
+    checksum: ChecksumAlgorithm,
 }
 
 impl WalWriter {
-    #[instrument(skip(wal_dir), fields(wal_dir = ?wal_dir, max_segment_size = max_segment_size))]
     pub fn new(wal_dir: &PathBuf, max_segment_size: u64, sync_mode: SyncMode) -> Result<Self> {
+        Self::with_buffer_size(
+            wal_dir,
+            max_segment_size,
+            sync_mode,
+            DEFAULT_WRITE_BUFFER_SIZE,
+        )
+    }
+
+    /// Create a writer that buffers up to `buffer_size` bytes before
+    /// writing to the segment file
+    ///
+    /// A larger buffer means fewer write calls when records are not synced
+    /// one by one (`SyncMode::Async` or `SyncMode::None`).
+    pub fn with_buffer_size(
+        wal_dir: &PathBuf,
+        max_segment_size: u64,
+        sync_mode: SyncMode,
+        buffer_size: usize,
+    ) -> Result<Self> {
+        Self::with_checksum(
+            wal_dir,
+            max_segment_size,
+            sync_mode,
+            buffer_size,
+            ChecksumAlgorithm::default(),
+        )
+    }
+
+    /// Create a writer whose records end with a `checksum` checksum
+    ///
+    /// The algorithm is recorded in the header of each new segment. If the
+    /// latest existing segment uses a different algorithm, the writer starts
+    /// a new segment rather than mixing algorithms within one.
+    #[instrument(skip(wal_dir), fields(wal_dir = ?wal_dir, max_segment_size = max_segment_size))]
+    pub fn with_checksum(
+        wal_dir: &PathBuf,
+        max_segment_size: u64,
+        sync_mode: SyncMode,
+        buffer_size: usize,
+        checksum: ChecksumAlgorithm,
+    ) -> Result<Self> {
         info!("Creating WAL writer");
 
         // Create WAL directory if it doesn't exist
@@ -104,26 +221,43 @@ impl WalWriter {
         // Get current file size for rotation tracking
         let current_size = file.metadata().map(|m| m.len()).unwrap_or(0);
 
-        // Write header if this is a new file (v1.0+)
-        if current_size == 0 {
-            let header = WalHeader::new();
+        // Write header if this is a new file (v1.0+), otherwise find the
+        // algorithm the segment's records use. Segments without a valid
+        // header predate v1.0 and use CRC32.
+        let segment_checksum = if current_size == 0 {
+            let header = WalHeader::with_checksum(checksum);
             header.write_to(&mut file)?;
             file.flush()?;
             debug!("Wrote WAL header to new segment");
-        }
+            checksum
+        } else {
+            WalHeader::read_from(&mut file)
+                .map(|header| header.checksum)
+                .unwrap_or(ChecksumAlgorithm::Crc32)
+        };
 
         // Get actual size after potentially writing header
         let actual_size = file.metadata().map(|m| m.len()).unwrap_or(0);
 
-        Ok(Self {
-            file: BufWriter::new(file),
+        let mut writer = Self {
+            file: Mutex::new(BufWriter::with_capacity(buffer_size, file)),
             current_segment: segment_path,
             current_size: actual_size,
             max_segment_size,
             sync_mode,
             sequence: starting_sequence,
             wal_dir: wal_dir.clone(),
-        })
+            sync_count: 0,
+            last_timestamp_ms: 0,
+            durable_sequence: starting_sequence,
+            buffer_size,
+            checksum,
+        };
+        if segment_checksum != checksum {
+            writer.rotate_segment()?;
+        }
+
+        Ok(writer)
     }
 
     /// Find the maximum sequence number from existing segments
@@ -151,12 +285,83 @@ impl WalWriter {
         Ok(max_seq)
     }
 
+    pub fn append(&mut self, record: WalRecord) -> Result<AppendOutcome> {
+        self.append_ref(&record)
+    }
+
+    /// Append a record the caller keeps ownership of
+    ///
+    /// Lets callers that need the record's key and value afterwards avoid
+    /// copying them just for the WAL.
     #[instrument(skip(self, record), fields(record_type = ?record))]
-    pub fn append(&mut self, record: WalRecord) -> Result<u64> {
+    pub fn append_ref(&mut self, record: &WalRecord) -> Result<AppendOutcome> {
         debug!(sequence = self.sequence, "Appending WAL record");
 
         // Encode the record
-        let encoded = record.encode()?;
+        let timestamp_ms = self.timestamp_for(record);
+        let encoded = record.encode_with_timestamp(timestamp_ms, self.checksum)?;
+        let sequence = self.write_encoded(&encoded)?;
+
+        // Sync if required
+        if matches!(self.sync_mode, SyncMode::Sync) {
+            self.sync()?;
+        }
+
+        Ok(AppendOutcome {
+            lsn: sequence,
+            durable: sequence <= self.durable_sequence,
+        })
+    }
+
+    /// Append several records with a single sync
+    ///
+    /// All records are encoded before anything is written, so an encoding
+    /// failure leaves the WAL untouched. Returns the sequence assigned to
+    /// each record, in order.
+    #[instrument(skip(self, records), fields(count = records.len()))]
+    pub fn append_batch(&mut self, records: Vec<WalRecord>) -> Result<Vec<u64>> {
+        debug!(sequence = self.sequence, "Appending WAL record batch");
+
+        let encoded = records
+            .iter()
+            .map(|record| {
+                let timestamp_ms = self.timestamp_for(record);
+                record.encode_with_timestamp(timestamp_ms, self.checksum)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut sequences = Vec::with_capacity(encoded.len());
+        for frame in &encoded {
+            sequences.push(self.write_encoded(frame)?);
+        }
+
+        // Sync once for the whole batch
+        if !sequences.is_empty() && matches!(self.sync_mode, SyncMode::Sync) {
+            self.sync()?;
+        }
+
+        Ok(sequences)
+    }
+
+    /// The append timestamp of a record: its own, or the current time if it
+    /// does not carry one
+    ///
+    /// Timestamps never go backwards within a writer, even if the system
+    /// clock does.
+    fn timestamp_for(&mut self, record: &WalRecord) -> u64 {
+        if record.timestamp_ms() != 0 {
+            return record.timestamp_ms();
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        self.last_timestamp_ms = self.last_timestamp_ms.max(now);
+        self.last_timestamp_ms
+    }
+
+    /// Write an encoded record, rotating the segment first if needed
+    fn write_encoded(&mut self, encoded: &[u8]) -> Result<u64> {
         let record_size = encoded.len() as u64;
 
         // Check if we need to rotate to a new segment
@@ -166,34 +371,71 @@ impl WalWriter {
 
         // Write the encoded record
         self.file
-            .write_all(&encoded)
+            .get_mut()
+            .map_err(|_| Error::LockPoisoned)?
+            .write_all(encoded)
             .map_err(|e| Error::Storage(format!("Failed to write WAL record: {}", e)))?;
 
         self.current_size += record_size;
         self.sequence += 1;
 
-        // Sync if required
-        if matches!(self.sync_mode, SyncMode::Sync) {
-            self.sync()?;
-        }
-
         Ok(self.sequence)
     }
 
-    pub fn sync(&mut self) -> Result<()> {
+    /// Hand buffered records to the OS without fsyncing them
+    ///
+    /// Readers of the segment files then see every appended record, but the
+    /// records are not durable until [`sync`](Self::sync).
+    pub fn flush(&self) -> Result<()> {
         self.file
+            .lock()
+            .map_err(|_| Error::LockPoisoned)?
             .flush()
-            .map_err(|e| Error::Storage(format!("Failed to flush WAL: {}", e)))?;
+            .map_err(|e| Error::Storage(format!("Failed to flush WAL: {}", e)))
+    }
+
+    pub fn sync(&mut self) -> Result<()> {
+        self.flush()?;
 
         self.file
+            .get_mut()
+            .map_err(|_| Error::LockPoisoned)?
             .get_ref()
             .sync_all()
             .map_err(|e| Error::Storage(format!("Failed to sync WAL: {}", e)))?;
 
+        self.sync_count += 1;
+        self.durable_sequence = self.sequence;
         Ok(())
     }
 
-    fn rotate_segment(&mut self) -> Result<()> {
+    /// Make every record up to and including `sequence` durable
+    ///
+    /// Returns without syncing if those records are already on disk.
+    pub fn sync_through(&mut self, sequence: u64) -> Result<()> {
+        if sequence > self.sequence {
+            return Err(Error::InvalidInput(format!(
+                "WAL sequence {} has not been written",
+                sequence
+            )));
+        }
+        if sequence <= self.durable_sequence {
+            return Ok(());
+        }
+        self.sync()
+    }
+
+    /// Highest sequence number known to be on disk
+    pub fn durable_sequence(&self) -> u64 {
+        self.durable_sequence
+    }
+
+    /// Number of times this writer has synced to disk
+    pub fn sync_count(&self) -> u64 {
+        self.sync_count
+    }
+
+    pub(crate) fn rotate_segment(&mut self) -> Result<()> {
         // Sync current segment before rotating
         self.sync()?;
 
@@ -212,15 +454,15 @@ impl WalWriter {
             .map_err(|e| Error::Storage(format!("Failed to create new segment: {}", e)))?;
 
         // Write header for new segment (v1.0+)
-        let header = WalHeader::new();
+        let header = WalHeader::with_checksum(self.checksum);
         header.write_to(&mut file)?;
         file.flush()?;
-        let header_size = WalHeader::SIZE as u64;
+        let header_size = header.encoded_len() as u64;
 
         debug!(segment = ?new_segment, "Rotated to new WAL segment");
 
         // Update state
-        self.file = BufWriter::new(file);
+        self.file = Mutex::new(BufWriter::with_capacity(self.buffer_size, file));
         self.current_segment = new_segment;
         self.current_size = header_size;
 
@@ -281,12 +523,65 @@ mod tests {
             .expect("Failed to create writer");
 
         let record = WalRecord::put(b"key1".to_vec(), b"value1".to_vec());
-        let seq = writer.append(record).expect("Failed to append");
+        let seq = writer.append(record).expect("Failed to append").lsn;
 
         assert_eq!(seq, 1);
         assert!(writer.current_segment_size() > 0);
     }
 
+    #[test]
+    fn test_append_reports_durability() {
+        let (_temp_dir, wal_path) = setup_test_wal();
+        let record = WalRecord::put(b"key1".to_vec(), b"value1".to_vec());
+
+        let mut writer = WalWriter::new(&wal_path, 64 * 1024 * 1024, SyncMode::Sync)
+            .expect("Failed to create writer");
+        let outcome = writer.append_ref(&record).expect("Failed to append");
+        assert!(outcome.durable);
+        drop(writer);
+
+        let mut writer = WalWriter::new(&wal_path, 64 * 1024 * 1024, SyncMode::None)
+            .expect("Failed to create writer");
+        let outcome = writer.append_ref(&record).expect("Failed to append");
+        assert!(!outcome.durable);
+        assert!(writer.durable_sequence() < outcome.lsn);
+
+        writer.sync().expect("Failed to sync");
+        assert!(writer.durable_sequence() >= outcome.lsn);
+        assert!(
+            !writer
+                .append_ref(&record)
+                .expect("Failed to append")
+                .durable
+        );
+    }
+
+    #[test]
+    fn test_append_ref_matches_append() {
+        let (_temp_dir, wal_path) = setup_test_wal();
+
+        let mut writer = WalWriter::new(&wal_path, 64 * 1024 * 1024, SyncMode::Sync)
+            .expect("Failed to create writer");
+
+        let record = WalRecord::put(b"key1".to_vec(), b"value1".to_vec());
+        assert_eq!(writer.append_ref(&record).expect("Failed to append").lsn, 1);
+        assert_eq!(
+            writer.append(record.clone()).expect("Failed to append").lsn,
+            2
+        );
+        // The caller's record is left unstamped
+        assert_eq!(record.timestamp_ms(), 0);
+        drop(writer);
+
+        let records = crate::WalReader::new(&wal_path)
+            .expect("Failed to create reader")
+            .read_all()
+            .expect("Failed to read");
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| *r == record));
+        assert!(records.iter().all(|r| r.timestamp_ms() > 0));
+    }
+
     #[test]
     fn test_append_multiple_records() {
         let (_temp_dir, wal_path) = setup_test_wal();
@@ -299,7 +594,7 @@ mod tests {
                 format!("key{}", i).into_bytes(),
                 format!("value{}", i).into_bytes(),
             );
-            let seq = writer.append(record).expect("Failed to append");
+            let seq = writer.append(record).expect("Failed to append").lsn;
             assert_eq!(seq, i as u64 + 1);
         }
     }
@@ -437,4 +732,55 @@ mod tests {
 
         assert!(writer.current_segment_size() > 1024 * 1024);
     }
+
+    #[test]
+    fn test_checksum_recorded_per_segment() {
+        use crate::reader::WalReader;
+
+        let (_temp_dir, wal_path) = setup_test_wal();
+
+        {
+            let mut writer = WalWriter::with_checksum(
+                &wal_path,
+                64 * 1024 * 1024,
+                SyncMode::Sync,
+                DEFAULT_WRITE_BUFFER_SIZE,
+                ChecksumAlgorithm::Crc64,
+            )
+            .expect("Failed to create writer");
+            writer
+                .append(WalRecord::put(b"a".to_vec(), b"1".to_vec()))
+                .expect("Failed to append");
+
+            let mut file = File::open(writer.current_segment_path()).unwrap();
+            let header = WalHeader::read_from(&mut file).unwrap();
+            assert_eq!(header.version, 2);
+            assert_eq!(header.checksum, ChecksumAlgorithm::Crc64);
+        }
+
+        // Reopening with CRC32 starts a new segment instead of mixing
+        // algorithms in the CRC64 one
+        {
+            let mut writer = WalWriter::new(&wal_path, 64 * 1024 * 1024, SyncMode::Sync)
+                .expect("Failed to create writer");
+            writer
+                .append(WalRecord::put(b"b".to_vec(), b"2".to_vec()))
+                .expect("Failed to append");
+
+            let mut file = File::open(writer.current_segment_path()).unwrap();
+            let header = WalHeader::read_from(&mut file).unwrap();
+            assert_eq!(header.version, 1);
+            assert_eq!(header.checksum, ChecksumAlgorithm::Crc32);
+        }
+
+        let mut reader = WalReader::new(&wal_path).unwrap();
+        assert_eq!(reader.segment_count(), 2);
+        let mut keys = Vec::new();
+        while let Some(record) = reader.next_record().unwrap() {
+            if let crate::record::RecordPayload::Put { key, .. } = record.payload {
+                keys.push(key);
+            }
+        }
+        assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+    }
 }
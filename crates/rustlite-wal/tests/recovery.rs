@@ -4,7 +4,10 @@
 mod common;
 
 use common::WalTestFixture;
-use rustlite_wal::{RecordType, RecoveryManager, SyncMode, WalConfig, WalManager, WalRecord};
+use rustlite_wal::{
+    ChecksumAlgorithm, ReadOutcome, RecordPayload, RecordType, RecoveryManager, RecoveryPolicy,
+    SyncMode, WalConfig, WalManager, WalReader, WalRecord,
+};
 
 #[test]
 fn test_recovery_from_clean_shutdown() {
@@ -42,29 +45,112 @@ fn test_recovery_from_clean_shutdown() {
 
 #[test]
 fn test_recovery_from_partial_write() {
-    // This test will simulate a crash scenario by:
-    // 1. Writing some complete records
-    // 2. Simulating a partial write (incomplete record at end)
-    // 3. Verifying recovery handles it gracefully
+    // Simulate a crash mid-write by truncating the last record of the
+    // segment, then verify everything before it is recovered
+    let fixture = WalTestFixture::new();
+
+    let mut config = WalConfig::default();
+    config.wal_dir = fixture.wal_dir().clone();
+
+    let mut manager = WalManager::new(config.clone()).expect("Failed to create WAL manager");
+    manager.open().expect("Failed to open WAL");
+    for i in 0..5 {
+        let key = format!("key{}", i).into_bytes();
+        let value = format!("value{}", i).into_bytes();
+        manager
+            .append(WalRecord::put(key, value))
+            .expect("Failed to append");
+    }
+    manager.sync().expect("Failed to sync");
+    manager.close().expect("Failed to close");
 
+    let segments = fixture.list_segments();
+    assert_eq!(segments.len(), 1);
+    let segment_path = fixture.wal_dir().join(&segments[0]);
+    let len = std::fs::metadata(&segment_path).unwrap().len();
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&segment_path)
+        .unwrap();
+    file.set_len(len - 3).unwrap();
+    drop(file);
+
+    // The reader reports the torn tail explicitly
+    let mut reader = WalReader::new(fixture.wal_dir()).unwrap();
+    let mut complete = 0;
+    let torn = loop {
+        match reader.next_outcome().unwrap() {
+            ReadOutcome::Record(_) => complete += 1,
+            ReadOutcome::TornTail(position) => break position,
+            ReadOutcome::End => panic!("Expected a torn tail"),
+        }
+    };
+    assert_eq!(complete, 4);
+    assert!(torn.offset < len - 3);
+    assert_eq!(reader.next_outcome().unwrap(), ReadOutcome::End);
+
+    let recovery = RecoveryManager::new(config).expect("Failed to create recovery manager");
+    let records = recovery.recover().expect("Recovery should succeed");
+    assert_eq!(records.len(), 4);
+    assert_eq!(
+        records[3],
+        WalRecord::put(b"key3".to_vec(), b"value3".to_vec())
+    );
+}
+
+#[test]
+fn test_recovery_after_write_following_partial_write() {
+    // A crash leaves a torn record; the next session appends after it, so
+    // the torn frame's length now spans the new record and fails its CRC
     let fixture = WalTestFixture::new();
 
     let mut config = WalConfig::default();
     config.wal_dir = fixture.wal_dir().clone();
 
-    // TODO: Implement when we have manual segment manipulation utilities
-    // For now, just verify basic recovery works
     let mut manager = WalManager::new(config.clone()).expect("Failed to create WAL manager");
     manager.open().expect("Failed to open WAL");
+    for i in 0..3 {
+        let key = format!("key{}", i).into_bytes();
+        let value = format!("value{}", i).into_bytes();
+        manager
+            .append(WalRecord::put(key, value))
+            .expect("Failed to append");
+    }
+    manager.sync().expect("Failed to sync");
+    manager.close().expect("Failed to close");
 
-    let record = WalRecord::new(RecordType::Put, b"key".to_vec(), b"value".to_vec());
-    manager.append(record).expect("Failed to append");
+    let segments = fixture.list_segments();
+    let segment_path = fixture.wal_dir().join(&segments[0]);
+    let len = std::fs::metadata(&segment_path).unwrap().len();
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&segment_path)
+        .unwrap();
+    // Keep only the length, type and timestamp of the last record, so the
+    // next record's header is read as the torn record's payload
+    let last_len = WalRecord::put(b"key2".to_vec(), b"value2".to_vec())
+        .with_timestamp(1)
+        .encode()
+        .unwrap()
+        .len() as u64;
+    file.set_len(len - last_len + 13).unwrap();
+    drop(file);
+
+    let mut manager = WalManager::new(config.clone()).expect("Failed to create WAL manager");
+    manager.open().expect("Failed to reopen WAL");
+    manager
+        .append(WalRecord::put(b"after".to_vec(), b"crash".to_vec()))
+        .expect("Failed to append");
     manager.sync().expect("Failed to sync");
     manager.close().expect("Failed to close");
 
     let recovery = RecoveryManager::new(config).expect("Failed to create recovery manager");
     let records = recovery.recover().expect("Recovery should succeed");
-    assert_eq!(records.len(), 1);
+    assert!(records.len() >= 2);
+    assert_eq!(
+        records[1],
+        WalRecord::put(b"key1".to_vec(), b"value1".to_vec())
+    );
 }
 
 #[test]
@@ -157,6 +243,64 @@ fn test_recovery_with_corrupted_crc() {
     assert!(!records.is_empty(), "Should recover some valid records");
 }
 
+#[test]
+fn test_recovery_policy_on_damaged_segment() {
+    let fixture = WalTestFixture::new();
+    let mut config = WalConfig::default();
+    config.wal_dir = fixture.wal_dir().clone();
+
+    {
+        let mut manager = WalManager::new(config.clone()).expect("Failed to create WAL manager");
+        manager.open().expect("Failed to open WAL");
+        for i in 0..3 {
+            let record = WalRecord::new(
+                RecordType::Put,
+                format!("key{}", i).as_bytes().to_vec(),
+                format!("value{}", i).as_bytes().to_vec(),
+            );
+            manager.append(record).expect("Failed to append");
+        }
+        manager.sync().expect("Failed to sync");
+        manager.close().expect("Failed to close");
+    }
+
+    let segments = fixture.list_segments();
+    assert_eq!(segments.len(), 1);
+    let segment = fixture.wal_dir().join(&segments[0]);
+    let original = std::fs::read(&segment).expect("Failed to read segment");
+
+    let recover = |policy: RecoveryPolicy| {
+        let config = WalConfig {
+            recovery_policy: policy,
+            ..config.clone()
+        };
+        RecoveryManager::new(config)
+            .expect("Failed to create recovery manager")
+            .recover()
+    };
+
+    // Flip a bit in the last record's CRC, then cut the last record short
+    let mut corrupted = original.clone();
+    *corrupted.last_mut().unwrap() ^= 0x01;
+    let torn = original[..original.len() - 2].to_vec();
+
+    for damaged in [corrupted, torn] {
+        std::fs::write(&segment, &damaged).expect("Failed to write segment");
+
+        let records = recover(RecoveryPolicy::Lenient).expect("Lenient recovery should succeed");
+        assert_eq!(records.len(), 2, "Lenient recovery keeps the valid prefix");
+        assert!(matches!(
+            &records[1].payload,
+            RecordPayload::Put { key, .. } if key == b"key1"
+        ));
+
+        match recover(RecoveryPolicy::Strict) {
+            Err(rustlite_core::Error::Corruption(_)) => {}
+            other => panic!("Strict recovery should fail, got {:?}", other),
+        }
+    }
+}
+
 #[test]
 fn test_recovery_empty_wal() {
     let fixture = WalTestFixture::new();
@@ -165,6 +309,11 @@ fn test_recovery_empty_wal() {
         wal_dir: fixture.wal_dir().clone(),
         sync_mode: SyncMode::Sync,
         max_segment_size: 1024 * 1024,
+        archive_dir: None,
+        max_total_wal_size: None,
+        write_buffer_bytes: rustlite_wal::writer::DEFAULT_WRITE_BUFFER_SIZE,
+        recovery_policy: RecoveryPolicy::Lenient,
+        checksum: ChecksumAlgorithm::Crc32,
     };
 
     // No WAL segments exist yet